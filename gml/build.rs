@@ -0,0 +1,34 @@
+use std::{fs, io};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+
+/// Fingerprint the compiler's source, so code cached by one build is not reused by another.
+fn main() -> io::Result<()> {
+    println!("cargo:rerun-if-changed=src");
+
+    let mut files = Vec::default();
+    visit(Path::new("src"), &mut files)?;
+    files.sort();
+
+    let mut hasher = DefaultHasher::new();
+    for path in &files[..] {
+        path.hash(&mut hasher);
+        fs::read(path)?.hash(&mut hasher);
+    }
+    println!("cargo:rustc-env=GML_BUILD={:016x}", hasher.finish());
+
+    Ok(())
+}
+
+fn visit(dir: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            visit(&path, files)?;
+        } else {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
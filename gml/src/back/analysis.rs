@@ -38,6 +38,21 @@ impl ControlFlow {
 
         control_flow
     }
+
+    /// Find the blocks that can be reached from the function's entry block.
+    pub fn reachable(&self) -> BitVec {
+        let mut reachable = BitVec::new();
+        let mut stack = vec![ssa::ENTRY];
+        reachable.set(ssa::ENTRY.index());
+        while let Some(block) = stack.pop() {
+            for &succ in &self.succ[block] {
+                if !reachable.set(succ.index()) {
+                    stack.push(succ);
+                }
+            }
+        }
+        reachable
+    }
 }
 
 /// Live value analysis.
@@ -1,8 +1,10 @@
 pub use crate::back::codegen::Codegen;
 pub use crate::back::analysis::ControlFlow;
+pub use crate::back::optimize::{optimize, Constant};
 
 pub mod ssa;
 
 mod analysis;
+mod optimize;
 mod regalloc;
 mod codegen;
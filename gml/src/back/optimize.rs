@@ -0,0 +1,609 @@
+use std::collections::HashMap;
+
+use crate::bit_vec::BitVec;
+use crate::handle_map::{Handle, HandleMap};
+use crate::symbol::Symbol;
+use crate::back::ssa;
+use crate::vm::{self, to_i32, to_bool};
+
+/// A value known at compile time.
+#[derive(Copy, Clone, Debug)]
+pub enum Constant {
+    Real(f64),
+    String(Symbol),
+}
+
+/// The result of simplifying a single instruction.
+enum Simplified {
+    /// Overwrite the instruction in place.
+    Replace(ssa::Instruction),
+    /// Forward uses of the instruction's result to another value, and remove it.
+    Forward(ssa::Value),
+    /// Remove the instruction, which has no result.
+    Remove,
+}
+
+/// The arguments passed to a block parameter along every incoming edge.
+enum Incoming {
+    Value(ssa::Value),
+    Constant(Constant),
+    Many,
+}
+
+/// Simplify a function between the front and back ends.
+///
+/// This folds operations on constants, including the values of project constants in `constants`,
+/// and forwards copies and block parameters that only ever receive one value. Branches on
+/// constants become jumps, and instructions left without uses are removed. Anything that might
+/// fail at run time is left alone, so programs report the same errors they did before.
+///
+/// The passes enable each other, so they run until none of them makes any progress.
+pub fn optimize(program: &mut ssa::Function, constants: &HashMap<i32, Constant>) {
+    let mut changed = true;
+    while changed {
+        changed = false;
+        changed |= fold_constants(program, constants);
+        changed |= prune_unreachable(program);
+        changed |= propagate_parameters(program);
+        program.resolve_aliases();
+        changed |= eliminate_dead_code(program);
+    }
+}
+
+impl Constant {
+    /// The value a function always returns, if it is known.
+    ///
+    /// This is used to find the values of project constants, for use in later code.
+    pub fn from_return(program: &ssa::Function) -> Option<Constant> {
+        match program.values[program.terminator(ssa::ENTRY)] {
+            ssa::Instruction::Unary { op: ssa::Opcode::Return, arg } => constant(program, arg),
+            _ => None,
+        }
+    }
+
+    fn instruction(self) -> ssa::Instruction {
+        let op = ssa::Opcode::Constant;
+        match self {
+            Constant::Real(real) => ssa::Instruction::UnaryReal { op, real },
+            Constant::String(symbol) => ssa::Instruction::UnarySymbol { op, symbol },
+        }
+    }
+
+    /// Compare two constants the way the VM does, by their representation.
+    fn same(self, other: Constant) -> bool {
+        vm::Value::from(self) == vm::Value::from(other)
+    }
+}
+
+impl From<Constant> for vm::Value {
+    fn from(constant: Constant) -> vm::Value {
+        match constant {
+            Constant::Real(real) => vm::Value::from(real),
+            Constant::String(symbol) => vm::Value::from(symbol),
+        }
+    }
+}
+
+fn fold_constants(program: &mut ssa::Function, constants: &HashMap<i32, Constant>) -> bool {
+    let mut changed = false;
+    for block in program.blocks.keys() {
+        let mut i = 0;
+        while i < program.blocks[block].instructions.len() {
+            let value = program.blocks[block].instructions[i];
+            match simplify(program, constants, value) {
+                Some(Simplified::Replace(instruction)) => {
+                    program.values[value] = instruction;
+                }
+                Some(Simplified::Forward(arg)) => {
+                    program.values[value] = ssa::Instruction::Alias { arg };
+                    program.blocks[block].instructions.remove(i);
+                    changed = true;
+                    continue;
+                }
+                Some(Simplified::Remove) => {
+                    program.blocks[block].instructions.remove(i);
+                    changed = true;
+                    continue;
+                }
+                None => {
+                    i += 1;
+                    continue;
+                }
+            }
+            changed = true;
+            i += 1;
+        }
+    }
+    changed
+}
+
+fn simplify(
+    program: &ssa::Function, constants: &HashMap<i32, Constant>, value: ssa::Value
+) -> Option<Simplified> {
+    use crate::back::ssa::Instruction::*;
+
+    let instruction = match program.values[value] {
+        UnaryInt { op: ssa::Opcode::GlobalConstant, int } => constants.get(&int)?.instruction(),
+
+        Unary { op: ssa::Opcode::ToScalar, arg } if is_scalar(program, arg) => {
+            return Some(Simplified::Forward(arg));
+        }
+        Unary { op, arg } => unary(op, constant(program, arg)?)?.instruction(),
+
+        // Before GMS, writing a scalar over another scalar simply replaces it.
+        Binary { op: ssa::Opcode::Write, args: [value, old] } if is_scalar(program, old) => {
+            return Some(Simplified::Forward(value));
+        }
+        Binary { op, args: [a, b] } => {
+            binary(op, constant(program, a)?, constant(program, b)?)?.instruction()
+        }
+
+        // The local is known to be initialized.
+        BinarySymbol { op: ssa::Opcode::Read, arg, .. } => match constant(program, arg)? {
+            Constant::Real(flag) if to_bool(flag) => return Some(Simplified::Remove),
+            _ => return None,
+        }
+
+        Branch { targets: [true_block, false_block], arg_lens: [true_args, _], ref args, .. } => {
+            let Constant::Real(condition) = constant(program, args[0])? else { return None };
+            let true_end = 1 + true_args as usize;
+            let (target, args) = if to_bool(condition) {
+                (true_block, &args[1..true_end])
+            } else {
+                (false_block, &args[true_end..])
+            };
+            Jump { op: ssa::Opcode::Jump, target, args: args.to_vec() }
+        }
+
+        _ => return None,
+    };
+    Some(Simplified::Replace(instruction))
+}
+
+fn unary(op: ssa::Opcode, a: Constant) -> Option<Constant> {
+    let Constant::Real(a) = a else { return None };
+    let result = match op {
+        ssa::Opcode::Negate => -a,
+        ssa::Opcode::Invert => bool(!to_bool(a)),
+        ssa::Opcode::BitInvert => !to_i32(a) as f64,
+        _ => return None,
+    };
+    Some(Constant::Real(result))
+}
+
+/// Evaluate a binary operation as the VM does, unless it would be an error.
+fn binary(op: ssa::Opcode, a: Constant, b: Constant) -> Option<Constant> {
+    use self::Constant::*;
+
+    let result = match (op, a, b) {
+        (ssa::Opcode::Eq, a, b) => bool(a.same(b)),
+        (ssa::Opcode::Ne, a, b) => bool(!a.same(b)),
+
+        (ssa::Opcode::Lt, Real(a), Real(b)) => bool(a < b),
+        (ssa::Opcode::Le, Real(a), Real(b)) => bool(a <= b),
+        (ssa::Opcode::Ge, Real(a), Real(b)) => bool(a >= b),
+        (ssa::Opcode::Gt, Real(a), Real(b)) => bool(a > b),
+        (ssa::Opcode::Lt, String(a), String(b)) => bool(a < b),
+        (ssa::Opcode::Le, String(a), String(b)) => bool(a <= b),
+        (ssa::Opcode::Ge, String(a), String(b)) => bool(a >= b),
+        (ssa::Opcode::Gt, String(a), String(b)) => bool(a > b),
+
+        (ssa::Opcode::Add, String(a), String(b)) => {
+            return Some(String(Symbol::intern(&[a, b].concat())));
+        }
+
+        (_, Real(a), Real(b)) => match op {
+            ssa::Opcode::Add => a + b,
+            ssa::Opcode::Subtract => a - b,
+            ssa::Opcode::Multiply => a * b,
+            ssa::Opcode::Divide if b != 0.0 => a / b,
+            ssa::Opcode::Div if b != 0.0 => to_i32(a / b) as f64,
+            ssa::Opcode::Mod if b != 0.0 => a % b,
+
+            ssa::Opcode::And => bool(to_bool(a) && to_bool(b)),
+            ssa::Opcode::Or => bool(to_bool(a) || to_bool(b)),
+            ssa::Opcode::Xor => bool(to_bool(a) != to_bool(b)),
+
+            ssa::Opcode::BitAnd => (to_i32(a) & to_i32(b)) as f64,
+            ssa::Opcode::BitOr => (to_i32(a) | to_i32(b)) as f64,
+            ssa::Opcode::BitXor => (to_i32(a) ^ to_i32(b)) as f64,
+            // Leave out-of-range shifts to the VM.
+            ssa::Opcode::ShiftLeft => to_i32(a).checked_shl(shift(b)?)? as f64,
+            ssa::Opcode::ShiftRight => to_i32(a).checked_shr(shift(b)?)? as f64,
+
+            _ => return None,
+        },
+
+        _ => return None,
+    };
+    Some(Real(result))
+}
+
+fn bool(value: bool) -> f64 { value as i32 as f64 }
+
+fn shift(b: f64) -> Option<u32> { u32::try_from(to_i32(b)).ok() }
+
+/// The value of a constant instruction.
+fn constant(program: &ssa::Function, value: ssa::Value) -> Option<Constant> {
+    use crate::back::ssa::Instruction::*;
+
+    match program.values[program.resolve_alias(value)] {
+        UnaryReal { op: ssa::Opcode::Constant, real } => Some(Constant::Real(real)),
+        UnarySymbol { op: ssa::Opcode::Constant, symbol } => Some(Constant::String(symbol)),
+        _ => None,
+    }
+}
+
+/// Whether a value is always a scalar, rather than a pre-GMS array.
+fn is_scalar(program: &ssa::Function, value: ssa::Value) -> bool {
+    use crate::back::ssa::Instruction::*;
+    use crate::back::ssa::Opcode::*;
+
+    match program.values[program.resolve_alias(value)] {
+        UnaryReal { op: Constant, .. } | UnarySymbol { op: Constant, .. } => true,
+        UnaryInt { op: GlobalConstant, .. } => true,
+        Unary { op: Negate | Invert | BitInvert | ToScalar | ExistsEntity, .. } => true,
+        Binary { op, .. } => is_arithmetic(op),
+        _ => false,
+    }
+}
+
+/// Whether a value is always a real.
+fn is_real(program: &ssa::Function, value: ssa::Value) -> bool {
+    use crate::back::ssa::Instruction::*;
+    use crate::back::ssa::Opcode::*;
+
+    match program.values[program.resolve_alias(value)] {
+        UnaryReal { op: Constant, .. } => true,
+        Unary { op: Negate | Invert | BitInvert | ExistsEntity, .. } => true,
+        // These also combine strings.
+        Binary { op: Add | Multiply, args: [a, b] } => is_real(program, a) && is_real(program, b),
+        Binary { op, .. } => is_arithmetic(op),
+        _ => false,
+    }
+}
+
+/// Binary operations on scalars that produce scalars.
+fn is_arithmetic(op: ssa::Opcode) -> bool {
+    use crate::back::ssa::Opcode::*;
+
+    matches!(op,
+        Lt | Le | Eq | Ne | Ge | Gt | NePointer |
+        Add | Subtract | Multiply | Divide | Div | Mod |
+        And | Or | Xor |
+        BitAnd | BitOr | BitXor | ShiftLeft | ShiftRight
+    )
+}
+
+/// Whether an instruction can be removed when its result is unused.
+///
+/// Instructions that might fail, like arithmetic on operands that might be strings, must stay to
+/// report their errors.
+fn is_pure(program: &ssa::Function, value: ssa::Value) -> bool {
+    use crate::back::ssa::Instruction::*;
+    use crate::back::ssa::Opcode::*;
+
+    match program.values[value] {
+        UnaryReal { op: Constant, .. } | UnarySymbol { op: Constant, .. } => true,
+        UnaryInt { op: GlobalConstant, .. } => true,
+        UnarySymbol { op: Lookup, .. } => true,
+        UnaryInt { op: LoadScope, int } => matches!(int, vm::SELF | vm::OTHER | vm::GLOBAL),
+
+        Unary { op: Negate | Invert | BitInvert, arg } => is_real(program, arg),
+        Unary { op: ToScalar, arg } => is_scalar(program, arg),
+
+        Binary { op: Eq | Ne | NePointer, .. } => true,
+        Binary { op: Divide | Div | Mod, args: [a, b] } => {
+            let nonzero = matches!(constant(program, b), Some(self::Constant::Real(b)) if b != 0.0);
+            is_real(program, a) && nonzero
+        }
+        Binary { op, args: [a, b] } if is_arithmetic(op) => {
+            is_real(program, a) && is_real(program, b)
+        }
+
+        _ => false,
+    }
+}
+
+/// Replace blocks that can no longer be reached with empty loops, so their uses do not keep
+/// anything alive.
+fn prune_unreachable(program: &mut ssa::Function) -> bool {
+    let mut reachable = BitVec::new();
+    let mut stack = vec![ssa::ENTRY];
+    reachable.set(ssa::ENTRY.index());
+    while let Some(block) = stack.pop() {
+        for &succ in program.successors(block) {
+            if !reachable.set(succ.index()) {
+                stack.push(succ);
+            }
+        }
+    }
+
+    let mut changed = false;
+    for block in program.blocks.keys() {
+        if reachable.get(block.index()) {
+            continue;
+        }
+
+        let terminator = program.terminator(block);
+        let op = ssa::Opcode::Jump;
+        let empty = ssa::Instruction::Jump { op, target: block, args: vec![] };
+        if program.blocks[block].instructions.len() == 1 && program.values[terminator] == empty {
+            continue;
+        }
+
+        let location = program.locations[terminator];
+        program.blocks[block].parameters.clear();
+        program.blocks[block].instructions.clear();
+        program.emit_instruction(block, empty, location);
+        changed = true;
+    }
+    changed
+}
+
+/// Forward block parameters that receive the same value from every predecessor.
+fn propagate_parameters(program: &mut ssa::Function) -> bool {
+    let mut changed = false;
+    for block in program.blocks.keys() {
+        // Parameters to the entry block are the function's parameters.
+        if block == ssa::ENTRY {
+            continue;
+        }
+
+        let mut index = 0;
+        while index < program.blocks[block].parameters.len() {
+            let parameter = program.blocks[block].parameters[index];
+            match incoming(program, block, index) {
+                Incoming::Value(arg) => {
+                    remove_parameter(program, block, index);
+                    program.values[parameter] = ssa::Instruction::Alias { arg };
+                }
+                Incoming::Constant(constant) => {
+                    remove_parameter(program, block, index);
+                    let first = program.blocks[block].instructions[0];
+                    let location = program.locations[first];
+                    program.values[parameter] = constant.instruction();
+                    program.blocks[block].instructions.insert(0, parameter);
+                    *program.locations.ensure(parameter) = location;
+                }
+                Incoming::Many => {
+                    index += 1;
+                    continue;
+                }
+            }
+            changed = true;
+        }
+    }
+    changed
+}
+
+fn incoming(program: &ssa::Function, block: ssa::Label, index: usize) -> Incoming {
+    let parameter = program.blocks[block].parameters[index];
+
+    let mut values = Vec::default();
+    for pred in program.blocks.keys() {
+        match program.values[program.terminator(pred)] {
+            ssa::Instruction::Jump { target, ref args, .. } if target == block => {
+                values.push(args[index]);
+            }
+            ssa::Instruction::Branch {
+                targets: [true_block, false_block], arg_lens: [true_args, _], ref args, ..
+            } => {
+                if true_block == block {
+                    values.push(args[1 + index]);
+                }
+                if false_block == block {
+                    values.push(args[1 + true_args as usize + index]);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // A parameter passed back to its own block adds no new values.
+    let mut values = values.into_iter()
+        .map(|value| program.resolve_alias(value))
+        .filter(|&value| value != parameter)
+        .peekable();
+    let Some(&first) = values.peek() else { return Incoming::Many };
+    if values.clone().all(|value| value == first) {
+        return Incoming::Value(first);
+    }
+    let Some(constant) = constant(program, first) else { return Incoming::Many };
+    if values.all(|value| self::constant(program, value).is_some_and(|c| c.same(constant))) {
+        return Incoming::Constant(constant);
+    }
+    Incoming::Many
+}
+
+/// Remove a block parameter, along with its arguments from every predecessor.
+fn remove_parameter(program: &mut ssa::Function, block: ssa::Label, index: usize) {
+    program.blocks[block].parameters.remove(index);
+    for pred in program.blocks.keys() {
+        let terminator = program.terminator(pred);
+        match program.values[terminator] {
+            ssa::Instruction::Jump { target, ref mut args, .. } if target == block => {
+                args.remove(index);
+            }
+            ssa::Instruction::Branch {
+                targets: [true_block, false_block],
+                arg_lens: [ref mut true_args, ref mut false_args],
+                ref mut args,
+                ..
+            } => {
+                // Remove the later argument first, so the earlier one's index stays the same.
+                if false_block == block {
+                    args.remove(1 + *true_args as usize + index);
+                    *false_args -= 1;
+                }
+                if true_block == block {
+                    args.remove(1 + index);
+                    *true_args -= 1;
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Remove pure instructions and block parameters whose results are never used.
+fn eliminate_dead_code(program: &mut ssa::Function) -> bool {
+    let mut uses = HandleMap::with_capacity_default(program.values.len(), 0);
+    for block in program.blocks.keys() {
+        for &value in &program.blocks[block].instructions {
+            for &arg in program.uses(value) {
+                uses[arg] += 1;
+            }
+        }
+    }
+
+    let mut changed = false;
+    for block in program.blocks.keys().rev() {
+        // Visit instructions backwards, so chains of dead instructions are removed in one pass.
+        let mut i = program.blocks[block].instructions.len();
+        while i > 0 {
+            i -= 1;
+            let value = program.blocks[block].instructions[i];
+            let mut defs = program.defs(value).peekable();
+            let dead = defs.peek().is_some() && defs.all(|def| uses[def] == 0);
+            if !dead || !is_pure(program, value) {
+                continue;
+            }
+
+            for &arg in program.uses(value) {
+                uses[arg] -= 1;
+            }
+            program.blocks[block].instructions.remove(i);
+            changed = true;
+        }
+
+        if block == ssa::ENTRY {
+            continue;
+        }
+        let mut index = 0;
+        while index < program.blocks[block].parameters.len() {
+            let parameter = program.blocks[block].parameters[index];
+            if uses[parameter] > 0 {
+                index += 1;
+                continue;
+            }
+
+            remove_parameter(program, block, index);
+            changed = true;
+        }
+    }
+    changed
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::io;
+
+    use crate::{ErrorPrinter, FunctionDisplay};
+    use crate::diagnostic::{ErrorsOnly, Text};
+    use crate::symbol::Symbol;
+    use crate::front::{self, Lexer, Parser, Lines};
+    use crate::back::{self, ssa};
+    use crate::vm::{self, code};
+    use super::{Constant, optimize};
+
+    fn compile(source: &str, constants: &HashMap<i32, Constant>) -> code::Function {
+        let mut prototypes = HashMap::default();
+        prototypes.insert(Symbol::intern(b"first"), ssa::Prototype::Constant { id: 0 });
+        prototypes.insert(Symbol::intern(b"second"), ssa::Prototype::Constant { id: 1 });
+        let native = ssa::Prototype::Native { arity: 1, variadic: false, dll: false };
+        prototypes.insert(Symbol::intern(b"show_message"), native);
+
+        let source = source.as_bytes();
+        let lines = Lines::from_code(source);
+        // Some tests store to locals only to check what the optimizer removes.
+        let emit = ErrorsOnly(Text(io::stderr()));
+        let mut errors = ErrorPrinter::new(FunctionDisplay::String, &lines, emit);
+        let program = Parser::new(Lexer::new(source, 0), &mut errors).parse_program();
+        let mut program = front::Codegen::new(&prototypes, &mut errors).compile_program(&program);
+        assert_eq!(errors.count, 0);
+
+        optimize(&mut program, constants);
+        let (function, _) = back::Codegen::new(&prototypes).compile(&program);
+        function
+    }
+
+    fn ops(function: &code::Function) -> Vec<code::Op> {
+        function.instructions.iter().map(|inst| inst.decode().0).collect()
+    }
+
+    fn reals(function: &code::Function) -> Vec<f64> {
+        function.constants.iter()
+            .filter_map(|value| match value.borrow().decode() {
+                vm::Data::Real(real) => Some(real),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn fold() {
+        let function = compile("return (2 * 3 + 1) mod 4 << 2", &HashMap::default());
+        assert_eq!(ops(&function), [code::Op::Const, code::Op::Ret]);
+        assert_eq!(reals(&function), [12.0]);
+
+        let function = compile(r#"return "a" + "b" == "ab""#, &HashMap::default());
+        assert_eq!(ops(&function), [code::Op::Const, code::Op::Ret]);
+        assert_eq!(reals(&function), [1.0]);
+    }
+
+    #[test]
+    fn errors() {
+        // Operations that fail at run time are kept, so they still report their errors.
+        let function = compile("return 1 / 0", &HashMap::default());
+        assert!(ops(&function).contains(&code::Op::Div));
+
+        let function = compile(r#"return "a" - 1"#, &HashMap::default());
+        assert!(ops(&function).contains(&code::Op::Sub));
+
+        let function = compile(r#"var a; a = -"a"; return 0"#, &HashMap::default());
+        assert!(ops(&function).contains(&code::Op::Neg));
+    }
+
+    #[test]
+    fn locals() {
+        // Initialized locals need no checks, and scalar writes need no array handling.
+        let function = compile("var a, b; a = 4; b = a * 2; return b + a", &HashMap::default());
+        assert_eq!(ops(&function), [code::Op::Const, code::Op::Ret]);
+        assert_eq!(reals(&function), [12.0]);
+
+        // Unused computations are removed, but uninitialized reads are still checked.
+        let function = compile("var a, b; b = 3 + 4; return a", &HashMap::default());
+        assert!(ops(&function).contains(&code::Op::Read));
+        assert!(!reals(&function).contains(&7.0));
+    }
+
+    #[test]
+    fn branches() {
+        let source = "var a; if (1 < 2) { a = 1 } else { a = 2 } return a";
+        let function = compile(source, &HashMap::default());
+        assert_eq!(ops(&function), [code::Op::Const, code::Op::Ret]);
+        assert_eq!(reals(&function), [1.0]);
+
+        // A local that has the same value on every path is a constant after the branch.
+        let source = "var a; if (x) { a = 5 } else { a = 5 } return a * 2";
+        let function = compile(source, &HashMap::default());
+        assert!(ops(&function).contains(&code::Op::BranchFalse));
+        assert!(!ops(&function).contains(&code::Op::Mul));
+        assert!(reals(&function).contains(&10.0));
+    }
+
+    #[test]
+    fn project_constants() {
+        let mut constants = HashMap::default();
+        constants.insert(0, Constant::Real(10.0));
+        let function = compile("show_message(first + 1); return second", &constants);
+        assert!(reals(&function).contains(&11.0));
+        assert_eq!(ops(&function).iter().filter(|&&op| op == code::Op::GlobalConst).count(), 1);
+
+        let function = compile("return first * 2", &HashMap::default());
+        assert!(ops(&function).contains(&code::Op::GlobalConst));
+        assert!(ops(&function).contains(&code::Op::Mul));
+    }
+}
@@ -383,6 +383,37 @@ impl Function {
             Branch { ref mut args, .. } => &mut args[..],
         }
     }
+
+    /// Update the uses of every instruction to skip over any `Alias`es.
+    pub fn resolve_aliases(&mut self) {
+        for block in self.blocks.keys() {
+            for i in 0..self.blocks[block].instructions.len() {
+                let value = self.blocks[block].instructions[i];
+                for j in 0..self.uses(value).len() {
+                    let arg = self.uses(value)[j];
+                    let resolved = self.resolve_alias(arg);
+                    if arg != resolved {
+                        self.uses_mut(value)[j] = resolved;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Follow a chain of `Alias`es to the value they forward to.
+    pub fn resolve_alias(&self, value: Value) -> Value {
+        let mut v = value;
+        let mut i = self.values.len();
+        while let Instruction::Alias { arg: original } = self.values[v] {
+            v = original;
+
+            i -= 1;
+            if i == 0 {
+                panic!("alias loop")
+            }
+        }
+        v
+    }
 }
 
 #[derive(Clone, PartialEq, Eq, Hash)]
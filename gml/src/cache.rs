@@ -0,0 +1,424 @@
+//! Compiled code saved between builds, to skip compiling functions that have not changed.
+//!
+//! Each function is stored with a hash of its source. The code it compiles to also depends on
+//! the names it refers to, the values of project constants, and the game's settings, so the
+//! cache records those too and is cleared whenever they change. Constants themselves are always
+//! recompiled, because later code may depend on their values.
+//!
+//! Functions that report any diagnostics are not cached, so their warnings are shown on every
+//! build. Unassigned instance variables are only found once every function is known, so cached
+//! functions keep the fields they read and write.
+
+use std::collections::{HashMap, HashSet};
+use std::hash::Hasher;
+use std::io::{self, Read, Write};
+
+use crate::{Function, Code};
+use crate::symbol::Symbol;
+use crate::diagnostic::{Diagnostic, Emit};
+use crate::front;
+use crate::back::{self, ssa};
+use crate::vm::{self, code};
+use crate::vm::serialize::{read_u8, read_u32, read_u64, read_symbol, write_symbol};
+use crate::vm::serialize::{read_value, write_value};
+
+#[derive(Default)]
+pub struct Cache {
+    prototypes: HashMap<Symbol, ssa::Prototype>,
+    constants: HashMap<i32, vm::Value>,
+    uninitialized_zero: bool,
+    entries: HashMap<Function, Entry>,
+}
+
+/// A function as compiled from source with the hash `hash`.
+#[derive(Clone)]
+pub(crate) struct Entry {
+    pub hash: u64,
+    pub code: code::Function,
+    pub locations: vm::Locations,
+    pub fields: front::Fields,
+}
+
+const MAGIC: &[u8; 4] = b"DJVC";
+const VERSION: u32 = 2;
+/// A fingerprint of the compiler's source. Other builds may compile the same code differently.
+const BUILD: &str = env!("GML_BUILD");
+
+const EVENT: u8 = 0;
+const SCRIPT: u8 = 1;
+const ROOM: u8 = 2;
+const INSTANCE: u8 = 3;
+const TIMELINE: u8 = 4;
+
+impl Cache {
+    /// Clear the cache if it was built against different names, constants or settings.
+    pub(crate) fn validate(
+        &mut self,
+        prototypes: &HashMap<Symbol, ssa::Prototype>,
+        constants: &HashMap<i32, back::Constant>,
+        uninitialized_zero: bool,
+    ) {
+        let constants: HashMap<_, _> = constants.iter()
+            .map(|(&id, &constant)| (id, vm::Value::from(constant)))
+            .collect();
+        if
+            self.prototypes != *prototypes ||
+            self.constants != constants ||
+            self.uninitialized_zero != uninitialized_zero
+        {
+            self.entries.clear();
+        }
+        self.prototypes = prototypes.clone();
+        self.constants = constants;
+        self.uninitialized_zero = uninitialized_zero;
+    }
+
+    pub(crate) fn get(&self, function: Function, hash: u64) -> Option<&Entry> {
+        self.entries.get(&function).filter(|entry| entry.hash == hash)
+    }
+
+    pub(crate) fn insert(&mut self, function: Function, entry: Entry) {
+        self.entries.insert(function, entry);
+    }
+
+    /// Forget functions that are no longer in the project.
+    pub(crate) fn retain(&mut self, code: &HashMap<Function, code::Function>) {
+        self.entries.retain(|function, _| code.contains_key(function));
+    }
+
+    pub fn read<R: Read>(read: &mut R) -> io::Result<Cache> {
+        let mut magic = [0; 4];
+        read.read_exact(&mut magic)?;
+        if &magic != MAGIC || read_u32(read)? != VERSION {
+            return Err(io::Error::from(io::ErrorKind::InvalidData));
+        }
+        let mut build = [0; BUILD.len()];
+        read.read_exact(&mut build)?;
+        if build != *BUILD.as_bytes() {
+            return Err(io::Error::from(io::ErrorKind::InvalidData));
+        }
+
+        let uninitialized_zero = read_u8(read)? != 0;
+        let len = read_u32(read)? as usize;
+        let mut prototypes = HashMap::default();
+        for _ in 0..len {
+            let name = read_symbol(read)?;
+            prototypes.insert(name, ssa::Prototype::read(read)?);
+        }
+        let len = read_u32(read)? as usize;
+        let mut constants = HashMap::default();
+        for _ in 0..len {
+            let id = read_u32(read)? as i32;
+            constants.insert(id, read_value(read)?);
+        }
+
+        let len = read_u32(read)? as usize;
+        let mut entries = HashMap::default();
+        for _ in 0..len {
+            let function = read_function(read)?;
+            let hash = read_u64(read)?;
+            let code = code::Function::read(read)?;
+            let locations = vm::Locations::read(read)?;
+            let fields = read_fields(read)?;
+            entries.insert(function, Entry { hash, code, locations, fields });
+        }
+
+        Ok(Cache { prototypes, constants, uninitialized_zero, entries })
+    }
+
+    pub fn write<W: Write>(&self, write: &mut W) -> io::Result<()> {
+        write.write_all(MAGIC)?;
+        write.write_all(&VERSION.to_le_bytes())?;
+        write.write_all(BUILD.as_bytes())?;
+
+        write.write_all(&[self.uninitialized_zero as u8])?;
+        write.write_all(&(self.prototypes.len() as u32).to_le_bytes())?;
+        for (&name, prototype) in &self.prototypes {
+            write_symbol(write, name)?;
+            prototype.write(write)?;
+        }
+        write.write_all(&(self.constants.len() as u32).to_le_bytes())?;
+        for (&id, value) in &self.constants {
+            write.write_all(&id.to_le_bytes())?;
+            write_value(write, value)?;
+        }
+
+        write.write_all(&(self.entries.len() as u32).to_le_bytes())?;
+        for (&function, Entry { hash, code, locations, fields }) in &self.entries {
+            write_function(write, function)?;
+            write.write_all(&hash.to_le_bytes())?;
+            code.write(write)?;
+            locations.write(write)?;
+            write_fields(write, fields)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Hash the source of a function.
+///
+/// This uses FNV-1a rather than the standard library's hasher, whose output may change between
+/// versions of Rust, so the same source always has the same hash.
+pub(crate) fn hash(code: Code<'_>) -> u64 {
+    let mut hasher = Fnv::default();
+    match code {
+        Code::Text(text) => write_bytes(&mut hasher, text),
+        Code::Actions(actions) => {
+            hasher.write_u32(actions.len() as u32);
+            for action in actions {
+                let project::Action {
+                    library, action, action_kind, has_relative, is_question, has_target,
+                    action_type, name, code, parameters_used, ref parameters, target, relative,
+                    ref arguments, negate,
+                } = *action;
+                hasher.write_u32(library);
+                hasher.write_u32(action);
+                hasher.write_u32(action_kind);
+                hasher.write(&[has_relative as u8, is_question as u8, has_target as u8]);
+                hasher.write_u32(action_type);
+                write_bytes(&mut hasher, name);
+                write_bytes(&mut hasher, code);
+                hasher.write_u32(parameters_used);
+                hasher.write_u32(parameters.len() as u32);
+                for &parameter in parameters {
+                    hasher.write_u32(parameter);
+                }
+                hasher.write_i32(target);
+                hasher.write(&[relative as u8, negate as u8]);
+                hasher.write_u32(arguments.len() as u32);
+                for &argument in arguments {
+                    write_bytes(&mut hasher, argument);
+                }
+            }
+        }
+    }
+    hasher.finish()
+}
+
+fn write_bytes(hasher: &mut Fnv, bytes: &[u8]) {
+    hasher.write_u32(bytes.len() as u32);
+    hasher.write(bytes);
+}
+
+struct Fnv(u64);
+
+impl Default for Fnv {
+    fn default() -> Fnv { Fnv(0xcbf2_9ce4_8422_2325) }
+}
+
+impl Hasher for Fnv {
+    fn finish(&self) -> u64 { self.0 }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 ^= byte as u64;
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    fn write_u32(&mut self, i: u32) { self.write(&i.to_le_bytes()) }
+
+    fn write_i32(&mut self, i: i32) { self.write(&i.to_le_bytes()) }
+}
+
+/// Count every diagnostic, including warnings, to find functions that should not be cached.
+pub(crate) struct Count<E> {
+    pub emit: E,
+    pub count: u32,
+}
+
+impl<E: Emit> Emit for Count<E> {
+    fn emit(&mut self, diagnostic: Diagnostic) {
+        self.count += 1;
+        self.emit.emit(diagnostic);
+    }
+}
+
+fn read_function<R: Read>(read: &mut R) -> io::Result<Function> {
+    let function = match read_u8(read)? {
+        EVENT => {
+            let object_index = read_u32(read)? as i32;
+            let event_type = read_u32(read)?;
+            let event_kind = read_u32(read)? as i32;
+            Function::Event { object_index, event_type, event_kind }
+        }
+        SCRIPT => Function::Script { id: read_u32(read)? as i32 },
+        ROOM => Function::Room { id: read_u32(read)? as i32 },
+        INSTANCE => Function::Instance { id: read_u32(read)? as i32 },
+        TIMELINE => {
+            let id = read_u32(read)? as i32;
+            let moment = read_u32(read)? as i32;
+            Function::Timeline { id, moment }
+        }
+        _ => return Err(io::Error::from(io::ErrorKind::InvalidData)),
+    };
+
+    Ok(function)
+}
+
+fn write_function<W: Write>(write: &mut W, function: Function) -> io::Result<()> {
+    match function {
+        Function::Event { object_index, event_type, event_kind } => {
+            write.write_all(&[EVENT])?;
+            write.write_all(&object_index.to_le_bytes())?;
+            write.write_all(&event_type.to_le_bytes())?;
+            write.write_all(&event_kind.to_le_bytes())?;
+        }
+        Function::Script { id } => {
+            write.write_all(&[SCRIPT])?;
+            write.write_all(&id.to_le_bytes())?;
+        }
+        Function::Room { id } => {
+            write.write_all(&[ROOM])?;
+            write.write_all(&id.to_le_bytes())?;
+        }
+        Function::Instance { id } => {
+            write.write_all(&[INSTANCE])?;
+            write.write_all(&id.to_le_bytes())?;
+        }
+        Function::Timeline { id, moment } => {
+            write.write_all(&[TIMELINE])?;
+            write.write_all(&id.to_le_bytes())?;
+            write.write_all(&moment.to_le_bytes())?;
+        }
+        // Constants and strings are never cached.
+        Function::Constant { .. } | Function::String { .. } =>
+            return Err(io::Error::from(io::ErrorKind::InvalidInput)),
+    }
+
+    Ok(())
+}
+
+fn read_fields<R: Read>(read: &mut R) -> io::Result<front::Fields> {
+    let len = read_u32(read)? as usize;
+    let mut reads = Vec::default();
+    for _ in 0..len {
+        let symbol = read_symbol(read)?;
+        reads.push((symbol, read_u32(read)? as usize));
+    }
+    let len = read_u32(read)? as usize;
+    let mut writes = HashSet::default();
+    for _ in 0..len {
+        writes.insert(read_symbol(read)?);
+    }
+
+    Ok(front::Fields { reads, writes })
+}
+
+fn write_fields<W: Write>(write: &mut W, fields: &front::Fields) -> io::Result<()> {
+    write.write_all(&(fields.reads.len() as u32).to_le_bytes())?;
+    for &(symbol, location) in &fields.reads[..] {
+        write_symbol(write, symbol)?;
+        write.write_all(&(location as u32).to_le_bytes())?;
+    }
+    write.write_all(&(fields.writes.len() as u32).to_le_bytes())?;
+    for &symbol in &fields.writes {
+        write_symbol(write, symbol)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::io;
+    use bstr::BStr;
+    use crate::{Item, Function};
+    use crate::symbol::Symbol;
+    use crate::vm;
+    use super::Cache;
+
+    fn build(game: &project::Game<'_>, cache: &mut Cache) -> vm::Assets<()> {
+        let items: HashMap<Symbol, Item<()>> = HashMap::default();
+        let (assets, _) = crate::build_cached(game, &[], &items, cache, io::sink).unwrap();
+        assets
+    }
+
+    #[test]
+    fn reuse() {
+        let mut game = project::Game::default();
+        game.constants.push(project::Constant { name: BStr::new(b"c"), value: BStr::new(b"3") });
+        game.scripts.push(project::Script { name: BStr::new(b"a"), body: BStr::new(b"{
+            return c + 1
+        }") });
+        game.scripts.push(project::Script { name: BStr::new(b"b"), body: BStr::new(b"{
+            return \"b\"
+        }") });
+        let a = Function::Script { id: 0 };
+        let b = Function::Script { id: 1 };
+
+        // Mark the cached code, to tell whether it is reused.
+        let mut cache = Cache::default();
+        build(&game, &mut cache);
+        for entry in cache.entries.values_mut() {
+            entry.code.locals = 99;
+        }
+
+        let assets = build(&game, &mut cache);
+        assert_eq!(assets.code[&a].locals, 99);
+        assert_eq!(assets.code[&b].locals, 99);
+
+        game.scripts[1].body = BStr::new(b"{ return \"c\" }");
+        let assets = build(&game, &mut cache);
+        assert_eq!(assets.code[&a].locals, 99);
+        assert_ne!(assets.code[&b].locals, 99);
+
+        game.constants[0].value = BStr::new(b"4");
+        let assets = build(&game, &mut cache);
+        assert_ne!(assets.code[&a].locals, 99);
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut game = project::Game::default();
+        game.scripts.push(project::Script { name: BStr::new(b"main"), body: BStr::new(b"{
+            var s;
+            s = argument0 * 1.5 + 2
+            field = s
+            return s + field + \"hello\"
+        }") });
+        let mut cache = Cache::default();
+        build(&game, &mut cache);
+
+        let mut data = Vec::default();
+        cache.write(&mut data).unwrap();
+        let read = Cache::read(&mut &data[..]).unwrap();
+        assert!(read.prototypes == cache.prototypes);
+        assert!(read.constants == cache.constants);
+        assert_eq!(read.entries.len(), 1);
+
+        let function = Function::Script { id: 0 };
+        let (entry, expected) = (&read.entries[&function], &cache.entries[&function]);
+        assert_eq!(entry.hash, expected.hash);
+        assert_eq!(format!("{:?}", entry.code), format!("{:?}", expected.code));
+        assert_eq!(entry.locations.lines.lines, expected.locations.lines.lines);
+        assert_eq!(entry.fields.reads, expected.fields.reads);
+        assert_eq!(entry.fields.writes, expected.fields.writes);
+    }
+
+    #[test]
+    fn corrupt() {
+        let mut data = Vec::default();
+        Cache::default().write(&mut data).unwrap();
+        let header = data.len() - 13;
+
+        // Lengths are read from the file, so a huge one must run out of data rather than memory.
+        for at in [header + 1, header + 5, header + 9] {
+            let mut data = data.clone();
+            data[at..at + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+            let error = Cache::read(&mut &data[..]).err().unwrap();
+            assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+        }
+
+        let mut data = data[..header].to_vec();
+        data.extend_from_slice(&[0, 1, 0, 0, 0, 0xff, 0xff, 0xff, 0xff]);
+        let error = Cache::read(&mut &data[..]).err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+
+        data[4] += 1;
+        let error = Cache::read(&mut &data[..]).err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+}
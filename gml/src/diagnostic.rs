@@ -0,0 +1,271 @@
+//! Diagnostics produced while building and running GML.
+//!
+//! A `Diagnostic` records what went wrong and where, independently of how it is shown. Sinks
+//! implementing `Emit` decide that: `Text` renders it for a terminal with an excerpt of the
+//! offending line, `Json` renders one object per line for editors and CI, and a `Vec` simply
+//! collects them.
+
+use std::{fmt, io};
+
+use crate::FunctionDisplay;
+use crate::front::{Lines, Position, Span};
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+#[derive(Clone, Debug)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// A short, stable name for the kind of problem, like `syntax` or `unknown-function`.
+    pub code: &'static str,
+    pub message: String,
+    pub notes: Vec<String>,
+    pub location: Option<Location>,
+}
+
+/// Where in a game a diagnostic applies.
+#[derive(Clone, Debug)]
+pub struct Location {
+    pub function: FunctionDisplay,
+    pub span: Span,
+    pub position: Position,
+    /// The line of code containing the start of `span`, when the source is available.
+    pub excerpt: Option<String>,
+}
+
+/// A destination for diagnostics.
+pub trait Emit {
+    fn emit(&mut self, diagnostic: Diagnostic);
+}
+
+/// Write diagnostics as human-readable text.
+pub struct Text<W>(pub W);
+
+/// Write diagnostics as JSON, one object per line.
+pub struct Json<W>(pub W);
+
+impl Diagnostic {
+    pub fn new(severity: Severity, code: &'static str, message: String) -> Diagnostic {
+        Diagnostic { severity, code, message, notes: Vec::default(), location: None }
+    }
+
+    pub fn write_text(&self, write: &mut dyn io::Write) -> io::Result<()> {
+        let Diagnostic { severity, ref message, ref location, .. } = *self;
+        match *location {
+            Some(ref location) => writeln!(write, "{} in {}: {}", severity, location, message)?,
+            None => writeln!(write, "{}: {}", severity, message)?,
+        }
+
+        if let Some(Location {
+            span,
+            position: Position { line: Some(line), column: Some(column), .. },
+            excerpt: Some(ref excerpt),
+            ..
+        }) = *location {
+            // Line up the carets with the excerpt, including any tabs before them.
+            let mut start = usize::min(column - 1, excerpt.len());
+            while !excerpt.is_char_boundary(start) { start -= 1; }
+            let mut end = usize::min(start + (span.high - span.low), excerpt.len());
+            while !excerpt.is_char_boundary(end) { end += 1; }
+            let indent: String = excerpt[..start].chars()
+                .map(|c| if c == '\t' { '\t' } else { ' ' })
+                .collect();
+            let carets = "^".repeat(usize::max(1, excerpt[start..end].chars().count()));
+
+            let line = line.to_string();
+            writeln!(write, "{} | {}", line, excerpt)?;
+            writeln!(write, "{:width$} | {}{}", "", indent, carets, width = line.len())?;
+        }
+
+        for note in &self.notes {
+            writeln!(write, "  note: {}", note)?;
+        }
+        Ok(())
+    }
+
+    pub fn write_json(&self, write: &mut dyn io::Write) -> io::Result<()> {
+        write!(write, "{{\"severity\":\"{}\",\"code\":", self.severity)?;
+        write!(write, "{}", JsonString(self.code))?;
+        write!(write, ",\"message\":")?;
+        write!(write, "{}", JsonString(&self.message))?;
+        write!(write, ",\"notes\":[")?;
+        for (i, note) in self.notes.iter().enumerate() {
+            if i > 0 { write!(write, ",")?; }
+            write!(write, "{}", JsonString(note))?;
+        }
+        write!(write, "],\"location\":")?;
+        match self.location {
+            Some(Location { function, span, ref position, ref excerpt }) => {
+                write!(write, "{{\"function\":")?;
+                write!(write, "{}", JsonString(&function.to_string()))?;
+                let Position { action, argument, line, column } = *position;
+                let fields = [("action", action), ("argument", argument), ("line", line)];
+                for (name, value) in fields.into_iter().chain([("column", column)]) {
+                    match value {
+                        Some(value) => write!(write, ",\"{}\":{}", name, value)?,
+                        None => write!(write, ",\"{}\":null", name)?,
+                    }
+                }
+                write!(write, ",\"low\":{},\"high\":{},\"excerpt\":", span.low, span.high)?;
+                match *excerpt {
+                    Some(ref excerpt) => write!(write, "{}", JsonString(excerpt))?,
+                    None => write!(write, "null")?,
+                }
+                write!(write, "}}")?;
+            }
+            None => write!(write, "null")?,
+        }
+        writeln!(write, "}}")
+    }
+}
+
+impl Location {
+    pub fn new(function: FunctionDisplay, lines: &Lines, source: Option<&[u8]>, span: Span) ->
+        Location
+    {
+        let position = lines.get_position(span.low);
+        let excerpt = Option::zip(source, position.column).and_then(|(source, column)| {
+            let line = source.get(span.low + 1 - column..)?;
+            let line = line.split(|&c| c == b'\n').next()?;
+            let line = line.strip_suffix(b"\r").unwrap_or(line);
+            Some(String::from_utf8_lossy(line).into_owned())
+        });
+        Location { function, span, position, excerpt }
+    }
+}
+
+impl<E: Emit + ?Sized> Emit for &mut E {
+    fn emit(&mut self, diagnostic: Diagnostic) { E::emit(self, diagnostic) }
+}
+
+impl Emit for Vec<Diagnostic> {
+    fn emit(&mut self, diagnostic: Diagnostic) { self.push(diagnostic) }
+}
+
+/// Pass on errors, discarding warnings.
+pub struct ErrorsOnly<E>(pub E);
+
+impl<E: Emit> Emit for ErrorsOnly<E> {
+    fn emit(&mut self, diagnostic: Diagnostic) {
+        if diagnostic.severity == Severity::Error { self.0.emit(diagnostic) }
+    }
+}
+
+/// Discard diagnostics.
+impl Emit for io::Sink {
+    fn emit(&mut self, _: Diagnostic) {}
+}
+
+impl<W: io::Write> Emit for Text<W> {
+    fn emit(&mut self, diagnostic: Diagnostic) { let _ = diagnostic.write_text(&mut self.0); }
+}
+
+impl<W: io::Write> Emit for Json<W> {
+    fn emit(&mut self, diagnostic: Diagnostic) { let _ = diagnostic.write_json(&mut self.0); }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Position { action, argument, line, column } = self.position;
+        write!(f, "{}", self.function)?;
+        if let Some(action) = action {
+            write!(f, ", action {}", action)?;
+        }
+        if let (Some(argument), None) = (argument, line) {
+            write!(f, ", argument {}", argument)?;
+        }
+        if let Some(line) = line {
+            write!(f, ":{}", line)?;
+        }
+        if let Some(column) = column {
+            write!(f, ":{}", column)?;
+        }
+        Ok(())
+    }
+}
+
+/// A string quoted and escaped for JSON.
+pub struct JsonString<'a>(pub &'a str);
+
+impl fmt::Display for JsonString<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "\"")?;
+        for c in self.0.chars() {
+            match c {
+                '"' => write!(f, "\\\"")?,
+                '\\' => write!(f, "\\\\")?,
+                '\n' => write!(f, "\\n")?,
+                '\r' => write!(f, "\\r")?,
+                '\t' => write!(f, "\\t")?,
+                c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+                c => write!(f, "{}", c)?,
+            }
+        }
+        write!(f, "\"")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str;
+
+    use crate::symbol::Symbol;
+    use crate::front::{Lines, Span};
+    use crate::{FunctionDisplay, ErrorPrinter};
+    use super::{Diagnostic, Severity, Emit, Text, Json};
+
+    fn report(source: &[u8], span: Span, notes: Vec<String>) -> Diagnostic {
+        let name = FunctionDisplay::Script { script: Symbol::intern(b"scr_test") };
+        let lines = Lines::from_code(source);
+        let mut diagnostics = Vec::default();
+        let mut errors = ErrorPrinter::new(name, &lines, &mut diagnostics).with_source(source);
+        let message = format_args!("bad");
+        ErrorPrinter::report(&mut errors, Severity::Error, "test", span, message, notes);
+        assert_eq!(errors.count, 1);
+        diagnostics.pop().unwrap()
+    }
+
+    #[test]
+    fn text() {
+        let source = b"a = 1;\r\n\tb = foo + 2;\r\n";
+        let notes = vec![String::from("called from somewhere")];
+        let diagnostic = report(source, Span { low: 13, high: 16 }, notes);
+
+        let mut text = Vec::default();
+        Text(&mut text).emit(diagnostic);
+        assert_eq!(str::from_utf8(&text).unwrap(), concat!(
+            "error in script scr_test:2:6: bad\n",
+            "2 | \tb = foo + 2;\n",
+            "  | \t    ^^^\n",
+            "  note: called from somewhere\n",
+        ));
+    }
+
+    #[test]
+    fn json() {
+        let diagnostic = report(b"x = \"\n", Span { low: 4, high: 5 }, vec![]);
+
+        let mut json = Vec::default();
+        Json(&mut json).emit(diagnostic);
+        Json(&mut json).emit(Diagnostic::new(Severity::Warning, "image", String::from("a\tb")));
+        assert_eq!(str::from_utf8(&json).unwrap(), concat!(
+            r#"{"severity":"error","code":"test","message":"bad","notes":[],"#,
+            r#""location":{"function":"script scr_test","action":null,"argument":null,"#,
+            r#""line":1,"column":5,"low":4,"high":5,"excerpt":"x = \""}}"#, "\n",
+            r#"{"severity":"warning","code":"image","message":"a\tb","notes":[],"#,
+            r#""location":null}"#, "\n",
+        ));
+    }
+}
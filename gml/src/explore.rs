@@ -0,0 +1,296 @@
+//! Show each stage of compiling a single function, to debug the compiler.
+//!
+//! Code is parsed into an AST, lowered to SSA form, optimized, and finally allocated registers
+//! and encoded as bytecode. This prints any of those forms for one script, event, timeline moment
+//! or room creation code, along with its control flow graph in Graphviz's dot format.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::io;
+
+use crate::{Item, Code, ErrorPrinter, FunctionDisplay, EventDisplay};
+use crate::symbol::Symbol;
+use crate::diagnostic::Emit;
+use crate::front::{self, Lexer, Parser, ActionParser, Lines, Position};
+use crate::back::{self, ssa, ControlFlow};
+use crate::handle_map::Handle;
+
+/// Which forms of the function to print.
+#[derive(Copy, Clone)]
+pub struct Stages {
+    /// The syntax tree, as parsed.
+    pub ast: bool,
+    /// The SSA form, both as generated and after optimization.
+    pub ssa: bool,
+    /// The bytecode, with the source location of each instruction.
+    pub code: bool,
+    /// The control flow graph between reachable basic blocks, as Graphviz source.
+    pub cfg: bool,
+}
+
+impl Stages {
+    pub fn none() -> Stages { Stages { ast: false, ssa: false, code: false, cfg: false } }
+}
+
+impl Default for Stages {
+    fn default() -> Stages { Stages { ast: true, ssa: true, code: true, cfg: true } }
+}
+
+/// Compile a single function from `game`, writing the chosen stages to `write`.
+///
+/// The function is named by its script or room, or by an object and event separated by colons,
+/// like `obj_player:step`, `obj_player:alarm:1` or `obj_wall:collision:obj_player`, or by a
+/// timeline and moment like `tl_intro:30`.
+///
+/// Returns the number of errors in the function, which stop it from being compiled further.
+pub fn explore<W, E: Emit>(
+    game: &project::Game<'_>,
+    extensions: &[project::Extension<'_>],
+    runner: &HashMap<Symbol, Item<W>>,
+    function: &str,
+    stages: Stages,
+    errors: E,
+    write: &mut dyn io::Write,
+) -> io::Result<u32> {
+    let message = || format!("no script, event or room {function}");
+    let (name, code) = find(game, function)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, message()))?;
+
+    let prototypes = crate::prototypes(game, extensions, runner);
+    let mut constants = HashMap::default();
+    for (id, &project::Constant { name, value }) in game.constants.iter().enumerate() {
+        let name = FunctionDisplay::Constant { constant: Symbol::intern(name) };
+        let (_, _, value, _) =
+            crate::compile_constant(&prototypes, &constants, name, value, io::sink());
+        if let Some(value) = value {
+            constants.insert(id as i32, value);
+        }
+    }
+
+    let (lines, source) = match code {
+        Code::Text(text) => (Lines::from_code(text), Cow::Borrowed(text)),
+        Code::Actions(actions) =>
+            (Lines::from_actions(actions), Cow::Owned(Lines::source_from_actions(actions))),
+    };
+    let mut errors = ErrorPrinter::new(name, &lines, errors).with_source(&source);
+
+    let mut program = match code {
+        Code::Text(text) => {
+            let program = Parser::new(Lexer::new(text, 0), &mut errors).parse_program();
+            if stages.ast {
+                writeln!(write, "// ast\n{:#?}", program)?;
+            }
+            if errors.count > 0 {
+                return Ok(errors.count);
+            }
+            front::Codegen::new(&prototypes, &mut errors).compile_program(&program)
+        }
+        Code::Actions(actions) => {
+            let program = ActionParser::new(actions.iter(), &mut errors).parse_event();
+            if stages.ast {
+                writeln!(write, "// ast\n{:#?}", program)?;
+            }
+            if errors.count > 0 {
+                return Ok(errors.count);
+            }
+            front::Codegen::new(&prototypes, &mut errors).compile_event(&program)
+        }
+    };
+    if errors.count > 0 {
+        return Ok(errors.count);
+    }
+
+    if stages.ssa {
+        writeln!(write, "// ssa\n{:?}", program)?;
+    }
+    back::optimize(&mut program, &constants);
+    if stages.ssa {
+        writeln!(write, "// optimized ssa\n{:?}", program)?;
+    }
+
+    if stages.code {
+        let (function, locations) = back::Codegen::new(&prototypes).compile(&program);
+        writeln!(write, "// code")?;
+        writeln!(write, "params {}, locals {}", function.params, function.locals)?;
+        for offset in 0..function.instructions.len() {
+            let location = locations.get_location(offset as u32) as usize;
+            let position = display_position(lines.get_position(location));
+            writeln!(write, "{:4} {:>12}  {}", offset, position, function.disassemble(offset))?;
+        }
+    }
+
+    if stages.cfg {
+        writeln!(write, "// cfg")?;
+        write_dot(&program, name, write)?;
+    }
+
+    Ok(0)
+}
+
+/// Event names accepted in place of their `project::event_type` numbers.
+const EVENTS: [&str; 12] = [
+    "create", "destroy", "alarm", "step", "collision", "keyboard",
+    "mouse", "other", "draw", "key_press", "key_release", "trigger",
+];
+
+/// Find the function named by `function`, as described by `explore`.
+fn find<'a>(game: &'a project::Game<'_>, function: &str) -> Option<(FunctionDisplay, Code<'a>)> {
+    let mut parts = function.split(':');
+    let name = parts.next()?.as_bytes();
+    let parts: Vec<_> = parts.collect();
+
+    if parts.is_empty() {
+        if let Some(script) = game.scripts.iter().find(|it| it.name == name) {
+            let script_name = FunctionDisplay::Script { script: Symbol::intern(script.name) };
+            return Some((script_name, Code::Text(script.body)));
+        }
+        let room = game.rooms.iter().find(|it| it.name == name)?;
+        let room_name = FunctionDisplay::Room { room: Symbol::intern(room.name) };
+        return Some((room_name, Code::Text(room.code)));
+    }
+
+    if let Some(timeline) = game.timelines.iter().find(|it| it.name == name) {
+        let [moment] = parts[..] else { return None };
+        let moment: u32 = moment.parse().ok()?;
+        let project::Moment { ref actions, .. } =
+            *timeline.moments.iter().find(|it| it.moment == moment)?;
+        let timeline = Symbol::intern(timeline.name);
+        let name = FunctionDisplay::Timeline { timeline, moment: moment as i32 };
+        return Some((name, Code::Actions(actions)));
+    }
+
+    let object = game.objects.iter().find(|it| it.name == name)?;
+    let (event_type, event_kind) = match parts[..] {
+        [event_type] => (event_type, None),
+        [event_type, event_kind] => (event_type, Some(event_kind)),
+        _ => return None,
+    };
+    let event_type = match EVENTS.iter().position(|&it| it == event_type) {
+        Some(event_type) => event_type as u32,
+        None => event_type.parse().ok()?,
+    };
+    let event_kind = match event_kind {
+        None => 0,
+        Some(kind) => match kind.parse() {
+            Ok(kind) => kind,
+            Err(_) => game.objects.iter().position(|it| it.name == kind.as_bytes())? as i32,
+        },
+    };
+
+    let project::Event { ref actions, .. } = *object.events.iter()
+        .find(|it| it.event_type == event_type && it.event_kind == event_kind)?;
+    let object = Symbol::intern(object.name);
+    let event_kind = match event_type {
+        project::event_type::COLLISION => game.objects.get(event_kind as usize)
+            .map_or(EventDisplay::Id(event_kind), |other| {
+                EventDisplay::Name(Symbol::intern(other.name))
+            }),
+        _ => EventDisplay::Id(event_kind),
+    };
+    Some((FunctionDisplay::Event { object, event_type, event_kind }, Code::Actions(actions)))
+}
+
+fn display_position(position: Position) -> String {
+    let Position { action, line, column, .. } = position;
+    let mut display = String::new();
+    if let Some(action) = action {
+        display.push_str(&format!("{}/", action));
+    }
+    if let (Some(line), Some(column)) = (line, column) {
+        display.push_str(&format!("{}:{}", line, column));
+    }
+    display
+}
+
+/// Write the reachable blocks of a function and the edges between them, as a dot graph.
+fn write_dot(
+    program: &ssa::Function, name: FunctionDisplay, write: &mut dyn io::Write
+) -> io::Result<()> {
+    let control_flow = ControlFlow::compute(program);
+    let reachable = control_flow.reachable();
+
+    let name = name.to_string().replace('\\', "\\\\").replace('"', "\\\"");
+    writeln!(write, "digraph \"{}\" {{", name)?;
+    writeln!(write, "    node [shape=box, fontname=monospace];")?;
+    for block in program.blocks.keys() {
+        if !reachable.get(block.index()) {
+            continue;
+        }
+
+        let instructions = program.blocks[block].instructions.len();
+        writeln!(write, "    b{0} [label=\"b{0} ({1})\"];", block.index(), instructions)?;
+        let labels: &[&str] = match program.values[program.terminator(block)] {
+            ssa::Instruction::Branch { .. } => &["true", "false"],
+            _ => &[""],
+        };
+        for (&succ, &label) in Iterator::zip(control_flow.succ[block].iter(), labels) {
+            write!(write, "    b{} -> b{}", block.index(), succ.index())?;
+            if !label.is_empty() {
+                write!(write, " [label={}]", label)?;
+            }
+            writeln!(write, ";")?;
+        }
+    }
+    writeln!(write, "}}")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::io;
+    use bstr::BStr;
+    use crate::Item;
+    use crate::symbol::Symbol;
+    use super::{Stages, explore};
+
+    #[test]
+    fn control_flow() {
+        let mut game = project::Game::default();
+        game.scripts.push(project::Script { name: BStr::new(b"main"), body: BStr::new(b"{
+            if argument0 { return 1 }
+            return 2
+        }") });
+        let items: HashMap<Symbol, Item<()>> = HashMap::default();
+
+        let stages = Stages { cfg: true, ..Stages::none() };
+        let mut write = Vec::default();
+        let errors = explore(&game, &[], &items, "main", stages, io::sink(), &mut write).unwrap();
+        assert_eq!(errors, 0);
+        assert_eq!(String::from_utf8(write).unwrap(), "\
+// cfg
+digraph \"script main\" {
+    node [shape=box, fontname=monospace];
+    b0 [label=\"b0 (2)\"];
+    b0 -> b1 [label=true];
+    b0 -> b2 [label=false];
+    b1 [label=\"b1 (2)\"];
+    b2 [label=\"b2 (2)\"];
+}
+");
+
+        let mut write = Vec::default();
+        let error = explore(&game, &[], &items, "obj:step", stages, io::sink(), &mut write);
+        assert_eq!(error.unwrap_err().kind(), io::ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn deleted_collision() {
+        let mut game = project::Game::default();
+        game.objects.push(project::Object {
+            name: BStr::new(b"obj"),
+            events: vec![project::Event {
+                event_type: project::event_type::COLLISION,
+                event_kind: 3,
+                actions: vec![],
+            }],
+            ..project::Object::default()
+        });
+        let items: HashMap<Symbol, Item<()>> = HashMap::default();
+
+        let stages = Stages { code: true, ..Stages::none() };
+        let mut write = Vec::default();
+        let errors = explore(&game, &[], &items, "obj:collision:3", stages, io::sink(), &mut write);
+        assert_eq!(errors.unwrap(), 0);
+        assert!(String::from_utf8(write).unwrap().starts_with("// code"));
+    }
+}
@@ -1,6 +1,7 @@
 use crate::symbol::Symbol;
 use crate::front::{ast, Span};
 
+#[derive(Debug)]
 pub enum Action {
     Error,
     Normal {
@@ -30,17 +31,20 @@ pub enum Action {
     },
 }
 
+#[derive(Debug)]
 pub struct Question {
     pub negate: bool,
     pub true_action: (Action, Span),
     pub false_action: Option<(Action, Span)>,
 }
 
+#[derive(Debug)]
 pub enum Exec {
     Function(Symbol),
     Code(Box<(ast::Stmt, Span)>),
 }
 
+#[derive(Debug)]
 pub enum Argument {
     Error,
     Expr(Box<(ast::Expr, Span)>),
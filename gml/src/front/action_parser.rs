@@ -77,7 +77,7 @@ impl<'s, 'e, 'f> ActionParser<'s, 'e, 'f> {
                 let span = self.span;
                 self.advance_action();
 
-                self.errors.error(span, format_args!("unexpected action kind"));
+                self.errors.error("action", span, format_args!("unexpected action kind"));
                 (ast::Action::Error, span)
             }
         }
@@ -265,7 +265,7 @@ impl<'s, 'e, 'f> ActionParser<'s, 'e, 'f> {
 
         if let ast::Argument::Error = argument {
             let span = Span { low: offset, high: offset };
-            self.errors.error(span, format_args!("corrupt argument"));
+            self.errors.error("action", span, format_args!("corrupt argument"));
         }
 
         argument
@@ -310,7 +310,7 @@ impl<'s, 'e, 'f> ActionParser<'s, 'e, 'f> {
 
         if action.parameters_used != 1 {
             let span = Span { low, high };
-            self.errors.error(span, format_args!("wrong number of arguments"));
+            self.errors.error("action", span, format_args!("wrong number of arguments"));
             return (ast::Action::Error, span);
         }
 
@@ -329,7 +329,7 @@ impl<'s, 'e, 'f> ActionParser<'s, 'e, 'f> {
 
             _ => {
                 let span = Span { low, high };
-                self.errors.error(span, format_args!("expected an expression"));
+                self.errors.error("action", span, format_args!("expected an expression"));
                 Box::new((ast::Expr::Error, self.span))
             }
         };
@@ -352,14 +352,14 @@ impl<'s, 'e, 'f> ActionParser<'s, 'e, 'f> {
 
         if !action.has_target {
             let span = Span { low, high };
-            self.errors.error(span, format_args!("expected a target"));
+            self.errors.error("action", span, format_args!("expected a target"));
             return (ast::Action::Error, span);
         }
         let target = action.target;
 
         if !action.has_relative {
             let span = Span { low, high };
-            self.errors.error(span, format_args!("expected relative"));
+            self.errors.error("action", span, format_args!("expected relative"));
             return (ast::Action::Error, span);
         }
         let relative = action.relative;
@@ -367,7 +367,7 @@ impl<'s, 'e, 'f> ActionParser<'s, 'e, 'f> {
         let len = action.parameters_used as usize;
         if len != 2 {
             let span = Span { low, high };
-            self.errors.error(span, format_args!("wrong number of arguments"));
+            self.errors.error("action", span, format_args!("wrong number of arguments"));
             return (ast::Action::Error, span);
         }
 
@@ -385,7 +385,7 @@ impl<'s, 'e, 'f> ActionParser<'s, 'e, 'f> {
 
             _ => {
                 let span = Span { low, high };
-                self.errors.error(span, format_args!("expected a variable"));
+                self.errors.error("action", span, format_args!("expected a variable"));
                 Box::new((ast::Expr::Error, self.span))
             }
         };
@@ -400,7 +400,7 @@ impl<'s, 'e, 'f> ActionParser<'s, 'e, 'f> {
 
             _ => {
                 let span = Span { low, high };
-                self.errors.error(span, format_args!("expected an expression"));
+                self.errors.error("action", span, format_args!("expected an expression"));
                 Box::new((ast::Expr::Error, self.span))
             }
         };
@@ -419,7 +419,7 @@ impl<'s, 'e, 'f> ActionParser<'s, 'e, 'f> {
 
         if !action.has_target {
             let span = Span { low, high };
-            self.errors.error(span, format_args!("expected a target"));
+            self.errors.error("action", span, format_args!("expected a target"));
             return (ast::Action::Error, span);
         }
         let target = action.target;
@@ -427,7 +427,7 @@ impl<'s, 'e, 'f> ActionParser<'s, 'e, 'f> {
         let len = action.parameters_used as usize;
         if len != 1 {
             let span = Span { low, high };
-            self.errors.error(span, format_args!("wrong number of arguments"));
+            self.errors.error("action", span, format_args!("wrong number of arguments"));
             return (ast::Action::Error, span);
         }
 
@@ -445,7 +445,7 @@ impl<'s, 'e, 'f> ActionParser<'s, 'e, 'f> {
 
             _ => {
                 let span = Span { low, high };
-                self.errors.error(span, format_args!("expected an expression"));
+                self.errors.error("action", span, format_args!("expected an expression"));
                 Box::new((ast::Stmt::Error(ast::Expr::Error), self.span))
             }
         };
@@ -0,0 +1,380 @@
+use std::slice;
+use std::collections::HashSet;
+
+use crate::ErrorPrinter;
+use crate::diagnostic::Severity;
+use crate::bit_vec::BitVec;
+use crate::handle_map::{Handle, HandleMap};
+use crate::symbol::Symbol;
+use crate::front::{self, Span};
+use crate::back::{ssa, ControlFlow};
+
+/// The source constructs compiled into each basic block, for checks that report warnings.
+///
+/// Warnings do not count as errors, so a program that triggers them still builds.
+#[derive(Default)]
+pub struct Trace {
+    /// Each statement or action, with the block where it begins, in source order.
+    pub statements: Vec<(ssa::Label, Span)>,
+    /// Each access to a `var` local, in the order it executes within its block.
+    pub accesses: HandleMap<ssa::Label, Vec<Access>>,
+}
+
+#[derive(Copy, Clone)]
+pub struct Access {
+    pub kind: AccessKind,
+    pub local: front::ssa::Local,
+    pub symbol: Symbol,
+    pub span: Span,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum AccessKind {
+    Read,
+    Write,
+    /// A write that keeps part of the old value, like a store to an array element.
+    Update,
+}
+
+/// The instance variables a function reads and assigns.
+///
+/// Any code in a project may assign an instance variable, so unlike locals these can only be
+/// checked once every function has been compiled.
+#[derive(Default, Clone)]
+pub struct Fields {
+    /// The first reachable read of each variable, and its location.
+    pub reads: Vec<(Symbol, usize)>,
+    /// The variables assigned by reachable code.
+    pub writes: HashSet<Symbol>,
+}
+
+impl Trace {
+    pub fn access(&mut self, block: ssa::Label, access: Access) {
+        self.accesses.ensure(block).push(access);
+    }
+
+    /// Report statements that can never run, and stores to locals that are never read.
+    pub fn check(&self, program: &ssa::Function, errors: &mut ErrorPrinter<'_>) {
+        let control_flow = ControlFlow::compute(program);
+        let reachable = control_flow.reachable();
+        self.check_unreachable(&reachable, errors);
+        self.check_dead_stores(program, &control_flow, &reachable, errors);
+    }
+
+    /// Report the first statement of each run of unreachable statements.
+    fn check_unreachable(&self, reachable: &BitVec, errors: &mut ErrorPrinter<'_>) {
+        let mut in_run = false;
+        for &(block, span) in &self.statements {
+            if reachable.get(block.index()) {
+                in_run = false;
+                continue;
+            }
+            if in_run {
+                continue;
+            }
+
+            in_run = true;
+            let message = format_args!("unreachable code");
+            errors.report(Severity::Warning, "unreachable-code", span, message, vec![]);
+        }
+    }
+
+    /// Report writes to locals that no path reads before they are overwritten or go out of scope.
+    ///
+    /// This is a backwards dataflow analysis over the locals live at the start of each block.
+    /// Unreachable blocks are skipped, since they are already reported as a whole.
+    fn check_dead_stores(
+        &self, program: &ssa::Function, control_flow: &ControlFlow, reachable: &BitVec,
+        errors: &mut ErrorPrinter<'_>
+    ) {
+        let mut live_in: HandleMap<_, HashSet<_>> = HandleMap::with_capacity(program.blocks.len());
+
+        let mut dirty = true;
+        while dirty {
+            dirty = false;
+
+            for block in program.blocks.keys().rev() {
+                if !reachable.get(block.index()) {
+                    continue;
+                }
+
+                let mut live = live_out(control_flow, &live_in, block);
+                for access in self.accesses(block).iter().rev() {
+                    match access.kind {
+                        AccessKind::Read | AccessKind::Update => { live.insert(access.local); }
+                        AccessKind::Write => { live.remove(&access.local); }
+                    }
+                }
+
+                if live != live_in[block] {
+                    live_in[block] = live;
+                    dirty = true;
+                }
+            }
+        }
+
+        let mut dead = Vec::default();
+        for block in program.blocks.keys() {
+            if !reachable.get(block.index()) {
+                continue;
+            }
+
+            let mut live = live_out(control_flow, &live_in, block);
+            for access in self.accesses(block).iter().rev() {
+                match access.kind {
+                    AccessKind::Read | AccessKind::Update => { live.insert(access.local); }
+                    AccessKind::Write => {
+                        if !live.remove(&access.local) {
+                            dead.push(access);
+                        }
+                    }
+                }
+            }
+        }
+
+        dead.sort_by_key(|access| access.span);
+        for access in dead {
+            let message = format_args!("value assigned to local is never read: {}", access.symbol);
+            errors.report(Severity::Warning, "dead-store", access.span, message, vec![]);
+        }
+    }
+
+    fn accesses(&self, block: ssa::Label) -> &[Access] {
+        self.accesses.get(block).map_or(&[], |accesses| &accesses[..])
+    }
+}
+
+/// Report reads of `var` locals that some path reaches before the local is assigned.
+///
+/// Each local has a flag recording whether it has been assigned, which `Read` checks at run time.
+/// A read may be of an uninitialized local when its flag may still hold the `0` it starts with,
+/// through the block parameters of any reachable predecessor.
+pub fn check_uninitialized_locals(program: &ssa::Function, errors: &mut ErrorPrinter<'_>) {
+    let control_flow = ControlFlow::compute(program);
+    let reachable = control_flow.reachable();
+
+    // Propagate zeros forward through block parameters until nothing changes.
+    let mut zero = HashSet::new();
+    let mut dirty = true;
+    while dirty {
+        dirty = false;
+
+        for block in program.blocks.keys() {
+            if !reachable.get(block.index()) {
+                continue;
+            }
+
+            for &pred in &control_flow.pred[block] {
+                if !reachable.get(pred.index()) {
+                    continue;
+                }
+
+                for args in arguments(program, pred, block) {
+                    let parameters = &program.blocks[block].parameters;
+                    for (&parameter, &arg) in Iterator::zip(parameters.iter(), args.iter()) {
+                        let may_be_zero = is_zero(program, arg) || zero.contains(&arg);
+                        if may_be_zero && zero.insert(parameter) {
+                            dirty = true;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    let mut reads = Vec::default();
+    for block in program.blocks.keys() {
+        if !reachable.get(block.index()) {
+            continue;
+        }
+
+        for &value in &program.blocks[block].instructions {
+            let ssa::Instruction::BinarySymbol { op: ssa::Opcode::Read, arg, symbol } =
+                program.values[value] else { continue };
+            if is_zero(program, arg) || zero.contains(&arg) {
+                reads.push((program.locations[value], symbol));
+            }
+        }
+    }
+
+    // Report only the first such read of each local.
+    reads.sort_by_key(|&(location, _)| location);
+    let mut reported = HashSet::new();
+    for (location, symbol) in reads {
+        if !reported.insert(symbol) {
+            continue;
+        }
+
+        let span = Span { low: location, high: location };
+        let message = format_args!("local variable may be read before it is assigned: {}", symbol);
+        errors.report(Severity::Warning, "uninitialized", span, message, vec![]);
+    }
+}
+
+impl Fields {
+    /// Collect the instance variables loaded and stored by a function.
+    ///
+    /// Built-in variables are accessed through their getters and setters instead, so they are not
+    /// included.
+    pub fn compute(program: &ssa::Function) -> Fields {
+        let reachable = ControlFlow::compute(program).reachable();
+
+        let mut fields = Fields::default();
+        let mut read = HashSet::new();
+        for block in program.blocks.keys() {
+            for &value in &program.blocks[block].instructions {
+                match program.values[value] {
+                    ssa::Instruction::BinarySymbol { op: ssa::Opcode::LoadField, symbol, .. }
+                        if reachable.get(block.index()) && read.insert(symbol) =>
+                    {
+                        fields.reads.push((symbol, program.locations[value]));
+                    }
+                    ssa::Instruction::TernarySymbol { op: ssa::Opcode::StoreField, symbol, .. }
+                        if reachable.get(block.index()) =>
+                    {
+                        fields.writes.insert(symbol);
+                    }
+                    _ => {}
+                }
+            }
+        }
+        fields
+    }
+}
+
+/// The argument lists a block passes to one of its successors.
+fn arguments(
+    program: &ssa::Function, pred: ssa::Label, block: ssa::Label
+) -> impl Iterator<Item = &[ssa::Value]> {
+    let (targets, args): (&[_], _) = match program.values[program.terminator(pred)] {
+        ssa::Instruction::Jump { ref target, ref args, .. } => {
+            (slice::from_ref(target), vec![&args[..]])
+        }
+        ssa::Instruction::Branch { ref targets, arg_lens: [true_args, _], ref args, .. } => {
+            let (true_args, false_args) = args[1..].split_at(true_args as usize);
+            (&targets[..], vec![true_args, false_args])
+        }
+        _ => (&[], vec![]),
+    };
+    Iterator::zip(targets.iter(), args)
+        .filter(move |&(&target, _)| target == block)
+        .map(|(_, args)| args)
+}
+
+fn is_zero(program: &ssa::Function, value: ssa::Value) -> bool {
+    matches!(program.values[value],
+        ssa::Instruction::UnaryReal { op: ssa::Opcode::Constant, real } if real == 0.0)
+}
+
+fn live_out(
+    control_flow: &ControlFlow, live_in: &HandleMap<ssa::Label, HashSet<front::ssa::Local>>,
+    block: ssa::Label
+) -> HashSet<front::ssa::Local> {
+    let mut live = HashSet::default();
+    for &succ in &control_flow.succ[block] {
+        live.extend(&live_in[succ]);
+    }
+    live
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::{ErrorPrinter, FunctionDisplay};
+    use crate::diagnostic::{Diagnostic, Severity};
+    use crate::symbol::Symbol;
+    use crate::front::{self, Lexer, Parser, Lines};
+    use crate::back::ssa;
+
+    fn warnings(source: &str) -> Vec<(&'static str, &str)> {
+        compile(source).1
+    }
+
+    fn compile(source: &str) -> (ssa::Function, Vec<(&'static str, &str)>) {
+        let prototypes = HashMap::default();
+        let lines = Lines::from_code(source.as_bytes());
+        let mut diagnostics: Vec<Diagnostic> = Vec::default();
+        let mut errors = ErrorPrinter::new(FunctionDisplay::String, &lines, &mut diagnostics);
+        let program = Parser::new(Lexer::new(source.as_bytes(), 0), &mut errors).parse_program();
+        let program = front::Codegen::new(&prototypes, &mut errors).compile_program(&program);
+        front::check_uninitialized_locals(&program, &mut errors);
+        assert_eq!(errors.count, 0);
+
+        let warnings = diagnostics.into_iter()
+            .inspect(|diagnostic| assert_eq!(diagnostic.severity, Severity::Warning))
+            .map(|diagnostic| {
+                let span = diagnostic.location.unwrap().span;
+                let high = if span.high > span.low { span.high } else { span.low + 1 };
+                (diagnostic.code, &source[span.low..high])
+            })
+            .collect();
+        (program, warnings)
+    }
+
+    #[test]
+    fn unreachable() {
+        assert_eq!(warnings("return 1; a = 2; b = 3"), [
+            ("unreachable-code", "a = 2;"),
+        ]);
+        assert_eq!(warnings("if x { exit } else { return 0 }\na = 1\nif a { b = 2 }"), [
+            ("unreachable-code", "a = 1"),
+        ]);
+        assert_eq!(warnings("while x { if y break; continue; z = 1 }"), [
+            ("unreachable-code", "z = 1"),
+        ]);
+        assert_eq!(warnings("while x { if y { exit } else { break } }\nz = 1"), []);
+        let switch = "switch x {\ncase 1: return 1\ncase 2: a = 2; break\ndefault: exit }";
+        assert_eq!(warnings(switch), []);
+        assert_eq!(warnings("switch x {\ncase 1: break; a = 1\ncase 2: exit }"), [
+            ("unreachable-code", "a = 1"),
+        ]);
+    }
+
+    #[test]
+    fn dead_stores() {
+        assert_eq!(warnings("var a;\na = 1\na = 2\nreturn a"), [
+            ("dead-store", "a"),
+        ]);
+        assert_eq!(warnings("var a;\na = 1\nif x { a = 2 }\nreturn a"), []);
+        assert_eq!(warnings("var a;\na = 0\nwhile a < 10 { a += 1 }"), []);
+        assert_eq!(warnings("var a;\na = 0\nrepeat 3 { a = 1 }"), [
+            ("dead-store", "a"),
+            ("dead-store", "a"),
+        ]);
+
+        // Array elements and instance variables are not tracked.
+        assert_eq!(warnings("var a;\na[0] = 1\na[1] = 2\nb = 3\nb = 4"), []);
+    }
+
+    #[test]
+    fn uninitialized() {
+        assert_eq!(warnings("var a, b;\nb = a\nreturn b"), [
+            ("uninitialized", "a"),
+        ]);
+        assert_eq!(warnings("var a;\nif x { a = 1 }\nreturn a + a"), [
+            ("uninitialized", "a"),
+        ]);
+        assert_eq!(warnings("var a;\nif x { a = 1 } else { a[2] = 2 }\nreturn a"), []);
+        assert_eq!(warnings("var a;\nwhile x { a = 1 }\nreturn a"), [
+            ("uninitialized", "a"),
+        ]);
+        assert_eq!(warnings("var a;\nrepeat 3 { if a > 0 { exit } a = 1 }"), [
+            ("uninitialized", "a"),
+        ]);
+        assert_eq!(warnings("return argument0 + argument1"), []);
+    }
+
+    #[test]
+    fn fields() {
+        let (program, _) = compile("a = b + c; b = 1; with x { c = a }; exit; d = e");
+        let fields = front::Fields::compute(&program);
+        let reads: Vec<_> = fields.reads.iter().map(|&(symbol, _)| symbol).collect();
+        let names: [&[u8]; 4] = [b"b", b"c", b"x", b"a"];
+        assert_eq!(reads, names.map(Symbol::intern));
+        assert!(["a", "b", "c"].iter().all(|&name| {
+            fields.writes.contains(&Symbol::intern(name.as_bytes()))
+        }));
+        assert!(!fields.writes.contains(&Symbol::intern(b"d")));
+    }
+}
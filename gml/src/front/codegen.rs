@@ -4,6 +4,7 @@ use std::collections::HashMap;
 use crate::ErrorPrinter;
 use crate::symbol::{Symbol, keyword};
 use crate::front::{self, ast, Span};
+use crate::front::analysis::{Trace, Access, AccessKind};
 use crate::back::ssa;
 use crate::vm;
 
@@ -32,6 +33,8 @@ pub struct Codegen<'p, 'e, 'f> {
     current_switch: Option<ssa::Value>,
     current_expr: Option<ssa::Label>,
     current_default: Option<ssa::Label>,
+
+    trace: Trace,
 }
 
 /// A location that can be read from or written to.
@@ -113,6 +116,8 @@ impl<'p, 'e, 'f> Codegen<'p, 'e, 'f> {
             current_switch: None,
             current_expr: None,
             current_default: None,
+
+            trace: Trace::default(),
         }
     }
 
@@ -154,6 +159,7 @@ impl<'p, 'e, 'f> Codegen<'p, 'e, 'f> {
         self.emit_unary(ssa::Opcode::Return, zero, end_loc);
 
         front::ssa::Builder::finish(&mut self.function);
+        self.trace.check(&self.function, self.errors);
         self.function.return_def = match self.function.blocks[ssa::ENTRY].parameters.get(0) {
             Some(&def) => def,
             None => self.function.values.push(ssa::Instruction::Parameter),
@@ -164,6 +170,7 @@ impl<'p, 'e, 'f> Codegen<'p, 'e, 'f> {
 
     fn emit_action(&mut self, action: &(ast::Action, Span)) {
         let (ref action, action_span) = *action;
+        self.trace.statements.push((self.current_block, action_span));
         match *action {
             ast::Action::Normal {
                 ref question, ref execution, target, relative, box ref arguments
@@ -291,6 +298,10 @@ impl<'p, 'e, 'f> Codegen<'p, 'e, 'f> {
 
     fn emit_statement(&mut self, statement: &(ast::Stmt, Span)) {
         let (ref statement, statement_span) = *statement;
+        // Labels belong to the block they open, which is reachable even after a `break`.
+        if !matches!(*statement, ast::Stmt::Case(_)) {
+            self.trace.statements.push((self.current_block, statement_span));
+        }
         match *statement {
             ast::Stmt::Assign(op, box ref place, box ref value) => {
                 self.emit_assign(op, place, value);
@@ -307,7 +318,7 @@ impl<'p, 'e, 'f> Codegen<'p, 'e, 'f> {
                 let names: Vec<_> = names.iter().filter_map(|&(name, name_span)| {
                     // TODO: check resource names
                     if name.is_argument() || self.field_is_builtin(name) {
-                        self.errors.error(name_span,
+                        self.errors.error("redeclare-builtin", name_span,
                             format_args!("cannot redeclare a builtin variable"));
                         return None;
                     }
@@ -482,6 +493,7 @@ impl<'p, 'e, 'f> Codegen<'p, 'e, 'f> {
                 self.seal_block(expr_block);
 
                 self.current_block = case_block;
+                self.trace.statements.push((self.current_block, statement_span));
             }
 
             ast::Stmt::Case(None) if self.current_expr.is_some() => {
@@ -491,10 +503,12 @@ impl<'p, 'e, 'f> Codegen<'p, 'e, 'f> {
                 self.emit_jump(default_block, statement_span.low);
 
                 self.current_block = default_block;
+                self.trace.statements.push((self.current_block, statement_span));
             }
 
             ast::Stmt::Case(_) => {
-                self.errors.error(statement_span, format_args!("case statement outside of switch"));
+                self.errors.error("case-outside-switch", statement_span,
+                    format_args!("case statement outside of switch"));
             }
 
             ast::Stmt::Jump(ast::Jump::Break) if self.current_exit.is_some() => {
@@ -555,6 +569,13 @@ impl<'p, 'e, 'f> Codegen<'p, 'e, 'f> {
             self.emit_value(value)
         };
 
+        if let Place { path: Path::Local(symbol), ref index } = place {
+            let kind = match *index {
+                None => AccessKind::Write,
+                Some(_) => AccessKind::Update,
+            };
+            self.trace_local(symbol, kind, place_span);
+        }
         self.emit_store(place, value, op_span.low);
     }
 
@@ -741,14 +762,14 @@ impl<'p, 'e, 'f> Codegen<'p, 'e, 'f> {
             Some(&ssa::Prototype::Resource { script: true, .. }) =>
                 (ssa::Opcode::Call, 0, true),
             _ => {
-                self.errors.error(symbol_span,
+                self.errors.error("unknown-function", symbol_span,
                     format_args!("unknown function or script: {}", symbol));
                 (ssa::Opcode::CallApi, 0, true)
             }
         };
 
         if args.len() < arity || (!variadic && args.len() > arity) {
-            self.errors.error(symbol_span,
+            self.errors.error("argument-count", symbol_span,
                 format_args!("wrong number of arguments to function or script"));
             return self.emit_real(0.0, symbol_span.low);
         }
@@ -821,7 +842,7 @@ impl<'p, 'e, 'f> Codegen<'p, 'e, 'f> {
 
             ast::Expr::Index(box ref expr, box ref indices) => {
                 if indices.len() < 1 || 2 < indices.len() {
-                    self.errors.error(expression_span,
+                    self.errors.error("array-indices", expression_span,
                         format_args!("invalid number of array indices"));
                 }
 
@@ -838,14 +859,16 @@ impl<'p, 'e, 'f> Codegen<'p, 'e, 'f> {
                     Place { path, index: None } => Ok(Place { path, index: Some([i, j]) }),
                     Place { index: Some(_), .. } => {
                         let (_, expr_span) = *expr;
-                        self.errors.error(expr_span, format_args!("expected a variable"));
+                        self.errors.error("expected-variable", expr_span,
+                            format_args!("expected a variable"));
                         Err(PlaceError)
                     }
                 }
             }
 
             _ => {
-                self.errors.error(expression_span, format_args!("expected a variable"));
+                self.errors.error("expected-variable", expression_span,
+                    format_args!("expected a variable"));
                 Err(PlaceError)
             }
         }
@@ -861,6 +884,7 @@ impl<'p, 'e, 'f> Codegen<'p, 'e, 'f> {
             // A locally-declared variable: check for initialization, then read it.
             Place { path: Path::Local(symbol), index } => {
                 let Local { flag, local } = self.locals[&symbol];
+                self.trace_local(symbol, AccessKind::Read, place_span);
 
                 let flag = self.read_local(flag);
                 self.emit_binary_symbol(ssa::Opcode::Read, flag, symbol, place_span.low);
@@ -1250,6 +1274,16 @@ impl<'p, 'e, 'f> Codegen<'p, 'e, 'f> {
         Local { flag, local }
     }
 
+    /// Record an access to a GML-level local, for warnings about dead stores.
+    fn trace_local(&mut self, symbol: Symbol, kind: AccessKind, span: Span) {
+        // Arguments are passed in, so writes to them are not stores of new values.
+        if symbol.is_argument() {
+            return;
+        }
+        let Local { local, .. } = self.locals[&symbol];
+        self.trace.access(self.current_block, Access { kind, local, symbol, span });
+    }
+
     fn emit_initializer(&mut self, instruction: ssa::Instruction) -> ssa::Value {
         let value = self.function.values.push(instruction);
         self.function.blocks[ssa::ENTRY].instructions.insert(self.initializers, value);
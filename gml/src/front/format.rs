@@ -0,0 +1,648 @@
+use crate::symbol::{Symbol, keyword};
+use crate::front::{ast, Lexer, Span};
+use crate::front::token::{Token, Delim};
+
+/// How to lay out formatted code.
+pub struct Options {
+    /// The number of spaces to indent each level of nesting.
+    pub indent: usize,
+    /// End every simple statement with a semicolon, rather than only those that already had one.
+    pub semicolons: bool,
+    /// Rewrite GM8's alternative syntax into its C-like equivalent.
+    ///
+    /// This replaces `begin` and `end` with braces, `:=` with `=`, `=` comparisons with `==`, `<>`
+    /// with `!=`, and `and`, `or`, `xor` and `not` with symbols. It also drops `then` and `do`.
+    pub normalize: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options { indent: 4, semicolons: true, normalize: true }
+    }
+}
+
+/// Print a parsed program in a consistent style.
+///
+/// The AST does not record comments, so they are recovered from the source and printed before
+/// the statement that follows them, or at the end of the line they were on.
+pub fn format(source: &[u8], (program, span): &(ast::Stmt, Span), options: &Options) -> Vec<u8> {
+    let mut printer = Printer {
+        source,
+        options,
+        comments: comments(source),
+        next: 0,
+        out: Vec::default(),
+        indent: 0,
+        last: 0,
+        fresh: true,
+    };
+
+    match *program {
+        ast::Stmt::Block(ref stmts) if !source[span.low..].starts_with(b"{") => {
+            for &(ref stmt, stmt_span) in &stmts[..] {
+                printer.statement((stmt, stmt_span));
+            }
+        }
+        _ => printer.statement((program, *span)),
+    }
+    printer.leading(source.len(), false);
+
+    printer.out
+}
+
+/// Find the span of every comment, between the tokens.
+fn comments(source: &[u8]) -> Vec<Span> {
+    let mut comments = Vec::default();
+    let mut lexer = Lexer::new(source, 0);
+    let mut last = 0;
+    loop {
+        let (token, span) = lexer.read_token();
+
+        let mut pos = last;
+        while pos < span.low {
+            let rest = &source[pos..span.low];
+            let len = if rest.starts_with(b"//") {
+                rest.iter().position(|&c| c == b'\n').unwrap_or(rest.len())
+            } else if rest.starts_with(b"/*") {
+                rest[2..].windows(2).position(|end| end == b"*/").map_or(rest.len(), |end| end + 4)
+            } else {
+                pos += 1;
+                continue;
+            };
+            comments.push(Span { low: pos, high: pos + len });
+            pos += len;
+        }
+
+        if token == Token::Eof { break; }
+        last = span.high;
+    }
+    comments
+}
+
+struct Printer<'a> {
+    source: &'a [u8],
+    options: &'a Options,
+
+    comments: Vec<Span>,
+    /// The first comment that has not been printed.
+    next: usize,
+
+    out: Vec<u8>,
+    indent: usize,
+    /// The end of the source that has been printed, to find blank lines.
+    last: usize,
+    /// Whether nothing has been printed since the start of the block.
+    fresh: bool,
+}
+
+type Stmt<'a> = (&'a ast::Stmt, Span);
+
+impl<'a> Printer<'a> {
+    fn statement(&mut self, (stmt, span): Stmt<'_>) {
+        self.leading(span.low, true);
+        self.write_indent();
+        self.stmt((stmt, span));
+    }
+
+    /// Print a statement after the indentation, through the end of its line.
+    fn stmt(&mut self, (stmt, span): Stmt<'_>) {
+        match *stmt {
+            ast::Stmt::Block(ref stmts) => {
+                self.block(stmts, span);
+                self.end_line(span.high);
+            }
+
+            ast::Stmt::If(
+                box (ref cond, cond_span),
+                box (ref body, body_span),
+                ref alternative,
+            ) => {
+                self.write(b"if ");
+                self.condition((cond, cond_span));
+                if self.has_keyword(cond_span.high, body_span.low, keyword::Then) {
+                    self.write(b" then");
+                }
+                let open = self.body((body, body_span), cond_span.high);
+
+                let Some(box (ref alternative, alternative_span)) = *alternative else {
+                    if open { self.end_line(span.high); }
+                    return;
+                };
+                if open {
+                    self.write(b" else");
+                } else {
+                    self.leading(alternative_span.low, false);
+                    self.write_indent();
+                    self.write(b"else");
+                }
+                if let ast::Stmt::If(..) = *alternative {
+                    self.write(b" ");
+                    self.stmt((alternative, alternative_span));
+                } else if self.body((alternative, alternative_span), body_span.high) {
+                    self.end_line(span.high);
+                }
+            }
+
+            ast::Stmt::Repeat(box (ref count, count_span), box (ref body, body_span)) => {
+                self.write(b"repeat ");
+                self.condition((count, count_span));
+                if self.body((body, body_span), count_span.high) { self.end_line(span.high); }
+            }
+
+            ast::Stmt::While(box (ref cond, cond_span), box (ref body, body_span)) |
+            ast::Stmt::With(box (ref cond, cond_span), box (ref body, body_span)) => {
+                let name = match *stmt { ast::Stmt::While(..) => "while ", _ => "with " };
+                self.write(name.as_bytes());
+                self.condition((cond, cond_span));
+                if self.has_keyword(cond_span.high, body_span.low, keyword::Do) {
+                    self.write(b" do");
+                }
+                if self.body((body, body_span), cond_span.high) { self.end_line(span.high); }
+            }
+
+            ast::Stmt::Do(box (ref body, body_span), box (ref cond, cond_span)) => {
+                self.write(b"do");
+                if self.body((body, body_span), span.low + 2) {
+                    self.write(b" ");
+                } else {
+                    self.write_indent();
+                }
+                self.write(b"until ");
+                self.condition((cond, cond_span));
+                self.semicolon(span);
+                self.end_line(span.high);
+            }
+
+            ast::Stmt::For(
+                box (ref init, init_span),
+                box (ref cond, cond_span),
+                box (ref next, next_span),
+                box (ref body, body_span),
+            ) => {
+                self.write(b"for (");
+                self.simple((init, init_span));
+                self.write(b"; ");
+                self.expr((cond, cond_span), 0);
+                self.write(b"; ");
+                self.simple((next, next_span));
+                self.write(b")");
+                if self.body((body, body_span), next_span.high) { self.end_line(span.high); }
+            }
+
+            ast::Stmt::Switch(box (ref expr, expr_span), ref stmts) => {
+                self.write(b"switch ");
+                self.condition((expr, expr_span));
+                self.write(b" ");
+
+                // Cases are indented inside the switch, and their statements inside the cases.
+                let open = self.open(expr_span.high, span.high);
+                self.indent += 1;
+                for &(ref stmt, stmt_span) in &stmts[..] {
+                    let case = matches!(*stmt, ast::Stmt::Case(..));
+                    if !case { self.indent += 1; }
+                    self.statement((stmt, stmt_span));
+                    if !case { self.indent -= 1; }
+                }
+                self.close(open, span.high);
+                self.end_line(span.high);
+            }
+
+            ast::Stmt::Case(ref expr) => {
+                match *expr {
+                    Some(box (ref expr, expr_span)) => {
+                        self.write(b"case ");
+                        self.expr((expr, expr_span), 0);
+                    }
+                    None => self.write(b"default"),
+                }
+                self.write(b":");
+                self.end_line(span.high);
+            }
+
+            _ => {
+                self.simple((stmt, span));
+                self.semicolon(span);
+                self.end_line(span.high);
+            }
+        }
+    }
+
+    /// Print a statement with no nested statements, without a semicolon.
+    fn simple(&mut self, (stmt, span): Stmt<'_>) {
+        match *stmt {
+            ast::Stmt::Assign(
+                (op, op_span),
+                box (ref place, place_span),
+                box (ref value, value_span),
+            ) => {
+                self.expr((place, place_span), 0);
+                self.write(b" ");
+                match op {
+                    None if self.options.normalize => self.write(b"="),
+                    None => self.source_text(op_span),
+                    Some(op) => { self.write(op_text(op).as_bytes()); self.write(b"="); }
+                }
+                self.write(b" ");
+                self.expr((value, value_span), 0);
+            }
+
+            ast::Stmt::Invoke(ref call) => self.call(call),
+
+            ast::Stmt::Declare(declare, ref names) => {
+                let declare = match declare {
+                    ast::Declare::Local => "var ",
+                    ast::Declare::Global => "globalvar ",
+                };
+                self.write(declare.as_bytes());
+                for (i, &(name, _)) in names.iter().enumerate() {
+                    if i > 0 { self.write(b", "); }
+                    self.write(&name);
+                }
+            }
+
+            ast::Stmt::Jump(jump) => {
+                let jump = match jump {
+                    ast::Jump::Break => "break",
+                    ast::Jump::Continue => "continue",
+                    ast::Jump::Exit => "exit",
+                };
+                self.write(jump.as_bytes());
+            }
+
+            ast::Stmt::Return(box (ref expr, expr_span)) => {
+                self.write(b"return ");
+                self.expr((expr, expr_span), 0);
+            }
+
+            // Anything else is copied from the source.
+            _ => {
+                let mut high = span.high;
+                while high > span.low && self.source[high - 1] == b';' { high -= 1; }
+                self.source_text(Span { low: span.low, high });
+            }
+        }
+    }
+
+    /// Print the body of a control flow statement, after its header.
+    ///
+    /// Blocks stay on the same line as the header and leave it open for an `else` or `until`.
+    /// Anything else goes on its own line, indented.
+    fn body(&mut self, (stmt, span): Stmt<'_>, header: usize) -> bool {
+        if let ast::Stmt::Block(ref stmts) = *stmt {
+            self.write(b" ");
+            self.block(stmts, span);
+            true
+        } else {
+            self.end_line(header);
+            self.indent += 1;
+            self.fresh = true;
+            self.statement((stmt, span));
+            self.indent -= 1;
+            false
+        }
+    }
+
+    fn block(&mut self, stmts: &[(ast::Stmt, Span)], span: Span) {
+        let open = self.open(span.low, span.high);
+        self.indent += 1;
+        for &(ref stmt, stmt_span) in stmts {
+            self.statement((stmt, stmt_span));
+        }
+        self.close(open, span.high);
+    }
+
+    /// Print the opening brace of a block, and end the line.
+    fn open(&mut self, low: usize, high: usize) -> bool {
+        let (_, brace) = self.find_token(low, high, |token| {
+            token == Token::OpenDelim(Delim::Brace) ||
+            token == Token::Keyword(keyword::Begin)
+        }).unwrap_or((Token::Eof, Span { low, high: low }));
+        let begin = self.source[brace.low..].starts_with(b"begin");
+
+        let begin = begin && !self.options.normalize;
+        self.write(if begin { b"begin" } else { b"{" });
+        self.end_line(brace.high);
+        self.fresh = true;
+        begin
+    }
+
+    /// Print the end of a block opened by `open`, leaving the line open.
+    fn close(&mut self, begin: bool, high: usize) {
+        self.leading(high, false);
+        self.indent -= 1;
+        self.write_indent();
+        self.write(if begin { b"end" } else { b"}" });
+        self.last = high;
+    }
+
+    /// Print the expression controlling a statement, in parentheses.
+    fn condition(&mut self, expr: (&ast::Expr, Span)) {
+        self.write(b"(");
+        self.expr(expr, 0);
+        self.write(b")");
+    }
+
+    fn semicolon(&mut self, span: Span) {
+        if self.options.semicolons || self.source[span.low..span.high].ends_with(b";") {
+            self.write(b";");
+        }
+    }
+
+    fn expr(&mut self, (expr, span): (&ast::Expr, Span), min_precedence: usize) {
+        let parens = precedence(expr) < min_precedence;
+        if parens { self.write(b"("); }
+        match *expr {
+            ast::Expr::Value(ast::Value::Ident(symbol)) => self.write(&symbol),
+            ast::Expr::Value(_) | ast::Expr::Error => self.source_text(span),
+
+            ast::Expr::Unary((op, op_span), box (ref expr, expr_span)) => {
+                if self.options.normalize {
+                    let op = match op {
+                        ast::Unary::Positive => "+",
+                        ast::Unary::Negate => "-",
+                        ast::Unary::Invert => "!",
+                        ast::Unary::BitInvert => "~",
+                    };
+                    self.write(op.as_bytes());
+                } else {
+                    self.source_text(op_span);
+                    if self.source[op_span.low..op_span.high] == *b"not" { self.write(b" "); }
+                }
+                self.expr((expr, expr_span), 7);
+            }
+
+            ast::Expr::Binary(
+                (op, op_span),
+                box (ref left, left_span),
+                box (ref right, right_span),
+            ) => {
+                let precedence = precedence(expr);
+                self.expr((left, left_span), precedence);
+                self.write(b" ");
+                if self.options.normalize {
+                    self.write(binary_text(op).as_bytes());
+                } else {
+                    self.source_text(op_span);
+                }
+                self.write(b" ");
+                self.expr((right, right_span), precedence + 1);
+            }
+
+            ast::Expr::Field(box (ref expr, expr_span), (field, _)) => {
+                self.expr((expr, expr_span), 8);
+                self.write(b".");
+                self.write(&field);
+            }
+
+            ast::Expr::Index(box (ref expr, expr_span), ref indices) => {
+                self.expr((expr, expr_span), 8);
+                self.write(b"[");
+                self.args(indices);
+                self.write(b"]");
+            }
+
+            ast::Expr::Call(ref call) => self.call(call),
+        }
+        if parens { self.write(b")"); }
+    }
+
+    fn call(&mut self, &ast::Call((symbol, _), ref args): &ast::Call) {
+        self.write(&symbol);
+        self.write(b"(");
+        self.args(args);
+        self.write(b")");
+    }
+
+    fn args(&mut self, args: &[(ast::Expr, Span)]) {
+        for (i, &(ref arg, arg_span)) in args.iter().enumerate() {
+            if i > 0 { self.write(b", "); }
+            self.expr((arg, arg_span), 0);
+        }
+    }
+
+    /// Print the comments that come before `pos`, each on its own line.
+    fn leading(&mut self, pos: usize, blank_before_pos: bool) {
+        while let Some(&comment) = self.comments.get(self.next) {
+            if comment.low >= pos { break; }
+
+            self.blank_line(comment.low);
+            self.write_indent();
+            self.source_text(comment);
+            self.write(b"\n");
+            self.next += 1;
+            self.last = comment.high;
+        }
+        if blank_before_pos { self.blank_line(pos); }
+        self.fresh = false;
+    }
+
+    /// End the current line, along with any comments that came before `pos` or on its line.
+    fn end_line(&mut self, pos: usize) {
+        let mut line_comment = false;
+        while let Some(&comment) = self.comments.get(self.next) {
+            let gap = &self.source[usize::min(pos, comment.low)..comment.low];
+            if comment.low >= pos && gap.contains(&b'\n') { break; }
+
+            if line_comment {
+                self.write(b"\n");
+                self.write_indent();
+            } else {
+                self.write(b" ");
+            }
+            self.source_text(comment);
+            line_comment = self.source[comment.low..].starts_with(b"//");
+            self.next += 1;
+            self.last = usize::max(self.last, comment.high);
+        }
+        self.write(b"\n");
+        self.last = usize::max(self.last, pos);
+    }
+
+    /// Keep a single blank line where the source had at least one before `pos`.
+    fn blank_line(&mut self, pos: usize) {
+        let gap = &self.source[usize::min(self.last, pos)..pos];
+        if !self.fresh && gap.iter().filter(|&&c| c == b'\n').count() > 1 {
+            self.write(b"\n");
+        }
+        self.fresh = false;
+        self.last = pos;
+    }
+
+    /// Check for an optional keyword between two parts of a statement.
+    fn has_keyword(&self, low: usize, high: usize, keyword: Symbol) -> bool {
+        !self.options.normalize &&
+        self.find_token(low, high, |token| token == Token::Keyword(keyword)).is_some()
+    }
+
+    /// Find the first token between two positions that matches `f`.
+    fn find_token(
+        &self, low: usize, high: usize, f: impl Fn(Token) -> bool
+    ) -> Option<(Token, Span)> {
+        let mut lexer = Lexer::new(&self.source[low..high], low);
+        loop {
+            match lexer.read_token() {
+                (Token::Eof, _) => return None,
+                (token, span) if f(token) => return Some((token, span)),
+                _ => {}
+            }
+        }
+    }
+
+    fn write_indent(&mut self) {
+        self.out.extend(std::iter::repeat_n(b' ', self.indent * self.options.indent));
+    }
+
+    fn source_text(&mut self, span: Span) {
+        self.out.extend_from_slice(&self.source[span.low..span.high]);
+    }
+
+    fn write(&mut self, text: &[u8]) {
+        self.out.extend_from_slice(text);
+    }
+}
+
+/// How tightly an expression binds, matching the parser.
+fn precedence(expr: &ast::Expr) -> usize {
+    match *expr {
+        ast::Expr::Binary((op, _), _, _) => match op {
+            ast::Binary::Op(ast::Op::Multiply) | ast::Binary::Op(ast::Op::Divide) |
+            ast::Binary::Div | ast::Binary::Mod => 6,
+            ast::Binary::Op(ast::Op::Add) | ast::Binary::Op(ast::Op::Subtract) => 5,
+            ast::Binary::ShiftLeft | ast::Binary::ShiftRight => 4,
+            ast::Binary::Op(_) => 3,
+            ast::Binary::Lt | ast::Binary::Le | ast::Binary::Eq |
+            ast::Binary::Ne | ast::Binary::Ge | ast::Binary::Gt => 2,
+            ast::Binary::And | ast::Binary::Or | ast::Binary::Xor => 1,
+        },
+        ast::Expr::Unary(..) => 7,
+        ast::Expr::Field(..) | ast::Expr::Index(..) | ast::Expr::Call(..) => 8,
+        ast::Expr::Value(_) | ast::Expr::Error => 9,
+    }
+}
+
+fn binary_text(op: ast::Binary) -> &'static str {
+    match op {
+        ast::Binary::Lt => "<",
+        ast::Binary::Le => "<=",
+        ast::Binary::Eq => "==",
+        ast::Binary::Ne => "!=",
+        ast::Binary::Ge => ">=",
+        ast::Binary::Gt => ">",
+        ast::Binary::And => "&&",
+        ast::Binary::Or => "||",
+        ast::Binary::Xor => "^^",
+        ast::Binary::Op(op) => op_text(op),
+        ast::Binary::Div => "div",
+        ast::Binary::Mod => "mod",
+        ast::Binary::ShiftLeft => "<<",
+        ast::Binary::ShiftRight => ">>",
+    }
+}
+
+fn op_text(op: ast::Op) -> &'static str {
+    match op {
+        ast::Op::Add => "+",
+        ast::Op::Subtract => "-",
+        ast::Op::Multiply => "*",
+        ast::Op::Divide => "/",
+        ast::Op::BitAnd => "&",
+        ast::Op::BitOr => "|",
+        ast::Op::BitXor => "^",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+    use crate::{FunctionDisplay, format_program};
+    use crate::symbol::Symbol;
+    use crate::diagnostic::Text;
+    use crate::front::format::Options;
+
+    fn format(source: &str, options: &Options) -> String {
+        let script = Symbol::intern(b"<test>");
+        let name = FunctionDisplay::Script { script };
+        let source = source.as_bytes();
+        let formatted = format_program(name, source, options, Text(io::stderr())).unwrap();
+        let again = format_program(name, &formatted[..], options, Text(io::stderr())).unwrap();
+        assert_eq!(formatted, again, "formatting should be idempotent");
+        String::from_utf8(formatted).unwrap()
+    }
+
+    const SOURCE: &str = "\
+// Step toward the target.
+var dx;;
+dx := target.x-x // horizontal
+
+
+/* a block
+   comment */
+if dx <> 0 and not done then begin
+  x += sign(dx)*(speed+1);   // step
+end else
+  exit
+switch (state) {
+case 0: state = 1; break
+default: while i < 10 do i += 1
+}
+";
+
+    #[test]
+    fn normalize() {
+        assert_eq!(format(SOURCE, &Options::default()), "\
+// Step toward the target.
+var dx;
+dx = target.x - x; // horizontal
+
+/* a block
+   comment */
+if (dx != 0 && !done) {
+    x += sign(dx) * (speed + 1); // step
+} else
+    exit;
+switch (state) {
+    case 0:
+        state = 1;
+        break;
+    default:
+        while (i < 10)
+            i += 1;
+}
+");
+    }
+
+    #[test]
+    fn preserve() {
+        let options = Options { indent: 2, semicolons: false, normalize: false };
+        assert_eq!(format(SOURCE, &options), "\
+// Step toward the target.
+var dx;
+dx := target.x - x // horizontal
+
+/* a block
+   comment */
+if (dx <> 0 and not done) then begin
+  x += sign(dx) * (speed + 1); // step
+end else
+  exit
+switch (state) {
+  case 0:
+    state = 1;
+    break
+  default:
+    while (i < 10) do
+      i += 1
+}
+");
+    }
+
+    #[test]
+    fn precedence() {
+        let source = "a = (b + c) * -(d - e) - (f - g); h = (i.j).k[0]; l = (m * n).o;";
+        assert_eq!(format(source, &Options::default()), "\
+a = (b + c) * -(d - e) - (f - g);
+h = i.j.k[0];
+l = (m * n).o;
+");
+    }
+}
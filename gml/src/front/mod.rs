@@ -11,11 +11,14 @@ mod parser;
 mod action_parser;
 mod ssa;
 mod codegen;
+mod analysis;
+pub mod format;
 
 pub use lexer::Lexer;
 pub use parser::Parser;
 pub use action_parser::ActionParser;
 pub use codegen::Codegen;
+pub use analysis::{Fields, check_uninitialized_locals};
 
 /// A range of positions in an event or script.
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
@@ -35,7 +38,7 @@ pub struct Span {
 ///
 /// Converting an absolute index (relative to a whole event) into a local index (relative to the
 /// parent item) works by subtracting the absolute index of the parent item's first child.
-#[derive(Default)]
+#[derive(Default, Clone)]
 pub struct Lines {
     /// The byte offset of each action, and the absolute index of its first argument.
     pub actions: Vec<(usize, usize)>,
@@ -46,6 +49,7 @@ pub struct Lines {
 }
 
 /// A user-facing position in an event or script.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
 pub struct Position {
     pub action: Option<usize>,
     pub argument: Option<usize>,
@@ -61,6 +65,22 @@ impl Lines {
         Lines { actions, arguments, lines }
     }
 
+    /// Concatenate the text of a sequence of actions, at the offsets `from_actions` describes.
+    pub fn source_from_actions(source: &[project::Action<'_>]) -> Vec<u8> {
+        let mut text = Vec::default();
+        for action in source {
+            match (action.action_kind, action.action_type) {
+                (action_kind::NORMAL, action_type::FUNCTION) => text.extend_from_slice(action.name),
+                (action_kind::NORMAL, action_type::CODE) => text.extend_from_slice(action.code),
+                (_, _) => {}
+            }
+            for argument in &action.arguments[..action.parameters_used as usize] {
+                text.extend_from_slice(argument);
+            }
+        }
+        text
+    }
+
     pub fn from_actions(source: &[project::Action<'_>]) -> Lines {
         let mut actions = Vec::default();
         let mut arguments = Vec::default();
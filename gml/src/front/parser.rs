@@ -51,7 +51,7 @@ impl<'s, 'e, 'f> Parser<'s, 'e, 'f> {
         let high = span.high;
 
         if self.current != Token::Eof {
-            self.errors.error(self.span,
+            self.errors.error("syntax", self.span,
                 format_args!("unexpected {}; expected {}", self.current, Token::Eof));
         }
 
@@ -113,7 +113,7 @@ impl<'s, 'e, 'f> Parser<'s, 'e, 'f> {
             BinOpEq(Pipe) => Some(BitOr),
             BinOpEq(Caret) => Some(BitXor),
             _ => {
-                self.errors.error(self.span,
+                self.errors.error("syntax", self.span,
                     format_args!("unexpected {}; expected assignment operator", self.current));
                 return (ast::Stmt::Error(place), left_span);
             }
@@ -155,7 +155,8 @@ impl<'s, 'e, 'f> Parser<'s, 'e, 'f> {
 
         if self.current == Token::Eq || self.current == Token::ColonEq {
             // TODO: gms allows this
-            self.errors.error(self.span, format_args!("unexpected {}; expected ;", self.current));
+            self.errors.error("syntax", self.span,
+                format_args!("unexpected {}; expected ;", self.current));
 
             self.advance_token();
             self.parse_expression(0);
@@ -189,7 +190,7 @@ impl<'s, 'e, 'f> Parser<'s, 'e, 'f> {
         let high;
         if self.current == Token::Eof {
             let expected = Token::CloseDelim(Delim::Brace);
-            self.errors.error(self.span,
+            self.errors.error("syntax", self.span,
                 format_args!("unexpected {}; expected {}", self.current, expected));
             high = self.span.low;
         } else {
@@ -315,7 +316,7 @@ impl<'s, 'e, 'f> Parser<'s, 'e, 'f> {
             self.current != Token::Keyword(keyword::Begin)
         {
             let expected = Token::OpenDelim(Delim::Brace);
-            self.errors.error(self.span,
+            self.errors.error("syntax", self.span,
                 format_args!("unexpected {}; expected {}", self.current, expected));
         }
 
@@ -405,7 +406,7 @@ impl<'s, 'e, 'f> Parser<'s, 'e, 'f> {
                         let (_, field_span) = self.advance_token();
                         (field, field_span)
                     } else {
-                        self.errors.error(self.span,
+                        self.errors.error("syntax", self.span,
                             format_args!("unexpected {}; expected identifier", self.current));
                         break;
                     };
@@ -455,17 +456,18 @@ impl<'s, 'e, 'f> Parser<'s, 'e, 'f> {
                 let (_, span) = self.advance_token();
                 // This should never fail in normal usage, as a `Real` token should always be UTF-8.
                 let symbol = str::from_utf8(&symbol[..]).unwrap_or_else(|error| {
-                    self.errors.error(span, format_args!("invalid numeric literal"));
+                    self.errors.error("syntax", span, format_args!("invalid numeric literal"));
                     let (valid, _) = symbol.split_at(error.valid_up_to());
                     unsafe { str::from_utf8_unchecked(valid) }
                 });
                 let value = match symbol.chars().next() {
                     Some('$') => u64::from_str_radix(&symbol[1..], 16).unwrap_or_else(|_| {
-                        self.errors.error(span, format_args!("invalid integer literal"));
+                        self.errors.error("syntax", span, format_args!("invalid integer literal"));
                         0
                     }) as f64,
                     _ => f64::from_str(&symbol).unwrap_or_else(|_| {
-                        self.errors.error(span, format_args!("invalid floating point literal"));
+                        self.errors.error("syntax", span,
+                            format_args!("invalid floating point literal"));
                         0.0
                     }),
                 };
@@ -504,7 +506,7 @@ impl<'s, 'e, 'f> Parser<'s, 'e, 'f> {
             }
 
             _ => {
-                self.errors.error(self.span,
+                self.errors.error("syntax", self.span,
                     format_args!("unexpected {}; expected expression", self.current));
 
                 let span = Span { low: low, high: low };
@@ -531,7 +533,7 @@ impl<'s, 'e, 'f> Parser<'s, 'e, 'f> {
         if self.current != Token::CloseDelim(delim) {
             let delim = Token::CloseDelim(delim);
             let comma = Token::Comma;
-            self.errors.error(self.span,
+            self.errors.error("syntax", self.span,
                 format_args!("unexpected {}; expected {} or {}", self.current, delim, comma));
         } else {
             self.advance_token();
@@ -549,7 +551,7 @@ impl<'s, 'e, 'f> Parser<'s, 'e, 'f> {
             self.advance_token();
             true
         } else {
-            self.errors.error(self.span,
+            self.errors.error("syntax", self.span,
                 format_args!("unexpected {}; expected {}", self.current, token));
             false
         }
@@ -672,16 +674,17 @@ impl Infix {
 mod tests {
     use std::io;
     use crate::{FunctionDisplay, ErrorPrinter};
+    use crate::diagnostic::Text;
     use crate::symbol::Symbol;
     use crate::front::{Span, Lexer, Parser, Lines};
     use crate::front::ast::*;
 
     fn setup<'e, 's>(lines: &'e mut Lines, source: &'s [u8]) ->
-        (Lexer<'s>, ErrorPrinter<'e, io::Stderr>)
+        (Lexer<'s>, ErrorPrinter<'e, Text<io::Stderr>>)
     {
         *lines = Lines::from_code(source);
         let script = Symbol::intern(b"<test>");
-        let errors = ErrorPrinter::new(FunctionDisplay::Script { script }, lines, io::stderr());
+        let errors = ErrorPrinter::new(FunctionDisplay::Script { script }, lines, Text(io::stderr()));
         (Lexer::new(source, 0), errors)
     }
 
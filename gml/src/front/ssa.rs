@@ -178,35 +178,6 @@ impl Builder {
 
     pub fn finish(function: &mut ssa::Function) {
         // resolve any aliases left during SSA construction
-        for block in function.blocks.keys() {
-            for i in 0..function.blocks[block].instructions.len() {
-                let value = function.blocks[block].instructions[i];
-                Self::replace_aliases(function, value);
-            }
-        }
-    }
-
-    fn replace_aliases(function: &mut ssa::Function, value: ssa::Value) {
-        for i in 0..function.uses(value).len() {
-            let arg = function.uses(value)[i];
-            let resolved = Self::resolve_alias(function, arg);
-            if arg != resolved {
-                function.uses_mut(value)[i] = resolved;
-            }
-        }
-    }
-
-    fn resolve_alias(function: &ssa::Function, value: ssa::Value) -> ssa::Value {
-        let mut v = value;
-        let mut i = function.values.len();
-        while let ssa::Instruction::Alias { arg: original } = function.values[v] {
-            v = original;
-
-            i -= 1;
-            if i == 0 {
-                panic!("alias loop")
-            }
-        }
-        v
+        function.resolve_aliases();
     }
 }
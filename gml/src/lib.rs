@@ -1,11 +1,12 @@
 #![feature(box_patterns)]
-#![feature(extern_types)]
 
-use std::collections::HashMap;
-use std::{fmt, io};
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
 
 use crate::symbol::Symbol;
-use crate::front::{Lexer, Parser, ActionParser, Lines, Position, Span};
+use crate::diagnostic::{Diagnostic, Emit, Location, Severity};
+use crate::front::{Lexer, Parser, ActionParser, Lines, Span};
 use crate::back::ssa;
 use crate::vm::code;
 
@@ -20,6 +21,10 @@ pub mod symbol;
 pub mod front;
 pub mod back;
 pub mod vm;
+pub mod report;
+pub mod explore;
+pub mod cache;
+pub mod diagnostic;
 
 /// The name of a single executable unit of GML or D&D actions.
 #[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
@@ -30,6 +35,8 @@ pub enum Function {
     Room { id: i32 },
     /// Instance creation code.
     Instance { id: i32 },
+    /// The actions at one moment of a timeline.
+    Timeline { id: i32, moment: i32 },
     Constant { id: i32 },
     String { id: i32 },
 }
@@ -58,90 +65,68 @@ pub fn load<W>(
 }
 
 /// Build the GML and D&D in a Game Maker project.
-pub fn build<W, F: FnMut() -> E, E: io::Write>(
+pub fn build<W, F: FnMut() -> E, E: Emit>(
     game: &project::Game<'_>,
     extensions: &[project::Extension<'_>],
     runner: &HashMap<Symbol, Item<W>>,
+    errors: F
+) -> Result<(vm::Assets<W>, vm::Debug), u32> {
+    build_with(game, extensions, runner, None, errors)
+}
+
+/// Build the GML and D&D in a Game Maker project, reusing code from `cache` for functions whose
+/// source has not changed, and saving newly compiled code to it.
+pub fn build_cached<W, F: FnMut() -> E, E: Emit>(
+    game: &project::Game<'_>,
+    extensions: &[project::Extension<'_>],
+    runner: &HashMap<Symbol, Item<W>>,
+    cache: &mut cache::Cache,
+    errors: F
+) -> Result<(vm::Assets<W>, vm::Debug), u32> {
+    build_with(game, extensions, runner, Some(cache), errors)
+}
+
+fn build_with<W, F: FnMut() -> E, E: Emit>(
+    game: &project::Game<'_>,
+    extensions: &[project::Extension<'_>],
+    runner: &HashMap<Symbol, Item<W>>,
+    mut cache: Option<&mut cache::Cache>,
     mut errors: F
 ) -> Result<(vm::Assets<W>, vm::Debug), u32> {
     let mut assets = vm::Assets::default();
     let mut debug = vm::Debug::default();
 
-    // Collect the prototypes of entities that may be referred to in code.
-    let prototypes = &mut assets.prototypes;
-    prototypes.reserve(
-        runner.len() +
-        game.constants.len() +
-        game.sprites.len() + game.scripts.len() + game.objects.len() + game.rooms.len()
-    );
-    for (&name, item) in runner.iter() {
-        match *item {
-            Item::Native(_, arity, variadic) => {
-                prototypes.insert(name, ssa::Prototype::Native { arity, variadic, dll: false });
-            }
-            Item::Member(_, _) => {
-                prototypes.insert(name, ssa::Prototype::Member);
-            }
-        }
-    }
-    for extension in extensions {
-        for file in &extension.files[..] {
-            for function in &file.functions[..] {
-                let name = Symbol::intern(function.name);
-                let arity = function.parameters_used as usize;
-                let variadic = false;
-                prototypes.insert(name, ssa::Prototype::Native { arity, variadic, dll: true });
-            }
-        }
-    }
-    for (id, &project::Constant { name, .. }) in game.constants.iter().enumerate() {
-        let id = id as i32;
-        let name = Symbol::intern(name);
-        prototypes.insert(name, ssa::Prototype::Constant { id });
-        debug.constants.push(name);
-    }
-    for (id, &project::Sprite { name, .. }) in game.sprites.iter().enumerate() {
-        let id = id as i32;
-        let name = Symbol::intern(name);
-        prototypes.insert(name, ssa::Prototype::Resource { id, script: false });
-    }
-    for (id, &project::Background { name, .. }) in game.backgrounds.iter().enumerate() {
-        let id = id as i32;
-        let name = Symbol::intern(name);
-        prototypes.insert(name, ssa::Prototype::Resource { id, script: false });
-    }
-    for (id, &project::Script { name, .. }) in game.scripts.iter().enumerate() {
-        let id = id as i32;
-        let name = Symbol::intern(name);
-        prototypes.insert(name, ssa::Prototype::Resource { id, script: true });
-        debug.scripts.push(name);
-    }
-    for (id, &project::Object { name, .. }) in game.objects.iter().enumerate() {
-        let id = id as i32;
-        let name = Symbol::intern(name);
-        prototypes.insert(name, ssa::Prototype::Resource { id, script: false });
-        debug.objects.push(name);
-    }
-    for (id, &project::Room { name, .. }) in game.rooms.iter().enumerate() {
-        let id = id as i32;
-        let name = Symbol::intern(name);
-        prototypes.insert(name, ssa::Prototype::Resource { id, script: false });
-        debug.rooms.push(name);
-    }
+    assets.prototypes = prototypes(game, extensions, runner);
+    let prototypes = &assets.prototypes;
+    debug.constants = game.constants.iter().map(|it| Symbol::intern(it.name)).collect();
+    debug.scripts = game.scripts.iter().map(|it| Symbol::intern(it.name)).collect();
+    debug.timelines = game.timelines.iter().map(|it| Symbol::intern(it.name)).collect();
+    debug.objects = game.objects.iter().map(|it| Symbol::intern(it.name)).collect();
+    debug.rooms = game.rooms.iter().map(|it| Symbol::intern(it.name)).collect();
 
     let mut total_errors = 0;
+    let uninitialized_zero = game.settings.uninitialized_zero;
+    let mut fields = FieldReads::default();
 
-    // Compile constants.
+    // Compile constants, in order so that those which refer to earlier ones can be folded.
+    let mut constants = HashMap::default();
     let resources = Iterator::zip(debug.constants.iter(), game.constants.iter());
     for (id, (&constant, &project::Constant { value, .. })) in resources.enumerate() {
         let function = Function::Constant { id: id as i32 };
         let name = FunctionDisplay::Constant { constant };
-        let (code, locations, errors) = compile_constant(prototypes, name, value, errors());
+        let (code, locations, value, errors) =
+            compile_constant(prototypes, &constants, name, value, errors());
+        if let Some(value) = value {
+            constants.insert(id as i32, value);
+        }
         assets.code.insert(function, code);
         debug.locations.insert(function, locations);
         total_errors += errors;
     }
     assets.constants = game.constants.len() as i32;
+    if let Some(cache) = cache.as_deref_mut() {
+        cache.validate(prototypes, &constants, uninitialized_zero);
+    }
 
     // Compile scripts.
     let resources = Iterator::zip(debug.scripts.iter(), game.scripts.iter());
@@ -150,12 +135,37 @@ pub fn build<W, F: FnMut() -> E, E: io::Write>(
 
         let function = Function::Script { id: id as i32 };
         let name = FunctionDisplay::Script { script };
-        let (code, locations, errors) = compile_program(prototypes, name, body, errors());
+        let source = Code::Text(body);
+        let (code, locations, read, errors) =
+            compile_cached(cache.as_deref_mut(), function, source, errors(), |errors| {
+                compile_program(prototypes, &constants, uninitialized_zero, name, body, errors)
+            });
+        fields.insert(function, name, source, read);
         assets.code.insert(function, code);
         debug.locations.insert(function, locations);
         total_errors += errors;
     }
 
+    // Compile timeline moments.
+    let resources = Iterator::zip(debug.timelines.iter(), game.timelines.iter());
+    for (id, (&timeline, &project::Timeline { ref moments, .. })) in resources.enumerate() {
+        let id = id as i32;
+        for &project::Moment { moment, ref actions } in moments {
+            let moment = moment as i32;
+            let function = Function::Timeline { id, moment };
+            let name = FunctionDisplay::Timeline { timeline, moment };
+            let source = Code::Actions(actions);
+            let (code, locations, read, errors) =
+                compile_cached(cache.as_deref_mut(), function, source, errors(), |errors| {
+                    compile_event(prototypes, &constants, uninitialized_zero, name, actions, errors)
+                });
+            fields.insert(function, name, source, read);
+            assets.code.insert(function, code);
+            debug.locations.insert(function, locations);
+            total_errors += errors;
+        }
+    }
+
     // Compile object events.
     let resources = Iterator::zip(debug.objects.iter(), game.objects.iter());
     for (object_index, (&object, &project::Object { ref events, .. })) in resources.enumerate() {
@@ -164,7 +174,12 @@ pub fn build<W, F: FnMut() -> E, E: io::Write>(
             let function = Function::Event { object_index, event_type, event_kind };
             let event_kind = EventDisplay::from_debug(&debug, event_type, event_kind);
             let name = FunctionDisplay::Event { object, event_type, event_kind };
-            let (code, locations, errors) = compile_event(prototypes, name, actions, errors());
+            let source = Code::Actions(actions);
+            let (code, locations, read, errors) =
+                compile_cached(cache.as_deref_mut(), function, source, errors(), |errors| {
+                    compile_event(prototypes, &constants, uninitialized_zero, name, actions, errors)
+                });
+            fields.insert(function, name, source, read);
             assets.code.insert(function, code);
             debug.locations.insert(function, locations);
             total_errors += errors;
@@ -179,7 +194,12 @@ pub fn build<W, F: FnMut() -> E, E: io::Write>(
         if code.len() > 0 {
             let function = Function::Room { id };
             let name = FunctionDisplay::Room { room };
-            let (code, locations, errors) = compile_program(prototypes, name, code, errors());
+            let source = Code::Text(code);
+            let (code, locations, read, errors) =
+                compile_cached(cache.as_deref_mut(), function, source, errors(), |errors| {
+                    compile_program(prototypes, &constants, uninitialized_zero, name, code, errors)
+                });
+            fields.insert(function, name, source, read);
             assets.code.insert(function, code);
             debug.locations.insert(function, locations);
             total_errors += errors;
@@ -190,7 +210,14 @@ pub fn build<W, F: FnMut() -> E, E: io::Write>(
             if code.len() > 0 {
                 let function = Function::Instance { id };
                 let name = FunctionDisplay::Instance { room, id };
-                let (code, locations, errors) = compile_program(prototypes, name, code, errors());
+                let source = Code::Text(code);
+                let (code, locations, read, errors) =
+                    compile_cached(cache.as_deref_mut(), function, source, errors(), |errors| {
+                        compile_program(
+                            prototypes, &constants, uninitialized_zero, name, code, errors
+                        )
+                    });
+                fields.insert(function, name, source, read);
                 assets.code.insert(function, code);
                 debug.locations.insert(function, locations);
                 total_errors += errors;
@@ -200,6 +227,13 @@ pub fn build<W, F: FnMut() -> E, E: io::Write>(
         }
     }
 
+    if !uninitialized_zero {
+        fields.check(&debug, errors);
+    }
+    if let Some(cache) = cache {
+        cache.retain(&assets.code);
+    }
+
     if total_errors > 0 {
         return Err(total_errors);
     }
@@ -207,121 +241,318 @@ pub fn build<W, F: FnMut() -> E, E: io::Write>(
     Ok((assets, debug))
 }
 
-fn compile_constant<E: io::Write>(
+/// Collect the prototypes of entities that may be referred to in code.
+pub fn prototypes<W>(
+    game: &project::Game<'_>,
+    extensions: &[project::Extension<'_>],
+    runner: &HashMap<Symbol, Item<W>>,
+) -> HashMap<Symbol, ssa::Prototype> {
+    let mut prototypes = HashMap::default();
+    prototypes.reserve(
+        runner.len() +
+        game.constants.len() +
+        game.sprites.len() + game.backgrounds.len() + game.scripts.len() + game.fonts.len() +
+        game.timelines.len() + game.objects.len() + game.rooms.len()
+    );
+    for (&name, item) in runner.iter() {
+        match *item {
+            Item::Native(_, arity, variadic) => {
+                prototypes.insert(name, ssa::Prototype::Native { arity, variadic, dll: false });
+            }
+            Item::Member(_, _) => {
+                prototypes.insert(name, ssa::Prototype::Member);
+            }
+        }
+    }
+    for extension in extensions {
+        for file in &extension.files[..] {
+            for function in &file.functions[..] {
+                let name = Symbol::intern(function.name);
+                let arity = function.parameters_used as usize;
+                let variadic = false;
+                prototypes.insert(name, ssa::Prototype::Native { arity, variadic, dll: true });
+            }
+        }
+    }
+    for (id, &project::Constant { name, .. }) in game.constants.iter().enumerate() {
+        let id = id as i32;
+        let name = Symbol::intern(name);
+        prototypes.insert(name, ssa::Prototype::Constant { id });
+    }
+    for (id, &project::Sprite { name, .. }) in game.sprites.iter().enumerate() {
+        let id = id as i32;
+        let name = Symbol::intern(name);
+        prototypes.insert(name, ssa::Prototype::Resource { id, script: false });
+    }
+    for (id, &project::Background { name, .. }) in game.backgrounds.iter().enumerate() {
+        let id = id as i32;
+        let name = Symbol::intern(name);
+        prototypes.insert(name, ssa::Prototype::Resource { id, script: false });
+    }
+    for (id, &project::Script { name, .. }) in game.scripts.iter().enumerate() {
+        let id = id as i32;
+        let name = Symbol::intern(name);
+        prototypes.insert(name, ssa::Prototype::Resource { id, script: true });
+    }
+    for (id, &project::Font { name, .. }) in game.fonts.iter().enumerate() {
+        let id = id as i32;
+        let name = Symbol::intern(name);
+        prototypes.insert(name, ssa::Prototype::Resource { id, script: false });
+    }
+    for (id, &project::Timeline { name, .. }) in game.timelines.iter().enumerate() {
+        let id = id as i32;
+        let name = Symbol::intern(name);
+        prototypes.insert(name, ssa::Prototype::Resource { id, script: false });
+    }
+    for (id, &project::Object { name, .. }) in game.objects.iter().enumerate() {
+        let id = id as i32;
+        let name = Symbol::intern(name);
+        prototypes.insert(name, ssa::Prototype::Resource { id, script: false });
+    }
+    for (id, &project::Room { name, .. }) in game.rooms.iter().enumerate() {
+        let id = id as i32;
+        let name = Symbol::intern(name);
+        prototypes.insert(name, ssa::Prototype::Resource { id, script: false });
+    }
+
+    prototypes
+}
+
+/// Compile a constant's expression, and find its value if it can be computed at build time.
+fn compile_constant<E: Emit>(
     prototypes: &HashMap<Symbol, ssa::Prototype>,
+    constants: &HashMap<i32, back::Constant>,
     name: FunctionDisplay,
     code: &[u8],
     errors: E,
-) -> (code::Function, vm::Locations, u32) {
+) -> (code::Function, vm::Locations, Option<back::Constant>, u32) {
     let lines = Lines::from_code(code);
-    let mut errors = ErrorPrinter::new(name, &lines, errors);
+    let mut errors = ErrorPrinter::new(name, &lines, errors).with_source(code);
     let program = Parser::new(Lexer::new(code, 0), &mut errors).parse_expression(0);
-    let program = front::Codegen::new(&prototypes, &mut errors).compile_constant(&program);
+    let mut program = front::Codegen::new(&prototypes, &mut errors).compile_constant(&program);
+    let mut value = None;
+    if errors.count == 0 {
+        back::optimize(&mut program, constants);
+        value = back::Constant::from_return(&program);
+    }
     let (code, locations) = back::Codegen::new(prototypes).compile(&program);
     let count = errors.count;
-    (code, vm::Locations { locations, lines }, count)
+    (code, vm::Locations { locations, lines }, value, count)
+}
+
+/// Reformat a script or event in a consistent style, unless it has syntax errors.
+pub fn format_program<E: Emit>(
+    name: FunctionDisplay,
+    code: &[u8],
+    options: &front::format::Options,
+    errors: E,
+) -> Result<Vec<u8>, u32> {
+    let lines = Lines::from_code(code);
+    let mut errors = ErrorPrinter::new(name, &lines, errors).with_source(code);
+    let program = Parser::new(Lexer::new(code, 0), &mut errors).parse_program();
+    if errors.count > 0 {
+        return Err(errors.count);
+    }
+    Ok(front::format::format(code, &program, options))
 }
 
-pub fn compile_program<E: io::Write>(
+pub fn compile_program<E: Emit>(
     prototypes: &HashMap<Symbol, ssa::Prototype>,
+    constants: &HashMap<i32, back::Constant>,
+    uninitialized_zero: bool,
     name: FunctionDisplay,
     code: &[u8],
     errors: E,
-) -> (code::Function, vm::Locations, u32) {
+) -> (code::Function, vm::Locations, front::Fields, u32) {
     let lines = Lines::from_code(code);
-    let mut errors = ErrorPrinter::new(name, &lines, errors);
+    let mut errors = ErrorPrinter::new(name, &lines, errors).with_source(code);
     let program = Parser::new(Lexer::new(code, 0), &mut errors).parse_program();
-    let program = front::Codegen::new(&prototypes, &mut errors).compile_program(&program);
+    let mut program = front::Codegen::new(&prototypes, &mut errors).compile_program(&program);
+    let mut fields = front::Fields::default();
+    if errors.count == 0 {
+        if !uninitialized_zero {
+            front::check_uninitialized_locals(&program, &mut errors);
+            fields = front::Fields::compute(&program);
+        }
+        back::optimize(&mut program, constants);
+    }
     let (code, locations) = back::Codegen::new(prototypes).compile(&program);
     let count = errors.count;
-    (code, vm::Locations { locations, lines }, count)
+    (code, vm::Locations { locations, lines }, fields, count)
 }
 
-fn compile_event<E: io::Write>(
+fn compile_event<E: Emit>(
     prototypes: &HashMap<Symbol, ssa::Prototype>,
+    constants: &HashMap<i32, back::Constant>,
+    uninitialized_zero: bool,
     name: FunctionDisplay,
     actions: &[project::Action<'_>],
     errors: E,
-) -> (code::Function, vm::Locations, u32) {
+) -> (code::Function, vm::Locations, front::Fields, u32) {
     let lines = Lines::from_actions(actions);
-    let mut errors = ErrorPrinter::new(name, &lines, errors);
+    let source = Lines::source_from_actions(actions);
+    let mut errors = ErrorPrinter::new(name, &lines, errors).with_source(&source);
     let program = ActionParser::new(actions.iter(), &mut errors).parse_event();
-    let program = front::Codegen::new(&prototypes, &mut errors).compile_event(&program);
+    let mut program = front::Codegen::new(&prototypes, &mut errors).compile_event(&program);
+    let mut fields = front::Fields::default();
+    if errors.count == 0 {
+        if !uninitialized_zero {
+            front::check_uninitialized_locals(&program, &mut errors);
+            fields = front::Fields::compute(&program);
+        }
+        back::optimize(&mut program, constants);
+    }
     let (code, locations) = back::Codegen::new(prototypes).compile(&program);
     let count = errors.count;
-    (code, vm::Locations { locations, lines }, count)
+    (code, vm::Locations { locations, lines }, fields, count)
+}
+
+/// Compile a function with `compile`, unless `cache` already has code for the same source.
+fn compile_cached<E: Emit>(
+    cache: Option<&mut cache::Cache>,
+    function: Function,
+    source: Code<'_>,
+    errors: E,
+    compile: impl FnOnce(&mut cache::Count<E>) -> Compiled,
+) -> Compiled {
+    let mut errors = cache::Count { emit: errors, count: 0 };
+    let Some(cache) = cache else { return compile(&mut errors) };
+
+    let hash = cache::hash(source);
+    if let Some(cache::Entry { code, locations, fields, .. }) = cache.get(function, hash) {
+        return (code.clone(), locations.clone(), fields.clone(), 0);
+    }
+    let (code, locations, fields, count) = compile(&mut errors);
+    if errors.count == 0 {
+        let (code, locations, fields) = (code.clone(), locations.clone(), fields.clone());
+        cache.insert(function, cache::Entry { hash, code, locations, fields });
+    }
+    (code, locations, fields, count)
+}
+
+/// A compiled function, its instance variable accesses, and the number of errors it reported.
+type Compiled = (code::Function, vm::Locations, front::Fields, u32);
+
+/// The source of a function, to quote in diagnostics reported after it is compiled.
+#[derive(Copy, Clone)]
+pub(crate) enum Code<'a> {
+    Text(&'a [u8]),
+    Actions(&'a [project::Action<'a>]),
+}
+
+/// Instance variables read by each function, to check once all assignments in a project are known.
+#[derive(Default)]
+struct FieldReads<'a> {
+    reads: Vec<FunctionReads<'a>>,
+    writes: HashSet<Symbol>,
 }
 
-pub struct ErrorPrinter<'a, W: ?Sized = dyn io::Write + 'a> {
+struct FunctionReads<'a> {
+    function: Function,
+    name: FunctionDisplay,
+    code: Code<'a>,
+    reads: Vec<(Symbol, usize)>,
+}
+
+impl<'a> FieldReads<'a> {
+    fn insert(
+        &mut self, function: Function, name: FunctionDisplay, code: Code<'a>, fields: front::Fields
+    ) {
+        self.writes.extend(fields.writes);
+        if !fields.reads.is_empty() {
+            self.reads.push(FunctionReads { function, name, code, reads: fields.reads });
+        }
+    }
+
+    /// Report reads of instance variables that nothing in the project ever assigns.
+    ///
+    /// Reading one of these is an error at run time, unless the game treats uninitialized
+    /// variables as 0.
+    fn check<F: FnMut() -> E, E: Emit>(self, debug: &vm::Debug, mut errors: F) {
+        for FunctionReads { function, name, code, reads } in self.reads {
+            let mut reads = reads.into_iter()
+                .filter(|&(symbol, _)| !self.writes.contains(&symbol))
+                .peekable();
+            if reads.peek().is_none() {
+                continue;
+            }
+
+            let source = match code {
+                Code::Text(text) => Cow::Borrowed(text),
+                Code::Actions(actions) => Cow::Owned(Lines::source_from_actions(actions)),
+            };
+            let lines = &debug.locations[&function].lines;
+            let mut errors = ErrorPrinter::new(name, lines, errors()).with_source(&source);
+            for (symbol, location) in reads {
+                let span = Span { low: location, high: location };
+                let message = format_args!("instance variable is never assigned: {}", symbol);
+                let severity = Severity::Warning;
+                ErrorPrinter::report(&mut errors, severity, "uninitialized", span, message, vec![]);
+            }
+        }
+    }
+}
+
+pub struct ErrorPrinter<'a, E: ?Sized = dyn Emit + 'a> {
     pub name: FunctionDisplay,
     pub lines: &'a Lines,
+    /// The code `lines` describes, to quote in diagnostics.
+    pub source: Option<&'a [u8]>,
+    /// The number of errors reported, not counting warnings.
     pub count: u32,
-    pub write: W,
+    pub emit: E,
 }
 
+#[derive(Copy, Clone, Debug)]
 pub enum FunctionDisplay {
     Event { object: Symbol, event_type: u32, event_kind: EventDisplay },
     Script { script: Symbol },
     Room { room: Symbol },
     Instance { room: Symbol, id: i32 },
+    Timeline { timeline: Symbol, moment: i32 },
     Constant { constant: Symbol },
     String,
 }
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug)]
 pub enum EventDisplay {
     Id(i32),
     Name(Symbol),
 }
 
 impl<'a> ErrorPrinter<'a> {
-    pub fn new<W: io::Write>(name: FunctionDisplay, lines: &'a Lines, write: W) ->
-        ErrorPrinter<'a, W>
-    {
-        ErrorPrinter { name, lines, count: 0, write }
+    pub fn new<E: Emit>(name: FunctionDisplay, lines: &'a Lines, emit: E) -> ErrorPrinter<'a, E> {
+        ErrorPrinter { name, lines, source: None, count: 0, emit }
     }
 
-    pub fn from_debug<W: io::Write>(debug: &vm::Debug, function: Function, write: W) ->
-        ErrorPrinter<W>
+    pub fn from_debug<E: Emit>(debug: &vm::Debug, function: Function, emit: E) ->
+        ErrorPrinter<'_, E>
     {
         let name = FunctionDisplay::from_debug(debug, function);
         let lines = &debug.locations[&function].lines;
-        ErrorPrinter::new(name, lines, write)
+        ErrorPrinter::new(name, lines, emit)
     }
 
-    pub fn error(&mut self, span: Span, message: fmt::Arguments<'_>) {
-        let _ = write!(self.write, "error in ");
-        Self::position(&mut self.write, &self.name, self.lines, span);
-        let _ = writeln!(self.write, ": {}", message);
-        self.count += 1;
+    pub fn error(&mut self, code: &'static str, span: Span, message: fmt::Arguments<'_>) {
+        self.report(Severity::Error, code, span, message, Vec::default());
     }
 
-    pub fn stack_from_debug(&mut self, debug: &vm::Debug, stack: &[vm::ErrorFrame]) {
-        for frame in stack.iter() {
-            let name = FunctionDisplay::from_debug(debug, frame.function);
-            let lines = &debug.locations[&frame.function].lines;
-            let span = Span::from_debug(&debug, frame);
-            let _= write!(self.write, "  ");
-            Self::position(&mut self.write, &name, lines, span);
-            let _= writeln!(self.write);
+    pub fn report(
+        &mut self, severity: Severity, code: &'static str, span: Span,
+        message: fmt::Arguments<'_>, notes: Vec<String>
+    ) {
+        if severity == Severity::Error {
+            self.count += 1;
         }
+        let message = message.to_string();
+        let location = Some(Location::new(self.name, self.lines, self.source, span));
+        self.emit.emit(Diagnostic { severity, code, message, notes, location });
     }
+}
 
-    fn position(write: &mut dyn io::Write, name: &FunctionDisplay, lines: &Lines, span: Span) {
-        let Position { action, argument, line, column } = lines.get_position(span.low);
-        let _ = write!(write, "{}", name);
-        if let Some(action) = action {
-            let _ = write!(write, ", action {}", action);
-        }
-        if let (Some(argument), None) = (argument, line) {
-            let _ = write!(write, ", argument {}", argument);
-        }
-        if let Some(line) = line {
-            let _ = write!(write, ":{}", line);
-        }
-        if let Some(column) = column {
-            let _ = write!(write, ":{}", column);
-        }
+impl<'a, E: Emit> ErrorPrinter<'a, E> {
+    pub fn with_source(self, source: &'a [u8]) -> ErrorPrinter<'a, E> {
+        ErrorPrinter { source: Some(source), ..self }
     }
 }
 
@@ -345,6 +576,10 @@ impl FunctionDisplay {
                 let room = debug.rooms[debug.instances[&id] as usize];
                 FunctionDisplay::Instance { room, id }
             }
+            Function::Timeline { id, moment } => {
+                let timeline = debug.timelines[id as usize];
+                FunctionDisplay::Timeline { timeline, moment }
+            }
             Function::Constant { id } => {
                 let constant = debug.constants[id as usize];
                 FunctionDisplay::Constant { constant }
@@ -363,6 +598,14 @@ impl EventDisplay {
     }
 }
 
+impl Location {
+    pub fn from_debug(debug: &vm::Debug, frame: &vm::ErrorFrame) -> Location {
+        let function = FunctionDisplay::from_debug(debug, frame.function);
+        let lines = &debug.locations[&frame.function].lines;
+        Location::new(function, lines, None, Span::from_debug(debug, frame))
+    }
+}
+
 impl Span {
     pub fn from_debug(debug: &vm::Debug, frame: &vm::ErrorFrame) -> Span {
         let offset = frame.instruction as u32;
@@ -380,6 +623,8 @@ impl fmt::Display for FunctionDisplay {
             FunctionDisplay::Room { room } => write!(f, "creation code of room {}", room),
             FunctionDisplay::Instance { room, id } =>
                 write!(f, "creation code for instance {} in room {}", id, room),
+            FunctionDisplay::Timeline { timeline, moment } =>
+                write!(f, "moment {} of timeline {}", moment, timeline),
             FunctionDisplay::Constant { constant } => write!(f, "constant {}", constant),
             FunctionDisplay::String => write!(f, "string to be executed"),
         }
@@ -0,0 +1,392 @@
+//! Find the functions and builtin variables a game uses that the runner does not define.
+//!
+//! Code generation stops at the first unknown function, and treats unknown builtin variables as
+//! ordinary instance variables. This instead parses every piece of code in a game and collects
+//! each use of a name the runner is missing, to show how much of the library a game needs.
+
+use std::collections::{HashMap, HashSet};
+use std::{cmp, fmt, io};
+
+use crate::{Item, ErrorPrinter, FunctionDisplay, EventDisplay};
+use crate::symbol::Symbol;
+use crate::diagnostic::Location;
+use crate::front::{ast, Lexer, Parser, ActionParser, Lines, Span};
+
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+pub enum Kind {
+    Function,
+    Variable,
+}
+
+/// A name the runner does not define, with the location of each use.
+pub struct Missing {
+    pub kind: Kind,
+    pub name: Symbol,
+    pub uses: Vec<String>,
+}
+
+/// Find every use of a missing function or builtin variable in `game`, most used first.
+pub fn missing<W>(
+    game: &project::Game<'_>,
+    extensions: &[project::Extension<'_>],
+    runner: &HashMap<Symbol, Item<W>>,
+) -> Vec<Missing> {
+    let mut functions = HashSet::default();
+    let mut variables = HashSet::default();
+    for (&name, item) in runner.iter() {
+        match *item {
+            Item::Native(..) => { functions.insert(name); }
+            Item::Member(..) => { variables.insert(name); }
+        }
+    }
+    for extension in extensions {
+        for file in &extension.files[..] {
+            functions.extend(file.functions.iter().map(|function| Symbol::intern(function.name)));
+        }
+    }
+    functions.extend(game.scripts.iter().map(|script| Symbol::intern(script.name)));
+
+    let builtins = BUILTIN_VARIABLES.iter().map(|name| Symbol::intern(name.as_bytes())).collect();
+    let mut finder = Finder {
+        functions, variables, builtins,
+        name: FunctionDisplay::String,
+        lines: Lines::default(),
+        uses: HashMap::default(),
+    };
+
+    for &project::Constant { name, value } in &game.constants[..] {
+        let name = FunctionDisplay::Constant { constant: Symbol::intern(name) };
+        finder.start(name, Lines::from_code(value));
+        let expr = Parser::new(Lexer::new(value, 0), &mut finder.errors()).parse_expression(0);
+        finder.expr(&expr);
+    }
+
+    for &project::Script { name, body } in &game.scripts[..] {
+        if name.is_empty() { continue; }
+        let script = Symbol::intern(name);
+        finder.start(FunctionDisplay::Script { script }, Lines::from_code(body));
+        finder.program(body);
+    }
+
+    for &project::Timeline { name, ref moments } in &game.timelines[..] {
+        let timeline = Symbol::intern(name);
+        for &project::Moment { moment, ref actions } in moments {
+            let moment = moment as i32;
+            let name = FunctionDisplay::Timeline { timeline, moment };
+            finder.start(name, Lines::from_actions(actions));
+            finder.actions(actions);
+        }
+    }
+
+    for &project::Object { name, ref events, .. } in &game.objects[..] {
+        let object = Symbol::intern(name);
+        for &project::Event { event_type, event_kind, ref actions } in events {
+            let event_kind = match event_type {
+                project::event_type::COLLISION => game.objects.get(event_kind as usize)
+                    .map_or(EventDisplay::Id(event_kind), |other| {
+                        EventDisplay::Name(Symbol::intern(other.name))
+                    }),
+                _ => EventDisplay::Id(event_kind),
+            };
+            let name = FunctionDisplay::Event { object, event_type, event_kind };
+            finder.start(name, Lines::from_actions(actions));
+            finder.actions(actions);
+        }
+    }
+
+    for &project::Room { name, code, ref instances, .. } in &game.rooms[..] {
+        let room = Symbol::intern(name);
+        if !code.is_empty() {
+            finder.start(FunctionDisplay::Room { room }, Lines::from_code(code));
+            finder.program(code);
+        }
+        for &project::Instance { id, code, .. } in instances {
+            if code.is_empty() { continue; }
+            finder.start(FunctionDisplay::Instance { room, id }, Lines::from_code(code));
+            finder.program(code);
+        }
+    }
+
+    let mut missing: Vec<_> = finder.uses.into_iter()
+        .map(|((kind, name), uses)| Missing { kind, name, uses })
+        .collect();
+    missing.sort_by_cached_key(|missing| {
+        (cmp::Reverse(missing.uses.len()), missing.kind, missing.name.to_string())
+    });
+    missing
+}
+
+struct Finder {
+    functions: HashSet<Symbol>,
+    variables: HashSet<Symbol>,
+    builtins: HashSet<Symbol>,
+
+    /// The code currently being searched.
+    name: FunctionDisplay,
+    lines: Lines,
+
+    uses: HashMap<(Kind, Symbol), Vec<String>>,
+}
+
+impl Finder {
+    fn start(&mut self, name: FunctionDisplay, lines: Lines) {
+        self.name = name;
+        self.lines = lines;
+    }
+
+    /// Parse errors are reported when the game is built, so ignore them here.
+    fn errors(&self) -> ErrorPrinter<'_, io::Sink> {
+        ErrorPrinter::new(self.name, &self.lines, io::sink())
+    }
+
+    fn program(&mut self, code: &[u8]) {
+        let program = Parser::new(Lexer::new(code, 0), &mut self.errors()).parse_program();
+        self.stmt(&program);
+    }
+
+    fn actions(&mut self, actions: &[project::Action<'_>]) {
+        let event = ActionParser::new(actions.iter(), &mut self.errors()).parse_event();
+        self.action(&event);
+    }
+
+    fn found(&mut self, kind: Kind, name: Symbol, span: Span) {
+        let location = Location::new(self.name, &self.lines, None, span).to_string();
+        self.uses.entry((kind, name)).or_default().push(location);
+    }
+
+    fn function(&mut self, name: Symbol, span: Span) {
+        if !self.functions.contains(&name) {
+            self.found(Kind::Function, name, span);
+        }
+    }
+
+    fn variable(&mut self, name: Symbol, span: Span) {
+        if self.builtins.contains(&name) && !self.variables.contains(&name) {
+            self.found(Kind::Variable, name, span);
+        }
+    }
+
+    fn action(&mut self, &(ref action, span): &(ast::Action, Span)) {
+        match *action {
+            ast::Action::Error | ast::Action::Exit => {}
+
+            ast::Action::Normal { ref question, ref execution, ref arguments, .. } => {
+                match *execution {
+                    ast::Exec::Function(name) => self.function(name, span),
+                    ast::Exec::Code(ref program) => self.stmt(program),
+                }
+                for argument in &arguments[..] {
+                    if let ast::Argument::Expr(ref expr) = *argument {
+                        self.expr(expr);
+                    }
+                }
+                if let Some(question) = question {
+                    self.action(&question.true_action);
+                    if let Some(ref false_action) = question.false_action {
+                        self.action(false_action);
+                    }
+                }
+            }
+
+            ast::Action::Block { ref body } => {
+                for action in &body[..] {
+                    self.action(action);
+                }
+            }
+
+            ast::Action::Repeat { ref count, ref body } => {
+                self.expr(count);
+                self.action(body);
+            }
+
+            ast::Action::Variable { ref variable, ref value, .. } => {
+                self.expr(variable);
+                self.expr(value);
+            }
+
+            ast::Action::Code { ref code, .. } => self.stmt(code),
+        }
+    }
+
+    fn stmt(&mut self, &(ref stmt, span): &(ast::Stmt, Span)) {
+        match *stmt {
+            ast::Stmt::Error(ref expr) => self.expr_at(expr, span),
+
+            ast::Stmt::Assign(_, box ref place, box ref value) => {
+                self.expr(place);
+                self.expr(value);
+            }
+
+            ast::Stmt::Invoke(ref call) => self.call(call),
+
+            ast::Stmt::Declare(..) | ast::Stmt::Jump(_) | ast::Stmt::Case(None) => {}
+
+            ast::Stmt::Block(ref body) => {
+                for stmt in &body[..] {
+                    self.stmt(stmt);
+                }
+            }
+
+            ast::Stmt::If(box ref cond, box ref true_branch, ref false_branch) => {
+                self.expr(cond);
+                self.stmt(true_branch);
+                if let Some(false_branch) = false_branch {
+                    self.stmt(false_branch);
+                }
+            }
+
+            ast::Stmt::Repeat(box ref expr, box ref body) |
+            ast::Stmt::While(box ref expr, box ref body) |
+            ast::Stmt::With(box ref expr, box ref body) => {
+                self.expr(expr);
+                self.stmt(body);
+            }
+
+            ast::Stmt::Do(box ref body, box ref expr) => {
+                self.stmt(body);
+                self.expr(expr);
+            }
+
+            ast::Stmt::For(box ref init, box ref cond, box ref next, box ref body) => {
+                self.stmt(init);
+                self.expr(cond);
+                self.stmt(next);
+                self.stmt(body);
+            }
+
+            ast::Stmt::Switch(box ref expr, ref body) => {
+                self.expr(expr);
+                for stmt in &body[..] {
+                    self.stmt(stmt);
+                }
+            }
+
+            ast::Stmt::Return(box ref expr) | ast::Stmt::Case(Some(box ref expr)) => {
+                self.expr(expr);
+            }
+        }
+    }
+
+    fn expr(&mut self, &(ref expr, span): &(ast::Expr, Span)) {
+        self.expr_at(expr, span)
+    }
+
+    fn expr_at(&mut self, expr: &ast::Expr, span: Span) {
+        match *expr {
+            ast::Expr::Error | ast::Expr::Value(ast::Value::Real(_) | ast::Value::String(_)) => {}
+
+            ast::Expr::Value(ast::Value::Ident(name)) => self.variable(name, span),
+
+            ast::Expr::Unary(_, box ref expr) => self.expr(expr),
+
+            ast::Expr::Binary(_, box ref left, box ref right) => {
+                self.expr(left);
+                self.expr(right);
+            }
+
+            ast::Expr::Field(box ref expr, (name, name_span)) => {
+                self.expr(expr);
+                self.variable(name, name_span);
+            }
+
+            ast::Expr::Index(box ref expr, ref indices) => {
+                self.expr(expr);
+                for index in &indices[..] {
+                    self.expr(index);
+                }
+            }
+
+            ast::Expr::Call(ref call) => self.call(call),
+        }
+    }
+
+    fn call(&mut self, &ast::Call((name, span), ref arguments): &ast::Call) {
+        self.function(name, span);
+        for argument in &arguments[..] {
+            self.expr(argument);
+        }
+    }
+}
+
+impl fmt::Display for Kind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Kind::Function => f.write_str("function"),
+            Kind::Variable => f.write_str("variable"),
+        }
+    }
+}
+
+/// The builtin variables of GM 8.1, which may be missing from the runner. Any other name used as a
+/// variable belongs to the game.
+const BUILTIN_VARIABLES: &[&str] = &[
+    // Instances
+    "x", "y", "xprevious", "yprevious", "xstart", "ystart", "hspeed", "vspeed", "direction",
+    "speed", "friction", "gravity", "gravity_direction", "path_index", "path_position",
+    "path_positionprevious", "path_speed", "path_scale", "path_orientation", "path_endaction",
+    "object_index", "id", "solid", "persistent", "mask_index", "instance_count", "instance_id",
+    "timeline_index", "timeline_position", "timeline_speed", "timeline_running", "timeline_loop",
+    "visible", "sprite_index", "sprite_width", "sprite_height", "sprite_xoffset", "sprite_yoffset",
+    "image_number", "image_index", "image_speed", "depth", "image_xscale", "image_yscale",
+    "image_angle", "image_alpha", "image_blend", "bbox_left", "bbox_right", "bbox_top",
+    "bbox_bottom", "alarm",
+
+    // Rooms, backgrounds and views
+    "room", "room_first", "room_last", "room_width", "room_height", "room_caption", "room_speed",
+    "room_persistent", "background_color", "background_showcolor", "background_visible",
+    "background_foreground", "background_index", "background_x", "background_y",
+    "background_width", "background_height", "background_htiled", "background_vtiled",
+    "background_xscale", "background_yscale", "background_hspeed", "background_vspeed",
+    "background_blend", "background_alpha", "view_enabled", "view_current", "view_visible",
+    "view_xview", "view_yview", "view_wview", "view_hview", "view_xport", "view_yport",
+    "view_wport", "view_hport", "view_angle", "view_hborder", "view_vborder", "view_hspeed",
+    "view_vspeed", "view_object", "transition_kind", "transition_steps",
+
+    // Score and game state
+    "score", "lives", "health", "show_score", "show_lives", "show_health", "caption_score",
+    "caption_lives", "caption_health", "fps", "current_time", "current_year", "current_month",
+    "current_day", "current_weekday", "current_hour", "current_minute", "current_second",
+    "event_type", "event_number", "event_object", "event_action", "secure_mode", "debug_mode",
+    "error_occurred", "error_last", "gamemaker_pro", "gamemaker_registered", "gamemaker_version",
+    "game_id", "working_directory", "program_directory", "temp_directory",
+
+    // Input
+    "keyboard_key", "keyboard_lastkey", "keyboard_lastchar", "keyboard_string", "mouse_x",
+    "mouse_y", "mouse_button", "mouse_lastbutton", "cursor_sprite",
+];
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use bstr::BStr;
+    use crate::Item;
+    use crate::symbol::Symbol;
+    use super::{Kind, missing};
+
+    #[test]
+    fn missing_names() {
+        let mut game = project::Game::default();
+        game.scripts.push(project::Script { name: BStr::new(b"helper"), body: BStr::new(b"{
+            return argument0
+        }") });
+        game.scripts.push(project::Script { name: BStr::new(b"main"), body: BStr::new(b"{
+            my_var = helper(view_xview[0])
+            draw_fancy(x, y)
+            draw_fancy(x, other.view_xview)
+        }") });
+
+        let mut items: HashMap<Symbol, Item<()>> = HashMap::default();
+        items.insert(Symbol::intern(b"x"), Item::Member(None, None));
+        items.insert(Symbol::intern(b"y"), Item::Member(None, None));
+
+        let missing = missing(&game, &[], &items);
+        let names: Vec<_> = missing.iter()
+            .map(|missing| (missing.kind, missing.name.to_string(), missing.uses.len()))
+            .collect();
+        assert_eq!(names, [
+            (Kind::Function, String::from("draw_fancy"), 2),
+            (Kind::Variable, String::from("view_xview"), 2),
+        ]);
+        assert_eq!(missing[0].uses, ["script main:3:13", "script main:4:13"]);
+    }
+}
@@ -3,6 +3,7 @@ use std::{u8, mem, fmt};
 use crate::symbol::Symbol;
 use crate::vm;
 
+#[derive(Clone)]
 pub struct Function {
     pub params: u32,
     pub locals: u32,
@@ -21,13 +22,19 @@ impl Function {
             instructions: vec![],
         }
     }
+
+    /// Display the instruction at `offset`.
+    pub fn disassemble(&self, offset: usize) -> Disassembly<'_> {
+        Disassembly { function: self, inst: self.instructions[offset] }
+    }
 }
 
-#[derive(Default)]
+#[derive(Default, Clone)]
 pub struct Locations {
     pub mappings: Vec<SourceMap>,
 }
 
+#[derive(Clone)]
 pub struct SourceMap {
     pub offset: u32,
     pub location: u32,
@@ -145,41 +152,56 @@ impl fmt::Debug for Function {
         }
         writeln!(f, ")[{:?}]", self.locals)?;
 
-        for &inst in &self.instructions {
-            let (op, a, b, c) = inst.decode();
-            match op {
-                Op::Const => writeln!(f, "  %{:?} = {:?} {:?}", a, op, self.constants[b])?,
-                Op::GlobalConst => writeln!(f, "  %{:?} = {:?} {:?}", a, op, b)?,
-                Op::Move => writeln!(f, "  %{:?} = %{:?}", a, b)?,
-                Op::Neg | Op::Not | Op::BitNot | Op::ToArray | Op::ToScalar |
-                Op::LoadPointer | Op::NextPointer | Op::ExistsEntity |
-                Op::ScopeError =>
-                    writeln!(f, "  %{:?} = {:?} %{:?}", a, op, b)?,
-                Op::DeclareGlobal => writeln!(f, "  {:?} {:?}", op, self.symbols[a])?,
-                Op::Lookup => writeln!(f, "  %{:?} = {:?} {:?}", a, op, self.symbols[b])?,
-                Op::LoadScope => writeln!(f, "  %{:?} = {:?} {:?}", a, op, b as i8)?,
-                Op::StoreScope => writeln!(f, "  {:?} %{:?}, {:?}", op, a, b as i32)?,
-                Op::With => writeln!(f, "  %{:?}, %{:?} = {:?} %{:?}", a, b, op, c)?,
-                Op::ReleaseWith | Op::ReleaseOwned | Op::Ret =>
-                    writeln!(f, "  {:?}", op)?,
-                Op::Read => writeln!(f, "  {:?} %{:?}, {:?}", op, a, self.symbols[b])?,
-                Op::LoadField | Op::LoadFieldDefault =>
-                    writeln!(f, "  %{:?} = {:?} %{:?}.{:?}", a, op, b, self.symbols[c])?,
-                Op::LoadRow | Op::LoadIndex | Op::StoreRow =>
-                    writeln!(f, "  %{:?} = {:?} %{:?}[%{:?}]", a, op, b, c)?,
-                Op::StoreField =>
-                    writeln!(f, "  {:?} %{:?}, %{:?}.{:?}", op, a, b, self.symbols[c])?,
-                Op::StoreIndex => writeln!(f, "  {:?} %{:?}, %{:?}[%{:?}]", op, a, b, c)?,
-                Op::Call =>
-                    writeln!(f, "  %{:?} = {:?} {:?}(%{:?} +{:?})", b, op, self.constants[a], b, c)?,
-                Op::CallApi | Op::CallGet =>
-                    writeln!(f, "  %{:?} = {:?} {:?}(%{:?} +{:?})", b, op, self.symbols[a], b, c)?,
-                Op::CallSet =>
-                    writeln!(f, "  {:?} {:?}(%{:?} +{:?})", op, self.symbols[a], b, c)?,
-                Op::Jump => writeln!(f, "  {:?} {:?}", op, a | (b << 8))?,
-                Op::BranchFalse => writeln!(f, "  {:?} %{:?}, {:?}", op, a, b | (c << 8))?,
-                _ => writeln!(f, "  %{:?} = {:?} %{:?}, %{:?}", a, op, b, c)?,
-            }
+        for offset in 0..self.instructions.len() {
+            writeln!(f, "  {}", self.disassemble(offset))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// A single instruction, displayed as it appears in its function's `Debug` output.
+pub struct Disassembly<'a> {
+    function: &'a Function,
+    inst: Inst,
+}
+
+impl fmt::Display for Disassembly<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Disassembly { function, inst } = *self;
+        let (op, a, b, c) = inst.decode();
+        match op {
+            Op::Const => write!(f, "%{:?} = {:?} {:?}", a, op, function.constants[b])?,
+            Op::GlobalConst => write!(f, "%{:?} = {:?} {:?}", a, op, b)?,
+            Op::Move => write!(f, "%{:?} = %{:?}", a, b)?,
+            Op::Neg | Op::Not | Op::BitNot | Op::ToArray | Op::ToScalar |
+            Op::LoadPointer | Op::NextPointer | Op::ExistsEntity |
+            Op::ScopeError =>
+                write!(f, "%{:?} = {:?} %{:?}", a, op, b)?,
+            Op::DeclareGlobal => write!(f, "{:?} {:?}", op, function.symbols[a])?,
+            Op::Lookup => write!(f, "%{:?} = {:?} {:?}", a, op, function.symbols[b])?,
+            Op::LoadScope => write!(f, "%{:?} = {:?} {:?}", a, op, b as i8)?,
+            Op::StoreScope => write!(f, "{:?} %{:?}, {:?}", op, a, b as i32)?,
+            Op::With => write!(f, "%{:?}, %{:?} = {:?} %{:?}", a, b, op, c)?,
+            Op::ReleaseWith | Op::ReleaseOwned | Op::Ret =>
+                write!(f, "{:?}", op)?,
+            Op::Read => write!(f, "{:?} %{:?}, {:?}", op, a, function.symbols[b])?,
+            Op::LoadField | Op::LoadFieldDefault =>
+                write!(f, "%{:?} = {:?} %{:?}.{:?}", a, op, b, function.symbols[c])?,
+            Op::LoadRow | Op::LoadIndex | Op::StoreRow =>
+                write!(f, "%{:?} = {:?} %{:?}[%{:?}]", a, op, b, c)?,
+            Op::StoreField =>
+                write!(f, "{:?} %{:?}, %{:?}.{:?}", op, a, b, function.symbols[c])?,
+            Op::StoreIndex => write!(f, "{:?} %{:?}, %{:?}[%{:?}]", op, a, b, c)?,
+            Op::Call =>
+                write!(f, "%{:?} = {:?} {:?}(%{:?} +{:?})", b, op, function.constants[a], b, c)?,
+            Op::CallApi | Op::CallGet =>
+                write!(f, "%{:?} = {:?} {:?}(%{:?} +{:?})", b, op, function.symbols[a], b, c)?,
+            Op::CallSet =>
+                write!(f, "{:?} {:?}(%{:?} +{:?})", op, function.symbols[a], b, c)?,
+            Op::Jump => write!(f, "{:?} {:?}", op, a | (b << 8))?,
+            Op::BranchFalse => write!(f, "{:?} %{:?}, {:?}", op, a, b | (c << 8))?,
+            _ => write!(f, "%{:?} = {:?} %{:?}, %{:?}", a, op, b, c)?,
         }
 
         Ok(())
@@ -62,7 +62,10 @@ pub fn thunk(calltype: Cc, restype: Type, argtypes: &[Type]) -> Result<Thunk, Er
         _ => unreachable!()
     };
 
-    let signature = ((calltype as u32 * 2) + restype as u32) * 28 + argtypes;
+    // Outside of Windows, stdcall is not distinct from cdecl.
+    let calltype = if cfg!(windows) { calltype } else { Cc::Cdecl };
+
+    let signature = ((calltype as u32 * 2) + restype as u32) * NUM_SIGNATURES + argtypes;
     Ok(THUNKS[signature as usize])
 }
 
@@ -72,10 +75,11 @@ macro_rules! generate_thunks { [$(($($t:ty),*))*] => { [
     $(#[cfg(windows)] <unsafe extern "stdcall" fn($($t),*) -> f64 as FnExtern>::call,)*
     $(#[cfg(windows)] <unsafe extern "stdcall" fn($($t),*) -> *const c_char as FnExtern>::call,)*
 ] } }
+const NUM_SIGNATURES: u32 = 43;
 #[cfg(not(windows))]
-const NUM_THUNKS: usize = 86;
+const NUM_THUNKS: usize = 2 * NUM_SIGNATURES as usize;
 #[cfg(windows)]
-const NUM_THUNKS: usize = 86 + 86;
+const NUM_THUNKS: usize = 4 * NUM_SIGNATURES as usize;
 static THUNKS: [Thunk; NUM_THUNKS] = generate_thunks![
     ()
 
@@ -210,7 +210,8 @@ impl Thread {
 }
 
 // Opaque type to erase the runner-side container for `vm::World` and `vm::Assets`.
-unsafe extern "C" { type W; }
+#[repr(C)]
+struct W { _private: [u8; 0] }
 
 fn execute_internal(
     thread: &mut Thread, cx: &mut dyn for<'r> vm::Project<'r, (&'r mut World, &'r mut Assets<W>)>,
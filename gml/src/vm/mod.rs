@@ -22,7 +22,7 @@ pub mod dll;
 mod entity_map;
 mod instance_map;
 mod interpreter;
-//mod serialize;
+pub(crate) mod serialize;
 mod value;
 mod array;
 mod debug;
@@ -42,12 +42,14 @@ pub struct Assets<W: ?Sized> {
 pub struct Debug {
     pub locations: HashMap<Function, Locations>,
     pub scripts: Vec<Symbol>,
+    pub timelines: Vec<Symbol>,
     pub objects: Vec<Symbol>,
     pub rooms: Vec<Symbol>,
     pub instances: HashMap<i32, i32>,
     pub constants: Vec<Symbol>,
 }
 
+#[derive(Clone)]
 pub struct Locations {
     pub locations: code::Locations,
     pub lines: Lines,
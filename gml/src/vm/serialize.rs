@@ -0,0 +1,226 @@
+//! A binary encoding of compiled code, to save it between builds.
+//!
+//! Symbols are written as their text and re-interned when read, because symbol indices are only
+//! meaningful to the interner that produced them. Everything else is little-endian.
+//!
+//! The data may come from a corrupt or unrelated file, so lengths are not trusted to preallocate.
+
+use std::io::{self, Read, Write};
+
+use crate::symbol::Symbol;
+use crate::back::ssa;
+use crate::front::Lines;
+use crate::vm::{self, code};
+
+const REAL: u8 = 0;
+const STRING: u8 = 1;
+
+const NATIVE: u8 = 0;
+const MEMBER: u8 = 1;
+const RESOURCE: u8 = 2;
+const CONSTANT: u8 = 3;
+
+impl code::Function {
+    pub fn read<R: Read>(read: &mut R) -> io::Result<code::Function> {
+        let params = read_u32(read)?;
+        let locals = read_u32(read)?;
+        let len = read_u32(read)? as usize;
+        let mut symbols = Vec::default();
+        for _ in 0..len {
+            symbols.push(read_symbol(read)?);
+        }
+        let len = read_u32(read)? as usize;
+        let mut constants = Vec::default();
+        for _ in 0..len {
+            constants.push(read_value(read)?);
+        }
+        let len = read_u32(read)? as usize;
+        let mut instructions = Vec::default();
+        for _ in 0..len {
+            instructions.push(code::Inst(read_u32(read)?));
+        }
+
+        Ok(code::Function { params, locals, symbols, constants, instructions })
+    }
+
+    pub fn write<W: Write>(&self, write: &mut W) -> io::Result<()> {
+        write.write_all(&self.params.to_le_bytes())?;
+        write.write_all(&self.locals.to_le_bytes())?;
+        write.write_all(&(self.symbols.len() as u32).to_le_bytes())?;
+        for &symbol in &self.symbols[..] {
+            write_symbol(write, symbol)?;
+        }
+        write.write_all(&(self.constants.len() as u32).to_le_bytes())?;
+        for constant in &self.constants[..] {
+            write_value(write, constant)?;
+        }
+        write.write_all(&(self.instructions.len() as u32).to_le_bytes())?;
+        for &code::Inst(inst) in &self.instructions[..] {
+            write.write_all(&inst.to_le_bytes())?;
+        }
+
+        Ok(())
+    }
+}
+
+impl vm::Locations {
+    pub fn read<R: Read>(read: &mut R) -> io::Result<vm::Locations> {
+        let len = read_u32(read)? as usize;
+        let mut mappings = Vec::default();
+        for _ in 0..len {
+            let offset = read_u32(read)?;
+            let location = read_u32(read)?;
+            mappings.push(code::SourceMap { offset, location });
+        }
+        let locations = code::Locations { mappings };
+
+        let actions = read_pairs(read)?;
+        let arguments = read_pairs(read)?;
+        let len = read_u32(read)? as usize;
+        let mut lines = Vec::default();
+        for _ in 0..len {
+            lines.push(read_u32(read)? as usize);
+        }
+        let lines = Lines { actions, arguments, lines };
+
+        Ok(vm::Locations { locations, lines })
+    }
+
+    pub fn write<W: Write>(&self, write: &mut W) -> io::Result<()> {
+        let mappings = &self.locations.mappings[..];
+        write.write_all(&(mappings.len() as u32).to_le_bytes())?;
+        for &code::SourceMap { offset, location } in mappings {
+            write.write_all(&offset.to_le_bytes())?;
+            write.write_all(&location.to_le_bytes())?;
+        }
+
+        let Lines { ref actions, ref arguments, ref lines } = self.lines;
+        write_pairs(write, actions)?;
+        write_pairs(write, arguments)?;
+        write.write_all(&(lines.len() as u32).to_le_bytes())?;
+        for &line in &lines[..] {
+            write.write_all(&(line as u32).to_le_bytes())?;
+        }
+
+        Ok(())
+    }
+}
+
+impl ssa::Prototype {
+    pub fn read<R: Read>(read: &mut R) -> io::Result<ssa::Prototype> {
+        let prototype = match read_u8(read)? {
+            NATIVE => {
+                let arity = read_u32(read)? as usize;
+                let variadic = read_u8(read)? != 0;
+                let dll = read_u8(read)? != 0;
+                ssa::Prototype::Native { arity, variadic, dll }
+            }
+            MEMBER => ssa::Prototype::Member,
+            RESOURCE => {
+                let id = read_u32(read)? as i32;
+                let script = read_u8(read)? != 0;
+                ssa::Prototype::Resource { id, script }
+            }
+            CONSTANT => ssa::Prototype::Constant { id: read_u32(read)? as i32 },
+            _ => return Err(io::Error::from(io::ErrorKind::InvalidData)),
+        };
+
+        Ok(prototype)
+    }
+
+    pub fn write<W: Write>(&self, write: &mut W) -> io::Result<()> {
+        match *self {
+            ssa::Prototype::Native { arity, variadic, dll } => {
+                write.write_all(&[NATIVE])?;
+                write.write_all(&(arity as u32).to_le_bytes())?;
+                write.write_all(&[variadic as u8, dll as u8])?;
+            }
+            ssa::Prototype::Member => write.write_all(&[MEMBER])?,
+            ssa::Prototype::Resource { id, script } => {
+                write.write_all(&[RESOURCE])?;
+                write.write_all(&id.to_le_bytes())?;
+                write.write_all(&[script as u8])?;
+            }
+            ssa::Prototype::Constant { id } => {
+                write.write_all(&[CONSTANT])?;
+                write.write_all(&id.to_le_bytes())?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Read a constant, which is always a real or a string.
+pub(crate) fn read_value<R: Read>(read: &mut R) -> io::Result<vm::Value> {
+    match read_u8(read)? {
+        REAL => Ok(vm::Value::from(f64::from_bits(read_u64(read)?))),
+        STRING => Ok(vm::Value::from(read_symbol(read)?)),
+        _ => Err(io::Error::from(io::ErrorKind::InvalidData)),
+    }
+}
+
+/// Write a constant. Arrays are only created at run time, so they cannot be written.
+pub(crate) fn write_value<W: Write>(write: &mut W, value: &vm::Value) -> io::Result<()> {
+    match value.borrow().decode() {
+        vm::Data::Real(real) => {
+            write.write_all(&[REAL])?;
+            write.write_all(&real.to_bits().to_le_bytes())
+        }
+        vm::Data::String(symbol) => {
+            write.write_all(&[STRING])?;
+            write_symbol(write, symbol)
+        }
+        vm::Data::Array(_) => Err(io::Error::from(io::ErrorKind::InvalidInput)),
+    }
+}
+
+pub(crate) fn read_symbol<R: Read>(read: &mut R) -> io::Result<Symbol> {
+    let len = read_u32(read)? as u64;
+    let mut buf = Vec::default();
+    if Read::take(read, len).read_to_end(&mut buf)? as u64 != len {
+        return Err(io::Error::from(io::ErrorKind::UnexpectedEof));
+    }
+    Ok(Symbol::intern(&buf))
+}
+
+pub(crate) fn write_symbol<W: Write>(write: &mut W, symbol: Symbol) -> io::Result<()> {
+    write.write_all(&(symbol.len() as u32).to_le_bytes())?;
+    write.write_all(&symbol)
+}
+
+fn read_pairs<R: Read>(read: &mut R) -> io::Result<Vec<(usize, usize)>> {
+    let len = read_u32(read)? as usize;
+    let mut pairs = Vec::default();
+    for _ in 0..len {
+        pairs.push((read_u32(read)? as usize, read_u32(read)? as usize));
+    }
+    Ok(pairs)
+}
+
+fn write_pairs<W: Write>(write: &mut W, pairs: &[(usize, usize)]) -> io::Result<()> {
+    write.write_all(&(pairs.len() as u32).to_le_bytes())?;
+    for &(a, b) in pairs {
+        write.write_all(&(a as u32).to_le_bytes())?;
+        write.write_all(&(b as u32).to_le_bytes())?;
+    }
+    Ok(())
+}
+
+pub(crate) fn read_u8<R: Read>(read: &mut R) -> io::Result<u8> {
+    let mut buf = [0; 1];
+    read.read_exact(&mut buf)?;
+    Ok(buf[0])
+}
+
+pub(crate) fn read_u32<R: Read>(read: &mut R) -> io::Result<u32> {
+    let mut buf = [0; 4];
+    read.read_exact(&mut buf)?;
+    Ok(u32::from_le_bytes(buf))
+}
+
+pub(crate) fn read_u64<R: Read>(read: &mut R) -> io::Result<u64> {
+    let mut buf = [0; 8];
+    read.read_exact(&mut buf)?;
+    Ok(u64::from_le_bytes(buf))
+}
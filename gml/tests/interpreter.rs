@@ -3,7 +3,7 @@ use std::convert::TryFrom;
 use std::io;
 use std::ops::Range;
 
-use gml::{Function, Item, symbol::Symbol, vm};
+use gml::{Function, Item, symbol::Symbol, vm, diagnostic::{ErrorsOnly, Text}};
 
 use bstr::BStr;
 
@@ -18,7 +18,8 @@ fn arguments() -> vm::Result<()> {
         return argument0 + argument1
     }") });
 
-    let (mut code, _) = gml::build(&game, &[], &items, io::stderr).unwrap_or_else(|_| panic!());
+    let (mut code, _) = gml::build(&game, &[], &items, || Text(io::stderr()))
+        .unwrap_or_else(|_| panic!());
     gml::load(&mut code, &items);
     let assets = Assets { code };
     let world = World::default();
@@ -53,7 +54,8 @@ fn member() -> vm::Result<()> {
         return c
     }") });
 
-    let (mut code, _) = gml::build(&game, &[], &items, io::stderr).unwrap_or_else(|_| panic!());
+    let (mut code, _) = gml::build(&game, &[], &items, || Text(io::stderr()))
+        .unwrap_or_else(|_| panic!());
     gml::load(&mut code, &items);
     let assets = Assets { code };
     let mut world = World::default();
@@ -98,7 +100,8 @@ fn builtin() -> vm::Result<()> {
         return global_array[1]
     }") });
 
-    let (mut code, _) = gml::build(&game, &[], &items, io::stderr).unwrap_or_else(|_| panic!());
+    let (mut code, _) = gml::build(&game, &[], &items, || Text(io::stderr()))
+        .unwrap_or_else(|_| panic!());
     gml::load(&mut code, &items);
     let assets = Assets { code };
     let mut world = World::default();
@@ -137,7 +140,8 @@ fn global() -> vm::Result<()> {
         return self.a + a
     }") });
 
-    let (mut code, _) = gml::build(&game, &[], &items, io::stderr).unwrap_or_else(|_| panic!());
+    let (mut code, _) = gml::build(&game, &[], &items, || Text(io::stderr()))
+        .unwrap_or_else(|_| panic!());
     gml::load(&mut code, &items);
     let assets = Assets { code };
     let mut world = World::default();
@@ -172,7 +176,8 @@ fn with_scopes() -> vm::Result<()> {
         return argument0.n + argument1.n + argument0.m + argument1.m
     }") });
 
-    let (mut code, _) = gml::build(&game, &[], &items, io::stderr).unwrap_or_else(|_| panic!());
+    let (mut code, _) = gml::build(&game, &[], &items, || Text(io::stderr()))
+        .unwrap_or_else(|_| panic!());
     gml::load(&mut code, &items);
     let assets = Assets { code };
     let mut world = World::default();
@@ -212,7 +217,8 @@ fn with_iterator() -> vm::Result<()> {
     let create_instance = Symbol::intern(b"create_instance");
     items.insert(create_instance, Item::Native(World::native_create_instance, 0, false));
 
-    let (mut code, _) = gml::build(&game, &[], &items, io::stderr).unwrap_or_else(|_| panic!());
+    let (mut code, _) = gml::build(&game, &[], &items, || Text(io::stderr()))
+        .unwrap_or_else(|_| panic!());
     gml::load(&mut code, &items);
     let assets = Assets { code };
     let mut world = World::default();
@@ -244,7 +250,8 @@ fn array() -> vm::Result<()> {
         return a + a[1] + b[0] + b[1] + b[2] + c + c[1, 1]
     }") });
 
-    let (mut code, _) = gml::build(&game, &[], &items, io::stderr).unwrap_or_else(|_| panic!());
+    let (mut code, _) = gml::build(&game, &[], &items, || Text(io::stderr()))
+        .unwrap_or_else(|_| panic!());
     gml::load(&mut code, &items);
     let assets = Assets { code };
     let world = World::default();
@@ -272,7 +279,8 @@ fn conditional_initialization() -> vm::Result<()> {
         return t
     }") });
 
-    let _: (vm::Assets<Context>, _) = gml::build(&game, &[], &items, io::stderr)
+    let errors = || ErrorsOnly(Text(io::stderr()));
+    let _: (vm::Assets<Context>, _) = gml::build(&game, &[], &items, errors)
         .unwrap_or_else(|_| panic!());
     Ok(())
 }
@@ -291,7 +299,8 @@ fn dead_undef() -> vm::Result<()> {
         return i
     }") });
 
-    let _: (vm::Assets<Context>, _) = gml::build(&game, &[], &items, io::stderr)
+    let errors = || ErrorsOnly(Text(io::stderr()));
+    let _: (vm::Assets<Context>, _) = gml::build(&game, &[], &items, errors)
         .unwrap_or_else(|_| panic!());
     Ok(())
 }
@@ -312,7 +321,8 @@ fn for_loop() -> vm::Result<()> {
         return j
     }") });
 
-    let (mut code, _) = gml::build(&game, &[], &items, io::stderr).unwrap_or_else(|_| panic!());
+    let (mut code, _) = gml::build(&game, &[], &items, || Text(io::stderr()))
+        .unwrap_or_else(|_| panic!());
     gml::load(&mut code, &items);
     let assets = Assets { code };
     let world = World::default();
@@ -345,7 +355,8 @@ fn switch() -> vm::Result<()> {
         return i
     }") });
 
-    let (mut code, _) = gml::build(&game, &[], &items, io::stderr).unwrap_or_else(|_| panic!());
+    let (mut code, _) = gml::build(&game, &[], &items, || Text(io::stderr()))
+        .unwrap_or_else(|_| panic!());
     gml::load(&mut code, &items);
     let assets = Assets { code };
     let world = World::default();
@@ -379,7 +390,7 @@ fn switch_empty() -> vm::Result<()> {
         }
     }") });
 
-    let _: (vm::Assets<Context>, _) = gml::build(&game, &[], &items, io::stderr)
+    let _: (vm::Assets<Context>, _) = gml::build(&game, &[], &items, || Text(io::stderr()))
         .unwrap_or_else(|_| panic!());
     Ok(())
 }
@@ -404,7 +415,8 @@ fn switch_fallthrough() -> vm::Result<()> {
         return i
     }") });
 
-    let (mut code, _) = gml::build(&game, &[], &items, io::stderr).unwrap_or_else(|_| panic!());
+    let (mut code, _) = gml::build(&game, &[], &items, || Text(io::stderr()))
+        .unwrap_or_else(|_| panic!());
     gml::load(&mut code, &items);
     let assets = Assets { code };
     let world = World::default();
@@ -438,7 +450,8 @@ fn call_script() -> vm::Result<()> {
     let call = Function::Script { id: game.scripts.len() as i32 };
     game.scripts.push(project::Script { name: BStr::new(b"call"), body: BStr::new(b"return id(3) + 5") });
 
-    let (mut code, _) = gml::build(&game, &[], &items, io::stderr).unwrap_or_else(|_| panic!());
+    let (mut code, _) = gml::build(&game, &[], &items, || Text(io::stderr()))
+        .unwrap_or_else(|_| panic!());
     gml::load(&mut code, &items);
     let assets = Assets { code };
     let world = World::default();
@@ -450,6 +463,73 @@ fn call_script() -> vm::Result<()> {
     Ok(())
 }
 
+/// Refer to fonts by name.
+#[test]
+fn font_resource() -> vm::Result<()> {
+    let mut game = project::Game::default();
+    let items = HashMap::default();
+
+    game.fonts.push(project::Font { name: BStr::new(b"small_fnt"), ..project::Font::default() });
+    game.fonts.push(project::Font { name: BStr::new(b"large_fnt"), ..project::Font::default() });
+
+    let fonts = Function::Script { id: game.scripts.len() as i32 };
+    game.scripts.push(project::Script {
+        name: BStr::new(b"fonts"),
+        body: BStr::new(b"return small_fnt * 10 + large_fnt"),
+    });
+
+    let (mut code, _) = gml::build(&game, &[], &items, || Text(io::stderr()))
+        .unwrap_or_else(|_| panic!());
+    gml::load(&mut code, &items);
+    let assets = Assets { code };
+    let world = World::default();
+
+    let mut thread = vm::Thread::default();
+    let mut cx = Context { world, assets };
+
+    assert_eq!(thread.execute(&mut cx, fonts, vec![])?, vm::Value::from(1));
+    Ok(())
+}
+
+/// Run the actions at a timeline moment.
+#[test]
+fn timeline_moment() -> vm::Result<()> {
+    let mut game = project::Game::default();
+    let items = HashMap::default();
+
+    let code = |code: &'static [u8]| project::Action {
+        library: 1,
+        action: 603,
+        action_kind: project::action_kind::CODE,
+        has_target: true,
+        parameters_used: 1,
+        parameters: vec![project::argument_type::STRING],
+        target: vm::SELF,
+        arguments: vec![BStr::new(code)],
+        ..project::Action::default()
+    };
+    game.timelines.push(project::Timeline {
+        name: BStr::new(b"spawner_tl"),
+        moments: vec![
+            project::Moment { moment: 0, actions: vec![code(b"return 1")] },
+            project::Moment { moment: 60, actions: vec![code(b"return spawner_tl + 60")] },
+        ],
+    });
+
+    let (mut code, _) = gml::build(&game, &[], &items, || Text(io::stderr()))
+        .unwrap_or_else(|_| panic!());
+    gml::load(&mut code, &items);
+    let assets = Assets { code };
+    let world = World::default();
+
+    let mut thread = vm::Thread::default();
+    let mut cx = Context { world, assets };
+
+    let moment = Function::Timeline { id: 0, moment: 60 };
+    assert_eq!(thread.execute(&mut cx, moment, vec![])?, vm::Value::from(60));
+    Ok(())
+}
+
 /// Recursively call a GML script.
 #[test]
 fn recurse() -> vm::Result<()> {
@@ -465,7 +545,8 @@ fn recurse() -> vm::Result<()> {
         }
     }") });
 
-    let (mut code, _) = gml::build(&game, &[], &items, io::stderr).unwrap_or_else(|_| panic!());
+    let (mut code, _) = gml::build(&game, &[], &items, || Text(io::stderr()))
+        .unwrap_or_else(|_| panic!());
     gml::load(&mut code, &items);
     let assets = Assets { code };
     let world = World::default();
@@ -497,7 +578,8 @@ fn ffi() -> vm::Result<()> {
         return add(3, 5) + 8
     }") });
 
-    let (mut code, _) = gml::build(&game, &[], &items, io::stderr).unwrap_or_else(|_| panic!());
+    let (mut code, _) = gml::build(&game, &[], &items, || Text(io::stderr()))
+        .unwrap_or_else(|_| panic!());
     gml::load(&mut code, &items);
     let assets = Assets { code };
     let world = World::default();
@@ -530,7 +612,8 @@ fn reentrant() -> vm::Result<()> {
         return argument0 + 5
     }") });
 
-    let (mut code, _) = gml::build(&game, &[], &items, io::stderr).unwrap_or_else(|_| panic!());
+    let (mut code, _) = gml::build(&game, &[], &items, || Text(io::stderr()))
+        .unwrap_or_else(|_| panic!());
     gml::load(&mut code, &items);
     let assets = Assets { code };
     let world = World::default();
@@ -3,6 +3,7 @@
 use std::{io, ptr, slice};
 use std::io::Write;
 use runner::World;
+use gml::diagnostic::{Emit, Text};
 use wasm::{JsValue, Reflect, Layout};
 use bstr::BStr;
 
@@ -136,7 +137,7 @@ pub extern "C" fn run<'a>(
     std::panic::set_hook(Box::new(|info| { let _ = writeln!(HostErr(), "{info}"); }));
     unsafe { clear() };
 
-    let (mut assets, debug) = match runner::build(game, &[], &arena, HostErr) {
+    let (mut assets, debug) = match runner::build(game, &[], &arena, None, || Text(HostErr())) {
         Ok(assets) => assets,
         Err(errors) => {
             if errors > 1 {
@@ -151,6 +152,7 @@ pub extern "C" fn run<'a>(
     let mut world = World::from_assets(&assets, debug);
     world.draw.platform.canvas = canvas;
     world.debug.error = |state, error| state.show_vm_error_write(error, HostErr());
+    world.debug.diagnostic = |_, diagnostic| Text(HostErr()).emit(diagnostic);
     world.debug.write = Box::new(HostOut);
 
     runner::run(runner::Context { world, assets })
@@ -0,0 +1,1690 @@
+//! A directory layout for projects, meant to be kept in version control.
+//!
+//! `game.txt` holds the game's identity, the names of its resources in order, and the lists that
+//! have no file of their own. `settings.txt` and `information.txt` hold the settings and game
+//! information. Each resource is stored under a directory for its kind, in a file named after it:
+//! scripts as `.gml`, images as `.png`, and everything else as text.
+//!
+//! Text files hold one `key value` field per line, in a fixed order. Strings are quoted, unless
+//! they span multiple lines, in which case the key is followed by one line per line of the string,
+//! each prefixed by `|`. Blank lines and indentation are ignored.
+
+use std::{fs, io, str};
+use std::collections::HashSet;
+use std::fmt::Display;
+use std::io::Write as _;
+use std::path;
+use std::str::FromStr;
+use bstr::BStr;
+use quickdry::Arena;
+
+use crate::{
+    Game, Settings, Trigger, Constant, IncludedFile, GameInformation,
+    Sound,
+    Sprite, Image, Mask, Bounds,
+    Background,
+    Path, Point,
+    Script,
+    Font, Glyph,
+    Timeline, Moment,
+    Object, Action, Event,
+    Room, RoomBackground, View, Instance, Tile,
+};
+use crate::read::alloc_buf;
+use crate::validate::{self, Resource};
+
+/// The directories written by `write_dir`, which it clears before writing.
+const KINDS: [&str; 10] = [
+    "sounds", "sprites", "backgrounds", "paths", "scripts", "fonts", "timelines", "objects",
+    "rooms", "included",
+];
+
+pub fn read_dir<'a>(dir: &path::Path, game: &mut Game<'a>, arena: &'a Arena) -> io::Result<()> {
+    let data = fs::read(dir.join("game.txt"))?;
+    let read = &mut TextRead::new(&data[..]);
+
+    game.version = read.next("version")?;
+    game.debug = read.next("debug")?;
+    game.pro = read.next("pro")?;
+    game.id = read.next("id")?;
+    game.guid = read.next_array("guid")?;
+    game.last_instance = read.next("last_instance")?;
+    game.last_tile = read.next("last_tile")?;
+
+    let len = read.next("triggers")?;
+    for _ in 0..len {
+        let trigger = Trigger {
+            name: read.next_bstr("trigger", arena)?,
+            condition: read.next_bstr("condition", arena)?,
+            moment: read.next("moment")?,
+            constant: read.next_bstr("constant", arena)?,
+        };
+        game.triggers.push(trigger);
+    }
+
+    let len = read.next("constants")?;
+    for _ in 0..len {
+        let constant = Constant {
+            name: read.next_bstr("constant", arena)?,
+            value: read.next_bstr("value", arena)?,
+        };
+        game.constants.push(constant);
+    }
+
+    let sounds = read_names(read, "sounds", "sound", arena)?;
+    let sprites = read_names(read, "sprites", "sprite", arena)?;
+    let backgrounds = read_names(read, "backgrounds", "background", arena)?;
+    let paths = read_names(read, "paths", "path", arena)?;
+    let scripts = read_names(read, "scripts", "script", arena)?;
+    let fonts = read_names(read, "fonts", "font", arena)?;
+    let timelines = read_names(read, "timelines", "timeline", arena)?;
+    let objects = read_names(read, "objects", "object", arena)?;
+    let rooms = read_names(read, "rooms", "room", arena)?;
+
+    let len = read.next("included_files")?;
+    for _ in 0..len {
+        let file = IncludedFile {
+            name: read.next_bstr("file", arena)?,
+            path: read.next_bstr("path", arena)?,
+            original: read.next("original")?,
+            size: read.next("size")?,
+            stored: read.next("stored")?,
+            export: read.next("export")?,
+            folder: read.next_bstr("folder", arena)?,
+            overwrite: read.next("overwrite")?,
+            free: read.next("free")?,
+            remove: read.next("remove")?,
+            ..IncludedFile::default()
+        };
+        game.included_files.push(file);
+    }
+
+    let len = read.next("extensions")?;
+    for _ in 0..len {
+        game.extensions.push(read.next_bstr("extension", arena)?);
+    }
+
+    game.room_order = read.next_list("room_order")?;
+    read.end()?;
+
+    let data = fs::read(dir.join("settings.txt"))?;
+    let read = &mut TextRead::new(&data[..]);
+    read_settings(read, &mut game.settings)?;
+    read.end()?;
+
+    let data = fs::read(dir.join("information.txt"))?;
+    let read = &mut TextRead::new(&data[..]);
+    read_info(read, &mut game.information, arena)?;
+    read.end()?;
+    let text = fs::read(dir.join("information.rtf"))?;
+    game.information.text = BStr::new(alloc(arena, &text[..]));
+
+    let names = file_names(game.included_files.iter().map(|file| file.name), true);
+    for (file, name) in Iterator::zip(game.included_files.iter_mut(), names) {
+        if let Some(data) = read_file(&dir.join("included").join(name))? {
+            file.data = alloc(arena, &data[..]);
+        }
+    }
+
+    let kind = dir.join("sounds");
+    game.sounds.reserve(sounds.len());
+    let names = file_names(sounds.iter().copied(), false);
+    for (name, file) in Iterator::zip(sounds.into_iter(), names) {
+        let mut sound = Sound { name, ..Sound::default() };
+        if !name.is_empty() {
+            let data = fs::read(kind.join(format!("{file}.txt")))?;
+            let read = &mut TextRead::new(&data[..]);
+            read_sound(read, &mut sound, arena)?;
+            read.end()?;
+
+            if let Some(data) = read_file(&kind.join(sound_file(&file, sound.file_type)))? {
+                sound.data = alloc(arena, &data[..]);
+            }
+        }
+        game.sounds.push(sound);
+    }
+
+    let kind = dir.join("sprites");
+    game.sprites.reserve(sprites.len());
+    let names = file_names(sprites.iter().copied(), false);
+    for (name, file) in Iterator::zip(sprites.into_iter(), names) {
+        let mut sprite = Sprite { name, ..Sprite::default() };
+        if !name.is_empty() {
+            let data = fs::read(kind.join(format!("{file}.txt")))?;
+            let read = &mut TextRead::new(&data[..]);
+            read_sprite(read, &mut sprite)?;
+            read.end()?;
+
+            let images = kind.join(&file);
+            for (i, image) in sprite.images.iter_mut().enumerate() {
+                if sprite.version < 800 {
+                    let data = read_image(&images.join(format!("{i}.bmp")), image.size)?;
+                    image.data = alloc(arena, &data[..]);
+                } else {
+                    let data = read_image(&images.join(format!("{i}.png")), image.size)?;
+                    if data.is_empty() { continue; }
+                    image.data = alloc(arena, &read_png(&data[..], image.size)?[..]);
+                }
+            }
+            for (i, mask) in sprite.masks.iter_mut().enumerate() {
+                let data = read_file(&images.join(format!("mask{i}.txt")))?.unwrap_or_default();
+                read_mask(&data[..], mask)?;
+            }
+        }
+        game.sprites.push(sprite);
+    }
+
+    let kind = dir.join("backgrounds");
+    game.backgrounds.reserve(backgrounds.len());
+    let names = file_names(backgrounds.iter().copied(), false);
+    for (name, file) in Iterator::zip(backgrounds.into_iter(), names) {
+        let mut background = Background { name, ..Background::default() };
+        if !name.is_empty() {
+            let data = fs::read(kind.join(format!("{file}.txt")))?;
+            let read = &mut TextRead::new(&data[..]);
+            read_background(read, &mut background)?;
+            read.end()?;
+
+            if background.version < 710 {
+                let data = read_image(&kind.join(format!("{file}.bmp")), background.size)?;
+                background.data = alloc(arena, &data[..]);
+            } else {
+                let data = read_image(&kind.join(format!("{file}.png")), background.size)?;
+                if !data.is_empty() {
+                    background.data = alloc(arena, &read_png(&data[..], background.size)?[..]);
+                }
+            }
+        }
+        game.backgrounds.push(background);
+    }
+
+    let kind = dir.join("paths");
+    game.paths.reserve(paths.len());
+    let names = file_names(paths.iter().copied(), false);
+    for (name, file) in Iterator::zip(paths.into_iter(), names) {
+        let mut path = Path { name, ..Path::default() };
+        if !name.is_empty() {
+            let data = fs::read(kind.join(format!("{file}.txt")))?;
+            let read = &mut TextRead::new(&data[..]);
+            read_path(read, &mut path)?;
+            read.end()?;
+        }
+        game.paths.push(path);
+    }
+
+    let kind = dir.join("scripts");
+    game.scripts.reserve(scripts.len());
+    let names = file_names(scripts.iter().copied(), false);
+    for (name, file) in Iterator::zip(scripts.into_iter(), names) {
+        let mut script = Script { name, ..Script::default() };
+        if !name.is_empty() {
+            let body = fs::read(kind.join(format!("{file}.gml")))?;
+            script.body = BStr::new(alloc(arena, &body[..]));
+        }
+        game.scripts.push(script);
+    }
+
+    let kind = dir.join("fonts");
+    game.fonts.reserve(fonts.len());
+    let names = file_names(fonts.iter().copied(), false);
+    for (name, file) in Iterator::zip(fonts.into_iter(), names) {
+        let mut font = Font { name, ..Font::default() };
+        if !name.is_empty() {
+            let data = fs::read(kind.join(format!("{file}.txt")))?;
+            let read = &mut TextRead::new(&data[..]);
+            read_font(read, &mut font, arena)?;
+            read.end()?;
+
+            let data = read_image(&kind.join(format!("{file}.png")), font.bitmap_size)?;
+            if !data.is_empty() {
+                font.bitmap = alloc(arena, &read_png_alpha(&data[..], font.bitmap_size)?[..]);
+            }
+        }
+        game.fonts.push(font);
+    }
+
+    let kind = dir.join("timelines");
+    game.timelines.reserve(timelines.len());
+    let names = file_names(timelines.iter().copied(), false);
+    for (name, file) in Iterator::zip(timelines.into_iter(), names) {
+        let mut timeline = Timeline { name, ..Timeline::default() };
+        if !name.is_empty() {
+            let data = fs::read(kind.join(format!("{file}.txt")))?;
+            let read = &mut TextRead::new(&data[..]);
+            read_timeline(read, &mut timeline, arena)?;
+            read.end()?;
+        }
+        game.timelines.push(timeline);
+    }
+
+    let kind = dir.join("objects");
+    game.objects.reserve(objects.len());
+    let names = file_names(objects.iter().copied(), false);
+    for (name, file) in Iterator::zip(objects.into_iter(), names) {
+        let mut object = Object { name, ..Object::default() };
+        if !name.is_empty() {
+            let data = fs::read(kind.join(format!("{file}.txt")))?;
+            let read = &mut TextRead::new(&data[..]);
+            read_object(read, &mut object, arena)?;
+            read.end()?;
+        }
+        game.objects.push(object);
+    }
+
+    let kind = dir.join("rooms");
+    game.rooms.reserve(rooms.len());
+    let names = file_names(rooms.iter().copied(), false);
+    for (name, file) in Iterator::zip(rooms.into_iter(), names) {
+        let mut room = Room { name, ..Room::default() };
+        if !name.is_empty() {
+            let data = fs::read(kind.join(format!("{file}.txt")))?;
+            let read = &mut TextRead::new(&data[..]);
+            read_room(read, &mut room, arena)?;
+            read.end()?;
+        }
+        game.rooms.push(room);
+    }
+
+    Ok(())
+}
+
+fn read_names<'a>(
+    read: &mut TextRead<'_>, list: &str, key: &str, arena: &'a Arena
+) -> io::Result<Vec<&'a BStr>> {
+    let len = read.next(list)?;
+    let mut names = Vec::default();
+    for _ in 0..len {
+        names.push(read.next_bstr(key, arena)?);
+    }
+    Ok(names)
+}
+
+fn read_settings(read: &mut TextRead<'_>, settings: &mut Settings) -> io::Result<()> {
+    settings.fullscreen = read.next("fullscreen")?;
+    settings.scaling = read.next("scaling")?;
+    settings.interpolation = read.next("interpolation")?;
+    settings.background_color = read.next_color("background_color")?;
+    settings.allow_resize = read.next("allow_resize")?;
+    settings.topmost = read.next("topmost")?;
+    settings.hide_border = read.next("hide_border")?;
+    settings.hide_buttons = read.next("hide_buttons")?;
+    settings.show_cursor = read.next("show_cursor")?;
+    settings.freeze = read.next("freeze")?;
+    settings.disable_screensaver = read.next("disable_screensaver")?;
+
+    settings.set_resolution = read.next("set_resolution")?;
+    settings.color_depth = read.next("color_depth")?;
+    settings.resolution = read.next("resolution")?;
+    settings.frequency = read.next("frequency")?;
+    settings.vsync = read.next("vsync")?;
+
+    settings.default_esc = read.next("default_esc")?;
+    settings.close_as_esc = read.next("close_as_esc")?;
+    settings.default_f1 = read.next("default_f1")?;
+    settings.default_f4 = read.next("default_f4")?;
+    settings.default_f5 = read.next("default_f5")?;
+    settings.default_f9 = read.next("default_f9")?;
+    settings.priority = read.next("priority")?;
+
+    settings.load_image = read.next("load_image")?;
+    settings.load_transparent = read.next("load_transparent")?;
+    settings.load_alpha = read.next("load_alpha")?;
+    settings.load_bar = read.next("load_bar")?;
+    settings.load_scale = read.next("load_scale")?;
+
+    settings.error_display = read.next("error_display")?;
+    settings.error_log = read.next("error_log")?;
+    settings.error_abort = read.next("error_abort")?;
+    settings.uninitialized_zero = read.next("uninitialized_zero")?;
+    settings.uninitialized_arguments = read.next("uninitialized_arguments")?;
+
+    Ok(())
+}
+
+fn read_info<'a>(
+    read: &mut TextRead<'_>, info: &mut GameInformation<'a>, arena: &'a Arena
+) -> io::Result<()> {
+    info.background_color = read.next_color("background_color")?;
+    info.separate_window = read.next("separate_window")?;
+    info.caption = read.next_bstr("caption", arena)?;
+    info.position = read.next_pair("position")?;
+    info.size = read.next_pair("size")?;
+    info.show_border = read.next("show_border")?;
+    info.allow_resize = read.next("allow_resize")?;
+    info.topmost = read.next("topmost")?;
+    info.freeze = read.next("freeze")?;
+
+    Ok(())
+}
+
+fn read_sound<'a>(
+    read: &mut TextRead<'_>, sound: &mut Sound<'a>, arena: &'a Arena
+) -> io::Result<()> {
+    sound.kind = read.next("kind")?;
+    sound.file_type = read.next_bstr("file_type", arena)?;
+    sound.file_name = read.next_bstr("file_name", arena)?;
+    sound.effects = read.next("effects")?;
+    sound.volume = read.next("volume")?;
+    sound.pan = read.next("pan")?;
+    sound.preload = read.next("preload")?;
+
+    Ok(())
+}
+
+fn read_sprite(read: &mut TextRead<'_>, sprite: &mut Sprite<'_>) -> io::Result<()> {
+    sprite.version = read.next("version")?;
+    if ![400, 542, 800, 810].contains(&sprite.version) {
+        return Err(read.error(format_args!("unsupported sprite version {}", sprite.version)));
+    }
+    sprite.size = read.next_pair("size")?;
+    sprite.transparent = read.next("transparent")?;
+    sprite.precise = read.next("precise")?;
+    sprite.use_vram = read.next("use_vram")?;
+    sprite.lazy_load = read.next("lazy_load")?;
+    sprite.origin = read.next_pair("origin")?;
+
+    sprite.shape = read.next("shape")?;
+    sprite.alpha_tolerance = read.next("alpha_tolerance")?;
+    sprite.separate_collision = read.next("separate_collision")?;
+    sprite.bounds_kind = read.next("bounds_kind")?;
+    sprite.bounds = read_bounds(read)?;
+
+    let len = read.next("images")?;
+    for _ in 0..len {
+        let size = read.next_pair("image")?;
+        sprite.images.push(Image { size, ..Image::default() });
+    }
+
+    let len = read.next("masks")?;
+    for _ in 0..len {
+        let size = read.next_pair("mask")?;
+        let bounds = read_bounds(read)?;
+        sprite.masks.push(Mask { size, bounds, ..Mask::default() });
+    }
+
+    Ok(())
+}
+
+fn read_bounds(read: &mut TextRead<'_>) -> io::Result<Bounds> {
+    let [left, right, bottom, top] = read.next_array("bounds")?;
+    Ok(Bounds { left, right, bottom, top })
+}
+
+/// Read a collision mask, stored as rows of `0` and `1`.
+fn read_mask(data: &[u8], mask: &mut Mask) -> io::Result<()> {
+    let size = mask.size.0 as usize * mask.size.1 as usize;
+    for row in data.split(|&c| c == b'\n').map(<[u8]>::trim_ascii).filter(|row| !row.is_empty()) {
+        if row.len() != mask.size.0 as usize {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "wrong mask width"));
+        }
+        for &c in row {
+            match c {
+                b'0' => mask.data.push(0),
+                b'1' => mask.data.push(1),
+                _ => return Err(io::Error::new(io::ErrorKind::InvalidData, "invalid mask")),
+            }
+        }
+    }
+    if mask.data.len() != size {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "wrong mask height"));
+    }
+
+    Ok(())
+}
+
+fn read_background(read: &mut TextRead<'_>, background: &mut Background<'_>) -> io::Result<()> {
+    background.version = read.next("version")?;
+    if ![400, 543, 710].contains(&background.version) {
+        let version = background.version;
+        return Err(read.error(format_args!("unsupported background version {version}")));
+    }
+    background.size = read.next_pair("size")?;
+    background.transparent = read.next("transparent")?;
+    background.use_vram = read.next("use_vram")?;
+    background.lazy_load = read.next("lazy_load")?;
+
+    Ok(())
+}
+
+fn read_path(read: &mut TextRead<'_>, path: &mut Path<'_>) -> io::Result<()> {
+    path.smooth = read.next("smooth")?;
+    path.closed = read.next("closed")?;
+    path.precision = read.next("precision")?;
+
+    let len = read.next("points")?;
+    for _ in 0..len {
+        let [x, y, speed] = read.next_array("point")?;
+        path.points.push(Point { position: (x, y), speed });
+    }
+
+    Ok(())
+}
+
+fn read_font<'a>(read: &mut TextRead<'_>, font: &mut Font<'a>, arena: &'a Arena) -> io::Result<()> {
+    font.font = read.next_bstr("font", arena)?;
+    font.size = read.next("size")?;
+    font.bold = read.next("bold")?;
+    font.italic = read.next("italic")?;
+    font.start = read.next("start")?;
+    font.end = read.next("end")?;
+    font.charset = read.next("charset")?;
+    font.antialias = read.next("antialias")?;
+
+    let len = read.next("glyphs")?;
+    for _ in 0..len {
+        let [x, y, width, height, shift, offset] = read.next_array::<i64, 6>("glyph")?;
+        font.glyphs.push(Glyph {
+            position: (x as u32, y as u32),
+            size: (width as u32, height as u32),
+            shift: shift as i32,
+            offset: offset as i32,
+        });
+    }
+    font.bitmap_size = read.next_pair("bitmap_size")?;
+
+    Ok(())
+}
+
+fn read_timeline<'a>(
+    read: &mut TextRead<'_>, timeline: &mut Timeline<'a>, arena: &'a Arena
+) -> io::Result<()> {
+    let len = read.next("moments")?;
+    for _ in 0..len {
+        let mut moment = Moment { moment: read.next("moment")?, ..Moment::default() };
+        read_actions(read, &mut moment.actions, arena)?;
+        timeline.moments.push(moment);
+    }
+
+    Ok(())
+}
+
+fn read_object<'a>(
+    read: &mut TextRead<'_>, object: &mut Object<'a>, arena: &'a Arena
+) -> io::Result<()> {
+    object.sprite = read.next("sprite")?;
+    object.solid = read.next("solid")?;
+    object.visible = read.next("visible")?;
+    object.depth = read.next("depth")?;
+    object.persistent = read.next("persistent")?;
+    object.parent = read.next("parent")?;
+    object.mask = read.next("mask")?;
+
+    let len = read.next("events")?;
+    for _ in 0..len {
+        let [event_type, event_kind] = read.next_array::<i64, 2>("event")?;
+        let mut event = Event {
+            event_type: event_type as u32,
+            event_kind: event_kind as i32,
+            ..Event::default()
+        };
+        read_actions(read, &mut event.actions, arena)?;
+        object.events.push(event);
+    }
+
+    Ok(())
+}
+
+fn read_actions<'a>(
+    read: &mut TextRead<'_>, actions: &mut Vec<Action<'a>>, arena: &'a Arena
+) -> io::Result<()> {
+    let len = read.next("actions")?;
+    for _ in 0..len {
+        let mut action = Action::default();
+        [action.library, action.action] = read.next_array("action")?;
+        action.action_kind = read.next("kind")?;
+        action.has_relative = read.next("has_relative")?;
+        action.is_question = read.next("is_question")?;
+        action.has_target = read.next("has_target")?;
+        action.action_type = read.next("type")?;
+        action.name = read.next_bstr("name", arena)?;
+        action.code = read.next_bstr("code", arena)?;
+        action.parameters_used = read.next("parameters_used")?;
+        action.parameters = read.next_list("parameters")?;
+        action.target = read.next("target")?;
+        action.relative = read.next("relative")?;
+
+        let len = read.next("arguments")?;
+        for _ in 0..len {
+            action.arguments.push(read.next_bstr("argument", arena)?);
+        }
+
+        action.negate = read.next("negate")?;
+        actions.push(action);
+    }
+
+    Ok(())
+}
+
+fn read_room<'a>(read: &mut TextRead<'_>, room: &mut Room<'a>, arena: &'a Arena) -> io::Result<()> {
+    room.caption = read.next_bstr("caption", arena)?;
+    room.width = read.next("width")?;
+    room.height = read.next("height")?;
+    room.speed = read.next("speed")?;
+    room.persistent = read.next("persistent")?;
+    room.clear_color = read.next_color("clear_color")?;
+    room.clear = read.next("clear")?;
+    room.code = read.next_bstr("code", arena)?;
+
+    let len = read.next("backgrounds")?;
+    for _ in 0..len {
+        let background = RoomBackground {
+            background: read.next("background")?,
+            visible: read.next("visible")?,
+            foreground: read.next("foreground")?,
+            x: read.next("x")?,
+            y: read.next("y")?,
+            htiled: read.next("htiled")?,
+            vtiled: read.next("vtiled")?,
+            hspeed: read.next("hspeed")?,
+            vspeed: read.next("vspeed")?,
+            stretch: read.next("stretch")?,
+        };
+        room.backgrounds.push(background);
+    }
+
+    room.enable_views = read.next("enable_views")?;
+    let len = read.next("views")?;
+    for _ in 0..len {
+        let mut view = View { visible: read.next("view")?, ..View::default() };
+        (view.view_x, view.view_y) = read.next_pair("view_position")?;
+        (view.view_w, view.view_h) = read.next_pair("view_size")?;
+        (view.port_x, view.port_y) = read.next_pair("port_position")?;
+        (view.port_w, view.port_h) = read.next_pair("port_size")?;
+        (view.h_border, view.v_border) = read.next_pair("border")?;
+        (view.h_speed, view.v_speed) = read.next_pair("speed")?;
+        view.target = read.next("target")?;
+        room.views.push(view);
+    }
+
+    let len = read.next("instances")?;
+    for _ in 0..len {
+        let [x, y, object_index, id] = read.next_array("instance")?;
+        let code = read.next_bstr("code", arena)?;
+        room.instances.push(Instance { x, y, object_index, id, code });
+    }
+
+    let len = read.next("tiles")?;
+    for _ in 0..len {
+        let [x, y, background, tile_x, tile_y, width, height, depth, id] =
+            read.next_array::<i64, 9>("tile")?;
+        room.tiles.push(Tile {
+            x: x as i32, y: y as i32,
+            background: background as i32,
+            tile_x: tile_x as i32, tile_y: tile_y as i32,
+            width: width as u32, height: height as u32,
+            depth: depth as i32,
+            id: id as i32,
+        });
+    }
+
+    Ok(())
+}
+
+/// Read a file that is only present when its contents are not empty.
+fn read_file(path: &path::Path) -> io::Result<Option<Vec<u8>>> {
+    match fs::read(path) {
+        Ok(data) => Ok(Some(data)),
+        Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(error) => Err(error),
+    }
+}
+
+/// Read a file holding an image, which may only be missing when the image has no pixels.
+fn read_image(path: &path::Path, (width, height): (u32, u32)) -> io::Result<Vec<u8>> {
+    match read_file(path)? {
+        Some(data) => Ok(data),
+        None if width == 0 || height == 0 => Ok(Vec::default()),
+        None => {
+            let message = format!("missing image {}", path.display());
+            Err(io::Error::new(io::ErrorKind::NotFound, message))
+        }
+    }
+}
+
+/// Decode a PNG into BGRA pixels, checking that it has the expected size.
+pub(crate) fn read_png(data: &[u8], size: (u32, u32)) -> io::Result<Vec<u8>> {
+    let (png_size, data) = decode_png(data)?;
+    if png_size != size {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "wrong image size"));
+    }
+    Ok(data)
+}
+
+/// Decode a PNG of any size into BGRA.
+pub(crate) fn decode_png(data: &[u8]) -> io::Result<((u32, u32), Vec<u8>)> {
+    let mut decoder = png::Decoder::new(data);
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info().map_err(png_error)?;
+    let mut buf = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buf[..]).map_err(png_error)?;
+    let size = (info.width, info.height);
+
+    let pixels = &buf[..info.buffer_size()];
+    let mut data = Vec::with_capacity(size.0 as usize * size.1 as usize * 4);
+    match info.color_type {
+        png::ColorType::Rgba => {
+            for rgba in pixels.chunks_exact(4) {
+                data.extend_from_slice(&[rgba[2], rgba[1], rgba[0], rgba[3]]);
+            }
+        }
+        png::ColorType::Rgb => {
+            for rgb in pixels.chunks_exact(3) {
+                data.extend_from_slice(&[rgb[2], rgb[1], rgb[0], 0xff]);
+            }
+        }
+        png::ColorType::GrayscaleAlpha => {
+            for la in pixels.chunks_exact(2) {
+                data.extend_from_slice(&[la[0], la[0], la[0], la[1]]);
+            }
+        }
+        png::ColorType::Grayscale => {
+            for &l in pixels {
+                data.extend_from_slice(&[l, l, l, 0xff]);
+            }
+        }
+        png::ColorType::Indexed => unreachable!(),
+    }
+
+    Ok((size, data))
+}
+
+/// Decode a grayscale PNG into one byte of alpha per pixel.
+fn read_png_alpha(data: &[u8], size: (u32, u32)) -> io::Result<Vec<u8>> {
+    let mut decoder = png::Decoder::new(data);
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info().map_err(png_error)?;
+    let mut buf = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buf[..]).map_err(png_error)?;
+    if (info.width, info.height) != size || info.color_type != png::ColorType::Grayscale {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "expected grayscale font bitmap"));
+    }
+
+    buf.truncate(info.buffer_size());
+    Ok(buf)
+}
+
+fn png_error(error: png::DecodingError) -> io::Error {
+    match error {
+        png::DecodingError::IoError(error) => error,
+        error => io::Error::new(io::ErrorKind::InvalidData, error),
+    }
+}
+
+pub(crate) fn alloc<'a>(arena: &'a Arena, buf: &[u8]) -> &'a [u8] {
+    if buf.is_empty() { &[] } else { alloc_buf(arena, buf) }
+}
+
+/// Write `game` to `dir`, replacing any resources previously written there.
+pub fn write_dir(dir: &path::Path, game: &Game<'_>) -> io::Result<()> {
+    fs::create_dir_all(dir)?;
+    for kind in KINDS {
+        match fs::remove_dir_all(dir.join(kind)) {
+            Err(error) if error.kind() != io::ErrorKind::NotFound => return Err(error),
+            _ => {}
+        }
+    }
+
+    let write = &mut TextWrite::default();
+
+    write.field("version", game.version);
+    write.field("debug", game.debug);
+    write.field("pro", game.pro);
+    write.field("id", game.id);
+    write.array("guid", &game.guid);
+    write.field("last_instance", game.last_instance);
+    write.field("last_tile", game.last_tile);
+
+    write.line();
+    write.field("triggers", game.triggers.len());
+    write.indent(|write| {
+        for trigger in &game.triggers[..] {
+            write.bstr("trigger", trigger.name);
+            write.bstr("condition", trigger.condition);
+            write.field("moment", trigger.moment);
+            write.bstr("constant", trigger.constant);
+        }
+    });
+
+    write.line();
+    write.field("constants", game.constants.len());
+    write.indent(|write| {
+        for constant in &game.constants[..] {
+            write.bstr("constant", constant.name);
+            write.bstr("value", constant.value);
+        }
+    });
+
+    write.names("sounds", "sound", game.sounds.iter().map(|sound| sound.name));
+    write.names("sprites", "sprite", game.sprites.iter().map(|sprite| sprite.name));
+    write.names("backgrounds", "background", game.backgrounds.iter().map(|back| back.name));
+    write.names("paths", "path", game.paths.iter().map(|path| path.name));
+    write.names("scripts", "script", game.scripts.iter().map(|script| script.name));
+    write.names("fonts", "font", game.fonts.iter().map(|font| font.name));
+    write.names("timelines", "timeline", game.timelines.iter().map(|timeline| timeline.name));
+    write.names("objects", "object", game.objects.iter().map(|object| object.name));
+    write.names("rooms", "room", game.rooms.iter().map(|room| room.name));
+
+    write.line();
+    write.field("included_files", game.included_files.len());
+    write.indent(|write| {
+        for file in &game.included_files[..] {
+            write.bstr("file", file.name);
+            write.indent(|write| {
+                write.bstr("path", file.path);
+                write.field("original", file.original);
+                write.field("size", file.size);
+                write.field("stored", file.stored);
+                write.field("export", file.export);
+                write.bstr("folder", file.folder);
+                write.field("overwrite", file.overwrite);
+                write.field("free", file.free);
+                write.field("remove", file.remove);
+            });
+        }
+    });
+
+    write.line();
+    write.field("extensions", game.extensions.len());
+    write.indent(|write| {
+        for &extension in &game.extensions[..] {
+            write.bstr("extension", extension);
+        }
+    });
+
+    write.line();
+    write.array("room_order", &game.room_order[..]);
+    write.finish(&dir.join("game.txt"))?;
+
+    write_settings(write, &game.settings);
+    write.finish(&dir.join("settings.txt"))?;
+
+    write_info(write, &game.information);
+    write.finish(&dir.join("information.txt"))?;
+    fs::write(dir.join("information.rtf"), game.information.text)?;
+
+    let kind = dir.join("included");
+    let names = file_names(game.included_files.iter().map(|file| file.name), true);
+    for (file, name) in Iterator::zip(game.included_files.iter(), names) {
+        write_file(&kind.join(name), file.data)?;
+    }
+
+    let kind = dir.join("sounds");
+    let names = file_names(game.sounds.iter().map(|sound| sound.name), false);
+    for (sound, file) in Iterator::zip(game.sounds.iter(), names) {
+        if sound.name.is_empty() { continue; }
+
+        write_sound(write, sound);
+        write.finish(&kind.join(format!("{file}.txt")))?;
+        write_file(&kind.join(sound_file(&file, sound.file_type)), sound.data)?;
+    }
+
+    let kind = dir.join("sprites");
+    let names = file_names(game.sprites.iter().map(|sprite| sprite.name), false);
+    for (sprite, file) in Iterator::zip(game.sprites.iter(), names) {
+        if sprite.name.is_empty() { continue; }
+
+        write_sprite(write, sprite);
+        write.finish(&kind.join(format!("{file}.txt")))?;
+
+        let images = kind.join(&file);
+        for (i, image) in sprite.images.iter().enumerate() {
+            if image.data.is_empty() { continue; }
+            if sprite.version < 800 {
+                write_file(&images.join(format!("{i}.bmp")), image.data)?;
+            } else {
+                write_file(&images.join(format!("{i}.png")), &write_png(image.size, image.data)?)?;
+            }
+        }
+        for (i, mask) in sprite.masks.iter().enumerate() {
+            write_file(&images.join(format!("mask{i}.txt")), &write_mask(mask)[..])?;
+        }
+    }
+
+    let kind = dir.join("backgrounds");
+    let names = file_names(game.backgrounds.iter().map(|back| back.name), false);
+    for (background, file) in Iterator::zip(game.backgrounds.iter(), names) {
+        if background.name.is_empty() { continue; }
+
+        write_background(write, background);
+        write.finish(&kind.join(format!("{file}.txt")))?;
+
+        if background.version < 710 {
+            // Older backgrounds keep their size even without an image, so mark the image as
+            // empty rather than missing.
+            let path = kind.join(format!("{file}.bmp"));
+            let (width, height) = background.size;
+            if background.data.is_empty() && width != 0 && height != 0 {
+                fs::create_dir_all(&kind)?;
+                fs::write(path, b"")?;
+            } else {
+                write_file(&path, background.data)?;
+            }
+        } else if !background.data.is_empty() {
+            let data = write_png(background.size, background.data)?;
+            write_file(&kind.join(format!("{file}.png")), &data[..])?;
+        }
+    }
+
+    let kind = dir.join("paths");
+    let names = file_names(game.paths.iter().map(|path| path.name), false);
+    for (path, file) in Iterator::zip(game.paths.iter(), names) {
+        if path.name.is_empty() { continue; }
+
+        write_path(write, path);
+        write.finish(&kind.join(format!("{file}.txt")))?;
+    }
+
+    let kind = dir.join("scripts");
+    let names = file_names(game.scripts.iter().map(|script| script.name), false);
+    for (script, file) in Iterator::zip(game.scripts.iter(), names) {
+        if script.name.is_empty() { continue; }
+
+        fs::create_dir_all(&kind)?;
+        fs::write(kind.join(format!("{file}.gml")), script.body)?;
+    }
+
+    let kind = dir.join("fonts");
+    let names = file_names(game.fonts.iter().map(|font| font.name), false);
+    for (font, file) in Iterator::zip(game.fonts.iter(), names) {
+        if font.name.is_empty() { continue; }
+
+        write_font(write, font);
+        write.finish(&kind.join(format!("{file}.txt")))?;
+
+        if font.bitmap.is_empty() { continue; }
+        let data = write_png_alpha(font.bitmap_size, font.bitmap)?;
+        write_file(&kind.join(format!("{file}.png")), &data[..])?;
+    }
+
+    let kind = dir.join("timelines");
+    let names = file_names(game.timelines.iter().map(|line| line.name), false);
+    for (timeline, file) in Iterator::zip(game.timelines.iter(), names) {
+        if timeline.name.is_empty() { continue; }
+
+        write_timeline(write, timeline);
+        write.finish(&kind.join(format!("{file}.txt")))?;
+    }
+
+    let kind = dir.join("objects");
+    let names = file_names(game.objects.iter().map(|object| object.name), false);
+    for (object, file) in Iterator::zip(game.objects.iter(), names) {
+        if object.name.is_empty() { continue; }
+
+        write_object(write, object);
+        write.finish(&kind.join(format!("{file}.txt")))?;
+    }
+
+    let kind = dir.join("rooms");
+    let names = file_names(game.rooms.iter().map(|room| room.name), false);
+    for (room, file) in Iterator::zip(game.rooms.iter(), names) {
+        if room.name.is_empty() { continue; }
+
+        write_room(write, room);
+        write.finish(&kind.join(format!("{file}.txt")))?;
+    }
+
+    Ok(())
+}
+
+fn write_settings(write: &mut TextWrite, settings: &Settings) {
+    write.field("fullscreen", settings.fullscreen);
+    write.field("scaling", settings.scaling);
+    write.field("interpolation", settings.interpolation);
+    write.color("background_color", settings.background_color);
+    write.field("allow_resize", settings.allow_resize);
+    write.field("topmost", settings.topmost);
+    write.field("hide_border", settings.hide_border);
+    write.field("hide_buttons", settings.hide_buttons);
+    write.field("show_cursor", settings.show_cursor);
+    write.field("freeze", settings.freeze);
+    write.field("disable_screensaver", settings.disable_screensaver);
+
+    write.line();
+    write.field("set_resolution", settings.set_resolution);
+    write.field("color_depth", settings.color_depth);
+    write.field("resolution", settings.resolution);
+    write.field("frequency", settings.frequency);
+    write.field("vsync", settings.vsync);
+
+    write.line();
+    write.field("default_esc", settings.default_esc);
+    write.field("close_as_esc", settings.close_as_esc);
+    write.field("default_f1", settings.default_f1);
+    write.field("default_f4", settings.default_f4);
+    write.field("default_f5", settings.default_f5);
+    write.field("default_f9", settings.default_f9);
+    write.field("priority", settings.priority);
+
+    write.line();
+    write.field("load_image", settings.load_image);
+    write.field("load_transparent", settings.load_transparent);
+    write.field("load_alpha", settings.load_alpha);
+    write.field("load_bar", settings.load_bar);
+    write.field("load_scale", settings.load_scale);
+
+    write.line();
+    write.field("error_display", settings.error_display);
+    write.field("error_log", settings.error_log);
+    write.field("error_abort", settings.error_abort);
+    write.field("uninitialized_zero", settings.uninitialized_zero);
+    write.field("uninitialized_arguments", settings.uninitialized_arguments);
+}
+
+fn write_info(write: &mut TextWrite, info: &GameInformation<'_>) {
+    write.color("background_color", info.background_color);
+    write.field("separate_window", info.separate_window);
+    write.bstr("caption", info.caption);
+    write.pair("position", info.position);
+    write.pair("size", info.size);
+    write.field("show_border", info.show_border);
+    write.field("allow_resize", info.allow_resize);
+    write.field("topmost", info.topmost);
+    write.field("freeze", info.freeze);
+}
+
+fn write_sound(write: &mut TextWrite, sound: &Sound<'_>) {
+    write.field("kind", sound.kind);
+    write.bstr("file_type", sound.file_type);
+    write.bstr("file_name", sound.file_name);
+    write.field("effects", sound.effects);
+    write.field("volume", sound.volume);
+    write.field("pan", sound.pan);
+    write.field("preload", sound.preload);
+}
+
+fn write_sprite(write: &mut TextWrite, sprite: &Sprite<'_>) {
+    write.field("version", sprite.version);
+    write.pair("size", sprite.size);
+    write.field("transparent", sprite.transparent);
+    write.field("precise", sprite.precise);
+    write.field("use_vram", sprite.use_vram);
+    write.field("lazy_load", sprite.lazy_load);
+    write.pair("origin", sprite.origin);
+
+    write.line();
+    write.field("shape", sprite.shape);
+    write.field("alpha_tolerance", sprite.alpha_tolerance);
+    write.field("separate_collision", sprite.separate_collision);
+    write.field("bounds_kind", sprite.bounds_kind);
+    write_bounds(write, &sprite.bounds);
+
+    write.line();
+    write.field("images", sprite.images.len());
+    write.indent(|write| {
+        for image in &sprite.images[..] {
+            write.pair("image", image.size);
+        }
+    });
+
+    write.field("masks", sprite.masks.len());
+    write.indent(|write| {
+        for mask in &sprite.masks[..] {
+            write.pair("mask", mask.size);
+            write_bounds(write, &mask.bounds);
+        }
+    });
+}
+
+fn write_bounds(write: &mut TextWrite, bounds: &Bounds) {
+    write.array("bounds", &[bounds.left, bounds.right, bounds.bottom, bounds.top]);
+}
+
+fn write_mask(mask: &Mask) -> Vec<u8> {
+    let mut data = Vec::with_capacity(mask.data.len() + mask.size.1 as usize);
+    for row in mask.data.chunks(mask.size.0.max(1) as usize) {
+        data.extend(row.iter().map(|&bit| if bit != 0 { b'1' } else { b'0' }));
+        data.push(b'\n');
+    }
+    data
+}
+
+fn write_background(write: &mut TextWrite, background: &Background<'_>) {
+    write.field("version", background.version);
+    write.pair("size", background.size);
+    write.field("transparent", background.transparent);
+    write.field("use_vram", background.use_vram);
+    write.field("lazy_load", background.lazy_load);
+}
+
+fn write_path(write: &mut TextWrite, path: &Path<'_>) {
+    write.field("smooth", path.smooth);
+    write.field("closed", path.closed);
+    write.field("precision", path.precision);
+
+    write.line();
+    write.field("points", path.points.len());
+    write.indent(|write| {
+        for point in &path.points[..] {
+            write.array("point", &[point.position.0, point.position.1, point.speed]);
+        }
+    });
+}
+
+fn write_font(write: &mut TextWrite, font: &Font<'_>) {
+    write.bstr("font", font.font);
+    write.field("size", font.size);
+    write.field("bold", font.bold);
+    write.field("italic", font.italic);
+    write.field("start", font.start);
+    write.field("end", font.end);
+    write.field("charset", font.charset);
+    write.field("antialias", font.antialias);
+
+    write.line();
+    write.field("glyphs", font.glyphs.len());
+    write.indent(|write| {
+        for glyph in &font.glyphs[..] {
+            let &Glyph { position: (x, y), size: (width, height), shift, offset } = glyph;
+            write.array("glyph", &[
+                x as i64, y as i64, width as i64, height as i64, shift as i64, offset as i64,
+            ]);
+        }
+    });
+    write.pair("bitmap_size", font.bitmap_size);
+}
+
+fn write_timeline(write: &mut TextWrite, timeline: &Timeline<'_>) {
+    write.field("moments", timeline.moments.len());
+    for moment in &timeline.moments[..] {
+        write.line();
+        write.field("moment", moment.moment);
+        write.indent(|write| write_actions(write, &moment.actions[..]));
+    }
+}
+
+fn write_object(write: &mut TextWrite, object: &Object<'_>) {
+    write.field("sprite", object.sprite);
+    write.field("solid", object.solid);
+    write.field("visible", object.visible);
+    write.field("depth", object.depth);
+    write.field("persistent", object.persistent);
+    write.field("parent", object.parent);
+    write.field("mask", object.mask);
+
+    write.line();
+    write.field("events", object.events.len());
+    for event in &object.events[..] {
+        write.line();
+        write.array("event", &[event.event_type as i64, event.event_kind as i64]);
+        write.indent(|write| write_actions(write, &event.actions[..]));
+    }
+}
+
+fn write_actions(write: &mut TextWrite, actions: &[Action<'_>]) {
+    write.field("actions", actions.len());
+    for action in actions {
+        write.array("action", &[action.library, action.action]);
+        write.indent(|write| {
+            write.field("kind", action.action_kind);
+            write.field("has_relative", action.has_relative);
+            write.field("is_question", action.is_question);
+            write.field("has_target", action.has_target);
+            write.field("type", action.action_type);
+            write.bstr("name", action.name);
+            write.bstr("code", action.code);
+            write.field("parameters_used", action.parameters_used);
+            write.array("parameters", &action.parameters[..]);
+            write.field("target", action.target);
+            write.field("relative", action.relative);
+            write.field("arguments", action.arguments.len());
+            for &argument in &action.arguments[..] {
+                write.bstr("argument", argument);
+            }
+            write.field("negate", action.negate);
+        });
+    }
+}
+
+fn write_room(write: &mut TextWrite, room: &Room<'_>) {
+    write.bstr("caption", room.caption);
+    write.field("width", room.width);
+    write.field("height", room.height);
+    write.field("speed", room.speed);
+    write.field("persistent", room.persistent);
+    write.color("clear_color", room.clear_color);
+    write.field("clear", room.clear);
+    write.bstr("code", room.code);
+
+    write.line();
+    write.field("backgrounds", room.backgrounds.len());
+    for background in &room.backgrounds[..] {
+        write.field("background", background.background);
+        write.indent(|write| {
+            write.field("visible", background.visible);
+            write.field("foreground", background.foreground);
+            write.field("x", background.x);
+            write.field("y", background.y);
+            write.field("htiled", background.htiled);
+            write.field("vtiled", background.vtiled);
+            write.field("hspeed", background.hspeed);
+            write.field("vspeed", background.vspeed);
+            write.field("stretch", background.stretch);
+        });
+    }
+
+    write.line();
+    write.field("enable_views", room.enable_views);
+    write.field("views", room.views.len());
+    for view in &room.views[..] {
+        write.field("view", view.visible);
+        write.indent(|write| {
+            write.pair("view_position", (view.view_x, view.view_y));
+            write.pair("view_size", (view.view_w, view.view_h));
+            write.pair("port_position", (view.port_x, view.port_y));
+            write.pair("port_size", (view.port_w, view.port_h));
+            write.pair("border", (view.h_border, view.v_border));
+            write.pair("speed", (view.h_speed, view.v_speed));
+            write.field("target", view.target);
+        });
+    }
+
+    write.line();
+    write.field("instances", room.instances.len());
+    for instance in &room.instances[..] {
+        write.array("instance", &[instance.x, instance.y, instance.object_index, instance.id]);
+        write.indent(|write| write.bstr("code", instance.code));
+    }
+
+    write.line();
+    write.field("tiles", room.tiles.len());
+    for tile in &room.tiles[..] {
+        let &Tile { x, y, background, tile_x, tile_y, width, height, depth, id } = tile;
+        write.array("tile", &[
+            x as i64, y as i64, background as i64, tile_x as i64, tile_y as i64,
+            width as i64, height as i64, depth as i64, id as i64,
+        ]);
+    }
+}
+
+/// Write a file only when its contents are not empty.
+fn write_file(path: &path::Path, data: &[u8]) -> io::Result<()> {
+    if data.is_empty() { return Ok(()); }
+
+    if let Some(dir) = path.parent() { fs::create_dir_all(dir)?; }
+    fs::write(path, data)
+}
+
+/// Encode BGRA pixels as a PNG.
+pub(crate) fn write_png((width, height): (u32, u32), data: &[u8]) -> io::Result<Vec<u8>> {
+    let rgba: Vec<u8> = data.chunks_exact(4)
+        .flat_map(|bgra| [bgra[2], bgra[1], bgra[0], bgra[3]])
+        .collect();
+
+    let mut png = Vec::default();
+    let mut encoder = png::Encoder::new(&mut png, width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&rgba[..])?;
+    writer.finish()?;
+    Ok(png)
+}
+
+/// Encode one byte of alpha per pixel as a grayscale PNG.
+fn write_png_alpha((width, height): (u32, u32), data: &[u8]) -> io::Result<Vec<u8>> {
+    let mut png = Vec::default();
+    let mut encoder = png::Encoder::new(&mut png, width, height);
+    encoder.set_color(png::ColorType::Grayscale);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(data)?;
+    writer.finish()?;
+    Ok(png)
+}
+
+/// The files `write_dir` stores each resource of a kind in, relative to the project directory.
+pub fn resource_files(game: &Game<'_>, resource: Resource) -> Vec<path::PathBuf> {
+    let (kind, extension) = match resource {
+        Resource::Sound => ("sounds", "txt"),
+        Resource::Sprite => ("sprites", "txt"),
+        Resource::Background => ("backgrounds", "txt"),
+        Resource::Path => ("paths", "txt"),
+        Resource::Script => ("scripts", "gml"),
+        Resource::Font => ("fonts", "txt"),
+        Resource::Timeline => ("timelines", "txt"),
+        Resource::Object => ("objects", "txt"),
+        Resource::Room => ("rooms", "txt"),
+    };
+    let names = file_names(validate::names(game, resource).into_iter(), false);
+    let kind = path::Path::new(kind);
+    names.into_iter().map(|file| kind.join(format!("{file}.{extension}"))).collect()
+}
+
+/// Choose a file name for each resource.
+///
+/// Names that are safe to use as file names on all platforms are used directly. Anything else,
+/// including names that collide when compared without case, uses the resource's index instead.
+/// Most resources get several files with different extensions, so only included files may use `.`.
+fn file_names<'a>(names: impl Iterator<Item = &'a BStr>, dots: bool) -> Vec<String> {
+    let mut used = HashSet::new();
+    names.enumerate().map(|(id, name)| {
+        let safe = match name.first() {
+            Some(&c) if c.is_ascii_alphabetic() || c == b'_' => {
+                name.iter().all(|&c| {
+                    c.is_ascii_alphanumeric() || c == b'_' || c == b'-' || c == b' ' ||
+                    (dots && c == b'.')
+                }) &&
+                !name.ends_with(b".") && !name.ends_with(b" ")
+            }
+            _ => false,
+        };
+        let name = str::from_utf8(name).ok().filter(|_| safe);
+        match name {
+            Some(name) if used.insert(name.to_ascii_lowercase()) => String::from(name),
+            _ => format!("{id}"),
+        }
+    }).collect()
+}
+
+/// The name of a sound's data file, using its file type as the extension where possible.
+fn sound_file(file: &str, file_type: &BStr) -> String {
+    let extension = file_type.strip_prefix(b".")
+        .filter(|ext| !ext.is_empty() && ext.iter().all(u8::is_ascii_alphanumeric))
+        .and_then(|extension| str::from_utf8(extension).ok())
+        .filter(|extension| !extension.eq_ignore_ascii_case("txt"))
+        .unwrap_or("data");
+    format!("{file}.{extension}")
+}
+
+#[derive(Default)]
+struct TextWrite {
+    data: Vec<u8>,
+    indent: usize,
+}
+
+impl TextWrite {
+    fn indent(&mut self, f: impl FnOnce(&mut TextWrite)) {
+        self.indent += 1;
+        f(self);
+        self.indent -= 1;
+    }
+
+    fn line(&mut self) {
+        self.data.push(b'\n');
+    }
+
+    fn key(&mut self, key: &str) {
+        self.data.extend(std::iter::repeat_n(b' ', self.indent * 4));
+        self.data.extend_from_slice(key.as_bytes());
+    }
+
+    fn field(&mut self, key: &str, value: impl Display) {
+        self.key(key);
+        let _ = writeln!(self.data, " {value}");
+    }
+
+    fn color(&mut self, key: &str, value: u32) {
+        self.key(key);
+        let _ = writeln!(self.data, " #{value:06x}");
+    }
+
+    fn pair<T: Display>(&mut self, key: &str, (a, b): (T, T)) {
+        self.key(key);
+        let _ = writeln!(self.data, " {a} {b}");
+    }
+
+    fn array<T: Display>(&mut self, key: &str, values: &[T]) {
+        self.key(key);
+        for value in values {
+            let _ = write!(self.data, " {value}");
+        }
+        self.data.push(b'\n');
+    }
+
+    fn bstr(&mut self, key: &str, value: &BStr) {
+        self.key(key);
+        if value.contains(&b'\n') {
+            self.data.push(b'\n');
+            for line in value.split(|&c| c == b'\n') {
+                self.data.extend(std::iter::repeat_n(b' ', (self.indent + 1) * 4));
+                self.data.push(b'|');
+                if !line.is_empty() {
+                    self.data.push(b' ');
+                    self.data.extend_from_slice(line);
+                }
+                self.data.push(b'\n');
+            }
+            return;
+        }
+
+        self.data.extend_from_slice(b" \"");
+        for &c in value.iter() {
+            match c {
+                b'"' => self.data.extend_from_slice(b"\\\""),
+                b'\\' => self.data.extend_from_slice(b"\\\\"),
+                b'\r' => self.data.extend_from_slice(b"\\r"),
+                b'\t' => self.data.extend_from_slice(b"\\t"),
+                c if c < 0x20 || c == 0x7f => { let _ = write!(self.data, "\\x{c:02x}"); }
+                c => self.data.push(c),
+            }
+        }
+        self.data.extend_from_slice(b"\"\n");
+    }
+
+    fn names<'a>(&mut self, list: &str, key: &str, names: impl ExactSizeIterator<Item = &'a BStr>) {
+        self.line();
+        self.field(list, names.len());
+        self.indent(|write| {
+            for name in names {
+                write.bstr(key, name);
+            }
+        });
+    }
+
+    /// Write out the file so far, and start a new one.
+    fn finish(&mut self, path: &path::Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() { fs::create_dir_all(dir)?; }
+        fs::write(path, &self.data[..])?;
+        self.data.clear();
+        Ok(())
+    }
+}
+
+struct TextRead<'b> {
+    data: &'b [u8],
+    line: usize,
+}
+
+impl<'b> TextRead<'b> {
+    fn new(data: &'b [u8]) -> TextRead<'b> {
+        TextRead { data, line: 0 }
+    }
+
+    fn error(&self, message: impl Display) -> io::Error {
+        io::Error::new(io::ErrorKind::InvalidData, format!("line {}: {}", self.line, message))
+    }
+
+    fn next_line(&mut self) -> Option<&'b [u8]> {
+        if self.data.is_empty() { return None; }
+
+        let end = self.data.iter().position(|&c| c == b'\n').unwrap_or(self.data.len());
+        let line = &self.data[..end];
+        self.data = self.data.get(end + 1..).unwrap_or(&[]);
+        self.line += 1;
+        Some(line)
+    }
+
+    /// Read the value of the next field, which must be `key`.
+    fn next_field(&mut self, key: &str) -> io::Result<&'b [u8]> {
+        loop {
+            let Some(line) = self.next_line() else {
+                return Err(self.error(format_args!("expected `{key}`, found end of file")));
+            };
+            let line = line.trim_ascii();
+            if line.is_empty() { continue; }
+
+            let end = line.iter().position(|&c| c == b' ').unwrap_or(line.len());
+            let (found, value) = line.split_at(end);
+            if found != key.as_bytes() {
+                let found = String::from_utf8_lossy(found);
+                return Err(self.error(format_args!("expected `{key}`, found `{found}`")));
+            }
+            return Ok(value.trim_ascii());
+        }
+    }
+
+    fn parse<T: FromStr>(&self, key: &str, value: &[u8]) -> io::Result<T> {
+        str::from_utf8(value).ok()
+            .and_then(|value| value.parse().ok())
+            .ok_or_else(|| self.error(format_args!("invalid value for `{key}`")))
+    }
+
+    fn next<T: FromStr>(&mut self, key: &str) -> io::Result<T> {
+        let value = self.next_field(key)?;
+        self.parse(key, value)
+    }
+
+    fn next_color(&mut self, key: &str) -> io::Result<u32> {
+        let value = self.next_field(key)?;
+        value.strip_prefix(b"#")
+            .and_then(|value| str::from_utf8(value).ok())
+            .and_then(|value| u32::from_str_radix(value, 16).ok())
+            .ok_or_else(|| self.error(format_args!("invalid color for `{key}`")))
+    }
+
+    fn next_list<T: FromStr>(&mut self, key: &str) -> io::Result<Vec<T>> {
+        let value = self.next_field(key)?;
+        value.split(|&c| c == b' ')
+            .filter(|value| !value.is_empty())
+            .map(|value| self.parse(key, value))
+            .collect()
+    }
+
+    fn next_array<T: FromStr, const N: usize>(&mut self, key: &str) -> io::Result<[T; N]> {
+        let values = self.next_list(key)?;
+        <[T; N]>::try_from(values)
+            .map_err(|_| self.error(format_args!("expected {N} values for `{key}`")))
+    }
+
+    fn next_pair<T: FromStr>(&mut self, key: &str) -> io::Result<(T, T)> {
+        let [a, b] = self.next_array(key)?;
+        Ok((a, b))
+    }
+
+    fn next_bstr<'a>(&mut self, key: &str, arena: &'a Arena) -> io::Result<&'a BStr> {
+        let value = self.next_field(key)?;
+        let mut buf = Vec::default();
+
+        if value.is_empty() {
+            // A string spanning multiple lines, each prefixed by `|`.
+            let mut first = true;
+            while let Some(line) = self.next_block_line() {
+                if !first { buf.push(b'\n'); }
+                first = false;
+                buf.extend_from_slice(line);
+            }
+            if first {
+                return Err(self.error(format_args!("expected a string for `{key}`")));
+            }
+        } else {
+            let Some(value) = value.strip_prefix(b"\"").and_then(|value| value.strip_suffix(b"\""))
+            else {
+                return Err(self.error(format_args!("expected a string for `{key}`")));
+            };
+
+            let mut chars = value.iter().copied();
+            while let Some(c) = chars.next() {
+                if c != b'\\' {
+                    buf.push(c);
+                    continue;
+                }
+                match chars.next() {
+                    Some(b'"') => buf.push(b'"'),
+                    Some(b'\\') => buf.push(b'\\'),
+                    Some(b'r') => buf.push(b'\r'),
+                    Some(b't') => buf.push(b'\t'),
+                    Some(b'x') => {
+                        let digits = [chars.next().unwrap_or(0), chars.next().unwrap_or(0)];
+                        let c = str::from_utf8(&digits[..]).ok()
+                            .and_then(|digits| u8::from_str_radix(digits, 16).ok())
+                            .ok_or_else(|| self.error("invalid escape"))?;
+                        buf.push(c);
+                    }
+                    _ => return Err(self.error("invalid escape")),
+                }
+            }
+        }
+
+        Ok(BStr::new(alloc(arena, &buf[..])))
+    }
+
+    /// Consume the next line if it continues a multi-line string, and return its contents.
+    fn next_block_line(&mut self) -> Option<&'b [u8]> {
+        let (data, line) = (self.data, self.line);
+        let next = self.next_line()?;
+        let start = next.iter().position(|&c| c != b' ' && c != b'\t').unwrap_or(next.len());
+        match next[start..].strip_prefix(b"|") {
+            Some(rest) => Some(rest.strip_prefix(b" ").unwrap_or(rest)),
+            None => {
+                (self.data, self.line) = (data, line);
+                None
+            }
+        }
+    }
+
+    /// Check that there are no fields left.
+    fn end(&mut self) -> io::Result<()> {
+        while let Some(line) = self.next_line() {
+            if !line.trim_ascii().is_empty() {
+                return Err(self.error("unexpected field"));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, io, process};
+    use std::path::Path;
+    use bstr::BStr;
+    use quickdry::Arena;
+    use crate::{Game, Sprite, Image, Background, Script, Object, Event, Action, Room, Instance};
+    use crate::action_kind;
+    use super::{read_dir, write_dir};
+
+    fn files(dir: &Path) -> Vec<(String, Vec<u8>)> {
+        let mut files = Vec::default();
+        for entry in fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            let name = path.file_name().unwrap().to_string_lossy().into_owned();
+            if path.is_dir() {
+                files.extend(files_in(&path, &name));
+            } else {
+                files.push((name, fs::read(&path).unwrap()));
+            }
+        }
+        files.sort();
+        files
+    }
+
+    fn files_in(dir: &Path, prefix: &str) -> Vec<(String, Vec<u8>)> {
+        files(dir).into_iter().map(|(name, data)| (format!("{prefix}/{name}"), data)).collect()
+    }
+
+    #[test]
+    fn round_trip() {
+        let pixels = [0x10, 0x20, 0x30, 0xff, 0x40, 0x50, 0x60, 0x80];
+
+        let mut game = Game { id: 1234, guid: [1, 2, 3, 4], ..Game::default() };
+        game.settings.background_color = 0x123456;
+        game.information.text = BStr::new(b"{\\rtf1 help}");
+        game.sprites.push(Sprite::default());
+        game.sprites.push(Sprite {
+            name: BStr::new(b"spr_player"),
+            version: 800,
+            origin: (1, 0),
+            images: vec![Image { size: (2, 1), data: &pixels[..] }],
+            ..Sprite::default()
+        });
+        game.backgrounds.push(Background {
+            name: BStr::new(b"bk_unused"),
+            version: 400,
+            size: (16, 16),
+            ..Background::default()
+        });
+        game.scripts.push(Script {
+            name: BStr::new(b"scr_move"),
+            body: BStr::new(b"x += argument0;\r\ny += argument1;\r\n"),
+        });
+        game.objects.push(Object {
+            name: BStr::new(b"obj_player"),
+            sprite: 1,
+            events: vec![Event {
+                event_type: 0,
+                event_kind: 0,
+                actions: vec![Action {
+                    library: 1,
+                    action: 603,
+                    action_kind: action_kind::CODE,
+                    parameters_used: 1,
+                    parameters: vec![1],
+                    arguments: vec![BStr::new(b"speed = 4\n\n  direction = 90\n")],
+                    ..Action::default()
+                }],
+            }],
+            ..Object::default()
+        });
+        game.rooms.push(Room {
+            name: BStr::new(b"room \"one\""),
+            caption: BStr::new(b"tab\there"),
+            instances: vec![
+                Instance { x: 16, y: -8, object_index: 0, id: 100001, code: BStr::new(b"") },
+            ],
+            ..Room::default()
+        });
+        game.room_order.push(0);
+
+        let dir = env::temp_dir().join(format!("dejavu-dir-{}", process::id()));
+        let first = dir.join("first");
+        let second = dir.join("second");
+        write_dir(&first, &game).unwrap();
+
+        let arena = Arena::default();
+        let mut read = Game::default();
+        read_dir(&first, &mut read, &arena).unwrap();
+
+        assert_eq!((read.id, read.guid), (1234, [1, 2, 3, 4]));
+        assert_eq!(read.settings.background_color, 0x123456);
+        assert_eq!(read.information.text, game.information.text);
+        assert_eq!(read.sprites[0].name, "");
+        assert_eq!(read.sprites[1].images[0].data, &pixels[..]);
+        assert_eq!((read.backgrounds[0].size, read.backgrounds[0].data), ((16, 16), &[][..]));
+        assert_eq!(read.scripts[0].body, game.scripts[0].body);
+        let action = &read.objects[0].events[0].actions[0];
+        assert_eq!(action.arguments, game.objects[0].events[0].actions[0].arguments);
+        assert_eq!(read.rooms[0].name, game.rooms[0].name);
+        assert_eq!(read.rooms[0].caption, game.rooms[0].caption);
+        assert_eq!(read.rooms[0].instances[0].y, -8);
+        assert_eq!(read.room_order, [0]);
+
+        // Writing the game back out reproduces every file exactly.
+        write_dir(&second, &read).unwrap();
+        assert_eq!(files(&first), files(&second));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn corrupt() {
+        let pixels = [0x10, 0x20, 0x30, 0xff];
+
+        let mut game = Game::default();
+        game.sprites.push(Sprite {
+            name: BStr::new(b"spr_dot"),
+            version: 800,
+            images: vec![Image { size: (1, 1), data: &pixels[..] }],
+            ..Sprite::default()
+        });
+
+        let dir = env::temp_dir().join(format!("dejavu-corrupt-{}", process::id()));
+        write_dir(&dir, &game).unwrap();
+        let read = |dir: &Path| {
+            let arena = Arena::default();
+            read_dir(dir, &mut Game::default(), &arena).err().unwrap()
+        };
+        let edit = |file: &Path, from: &str, to: &str| {
+            let text = fs::read_to_string(file).unwrap();
+            assert!(text.contains(from));
+            fs::write(file, text.replace(from, to)).unwrap();
+        };
+
+        // Counts are read from the file, so a huge one must run out of lines rather than memory.
+        let file = dir.join("game.txt");
+        edit(&file, "triggers 0", "triggers 18446744073709551615");
+        assert_eq!(read(&dir).kind(), io::ErrorKind::InvalidData);
+        edit(&file, "triggers 18446744073709551615", "triggers 0");
+
+        let file = dir.join("sprites").join("spr_dot.txt");
+        edit(&file, "version 800", "version 700");
+        assert_eq!(read(&dir).kind(), io::ErrorKind::InvalidData);
+        edit(&file, "version 700", "version 800");
+
+        fs::remove_file(dir.join("sprites").join("spr_dot").join("0.png")).unwrap();
+        let error = read(&dir);
+        assert_eq!(error.kind(), io::ErrorKind::NotFound);
+        assert!(error.to_string().contains("0.png"));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,1095 @@
+//! Import GameMaker: Studio 1.x projects, stored as a `.project.gmx` file that lists the XML and
+//! image files for each resource.
+//!
+//! Sprites, backgrounds, scripts, fonts, timelines, objects, rooms, included files and constants
+//! are imported into `Game`. Everything else is skipped and reported as a warning.
+
+use std::{fs, io, str};
+use std::collections::HashMap;
+use std::path::{self, PathBuf};
+use bstr::BStr;
+use quickdry::Arena;
+
+use crate::{
+    Game, Constant, IncludedFile,
+    Sprite, Image,
+    Background,
+    Script,
+    Font, Glyph,
+    Timeline, Moment,
+    Object, Action, Event,
+    Room, RoomBackground, View, Instance, Tile,
+    event_type,
+    Warning,
+};
+use crate::dir::{alloc, read_png, decode_png};
+
+/// The resource kinds that are not imported, by the name of their group in the project.
+const UNSUPPORTED: [(&str, &str); 6] = [
+    ("sounds", "sounds"), ("paths", "paths"), ("shaders", "shaders"),
+    ("NewExtensions", "extensions"), ("Configs", "game options"), ("help", "game information"),
+];
+
+/// Import a project, returning warnings for everything that was skipped or approximated.
+pub fn read_gmx<'a>(
+    path: &path::Path, game: &mut Game<'a>, arena: &'a Arena
+) -> io::Result<Vec<Warning>> {
+    let mut warnings = Vec::default();
+    let dir = path.parent().unwrap_or(path::Path::new(""));
+    let project = read_xml(path)?;
+    if project.name != "assets" {
+        return Err(invalid(path, "expected <assets>"));
+    }
+
+    for (group, kind) in UNSUPPORTED {
+        let len = project.children(group).map(Element::leaves).sum::<usize>();
+        if len > 0 {
+            warnings.push(Warning(format!("{len} {kind} are not supported and were skipped")));
+        }
+    }
+
+    let sprites = resources(&project, "sprites", "sprite");
+    let backgrounds = resources(&project, "backgrounds", "background");
+    let scripts = resources(&project, "scripts", "script");
+    let fonts = resources(&project, "fonts", "font");
+    let timelines = resources(&project, "timelines", "timeline");
+    let objects = resources(&project, "objects", "object");
+    let rooms = resources(&project, "rooms", "room");
+
+    let names = Names {
+        sprites: index(&sprites),
+        backgrounds: index(&backgrounds),
+        scripts: index(&scripts),
+        fonts: index(&fonts),
+        timelines: index(&timelines),
+        objects: index(&objects),
+        rooms: index(&rooms),
+    };
+
+    for constants in project.children("constants") {
+        for constant in constants.children("constant") {
+            let name = constant.attribute("name").unwrap_or_default();
+            game.constants.push(Constant {
+                name: BStr::new(alloc(arena, name.as_bytes())),
+                value: BStr::new(alloc(arena, constant.text.as_bytes())),
+            });
+        }
+    }
+
+    game.sprites.reserve(sprites.len());
+    for (name, file) in &sprites[..] {
+        let path = dir.join(file).with_extension("sprite.gmx");
+        let sprite = read_sprite(&path, name, arena, &mut warnings)?;
+        game.sprites.push(sprite);
+    }
+
+    game.backgrounds.reserve(backgrounds.len());
+    for (name, file) in &backgrounds[..] {
+        let path = dir.join(file).with_extension("background.gmx");
+        let background = read_background(&path, name, arena)?;
+        game.backgrounds.push(background);
+    }
+
+    game.scripts.reserve(scripts.len());
+    for (name, file) in &scripts[..] {
+        let body = fs::read(dir.join(file))?;
+        game.scripts.push(Script {
+            name: BStr::new(alloc(arena, name.as_bytes())),
+            body: BStr::new(alloc(arena, &body[..])),
+        });
+    }
+
+    game.fonts.reserve(fonts.len());
+    for (name, file) in &fonts[..] {
+        let path = dir.join(file).with_extension("font.gmx");
+        let font = read_font(&path, name, arena, &mut warnings)?;
+        game.fonts.push(font);
+    }
+
+    game.timelines.reserve(timelines.len());
+    for (name, file) in &timelines[..] {
+        let path = dir.join(file).with_extension("timeline.gmx");
+        let timeline = read_timeline(&path, name, &names, arena, &mut warnings)?;
+        game.timelines.push(timeline);
+    }
+
+    game.objects.reserve(objects.len());
+    for (name, file) in &objects[..] {
+        let path = dir.join(file).with_extension("object.gmx");
+        let object = read_object(&path, name, &names, arena, &mut warnings)?;
+        game.objects.push(object);
+    }
+
+    // Instance ids are assigned on import, after those of any rooms already in the game.
+    game.last_instance = i32::max(game.last_instance, 100000);
+    game.rooms.reserve(rooms.len());
+    for (id, (name, file)) in rooms.iter().enumerate() {
+        let path = dir.join(file).with_extension("room.gmx");
+        let room = read_room(&path, name, &names, game, arena, &mut warnings)?;
+        game.rooms.push(room);
+        game.room_order.push(id as u32);
+    }
+
+    for datafiles in project.children("datafiles") {
+        let folder = path::Path::new("datafiles");
+        read_datafiles(path, datafiles, folder, game, arena, &mut warnings)?;
+    }
+
+    Ok(warnings)
+}
+
+/// The indices of resources that can be referred to by name.
+struct Names {
+    sprites: HashMap<String, i32>,
+    backgrounds: HashMap<String, i32>,
+    scripts: HashMap<String, i32>,
+    fonts: HashMap<String, i32>,
+    timelines: HashMap<String, i32>,
+    objects: HashMap<String, i32>,
+    rooms: HashMap<String, i32>,
+}
+
+impl Names {
+    /// Look up a resource by name, where `<undefined>` or an empty name means none.
+    fn get(names: &HashMap<String, i32>, name: Option<&str>) -> Option<i32> {
+        match name {
+            None | Some("") | Some("<undefined>") => Some(-1),
+            Some(name) => names.get(name).copied(),
+        }
+    }
+}
+
+/// Find the name and file of each resource in a group, including nested groups.
+fn resources(project: &Element, group: &str, kind: &str) -> Vec<(String, PathBuf)> {
+    fn visit(group: &Element, kind: &str, resources: &mut Vec<(String, PathBuf)>) {
+        for child in &group.children[..] {
+            if child.name == kind {
+                let file: PathBuf = child.text.trim().split('\\').collect();
+                let name = file.file_stem().unwrap_or_default().to_string_lossy().into_owned();
+                resources.push((name, file));
+            } else {
+                visit(child, kind, resources);
+            }
+        }
+    }
+
+    let mut resources = Vec::default();
+    for group in project.children(group) {
+        visit(group, kind, &mut resources);
+    }
+    resources
+}
+
+fn index(resources: &[(String, PathBuf)]) -> HashMap<String, i32> {
+    resources.iter().enumerate().map(|(id, (name, _))| (name.clone(), id as i32)).collect()
+}
+
+fn read_sprite<'a>(
+    path: &path::Path, name: &str, arena: &'a Arena, warnings: &mut Vec<Warning>
+) -> io::Result<Sprite<'a>> {
+    let xml = read_xml(path)?;
+    let mut sprite = Sprite {
+        name: BStr::new(alloc(arena, name.as_bytes())),
+        version: 800,
+        size: (xml.parse(path, "width")?, xml.parse(path, "height")?),
+        origin: (xml.parse(path, "xorig")?, xml.parse(path, "yorigin")?),
+        shape: xml.parse(path, "colkind")?,
+        alpha_tolerance: xml.parse(path, "coltolerance")?,
+        separate_collision: xml.parse::<i32>(path, "sepmasks")? != 0,
+        bounds_kind: xml.parse(path, "bboxmode")?,
+        ..Sprite::default()
+    };
+    sprite.precise = sprite.shape == crate::shape::PRECISE;
+    sprite.bounds.left = xml.parse(path, "bbox_left")?;
+    sprite.bounds.right = xml.parse(path, "bbox_right")?;
+    sprite.bounds.bottom = xml.parse(path, "bbox_bottom")?;
+    sprite.bounds.top = xml.parse(path, "bbox_top")?;
+
+    if xml.parse::<i32>(path, "type").unwrap_or(0) != 0 {
+        let message = format!("{name}: vector and skeletal sprites are not supported");
+        warnings.push(Warning(message));
+        return Ok(sprite);
+    }
+
+    let dir = path.parent().unwrap_or(path::Path::new(""));
+    for frames in xml.children("frames") {
+        for frame in frames.children("frame") {
+            let file: PathBuf = frame.text.trim().split('\\').collect();
+            let data = fs::read(dir.join(file))?;
+            let data = read_png(&data[..], sprite.size)?;
+            sprite.images.push(Image { size: sprite.size, data: alloc(arena, &data[..]) });
+        }
+    }
+
+    Ok(sprite)
+}
+
+fn read_background<'a>(
+    path: &path::Path, name: &str, arena: &'a Arena
+) -> io::Result<Background<'a>> {
+    let xml = read_xml(path)?;
+    let mut background = Background {
+        name: BStr::new(alloc(arena, name.as_bytes())),
+        version: 710,
+        size: (xml.parse(path, "width")?, xml.parse(path, "height")?),
+        ..Background::default()
+    };
+
+    let dir = path.parent().unwrap_or(path::Path::new(""));
+    if let Some(image) = xml.child("data").filter(|image| !image.text.trim().is_empty()) {
+        let file: PathBuf = image.text.trim().split('\\').collect();
+        let data = fs::read(dir.join(file))?;
+        let data = read_png(&data[..], background.size)?;
+        background.data = alloc(arena, &data[..]);
+    }
+
+    Ok(background)
+}
+
+fn read_font<'a>(
+    path: &path::Path, name: &str, arena: &'a Arena, warnings: &mut Vec<Warning>
+) -> io::Result<Font<'a>> {
+    let xml = read_xml(path)?;
+    let face = xml.child("name").map_or("", |child| child.text.as_str());
+    let mut font = Font {
+        name: BStr::new(alloc(arena, name.as_bytes())),
+        font: BStr::new(alloc(arena, face.as_bytes())),
+        size: xml.parse(path, "size")?,
+        bold: xml.parse::<i32>(path, "bold")? != 0,
+        italic: xml.parse::<i32>(path, "italic")? != 0,
+        charset: xml.parse(path, "charset").unwrap_or(0),
+        antialias: xml.parse(path, "aa").unwrap_or(3),
+        ..Font::default()
+    };
+
+    // Older projects store a single range of characters, and newer ones a list of them.
+    let ranges = xml.child("ranges").map_or(&[][..], |ranges| &ranges.children[..]);
+    if ranges.is_empty() {
+        font.start = xml.parse(path, "first")?;
+        font.end = xml.parse(path, "last")?;
+    } else {
+        (font.start, font.end) = (u32::MAX, 0);
+        for range in ranges {
+            let parse = |bound: &str| bound.trim().parse().ok();
+            let (start, end) = range.text.split_once(',')
+                .and_then(|(start, end)| Some((parse(start)?, parse(end)?)))
+                .ok_or_else(|| invalid(path, format_args!("expected range in <{}>", range.name)))?;
+            font.start = u32::min(font.start, start);
+            font.end = u32::max(font.end, end);
+        }
+    }
+    if font.end > 255 {
+        warnings.push(Warning(format!("{name}: characters past 255 are not supported")));
+        font.end = 255;
+    }
+
+    // The glyphs are pre-rendered white on a transparent background, so only keep their alpha.
+    let dir = path.parent().unwrap_or(path::Path::new(""));
+    if let Some(image) = xml.child("image").filter(|image| !image.text.trim().is_empty()) {
+        let file: PathBuf = image.text.trim().split('\\').collect();
+        let data = fs::read(dir.join(file))?;
+        let (size, data) = decode_png(&data[..])?;
+        let bitmap: Vec<_> = data.chunks_exact(4).map(|bgra| bgra[3]).collect();
+        font.bitmap_size = size;
+        font.bitmap = alloc(arena, &bitmap[..]);
+
+        font.glyphs.resize_with(256, Glyph::default);
+        for glyphs in xml.children("glyphs") {
+            for glyph in glyphs.children("glyph") {
+                let character: usize = glyph.attribute_parse(path, "character")?;
+                let Some(entry) = font.glyphs.get_mut(character) else { continue };
+                let field = |name| glyph.attribute_parse(path, name);
+                *entry = Glyph {
+                    position: (field("x")?, field("y")?),
+                    size: (field("w")?, field("h")?),
+                    shift: glyph.attribute_parse(path, "shift")?,
+                    offset: glyph.attribute_parse(path, "offset")?,
+                };
+            }
+        }
+    }
+
+    Ok(font)
+}
+
+fn read_timeline<'a>(
+    path: &path::Path, name: &str, names: &Names, arena: &'a Arena,
+    warnings: &mut Vec<Warning>
+) -> io::Result<Timeline<'a>> {
+    let xml = read_xml(path)?;
+    let mut timeline = Timeline {
+        name: BStr::new(alloc(arena, name.as_bytes())),
+        ..Timeline::default()
+    };
+
+    for entry in xml.children("entry") {
+        let mut moment = Moment { moment: entry.parse(path, "step")?, ..Moment::default() };
+        for event in entry.children("event") {
+            for action in event.children("action") {
+                moment.actions.push(read_action(path, action, name, names, arena, warnings)?);
+            }
+        }
+        timeline.moments.push(moment);
+    }
+
+    Ok(timeline)
+}
+
+fn read_object<'a>(
+    path: &path::Path, name: &str, names: &Names, arena: &'a Arena,
+    warnings: &mut Vec<Warning>
+) -> io::Result<Object<'a>> {
+    let xml = read_xml(path)?;
+    let mut object = Object {
+        name: BStr::new(alloc(arena, name.as_bytes())),
+        solid: xml.parse::<i32>(path, "solid")? != 0,
+        visible: xml.parse::<i32>(path, "visible")? != 0,
+        depth: xml.parse(path, "depth")?,
+        persistent: xml.parse::<i32>(path, "persistent")? != 0,
+        ..Object::default()
+    };
+
+    let mut resource = |names: &HashMap<String, i32>, field: &str| {
+        let resource = xml.child(field).map(|child| child.text.as_str());
+        Names::get(names, resource).unwrap_or_else(|| {
+            let resource = resource.unwrap_or_default();
+            warnings.push(Warning(format!("{name}: unknown {field} `{resource}`")));
+            -1
+        })
+    };
+    object.sprite = resource(&names.sprites, "spriteName");
+    object.parent = resource(&names.objects, "parentName");
+    object.mask = resource(&names.sprites, "maskName");
+
+    if xml.parse::<i32>(path, "PhysicsObject").unwrap_or(0) != 0 {
+        warnings.push(Warning(format!("{name}: physics is not supported")));
+    }
+
+    for events in xml.children("events") {
+        for event in events.children("event") {
+            let mut event_type = event.attribute_parse(path, "eventtype")?;
+            let event_kind = match event.attribute("ename") {
+                Some(other) if event_type == event_type::COLLISION => {
+                    Names::get(&names.objects, Some(other)).unwrap_or_else(|| {
+                        let message = format!("{name}: collision with unknown object `{other}`");
+                        warnings.push(Warning(message));
+                        -1
+                    })
+                }
+                _ => event.attribute_parse(path, "enumb")?,
+            };
+            if event_type > event_type::KEY_RELEASE {
+                let message = format!("{name}: event type {event_type} is not supported");
+                warnings.push(Warning(message));
+                event_type = u32::MAX;
+            }
+
+            let mut actions = Vec::default();
+            for action in event.children("action") {
+                actions.push(read_action(path, action, name, names, arena, warnings)?);
+            }
+            if event_type != u32::MAX {
+                object.events.push(Event { event_type, event_kind, actions });
+            }
+        }
+    }
+
+    Ok(object)
+}
+
+fn read_action<'a>(
+    path: &path::Path, xml: &Element, name: &str, names: &Names, arena: &'a Arena,
+    warnings: &mut Vec<Warning>
+) -> io::Result<Action<'a>> {
+    let mut action = Action {
+        library: xml.parse(path, "libid")?,
+        action: xml.parse(path, "id")?,
+        action_kind: xml.parse(path, "kind")?,
+        has_relative: xml.parse::<i32>(path, "userelative")? != 0,
+        is_question: xml.parse::<i32>(path, "isquestion")? != 0,
+        has_target: xml.parse::<i32>(path, "useapplyto")? != 0,
+        action_type: xml.parse(path, "exetype")?,
+        relative: xml.parse::<i32>(path, "relative")? != 0,
+        negate: xml.parse::<i32>(path, "isnot")? != 0,
+        ..Action::default()
+    };
+    let text = |field| xml.child(field).map_or("", |child| child.text.as_str());
+    action.name = BStr::new(alloc(arena, text("functionname").as_bytes()));
+    action.code = BStr::new(alloc(arena, text("codestring").as_bytes()));
+    action.target = match text("whoName") {
+        "self" => -1,
+        "other" => -2,
+        object => Names::get(&names.objects, Some(object)).unwrap_or_else(|| {
+            warnings.push(Warning(format!("{name}: unknown object `{object}`")));
+            -1
+        }),
+    };
+
+    for arguments in xml.children("arguments") {
+        for argument in arguments.children("argument") {
+            let kind = argument.parse(path, "kind")?;
+            let value = match argument.children.iter().find(|child| child.name != "kind") {
+                None => String::new(),
+                Some(value) if value.name == "string" => value.text.clone(),
+                Some(value) => {
+                    let resources = match &value.name[..] {
+                        "sprite" => Some(&names.sprites),
+                        "background" => Some(&names.backgrounds),
+                        "script" => Some(&names.scripts),
+                        "font" => Some(&names.fonts),
+                        "timeline" => Some(&names.timelines),
+                        "object" => Some(&names.objects),
+                        "room" => Some(&names.rooms),
+                        _ => None,
+                    };
+                    let id = resources.and_then(|names| Names::get(names, Some(&value.text)));
+                    let id = id.unwrap_or_else(|| {
+                        let message = format!(
+                            "{name}: unsupported {} argument `{}`", value.name, value.text
+                        );
+                        warnings.push(Warning(message));
+                        -1
+                    });
+                    id.to_string()
+                }
+            };
+            action.parameters.push(kind);
+            action.arguments.push(BStr::new(alloc(arena, value.as_bytes())));
+        }
+    }
+    action.parameters_used = action.arguments.len() as u32;
+
+    Ok(action)
+}
+
+fn read_room<'a>(
+    path: &path::Path, name: &str, names: &Names, game: &mut Game<'a>, arena: &'a Arena,
+    warnings: &mut Vec<Warning>
+) -> io::Result<Room<'a>> {
+    let xml = read_xml(path)?;
+    let text = |field| xml.child(field).map_or("", |child| child.text.as_str());
+    let mut room = Room {
+        name: BStr::new(alloc(arena, name.as_bytes())),
+        caption: BStr::new(alloc(arena, text("caption").as_bytes())),
+        width: xml.parse(path, "width")?,
+        height: xml.parse(path, "height")?,
+        speed: xml.parse(path, "speed")?,
+        persistent: xml.parse::<i32>(path, "persistent")? != 0,
+        clear_color: xml.parse(path, "colour")?,
+        clear: xml.parse::<i32>(path, "showcolour")? != 0,
+        code: BStr::new(alloc(arena, text("code").as_bytes())),
+        enable_views: xml.parse::<i32>(path, "enableViews")? != 0,
+        ..Room::default()
+    };
+
+    if xml.child("PhysicsWorld").is_some_and(|physics| physics.text.trim() != "0") {
+        warnings.push(Warning(format!("{name}: physics is not supported")));
+    }
+
+    let mut resource = |names: &HashMap<String, i32>, kind: &str, resource: Option<&str>| {
+        Names::get(names, resource).unwrap_or_else(|| {
+            let resource = resource.unwrap_or_default();
+            warnings.push(Warning(format!("{name}: unknown {kind} `{resource}`")));
+            -1
+        })
+    };
+
+    for backgrounds in xml.children("backgrounds") {
+        for background in backgrounds.children("background") {
+            let name = background.attribute("name");
+            room.backgrounds.push(RoomBackground {
+                visible: background.attribute_parse::<i32>(path, "visible")? != 0,
+                foreground: background.attribute_parse::<i32>(path, "foreground")? != 0,
+                background: resource(&names.backgrounds, "background", name),
+                x: background.attribute_parse(path, "x")?,
+                y: background.attribute_parse(path, "y")?,
+                htiled: background.attribute_parse::<i32>(path, "htiled")? != 0,
+                vtiled: background.attribute_parse::<i32>(path, "vtiled")? != 0,
+                hspeed: background.attribute_parse(path, "hspeed")?,
+                vspeed: background.attribute_parse(path, "vspeed")?,
+                stretch: background.attribute_parse::<i32>(path, "stretch")? != 0,
+            });
+        }
+    }
+
+    for views in xml.children("views") {
+        for view in views.children("view") {
+            let target = view.attribute("objName");
+            room.views.push(View {
+                visible: view.attribute_parse::<i32>(path, "visible")? != 0,
+                view_x: view.attribute_parse(path, "xview")?,
+                view_y: view.attribute_parse(path, "yview")?,
+                view_w: view.attribute_parse(path, "wview")?,
+                view_h: view.attribute_parse(path, "hview")?,
+                port_x: view.attribute_parse(path, "xport")?,
+                port_y: view.attribute_parse(path, "yport")?,
+                port_w: view.attribute_parse(path, "wport")?,
+                port_h: view.attribute_parse(path, "hport")?,
+                h_border: view.attribute_parse(path, "hborder")?,
+                v_border: view.attribute_parse(path, "vborder")?,
+                h_speed: view.attribute_parse(path, "hspeed")?,
+                v_speed: view.attribute_parse(path, "vspeed")?,
+                target: resource(&names.objects, "object", target),
+            });
+        }
+    }
+
+    // Instances and tiles can be scaled, rotated and blended in GM:Studio, but not here.
+    let mut transformed = false;
+    let mut is_transformed = |xml: &Element| {
+        let attribute = |name, default| xml.attribute(name).map_or(default, str::trim);
+        transformed |= attribute("scaleX", "1") != "1" || attribute("scaleY", "1") != "1" ||
+            attribute("rotation", "0") != "0" || attribute("colour", "4294967295") != "4294967295";
+    };
+
+    // GM:Studio does not store instance ids in rooms, so number them in order.
+    for instances in xml.children("instances") {
+        for instance in instances.children("instance") {
+            is_transformed(instance);
+            let object_index = resource(&names.objects, "object", instance.attribute("objName"));
+            if object_index < 0 { continue; }
+
+            game.last_instance += 1;
+            let code = instance.attribute("code").unwrap_or_default();
+            room.instances.push(Instance {
+                x: instance.attribute_parse::<f64>(path, "x")? as i32,
+                y: instance.attribute_parse::<f64>(path, "y")? as i32,
+                object_index,
+                id: game.last_instance,
+                code: BStr::new(alloc(arena, code.as_bytes())),
+            });
+        }
+    }
+
+    for tiles in xml.children("tiles") {
+        for tile in tiles.children("tile") {
+            is_transformed(tile);
+            let background = resource(&names.backgrounds, "background", tile.attribute("bgName"));
+            let id = tile.attribute_parse(path, "id")?;
+            game.last_tile = i32::max(game.last_tile, id);
+            room.tiles.push(Tile {
+                x: tile.attribute_parse::<f64>(path, "x")? as i32,
+                y: tile.attribute_parse::<f64>(path, "y")? as i32,
+                background,
+                tile_x: tile.attribute_parse(path, "xo")?,
+                tile_y: tile.attribute_parse(path, "yo")?,
+                width: tile.attribute_parse(path, "w")?,
+                height: tile.attribute_parse(path, "h")?,
+                depth: tile.attribute_parse(path, "depth")?,
+                id,
+            });
+        }
+    }
+
+    if transformed {
+        let message = format!(
+            "{name}: scaled, rotated or colored instances and tiles are drawn without their \
+            transformation"
+        );
+        warnings.push(Warning(message));
+    }
+
+    Ok(room)
+}
+
+/// Read the included files in a group, which are stored in `folder` along with nested groups.
+fn read_datafiles<'a>(
+    path: &path::Path, group: &Element, folder: &path::Path, game: &mut Game<'a>,
+    arena: &'a Arena, warnings: &mut Vec<Warning>
+) -> io::Result<()> {
+    let dir = path.parent().unwrap_or(path::Path::new(""));
+    for xml in &group.children[..] {
+        if xml.name == "datafiles" {
+            let folder = folder.join(xml.attribute("name").unwrap_or_default());
+            read_datafiles(path, xml, &folder, game, arena, warnings)?;
+            continue;
+        }
+        if xml.name != "datafile" { continue; }
+
+        let text = |field| xml.child(field).map_or("", |child| child.text.trim());
+        let name = text("name");
+        let file = folder.join(text("filename"));
+        let data = match fs::read(dir.join(&file)) {
+            Ok(data) => Some(data),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => {
+                warnings.push(Warning(format!("{name}: included file is missing")));
+                None
+            }
+            Err(error) => return Err(error),
+        };
+        let file = file.iter().map(|part| part.to_string_lossy()).collect::<Vec<_>>().join("\\");
+
+        game.included_files.push(IncludedFile {
+            name: BStr::new(alloc(arena, name.as_bytes())),
+            path: BStr::new(alloc(arena, file.as_bytes())),
+            original: xml.parse::<i32>(path, "exists")? != 0,
+            size: data.as_ref().map_or(xml.parse(path, "size")?, |data| data.len() as u32),
+            stored: data.is_some(),
+            data: alloc(arena, data.as_deref().unwrap_or_default()),
+            export: xml.parse(path, "exportAction")?,
+            folder: BStr::new(alloc(arena, text("exportDir").as_bytes())),
+            overwrite: xml.parse::<i32>(path, "overwrite")? != 0,
+            free: xml.parse::<i32>(path, "freeData")? != 0,
+            remove: xml.parse::<i32>(path, "removeEnd")? != 0,
+        });
+    }
+
+    Ok(())
+}
+
+fn invalid(path: &path::Path, message: impl std::fmt::Display) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", path.display(), message))
+}
+
+fn read_xml(path: &path::Path) -> io::Result<Element> {
+    let data = fs::read(path)?;
+    let data = str::from_utf8(&data[..]).map_err(|_| invalid(path, "expected UTF-8"))?;
+    let data = data.strip_prefix('\u{feff}').unwrap_or(data);
+    parse_xml(data).map_err(|message| invalid(path, message))
+}
+
+/// An XML element, with the concatenation of its text content.
+#[derive(Default, Debug)]
+struct Element {
+    name: String,
+    attributes: Vec<(String, String)>,
+    children: Vec<Element>,
+    text: String,
+}
+
+impl Element {
+    fn child(&self, name: &str) -> Option<&Element> {
+        self.children.iter().find(|child| child.name == name)
+    }
+
+    fn children<'s>(&'s self, name: &'s str) -> impl Iterator<Item = &'s Element> {
+        self.children.iter().filter(move |child| child.name == name)
+    }
+
+    /// The number of elements with no children, used to count the resources in a group.
+    fn leaves(&self) -> usize {
+        if self.children.is_empty() { return 1; }
+        self.children.iter().map(Element::leaves).sum()
+    }
+
+    fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes.iter().find(|(key, _)| key == name).map(|(_, value)| &value[..])
+    }
+
+    fn parse<T: str::FromStr>(&self, path: &path::Path, name: &str) -> io::Result<T> {
+        self.child(name)
+            .and_then(|child| child.text.trim().parse().ok())
+            .ok_or_else(|| invalid(path, format_args!("expected <{name}> in <{}>", self.name)))
+    }
+
+    fn attribute_parse<T: str::FromStr>(&self, path: &path::Path, name: &str) -> io::Result<T> {
+        self.attribute(name)
+            .and_then(|value| value.trim().parse().ok())
+            .ok_or_else(|| invalid(path, format_args!("expected `{name}` in <{}>", self.name)))
+    }
+}
+
+/// Parse the subset of XML used by GM:Studio: elements, attributes, text, and character
+/// references. Declarations, comments, and processing instructions are skipped.
+fn parse_xml(data: &str) -> Result<Element, String> {
+    let mut stack = vec![Element::default()];
+    let mut rest = data;
+    while !rest.is_empty() {
+        let Some(start) = rest.find('<') else {
+            stack.last_mut().unwrap().text.push_str(&unescape(rest)?);
+            break;
+        };
+        stack.last_mut().unwrap().text.push_str(&unescape(&rest[..start])?);
+        rest = &rest[start..];
+
+        if let Some(tail) = rest.strip_prefix("<!--") {
+            let end = tail.find("-->").ok_or("unterminated comment")?;
+            rest = &tail[end + 3..];
+        } else if let Some(tail) = rest.strip_prefix("<![CDATA[") {
+            let end = tail.find("]]>").ok_or("unterminated CDATA section")?;
+            stack.last_mut().unwrap().text.push_str(&tail[..end]);
+            rest = &tail[end + 3..];
+        } else if rest.starts_with("<?") || rest.starts_with("<!") {
+            let end = rest.find('>').ok_or("unterminated declaration")?;
+            rest = &rest[end + 1..];
+        } else if let Some(tail) = rest.strip_prefix("</") {
+            let end = tail.find('>').ok_or("unterminated closing tag")?;
+            let name = tail[..end].trim();
+            let element = stack.pop().filter(|_| !stack.is_empty()).ok_or("unbalanced tags")?;
+            if element.name != name {
+                return Err(format!("expected </{}>, found </{name}>", element.name));
+            }
+            stack.last_mut().unwrap().children.push(element);
+            rest = &tail[end + 1..];
+        } else {
+            let tail = &rest[1..];
+            let end = tail.find(['/', '>', ' ', '\t', '\r', '\n']).ok_or("unterminated tag")?;
+            let mut element = Element { name: tail[..end].to_string(), ..Element::default() };
+            let mut tail = tail[end..].trim_start();
+            loop {
+                if let Some(after) = tail.strip_prefix("/>") {
+                    stack.last_mut().unwrap().children.push(element);
+                    rest = after;
+                    break;
+                }
+                if let Some(after) = tail.strip_prefix('>') {
+                    stack.push(element);
+                    rest = after;
+                    break;
+                }
+
+                let eq = tail.find('=').ok_or("expected attribute")?;
+                let key = tail[..eq].trim().to_string();
+                let value = tail[eq + 1..].trim_start();
+                let quote = value.chars().next().filter(|&c| c == '"' || c == '\'');
+                let quote = quote.ok_or("expected quoted attribute value")?;
+                let value = &value[1..];
+                let end = value.find(quote).ok_or("unterminated attribute value")?;
+                element.attributes.push((key, unescape(&value[..end])?));
+                tail = value[end + 1..].trim_start();
+            }
+        }
+    }
+
+    let mut document = stack.pop().filter(|_| stack.is_empty()).ok_or("unclosed element")?;
+    document.children.pop().filter(|_| document.children.is_empty()).ok_or_else(|| {
+        String::from("expected a single root element")
+    })
+}
+
+fn unescape(text: &str) -> Result<String, String> {
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        result.push_str(&rest[..start]);
+        let end = rest[start..].find(';').ok_or("unterminated character reference")?;
+        let entity = &rest[start + 1..start + end];
+        let c = match entity {
+            "lt" => '<',
+            "gt" => '>',
+            "amp" => '&',
+            "quot" => '"',
+            "apos" => '\'',
+            _ => {
+                let code = if let Some(hex) = entity.strip_prefix("#x") {
+                    u32::from_str_radix(hex, 16).ok()
+                } else if let Some(decimal) = entity.strip_prefix('#') {
+                    decimal.parse().ok()
+                } else {
+                    None
+                };
+                code.and_then(char::from_u32).ok_or_else(|| format!("unknown entity &{entity};"))?
+            }
+        };
+        result.push(c);
+        rest = &rest[start + end + 1..];
+    }
+    result.push_str(rest);
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, process};
+    use quickdry::Arena;
+    use crate::{Game, Warning, event_type};
+    use crate::dir::write_png;
+    use super::{read_gmx, unescape};
+
+    const PROJECT: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<!-- This Document is generated by GameMaker, if you edit it by hand then you do so at your own risk! -->
+<assets>
+  <Configs name="configs">
+    <Config>Configs\Default</Config>
+  </Configs>
+  <sounds name="sound">
+    <sound>sound\snd_jump</sound>
+  </sounds>
+  <sprites name="sprites">
+    <sprite>sprites\spr_player</sprite>
+  </sprites>
+  <scripts name="scripts">
+    <scripts name="movement">
+      <script>scripts\scr_move.gml</script>
+    </scripts>
+  </scripts>
+  <fonts name="fonts">
+    <font>fonts\fnt_score</font>
+  </fonts>
+  <timelines name="timelines">
+    <timeline>timelines\tl_intro</timeline>
+  </timelines>
+  <objects name="objects">
+    <object>objects\obj_player</object>
+  </objects>
+  <datafiles name="datafiles" number="1">
+    <datafiles name="levels">
+      <datafile>
+        <name>level.txt</name>
+        <exists>-1</exists>
+        <size>0</size>
+        <exportAction>2</exportAction>
+        <exportDir></exportDir>
+        <overwrite>0</overwrite>
+        <freeData>-1</freeData>
+        <removeEnd>0</removeEnd>
+        <store>0</store>
+        <filename>level.txt</filename>
+      </datafile>
+    </datafiles>
+  </datafiles>
+  <constants number="1">
+    <constant name="SPEED">4</constant>
+  </constants>
+  <rooms name="rooms">
+    <room>rooms\room0</room>
+  </rooms>
+</assets>
+"#;
+
+    const SPRITE: &str = r#"<!--This Document is generated by GameMaker-->
+<sprite>
+  <type>0</type>
+  <xorig>1</xorig>
+  <yorigin>0</yorigin>
+  <colkind>1</colkind>
+  <coltolerance>0</coltolerance>
+  <sepmasks>0</sepmasks>
+  <bboxmode>0</bboxmode>
+  <bbox_left>0</bbox_left>
+  <bbox_right>1</bbox_right>
+  <bbox_top>0</bbox_top>
+  <bbox_bottom>0</bbox_bottom>
+  <width>2</width>
+  <height>1</height>
+  <frames>
+    <frame index="0">images\spr_player_0.png</frame>
+  </frames>
+</sprite>
+"#;
+
+    const FONT: &str = r#"<font>
+  <name>Arial</name>
+  <size>12</size>
+  <bold>-1</bold>
+  <renderhq>-1</renderhq>
+  <italic>0</italic>
+  <charset>0</charset>
+  <aa>3</aa>
+  <includeTTF>0</includeTTF>
+  <TTFName></TTFName>
+  <ranges>
+    <range0>32,127</range0>
+  </ranges>
+  <glyphs>
+    <glyph character="65" x="1" y="0" w="1" h="1" shift="2" offset="0"/>
+  </glyphs>
+  <kerningPairs/>
+  <image>fnt_score.png</image>
+</font>
+"#;
+
+    const TIMELINE: &str = r#"<timeline>
+  <entry>
+    <step>30</step>
+    <event>
+      <action>
+        <libid>1</libid>
+        <id>603</id>
+        <kind>7</kind>
+        <userelative>0</userelative>
+        <isquestion>0</isquestion>
+        <useapplyto>-1</useapplyto>
+        <exetype>2</exetype>
+        <functionname></functionname>
+        <codestring></codestring>
+        <whoName>self</whoName>
+        <relative>0</relative>
+        <isnot>0</isnot>
+        <arguments>
+          <argument>
+            <kind>1</kind>
+            <string>room_goto_next()</string>
+          </argument>
+        </arguments>
+      </action>
+    </event>
+  </entry>
+</timeline>
+"#;
+
+    const OBJECT: &str = r#"<object>
+  <spriteName>spr_player</spriteName>
+  <solid>0</solid>
+  <visible>-1</visible>
+  <depth>-10</depth>
+  <persistent>0</persistent>
+  <parentName>&lt;undefined&gt;</parentName>
+  <maskName>&lt;undefined&gt;</maskName>
+  <events>
+    <event eventtype="0" enumb="0">
+      <action>
+        <libid>1</libid>
+        <id>603</id>
+        <kind>7</kind>
+        <userelative>0</userelative>
+        <isquestion>0</isquestion>
+        <useapplyto>-1</useapplyto>
+        <exetype>2</exetype>
+        <functionname></functionname>
+        <codestring></codestring>
+        <whoName>self</whoName>
+        <relative>0</relative>
+        <isnot>0</isnot>
+        <arguments>
+          <argument>
+            <kind>1</kind>
+            <string>scr_move(SPEED, 0) &amp;&amp; true</string>
+          </argument>
+        </arguments>
+      </action>
+    </event>
+    <event eventtype="4" ename="obj_player">
+      <action>
+        <libid>1</libid>
+        <id>201</id>
+        <kind>0</kind>
+        <userelative>0</userelative>
+        <isquestion>0</isquestion>
+        <useapplyto>-1</useapplyto>
+        <exetype>1</exetype>
+        <functionname>action_create_object</functionname>
+        <codestring></codestring>
+        <whoName>other</whoName>
+        <relative>0</relative>
+        <isnot>0</isnot>
+        <arguments>
+          <argument>
+            <kind>10</kind>
+            <object>obj_player</object>
+          </argument>
+        </arguments>
+      </action>
+    </event>
+  </events>
+  <PhysicsObject>0</PhysicsObject>
+</object>
+"#;
+
+    const ROOM: &str = r#"<room>
+  <caption><![CDATA[first <room>]]></caption>
+  <width>64</width>
+  <height>48</height>
+  <speed>30</speed>
+  <persistent>0</persistent>
+  <colour>12632256</colour>
+  <showcolour>-1</showcolour>
+  <code></code>
+  <enableViews>0</enableViews>
+  <backgrounds>
+    <background visible="0" foreground="0" name="" x="0" y="0" htiled="-1" vtiled="-1"
+      hspeed="0" vspeed="0" stretch="0"/>
+  </backgrounds>
+  <views/>
+  <instances>
+    <instance objName="obj_player" x="16" y="8" name="inst_1" locked="0" code=""
+      scaleX="2" scaleY="1" colour="4294967295" rotation="0"/>
+    <instance objName="obj_missing" x="0" y="0" name="inst_2" locked="0" code=""
+      scaleX="1" scaleY="1" colour="4294967295" rotation="0"/>
+  </instances>
+  <tiles/>
+  <PhysicsWorld>0</PhysicsWorld>
+</room>
+"#;
+
+    #[test]
+    fn read() {
+        let dir = env::temp_dir().join(format!("dejavu-gmx-{}", process::id()));
+        let kinds = ["sprites/images", "scripts", "fonts", "timelines", "objects", "rooms"];
+        for kind in kinds.into_iter().chain(["datafiles/levels"]) {
+            fs::create_dir_all(dir.join(kind)).unwrap();
+        }
+        let pixels = [0x10, 0x20, 0x30, 0xff, 0x40, 0x50, 0x60, 0x80];
+        fs::write(dir.join("game.project.gmx"), PROJECT).unwrap();
+        fs::write(dir.join("sprites/spr_player.sprite.gmx"), SPRITE).unwrap();
+        let png = write_png((2, 1), &pixels[..]).unwrap();
+        fs::write(dir.join("sprites/images/spr_player_0.png"), png).unwrap();
+        fs::write(dir.join("scripts/scr_move.gml"), "x += argument0;\r\n").unwrap();
+        fs::write(dir.join("fonts/fnt_score.font.gmx"), FONT).unwrap();
+        let png = write_png((2, 1), &[0xff, 0xff, 0xff, 0x00, 0xff, 0xff, 0xff, 0x80]).unwrap();
+        fs::write(dir.join("fonts/fnt_score.png"), png).unwrap();
+        fs::write(dir.join("timelines/tl_intro.timeline.gmx"), TIMELINE).unwrap();
+        fs::write(dir.join("objects/obj_player.object.gmx"), OBJECT).unwrap();
+        fs::write(dir.join("datafiles/levels/level.txt"), "1 2 3").unwrap();
+        fs::write(dir.join("rooms/room0.room.gmx"), ROOM).unwrap();
+
+        let arena = Arena::default();
+        let mut game = Game::default();
+        let warnings = read_gmx(&dir.join("game.project.gmx"), &mut game, &arena).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(game.constants[0].name, "SPEED");
+        assert_eq!(game.constants[0].value, "4");
+
+        let sprite = &game.sprites[0];
+        assert_eq!(sprite.name, "spr_player");
+        assert_eq!((sprite.origin, sprite.size), ((1, 0), (2, 1)));
+        assert!(!sprite.precise);
+        assert_eq!(sprite.images[0].data, &pixels[..]);
+
+        assert_eq!(game.scripts[0].name, "scr_move");
+        assert_eq!(game.scripts[0].body, "x += argument0;\r\n");
+
+        let font = &game.fonts[0];
+        assert_eq!((font.name, font.font, font.size), ("fnt_score".into(), "Arial".into(), 12));
+        assert_eq!((font.bold, font.start, font.end), (true, 32, 127));
+        assert_eq!((font.bitmap_size, font.bitmap), ((2, 1), &[0x00, 0x80][..]));
+        assert_eq!(font.glyphs.len(), 256);
+        assert_eq!((font.glyphs[65].position, font.glyphs[65].shift), ((1, 0), 2));
+
+        let timeline = &game.timelines[0];
+        assert_eq!((timeline.name, timeline.moments[0].moment), ("tl_intro".into(), 30));
+        assert_eq!(timeline.moments[0].actions[0].arguments[0], "room_goto_next()");
+
+        let file = &game.included_files[0];
+        assert_eq!(file.name, "level.txt");
+        assert_eq!(file.path, "datafiles\\levels\\level.txt");
+        assert_eq!((file.stored, file.data, file.size), (true, &b"1 2 3"[..], 5));
+        assert_eq!((file.export, file.free), (crate::export_kind::GAME, true));
+
+        let object = &game.objects[0];
+        assert_eq!((object.sprite, object.parent, object.mask, object.depth), (0, -1, -1, -10));
+        assert!(object.visible && !object.solid);
+        let action = &object.events[0].actions[0];
+        assert_eq!(action.arguments[0], "scr_move(SPEED, 0) && true");
+        assert_eq!(action.target, -1);
+        let collision = &object.events[1];
+        assert_eq!((collision.event_type, collision.event_kind), (event_type::COLLISION, 0));
+        assert_eq!(collision.actions[0].target, -2);
+        assert_eq!(collision.actions[0].arguments[0], "0");
+
+        let room = &game.rooms[0];
+        assert_eq!(room.caption, "first <room>");
+        assert_eq!((room.width, room.height, room.clear_color), (64, 48, 12632256));
+        assert_eq!(room.backgrounds[0].background, -1);
+        assert_eq!(room.instances.len(), 1);
+        assert_eq!((room.instances[0].x, room.instances[0].id), (16, 100001));
+        assert_eq!(game.last_instance, 100001);
+        assert_eq!(game.room_order, [0]);
+
+        let warnings: Vec<_> = warnings.iter().map(Warning::to_string).collect();
+        assert_eq!(warnings, [
+            "1 sounds are not supported and were skipped",
+            "1 game options are not supported and were skipped",
+            "room0: unknown object `obj_missing`",
+            "room0: scaled, rotated or colored instances and tiles are drawn without their \
+            transformation",
+        ]);
+    }
+
+    #[test]
+    fn entities() {
+        assert_eq!(unescape("&lt;a&gt; &amp; &#65;&#x42;").unwrap(), "<a> & AB");
+        assert!(unescape("&bogus;").is_err());
+    }
+}
@@ -3,11 +3,47 @@ use bstr::BStr;
 #[cfg(feature = "wasm")]
 use wasm::Reflect;
 
+pub use validate::{validate, Problem, Resource};
+
 #[cfg(feature = "read")]
 pub use read::{read_project, read_exe, read_ged, read_gex, read_bmp};
 
+#[cfg(feature = "write")]
+pub use write::write_project;
+
+#[cfg(feature = "dir")]
+pub use dir::{read_dir, write_dir, resource_files};
+
+#[cfg(feature = "gmx")]
+pub use gmx::read_gmx;
+
+#[cfg(feature = "win")]
+pub use win::read_win;
+
+mod validate;
 #[cfg(feature = "read")]
 mod read;
+#[cfg(feature = "write")]
+mod write;
+#[cfg(feature = "dir")]
+mod dir;
+#[cfg(feature = "gmx")]
+mod gmx;
+#[cfg(feature = "win")]
+mod win;
+
+/// Something an importer skipped or only approximated, in a project that still loaded.
+#[cfg(any(feature = "gmx", feature = "win"))]
+#[derive(Debug, PartialEq)]
+pub struct Warning(pub String);
+
+#[cfg(any(feature = "gmx", feature = "win"))]
+impl std::fmt::Display for Warning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let Warning(message) = self;
+        f.write_str(message)
+    }
+}
 
 #[cfg_attr(feature = "wasm", derive(Reflect))]
 pub struct Game<'a> {
@@ -20,6 +56,7 @@ pub struct Game<'a> {
     pub id: u32,
     pub guid: [u32; 4],
 
+    pub triggers: Vec<Trigger<'a>>,
     pub constants: Vec<Constant<'a>>,
 
     pub sounds: Vec<Sound<'a>>,
@@ -27,13 +64,17 @@ pub struct Game<'a> {
     pub backgrounds: Vec<Background<'a>>,
     pub paths: Vec<Path<'a>>,
     pub scripts: Vec<Script<'a>>,
+    pub fonts: Vec<Font<'a>>,
+    pub timelines: Vec<Timeline<'a>>,
     pub objects: Vec<Object<'a>>,
     pub rooms: Vec<Room<'a>>,
 
     pub last_instance: i32,
     pub last_tile: i32,
 
+    pub included_files: Vec<IncludedFile<'a>>,
     pub extensions: Vec<&'a BStr>,
+    pub information: GameInformation<'a>,
 
     pub room_order: Vec<u32>,
 }
@@ -76,6 +117,24 @@ pub struct Settings {
     pub error_log: bool,
     pub error_abort: bool,
     pub uninitialized_zero: bool,
+    /// Whether reading an argument that was not passed is an error, rather than 0. New in 8.1.
+    pub uninitialized_arguments: bool,
+}
+
+#[derive(Default)]
+#[cfg_attr(feature = "wasm", derive(Reflect))]
+pub struct Trigger<'a> {
+    pub name: &'a BStr,
+    pub condition: &'a BStr,
+    pub moment: u32,
+    /// The name of the constant for this trigger's event kind, for use with `event_perform`.
+    pub constant: &'a BStr,
+}
+
+pub mod trigger_moment {
+    pub const BEGIN_STEP: u32 = 0;
+    pub const STEP: u32 = 1;
+    pub const END_STEP: u32 = 2;
 }
 
 #[derive(Default)]
@@ -198,6 +257,51 @@ pub struct Script<'a> {
 }
 
 #[derive(Default)]
+#[cfg_attr(feature = "wasm", derive(Reflect))]
+pub struct Font<'a> {
+    pub name: &'a BStr,
+    pub font: &'a BStr,
+    pub size: u32,
+    pub bold: bool,
+    pub italic: bool,
+    pub start: u32,
+    pub end: u32,
+    /// The Windows character set and antialiasing level. New in 8.1.
+    pub charset: u32,
+    pub antialias: u32,
+
+    /// The pre-rendered glyphs for all 256 characters, only present in executables.
+    pub glyphs: Vec<Glyph>,
+    pub bitmap_size: (u32, u32),
+    /// The alpha channel of the pre-rendered bitmap, one byte per pixel.
+    pub bitmap: &'a [u8],
+}
+
+#[derive(Default)]
+#[cfg_attr(feature = "wasm", derive(Reflect))]
+pub struct Glyph {
+    pub position: (u32, u32),
+    pub size: (u32, u32),
+    /// The horizontal distance to the next glyph.
+    pub shift: i32,
+    /// The horizontal distance from the pen position to the left edge of the glyph.
+    pub offset: i32,
+}
+
+#[derive(Default)]
+#[cfg_attr(feature = "wasm", derive(Reflect))]
+pub struct Timeline<'a> {
+    pub name: &'a BStr,
+    pub moments: Vec<Moment<'a>>,
+}
+
+#[derive(Default)]
+#[cfg_attr(feature = "wasm", derive(Reflect))]
+pub struct Moment<'a> {
+    pub moment: u32,
+    pub actions: Vec<Action<'a>>,
+}
+
 #[cfg_attr(feature = "wasm", derive(Reflect))]
 pub struct Object<'a> {
     pub name: &'a BStr,
@@ -428,6 +532,46 @@ pub struct Tile {
     pub id: i32,
 }
 
+#[derive(Default)]
+#[cfg_attr(feature = "wasm", derive(Reflect))]
+pub struct IncludedFile<'a> {
+    pub name: &'a BStr,
+    pub path: &'a BStr,
+    pub original: bool,
+    pub size: u32,
+    /// Whether the contents are stored in the game, rather than only referred to by `path`.
+    pub stored: bool,
+    pub data: &'a [u8],
+    pub export: u32,
+    pub folder: &'a BStr,
+    pub overwrite: bool,
+    pub free: bool,
+    pub remove: bool,
+}
+
+pub mod export_kind {
+    pub const NONE: u32 = 0;
+    pub const TEMP: u32 = 1;
+    pub const GAME: u32 = 2;
+    pub const CUSTOM: u32 = 3;
+}
+
+/// The help text shown with F1.
+#[cfg_attr(feature = "wasm", derive(Reflect))]
+pub struct GameInformation<'a> {
+    pub background_color: u32,
+    pub separate_window: bool,
+    pub caption: &'a BStr,
+    pub position: (i32, i32),
+    pub size: (u32, u32),
+    pub show_border: bool,
+    pub allow_resize: bool,
+    pub topmost: bool,
+    pub freeze: bool,
+    /// The text, in RTF.
+    pub text: &'a BStr,
+}
+
 #[derive(Default)]
 #[cfg_attr(feature = "wasm", derive(Reflect))]
 pub struct Extension<'a> {
@@ -508,6 +652,7 @@ impl<'a> Default for Game<'a> {
             id: 0,
             guid: [0; 4],
 
+            triggers: Vec::default(),
             constants: Vec::default(),
 
             sounds: Vec::default(),
@@ -515,19 +660,56 @@ impl<'a> Default for Game<'a> {
             backgrounds: Vec::default(),
             paths: Vec::default(),
             scripts: Vec::default(),
+            fonts: Vec::default(),
+            timelines: Vec::default(),
             objects: Vec::default(),
             rooms: Vec::default(),
 
             last_instance: 100000,
             last_tile: 10000000,
 
+            included_files: Vec::default(),
             extensions: Vec::default(),
+            information: GameInformation::default(),
 
             room_order: Vec::default(),
         }
     }
 }
 
+impl<'a> Default for Object<'a> {
+    fn default() -> Object<'a> {
+        Object {
+            name: BStr::new(b""),
+            sprite: -1,
+            solid: false,
+            visible: false,
+            depth: 0,
+            persistent: false,
+            parent: -1,
+            mask: -1,
+            events: Vec::default(),
+        }
+    }
+}
+
+impl<'a> Default for GameInformation<'a> {
+    fn default() -> GameInformation<'a> {
+        GameInformation {
+            background_color: 0xe1ffff,
+            separate_window: false,
+            caption: BStr::new(b"Game Information"),
+            position: (-1, -1),
+            size: (600, 400),
+            show_border: true,
+            allow_resize: true,
+            topmost: false,
+            freeze: true,
+            text: BStr::new(b""),
+        }
+    }
+}
+
 impl Default for Settings {
     fn default() -> Settings {
         Settings {
@@ -567,6 +749,7 @@ impl Default for Settings {
             error_log: false,
             error_abort: false,
             uninitialized_zero: false,
+            uninitialized_arguments: false,
         }
     }
 }
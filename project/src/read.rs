@@ -6,25 +6,40 @@ use flate2::bufread::ZlibDecoder;
 use quickdry::Arena;
 
 use crate::{
-    Game, Constant,
+    Game, Trigger, Constant, IncludedFile, GameInformation, export_kind,
     Sound,
     Sprite, Image, Mask,
     Background,
     Path, Point,
     Script,
+    Font, Glyph,
+    Timeline, Moment,
     Object, Action, Event,
     Room, RoomBackground, View, Instance, Tile,
     Extension, ExtensionFile, ExtensionFunction, ExtensionConstant,
 };
 
 const GM_OFFSET_500: u64 = 1_500_000;
+const GM_OFFSET_600: u64 = 700_000;
 const EXE_MAGIC_500: u32 = 1230500;
 
 const GM_OFFSET_800: u64 = 2_000_000;
 const GM_MAGIC: u32 = 1234321;
 
-pub fn read_project<'a>(read: &[u8], game: &mut Game<'a>, arena: &'a Arena) -> io::Result<()> {
-    let read = &mut { read };
+/// The location of the code in the 8.1 runner that loads the game data.
+///
+/// 8.1 games do not store their data at a fixed offset. Instead, the runner's loader function
+/// begins here with `call rel32; mov dword ptr [ebp - 0x10], imm32`, where the immediate is the
+/// offset to start searching from. Both were found by disassembling the 8.1 runner, which is the
+/// same in every game built by a given release, and `LOADER_810` checks for them.
+const LOADER_OFFSET_810: u64 = 0x226cf3;
+const LOADER_810: [u8; 8] = [0xe8, 0x80, 0xf2, 0xdd, 0xff, 0xc7, 0x45, 0xf0];
+/// The length of the loader code between the start offset and its comparison against the magic
+/// number of the header.
+const LOADER_SKIP_810: usize = 125;
+
+pub fn read_project<'a>(file: &[u8], game: &mut Game<'a>, arena: &'a Arena) -> io::Result<()> {
+    let read = &mut { file };
     let buf = &mut Vec::default();
 
     if read.next_u32()? != GM_MAGIC {
@@ -32,16 +47,59 @@ pub fn read_project<'a>(read: &[u8], game: &mut Game<'a>, arena: &'a Arena) -> i
     }
 
     read.read_u32(&mut game.version)?;
-    if game.version != 530 && game.version != 800 {
+    if ![530, 600, 701, 800, 810].contains(&game.version) {
         return Err(io::Error::from(io::ErrorKind::InvalidData));
     }
 
     if game.version == 530 { read.read_u32(&mut game.debug)?; }
+    if game.version == 701 {
+        // 7.0 projects encrypt everything after the version, past a seed hidden among junk words.
+
+        // swap tables
+
+        let skip_1 = read.next_u32()? as usize;
+        let skip_2 = read.next_u32()? as usize;
+        *read = read.get(skip_1 * 4..).ok_or(io::ErrorKind::UnexpectedEof)?;
+        let seed = read.next_u32()? as usize;
+        *read = read.get(skip_2 * 4..).ok_or(io::ErrorKind::UnexpectedEof)?;
+
+        let a = seed % 250 + 6;
+        let b = seed / 250;
+
+        let mut forward = [0u8; 256];
+        for i in 0..256 { forward[i] = i as u8; }
+        for i in 1..10001 {
+            let j = (i * a + b) % 254 + 1;
+            forward.swap(j, j + 1);
+        }
+
+        let mut reverse = [0u8; 256];
+        for i in 0..256 { reverse[forward[i] as usize] = i as u8; }
+
+        // decrypt
+
+        if read.is_empty() {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof));
+        }
+        let start = file.len() - read.len();
+        let mut data = Vec::with_capacity(read.len());
+        data.push(read[0]);
+        for (i, &x) in read.iter().enumerate().skip(1) {
+            data.push(reverse[x as usize].wrapping_sub((start + i) as u8));
+        }
+
+        read_body(&mut &data[..], buf, false, game, &mut Vec::default(), arena)?;
+        return Ok(());
+    }
     read_body(read, buf, false, game, &mut Vec::default(), arena)?;
 
     Ok(())
 }
 
+/// Read a game from a 5.x, 6.x, 8.0 or 8.1 executable.
+///
+/// 7.0 executables are not supported: their layout has not been worked out, so they are rejected
+/// as invalid data.
 pub fn read_exe<'a, R: BufRead + Seek>(
     read: &mut R, game: &mut Game<'a>, extensions: &mut Vec<Extension<'a>>, arena: &'a Arena
 ) -> io::Result<()> {
@@ -52,6 +110,9 @@ pub fn read_exe<'a, R: BufRead + Seek>(
     }
 
     if {
+        read.seek(SeekFrom::Start(GM_OFFSET_600))?;
+        read.next_u32()? == EXE_MAGIC_500
+    } || {
         read.seek(SeekFrom::Start(GM_OFFSET_500))?;
         read.next_u32()? == EXE_MAGIC_500
     } {
@@ -87,71 +148,192 @@ pub fn read_exe<'a, R: BufRead + Seek>(
         read.seek(SeekFrom::Start(GM_OFFSET_800))?;
         read.next_u32()? == GM_MAGIC
     } {
-        let buf = &mut Vec::default();
+        read_exe_800(read, game, extensions, arena)?;
+    } else if let Some((start, magic)) = {
+        read.seek(SeekFrom::Start(LOADER_OFFSET_810))?;
+        read_loader_810(read)?
+    } {
+        let mut data = Vec::default();
+        read.seek(SeekFrom::Start(start))?;
+        read.read_to_end(&mut data)?;
 
-        read.read_u32(&mut game.version)?;
-        if game.version != 800 {
+        // The header is stored with the bytes of the magic number interleaved with other data.
+        let header = (0..data.len().saturating_sub(8)).find(|&i| {
+            let a = u32::from_le_bytes([data[i], data[i + 1], data[i + 2], data[i + 3]]);
+            let b = u32::from_le_bytes([data[i + 4], data[i + 5], data[i + 6], data[i + 7]]);
+            (a & 0xff00ff00) | (b & 0x00ff00ff) == magic
+        });
+        let Some(header) = header else {
             return Err(io::Error::from(io::ErrorKind::InvalidData));
-        }
+        };
 
-        read.read_u32(&mut game.debug)?;
+        let data = &mut data[header + 8..];
+        decrypt_810(data)?;
 
-        {
-            let version = read.next_u32()?;
-            if version != 800 {
-                return Err(io::Error::from(io::ErrorKind::InvalidData));
-            }
+        let read = &mut io::Cursor::new(&data[8..]);
+        read_exe_800(read, game, extensions, arena)?;
+    } else {
+        return Err(io::Error::from(io::ErrorKind::InvalidData));
+    }
 
-            let read = &mut read.read_blob_zlib(buf)?;
-            read_settings(read, true, version, game, arena)?;
-            assert!(read.is_empty());
+    Ok(())
+}
+
+/// Read the rest of a GM 8.0 or 8.1 executable, following the header.
+fn read_exe_800<'a, R: BufRead + Seek>(
+    read: &mut R, game: &mut Game<'a>, extensions: &mut Vec<Extension<'a>>, arena: &'a Arena
+) -> io::Result<()> {
+    let buf = &mut Vec::default();
+
+    read.read_u32(&mut game.version)?;
+    if game.version != 800 && game.version != 810 {
+        return Err(io::Error::from(io::ErrorKind::InvalidData));
+    }
+
+    read.read_u32(&mut game.debug)?;
+
+    {
+        let version = read.next_u32()?;
+        if version != 800 && version != 810 {
+            return Err(io::Error::from(io::ErrorKind::InvalidData));
         }
 
-        read.skip_blob()?;
-        read.skip_blob()?;
+        let read = &mut read.read_blob_zlib(buf)?;
+        read_settings(read, true, version, game, arena)?;
+        assert!(read.is_empty());
+    }
 
-        // swap tables
+    read.skip_blob()?;
+    read.skip_blob()?;
 
-        let mut forward = [0u8; 256];
-        let skip_1 = read.next_u32()? as i64;
-        let skip_2 = read.next_u32()? as i64;
-        read.seek(SeekFrom::Current(skip_1 * 4))?;
-        read.read_exact(&mut forward)?;
-        read.seek(SeekFrom::Current(skip_2 * 4))?;
+    // swap tables
 
-        let mut reverse = [0u8; 256];
-        for i in 0..256 { reverse[forward[i] as usize] = i as u8; }
+    let mut forward = [0u8; 256];
+    let skip_1 = read.next_u32()? as i64;
+    let skip_2 = read.next_u32()? as i64;
+    read.seek(SeekFrom::Current(skip_1 * 4))?;
+    read.read_exact(&mut forward)?;
+    read.seek(SeekFrom::Current(skip_2 * 4))?;
 
-        // decrypt
+    let mut reverse = [0u8; 256];
+    for i in 0..256 { reverse[forward[i] as usize] = i as u8; }
 
-        let len = read.next_u32()? as usize;
-        let mut data = Vec::from_iter(iter::repeat(0).take(len));
-        read.read_exact(&mut data[..])?;
+    // decrypt
 
-        for i in (1..data.len()).rev() {
-            data[i] = (reverse[data[i] as usize] as i32 - data[i - 1] as i32 - i as i32) as u8;
-        }
-        for i in (0..data.len()).rev() {
-            let j = usize::saturating_sub(i, forward[i & 0xff] as usize);
-            data.swap(i, j);
-        }
+    let len = read.next_u32()? as usize;
+    let mut data = Vec::from_iter(iter::repeat(0).take(len));
+    read.read_exact(&mut data[..])?;
 
-        // game data
+    for i in (1..data.len()).rev() {
+        data[i] = (reverse[data[i] as usize] as i32 - data[i - 1] as i32 - i as i32) as u8;
+    }
+    for i in (0..data.len()).rev() {
+        let j = usize::saturating_sub(i, forward[i & 0xff] as usize);
+        data.swap(i, j);
+    }
 
-        let read = &mut &data[..];
+    // game data
 
-        let skip = read.next_u32()? as usize;
-        *read = &read[skip * 4..];
+    let read = &mut &data[..];
 
-        read.read_bool(&mut game.pro)?;
-        read_body(read, buf, true, game, extensions, arena)?;
-    } else {
-        return Err(io::Error::from(io::ErrorKind::InvalidData));
+    let skip = read.next_u32()? as usize;
+    *read = &read[skip * 4..];
+
+    read.read_bool(&mut game.pro)?;
+    read_body(read, buf, true, game, extensions, arena)?;
+
+    Ok(())
+}
+
+/// Find where to search for an 8.1 header, and its magic number, in the runner code that loads it.
+fn read_loader_810<R: Read>(read: &mut R) -> io::Result<Option<(u64, u32)>> {
+    let mut buf = [0; 8];
+    read.read_exact(&mut buf)?;
+    if buf != LOADER_810 {
+        return Ok(None);
+    }
+
+    let start = read.next_u32()? as u64;
+
+    // Skip the code between the offset and the comparison against the magic number.
+    let mut buf = [0; LOADER_SKIP_810];
+    read.read_exact(&mut buf)?;
+
+    let mut opcode = [0; 1];
+    read.read_exact(&mut opcode)?;
+    let magic = match opcode[0] {
+        // cmp eax, imm32; jne
+        0x3d => {
+            let magic = read.next_u32()?;
+            let mut jump = [0; 2];
+            read.read_exact(&mut jump)?;
+            if jump != [0x0f, 0x85] { return Ok(None); }
+            magic
+        }
+        // cmp dword ptr [ebp - 0x14], imm32; je
+        0x81 => {
+            let mut operand = [0; 2];
+            read.read_exact(&mut operand)?;
+            if operand != [0x7d, 0xec] { return Ok(None); }
+            let magic = read.next_u32()?;
+            let mut jump = [0; 1];
+            read.read_exact(&mut jump)?;
+            if jump != [0x74] { return Ok(None); }
+            magic
+        }
+        _ => return Ok(None),
+    };
+
+    Ok(Some((start, magic)))
+}
+
+/// Decrypt 8.1 game data in place, starting at the key that follows the header.
+///
+/// The key is combined with a fixed string and hashed with CRC-32 to seed one generator, and the
+/// following word seeds another. The first few bytes of data after that are left unencrypted.
+fn decrypt_810(data: &mut [u8]) -> io::Result<()> {
+    let read = &mut &data[..];
+    let key = format!("_MJD{}#RWK", read.next_u32()?);
+    let mut seed_1 = read.next_u32()?;
+
+    let key: Vec<u8> = key.bytes().flat_map(|c| [c, 0]).collect();
+    let mut seed_2 = crc_32(&key[..]);
+
+    let start = 8 + (seed_2 & 0xff) as usize + 10;
+    if data.len() < start {
+        return Err(io::Error::from(io::ErrorKind::UnexpectedEof));
+    }
+
+    for word in data[start..].chunks_exact_mut(4) {
+        seed_1 = (seed_1 & 0xffff) * 0x9069 + (seed_1 >> 16);
+        seed_2 = (seed_2 & 0xffff) * 0x4650 + (seed_2 >> 16);
+        let mask = (seed_1 << 16).wrapping_add(seed_2 & 0xffff);
+
+        let value = u32::from_le_bytes([word[0], word[1], word[2], word[3]]) ^ mask;
+        word.copy_from_slice(&value.to_le_bytes());
     }
 
     Ok(())
 }
 
+/// CRC-32, without the final inversion.
+fn crc_32(data: &[u8]) -> u32 {
+    let mut table = [0u32; 256];
+    for (i, entry) in table.iter_mut().enumerate() {
+        let mut crc = i as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xedb88320 } else { crc >> 1 };
+        }
+        *entry = crc;
+    }
+
+    let mut crc = !0;
+    for &byte in data {
+        crc = (crc >> 8) ^ table[((crc ^ byte as u32) & 0xff) as usize];
+    }
+    crc
+}
+
 fn read_body<'a>(
     read: &mut &[u8], buf: &mut Vec<u8>, exe: bool,
     game: &mut Game<'a>, extensions: &mut Vec<Extension<'a>>, arena: &'a Arena
@@ -164,16 +346,16 @@ fn read_body<'a>(
 
     // settings
 
-    if game.version == 530 || (game.version == 800 && !exe) {
+    if game.version < 800 || !exe {
         let version = read.next_u32()?;
-        if version != 530 && version != 800 {
+        if ![530, 600, 702, 800, 810].contains(&version) {
             return Err(io::Error::from(io::ErrorKind::InvalidData));
         }
 
-        if version == 530 {
+        if version < 800 {
             read_settings(read, exe, version, game, arena)?;
         }
-        if version == 800 {
+        if version >= 800 {
             let read = &mut read.read_blob_zlib(buf)?;
             read_settings(read, exe, version, game, arena)?;
             assert!(read.is_empty());
@@ -182,7 +364,7 @@ fn read_body<'a>(
 
     // extensions
 
-    if game.version == 800 && exe {
+    if game.version >= 800 && exe {
         let _version = read.next_u32()?;
         let len = read.next_u32()? as usize;
         game.extensions.reserve(len);
@@ -235,16 +417,42 @@ fn read_body<'a>(
 
     // triggers
 
-    if game.version == 800 {
+    if game.version >= 800 {
         let _version = read.next_u32()?;
-        let _len = read.next_u32()?;
-        assert_eq!(_len, 0);
+        let len = read.next_u32()? as usize;
+        game.triggers.reserve(len);
+        for id in 0..len {
+            game.triggers.push(Trigger::default());
+
+            let trigger = &mut game.triggers[id];
+            let read = &mut read.read_blob_zlib(buf)?;
+            read_trigger(read, trigger, arena)?;
+            assert!(read.is_empty());
+        }
         if !exe { let _time = read.next_f64()?; }
     }
 
+    fn read_trigger<'a>(read: &mut &[u8], trigger: &mut Trigger<'a>, arena: &'a Arena) ->
+        io::Result<()>
+    {
+        if !read.next_bool()? { return Ok(()); }
+
+        let version = read.next_u32()?;
+        if version != 800 {
+            return Err(io::Error::from(io::ErrorKind::InvalidData));
+        }
+
+        read.read_bstr(&mut trigger.name, arena)?;
+        read.read_bstr(&mut trigger.condition, arena)?;
+        read.read_u32(&mut trigger.moment)?;
+        read.read_bstr(&mut trigger.constant, arena)?;
+
+        Ok(())
+    }
+
     // constants
 
-    if game.version == 800 {
+    if game.version >= 800 {
         let _version = read.next_u32()?;
         read_constants(read, game, arena)?;
         if !exe { let _time = read.next_f64()?; }
@@ -266,7 +474,7 @@ fn read_body<'a>(
         if version == 400 {
             read_sound(read, exe, version, sound, arena)?;
         }
-        if version == 800 {
+        if version >= 800 {
             let read = &mut read.read_blob_zlib(buf)?;
             read_sound(read, exe, version, sound, arena)?;
             assert!(read.is_empty());
@@ -279,10 +487,10 @@ fn read_body<'a>(
         if !read.next_bool()? { return Ok(()); }
 
         read.read_bstr(&mut sound.name, arena)?;
-        if version == 800 && !exe { let _time = read.next_f64()?; }
+        if version >= 800 && !exe { let _time = read.next_f64()?; }
 
         let version = read.next_u32()?;
-        if version != 440 && version != 800 {
+        if version != 440 && version != 600 && version != 800 {
             return Err(io::Error::from(io::ErrorKind::InvalidData));
         }
 
@@ -290,7 +498,7 @@ fn read_body<'a>(
         if version == 440 {
             read.read_i32(&mut kind_440)?;
         }
-        if version == 800 {
+        if version >= 600 {
             read.read_u32(&mut sound.kind)?;
         }
         read.read_bstr(&mut sound.file_type, arena)?;
@@ -303,10 +511,16 @@ fn read_body<'a>(
             let _buffers = read.next_u32()?;
             let _load_on_use = read.next_bool()?;
         }
-        if version == 800 {
+        if version >= 600 {
             read.read_bstr(&mut sound.file_name, arena)?;
             if read.next_bool()? {
-                read.read_blob(&mut sound.data, arena)?;
+                if version == 600 {
+                    let mut data = Vec::default();
+                    read.read_blob_zlib(&mut data)?;
+                    sound.data = alloc_buf(arena, &data[..]);
+                } else {
+                    read.read_blob(&mut sound.data, arena)?;
+                }
             }
             read.read_u32(&mut sound.effects)?;
             read.read_f64(&mut sound.volume)?;
@@ -320,7 +534,7 @@ fn read_body<'a>(
     // sprites
 
     let version = read.next_u32()?;
-    if version != 400 && version != 800 {
+    if version != 400 && version != 800 && version != 810 {
         return Err(io::Error::from(io::ErrorKind::InvalidData));
     }
 
@@ -333,7 +547,7 @@ fn read_body<'a>(
         if version == 400 {
             read_sprite(read, buf, exe, version, sprite, arena)?;
         }
-        if version == 800 {
+        if version >= 800 {
             let read = &mut read.read_blob_zlib(buf)?;
             read_sprite(read, &mut Vec::default(), exe, version, sprite, arena)?;
             assert!(read.is_empty());
@@ -347,14 +561,14 @@ fn read_body<'a>(
         if !read.next_bool()? { return Ok(()); }
 
         read.read_bstr(&mut sprite.name, arena)?;
-        if version == 800 && !exe { let _time = read.next_f64()?; }
+        if version >= 800 && !exe { let _time = read.next_f64()?; }
 
         read.read_u32(&mut sprite.version)?;
-        if sprite.version != 400 && sprite.version != 800 {
+        if ![400, 542, 800, 810].contains(&sprite.version) {
             return Err(io::Error::from(io::ErrorKind::InvalidData));
         }
 
-        if sprite.version == 400 {
+        if sprite.version < 800 {
             read.read_u32(&mut sprite.size.0)?;
             read.read_u32(&mut sprite.size.1)?;
 
@@ -364,11 +578,17 @@ fn read_body<'a>(
             read.read_i32(&mut sprite.bounds.top)?;
 
             read.read_bool(&mut sprite.transparent)?;
+            if sprite.version == 542 {
+                let _smooth = read.next_bool()?;
+                sprite.lazy_load = !read.next_bool()?;
+            }
 
             read.read_u32(&mut sprite.bounds_kind)?;
             read.read_bool(&mut sprite.precise)?;
-            read.read_bool(&mut sprite.use_vram)?;
-            read.read_bool(&mut sprite.lazy_load)?;
+            if sprite.version == 400 {
+                read.read_bool(&mut sprite.use_vram)?;
+                read.read_bool(&mut sprite.lazy_load)?;
+            }
         }
 
         read.read_u32(&mut sprite.origin.0)?;
@@ -380,7 +600,7 @@ fn read_body<'a>(
             sprite.images.push(Image::default());
 
             let image = &mut sprite.images[i];
-            if sprite.version == 400 {
+            if sprite.version < 800 {
                 if read.next_i32()? == -1 { continue; }
 
                 image.size = sprite.size;
@@ -388,9 +608,9 @@ fn read_body<'a>(
                 read.read_blob_zlib(buf)?;
                 image.data = alloc_buf(arena, &buf[..]);
             }
-            if sprite.version == 800 {
+            if sprite.version >= 800 {
                 let version = read.next_u32()?;
-                if version != 800 {
+                if version != 800 && version != 810 {
                     return Err(io::Error::from(io::ErrorKind::InvalidData));
                 }
 
@@ -402,7 +622,7 @@ fn read_body<'a>(
             }
         }
 
-        if sprite.version == 800 {
+        if sprite.version >= 800 {
             if !exe {
                 read.read_u32(&mut sprite.shape)?;
                 read.read_u32(&mut sprite.alpha_tolerance)?;
@@ -459,7 +679,7 @@ fn read_body<'a>(
         if version == 400 {
             read_background(read, buf, exe, version, background, arena)?;
         }
-        if version == 800 {
+        if version >= 800 {
             let read = &mut read.read_blob_zlib(buf)?;
             read_background(read, &mut Vec::default(), exe, version, background, arena)?;
             assert!(read.is_empty());
@@ -473,20 +693,33 @@ fn read_body<'a>(
         if !read.next_bool()? { return Ok(()); }
 
         read.read_bstr(&mut background.name, arena)?;
-        if version == 800 && !exe { let _time = read.next_f64()?; }
+        if version >= 800 && !exe { let _time = read.next_f64()?; }
 
         read.read_u32(&mut background.version)?;
-        if background.version != 400 && background.version != 710 {
+        if ![400, 543, 710].contains(&background.version) {
             return Err(io::Error::from(io::ErrorKind::InvalidData));
         }
 
-        if background.version == 400 {
+        if background.version < 710 {
             read.read_u32(&mut background.size.0)?;
             read.read_u32(&mut background.size.1)?;
 
             read.read_bool(&mut background.transparent)?;
-            read.read_bool(&mut background.use_vram)?;
-            read.read_bool(&mut background.lazy_load)?;
+            if background.version == 400 {
+                read.read_bool(&mut background.use_vram)?;
+                read.read_bool(&mut background.lazy_load)?;
+            }
+            if background.version == 543 {
+                let _smooth = read.next_bool()?;
+                background.lazy_load = !read.next_bool()?;
+                let _tileset = read.next_bool()?;
+                let _tile_width = read.next_u32()?;
+                let _tile_height = read.next_u32()?;
+                let _tile_off_x = read.next_u32()?;
+                let _tile_off_y = read.next_u32()?;
+                let _tile_sep_x = read.next_u32()?;
+                let _tile_sep_y = read.next_u32()?;
+            }
 
             if read.next_bool()? && read.next_i32()? != -1 {
                 read.read_blob_zlib(buf)?;
@@ -535,7 +768,7 @@ fn read_body<'a>(
         if version == 420 {
             read_path(read, exe, version, path, arena)?;
         }
-        if version == 800 {
+        if version >= 800 {
             let read = &mut read.read_blob_zlib(buf)?;
             read_path(read, exe, version, path, arena)?;
             assert!(read.is_empty());
@@ -548,7 +781,7 @@ fn read_body<'a>(
         if !read.next_bool()? { return Ok(()); }
 
         read.read_bstr(&mut path.name, arena)?;
-        if version == 800 && !exe { let _time = read.next_f64()?; }
+        if version >= 800 && !exe { let _time = read.next_f64()?; }
 
         let version = read.next_u32()?;
         if version != 530 {
@@ -594,7 +827,7 @@ fn read_body<'a>(
         if version == 400 {
             read_script(read, exe, version, script, arena)?;
         }
-        if version == 800 {
+        if version >= 800 {
             let read = &mut read.read_blob_zlib(buf)?;
             read_script(read, exe, version, script, arena)?;
             assert!(read.is_empty());
@@ -607,7 +840,7 @@ fn read_body<'a>(
         if !read.next_bool()? { return Ok(()); }
 
         read.read_bstr(&mut script.name, arena)?;
-        if version == 800 && !exe { let _time = read.next_f64()?; }
+        if version >= 800 && !exe { let _time = read.next_f64()?; }
 
         let version = read.next_u32()?;
         if version != 400 && version != 800 {
@@ -622,18 +855,28 @@ fn read_body<'a>(
     // data files and fonts
 
     let version = read.next_u32()?;
-    if version != 440 && version != 800 {
+    if version != 440 && version != 540 && version != 800 {
         return Err(io::Error::from(io::ErrorKind::InvalidData));
     }
 
     let len = read.next_u32()? as usize;
-    for _ in 0..len {
+    if version >= 540 { game.fonts.reserve(len); }
+    for id in 0..len {
         if version == 440 {
             read_data(read, buf, version, arena)?;
         }
-        if version == 800 {
+        if version == 540 {
+            game.fonts.push(Font::default());
+
+            let font = &mut game.fonts[id];
+            read_font(read, exe, version, game.version, font, arena)?;
+        }
+        if version >= 800 {
+            game.fonts.push(Font::default());
+
+            let font = &mut game.fonts[id];
             let read = &mut read.read_blob_zlib(buf)?;
-            read_font(read, exe, version, arena)?;
+            read_font(read, exe, version, game.version, font, arena)?;
             assert!(read.is_empty());
         }
     }
@@ -667,27 +910,49 @@ fn read_body<'a>(
     }
 
     fn read_font<'a>(
-        read: &mut &[u8], exe: bool, _version: u32, arena: &'a Arena
+        read: &mut &[u8], exe: bool, version: u32, game_version: u32,
+        font: &mut Font<'a>, arena: &'a Arena
     ) -> io::Result<()> {
         if !read.next_bool()? { return Ok(()); }
 
-        let mut name = BStr::new(&[][..]);
-        read.read_bstr(&mut name, arena)?;
-        if !exe { let _time = read.next_f64()?; }
+        read.read_bstr(&mut font.name, arena)?;
+        if version >= 800 && !exe { let _time = read.next_f64()?; }
 
         let version = read.next_u32()?;
-        if version != 800 {
+        if version != 540 && version != 800 {
             return Err(io::Error::from(io::ErrorKind::InvalidData));
         }
 
-        let mut font = BStr::new(&[][..]);
-        read.read_bstr(&mut font, arena)?;
+        read.read_bstr(&mut font.font, arena)?;
+        read.read_u32(&mut font.size)?;
+        read.read_bool(&mut font.bold)?;
+        read.read_bool(&mut font.italic)?;
+        read.read_u32(&mut font.start)?;
+        read.read_u32(&mut font.end)?;
+        if game_version >= 810 {
+            font.charset = (font.start >> 16) & 0xff;
+            font.antialias = font.start >> 24;
+            font.start &= 0xffff;
+        }
+
+        if exe {
+            font.glyphs.reserve(256);
+            for i in 0..256 {
+                font.glyphs.push(Glyph::default());
+
+                let glyph = &mut font.glyphs[i];
+                read.read_u32(&mut glyph.position.0)?;
+                read.read_u32(&mut glyph.position.1)?;
+                read.read_u32(&mut glyph.size.0)?;
+                read.read_u32(&mut glyph.size.1)?;
+                read.read_i32(&mut glyph.shift)?;
+                read.read_i32(&mut glyph.offset)?;
+            }
 
-        let _size = read.next_u32()?;
-        let _bold = read.next_bool()?;
-        let _italic = read.next_bool()?;
-        let _start = read.next_u32()?;
-        let _end = read.next_u32()?;
+            read.read_u32(&mut font.bitmap_size.0)?;
+            read.read_u32(&mut font.bitmap_size.1)?;
+            read.read_blob(&mut font.bitmap, arena)?;
+        }
 
         Ok(())
     }
@@ -700,42 +965,54 @@ fn read_body<'a>(
     }
 
     let len = read.next_u32()? as usize;
-    for _ in 0..len {
+    game.timelines.reserve(len);
+    for id in 0..len {
+        game.timelines.push(Timeline::default());
+
+        let timeline = &mut game.timelines[id];
         if version == 500 {
-            read_timeline(read, exe, version, arena)?;
+            read_timeline(read, exe, version, timeline, arena)?;
         }
-        if version == 800 {
+        if version >= 800 {
             let read = &mut read.read_blob_zlib(buf)?;
-            read_timeline(read, exe, version, arena)?;
+            read_timeline(read, exe, version, timeline, arena)?;
             assert!(read.is_empty());
         }
     }
 
     fn read_timeline<'a>(
-        read: &mut &[u8], exe: bool, version: u32, arena: &'a Arena
+        read: &mut &[u8], exe: bool, version: u32, timeline: &mut Timeline<'a>, arena: &'a Arena
     ) -> io::Result<()> {
         if !read.next_bool()? { return Ok(()); }
 
-        let mut name = BStr::new(&[][..]);
-        read.read_bstr(&mut name, arena)?;
-        if version == 800 && !exe { let _time = read.next_f64()?; }
+        read.read_bstr(&mut timeline.name, arena)?;
+        if version >= 800 && !exe { let _time = read.next_f64()?; }
 
         let version = read.next_u32()?;
         if version != 500 {
             return Err(io::Error::from(io::ErrorKind::InvalidData));
         }
 
-        for _ in 0..read.next_u32()? {
-            let _time = read.next_u32()?;
+        let len = read.next_u32()? as usize;
+        timeline.moments.reserve(len);
+        for id in 0..len {
+            timeline.moments.push(Moment::default());
+
+            let moment = &mut timeline.moments[id];
+            read.read_u32(&mut moment.moment)?;
 
             let version = read.next_u32()?;
             if version != 400 {
                 return Err(io::Error::from(io::ErrorKind::InvalidData));
             }
 
-            for _ in 0..read.next_u32()? {
-                let mut action = Action::default();
-                read_action(read, &mut action, arena)?;
+            let len = read.next_u32()? as usize;
+            moment.actions.reserve(len);
+            for id in 0..len {
+                moment.actions.push(Action::default());
+
+                let action = &mut moment.actions[id];
+                read_action(read, action, arena)?;
             }
         }
 
@@ -758,7 +1035,7 @@ fn read_body<'a>(
         if version == 400 {
             read_object(read, exe, version, object, arena)?;
         }
-        if version == 800 {
+        if version >= 800 {
             let read = &mut read.read_blob_zlib(buf)?;
             read_object(read, exe, version, object, arena)?;
             assert!(read.is_empty());
@@ -771,7 +1048,7 @@ fn read_body<'a>(
         if !read.next_bool()? { return Ok(()); }
 
         read.read_bstr(&mut object.name, arena)?;
-        if version == 800 && !exe { let _time = read.next_f64()?; }
+        if version >= 800 && !exe { let _time = read.next_f64()?; }
 
         let version = read.next_u32()?;
         if version != 430 {
@@ -836,7 +1113,7 @@ fn read_body<'a>(
         if version == 420 {
             read_room(read, exe, version, room, arena)?;
         }
-        if version == 800 {
+        if version >= 800 {
             let read = &mut read.read_blob_zlib(buf)?;
             read_room(read, exe, version, room, arena)?;
             assert!(read.is_empty());
@@ -849,7 +1126,7 @@ fn read_body<'a>(
         if !read.next_bool()? { return Ok(()); }
 
         read.read_bstr(&mut room.name, arena)?;
-        if version == 800 && !exe { let _time = read.next_f64()?; }
+        if version >= 800 && !exe { let _time = read.next_f64()?; }
 
         let version = read.next_u32()?;
         if version != 520 && version != 541 {
@@ -980,59 +1257,61 @@ fn read_body<'a>(
 
     // includes
 
-    if game.version == 800 {
+    if game.version >= 700 {
         let version = read.next_u32()?;
-        if version != 800 {
+        if version != 620 && version != 800 {
             return Err(io::Error::from(io::ErrorKind::InvalidData));
         }
 
         let len = read.next_u32()? as usize;
-        for _ in 0..len {
-            let read = &mut read.read_blob_zlib(buf)?;
-            read_include(read, exe, version, arena)?;
-            assert!(read.is_empty());
+        game.included_files.reserve(len);
+        for id in 0..len {
+            game.included_files.push(IncludedFile::default());
+
+            let file = &mut game.included_files[id];
+            if version == 620 {
+                read_include(read, exe, version, file, arena)?;
+            }
+            if version >= 800 {
+                let read = &mut read.read_blob_zlib(buf)?;
+                read_include(read, exe, version, file, arena)?;
+                assert!(read.is_empty());
+            }
         }
     }
 
     fn read_include<'a>(
-        read: &mut &[u8], exe: bool, version: u32, arena: &'a Arena
+        read: &mut &[u8], exe: bool, version: u32, file: &mut IncludedFile<'a>, arena: &'a Arena
     ) -> io::Result<()> {
-        if version == 800 && !exe { let _time = read.next_f64()?; }
+        if version >= 800 && !exe { let _time = read.next_f64()?; }
 
         let version = read.next_u32()?;
-        if version != 800 {
+        if version != 620 && version != 800 {
             return Err(io::Error::from(io::ErrorKind::InvalidData));
         }
 
-        let mut name = BStr::new(&[][..]);
-        read.read_bstr(&mut name, arena)?;
+        read.read_bstr(&mut file.name, arena)?;
+        read.read_bstr(&mut file.path, arena)?;
 
-        let mut path = BStr::new(&[][..]);
-        read.read_bstr(&mut path, arena)?;
-
-        let original = read.next_bool()?;
-        let _size = read.next_u32()?;
-        let in_gmk = read.next_bool()?;
-        if original && in_gmk {
-            let mut data = &[][..];
-            read.read_blob(&mut data, arena)?;
+        read.read_bool(&mut file.original)?;
+        read.read_u32(&mut file.size)?;
+        read.read_bool(&mut file.stored)?;
+        if file.original && file.stored {
+            read.read_blob(&mut file.data, arena)?;
         }
 
-        let _export = read.next_bool()?;
-
-        let mut folder = BStr::new(&[][..]);
-        read.read_bstr(&mut folder, arena)?;
-
-        let _overwrite = read.next_bool()?;
-        let _free = read.next_bool()?;
-        let _remove = read.next_bool()?;
+        read.read_u32(&mut file.export)?;
+        read.read_bstr(&mut file.folder, arena)?;
+        read.read_bool(&mut file.overwrite)?;
+        read.read_bool(&mut file.free)?;
+        read.read_bool(&mut file.remove)?;
 
         Ok(())
     }
 
     // extensions
 
-    if game.version == 800 && !exe {
+    if game.version >= 700 && !exe {
         let version = read.next_u32()?;
         if version != 700 {
             return Err(io::Error::from(io::ErrorKind::InvalidData));
@@ -1051,43 +1330,41 @@ fn read_body<'a>(
     // game info
 
     let version = read.next_u32()?;
-    if version != 430 && version != 800 {
+    if ![430, 600, 620, 800].contains(&version) {
         return Err(io::Error::from(io::ErrorKind::InvalidData));
     }
 
-    if version == 430 {
-        read_info(read, exe, version, arena)?;
+    if version < 800 {
+        read_info(read, exe, version, &mut game.information, arena)?;
     }
-    if version == 800 {
+    if version >= 800 {
         let read = &mut read.read_blob_zlib(buf)?;
-        read_info(read, exe, version, arena)?;
+        read_info(read, exe, version, &mut game.information, arena)?;
         assert!(read.is_empty());
     }
 
     fn read_info<'a>(
-        read: &mut &[u8], exe: bool, version: u32, arena: &'a Arena
+        read: &mut &[u8], exe: bool, version: u32, info: &mut GameInformation<'a>, arena: &'a Arena
     ) -> io::Result<()> {
-        let _background = read.next_u32()?;
-        let _window = read.next_bool()?;
+        read.read_u32(&mut info.background_color)?;
+        read.read_bool(&mut info.separate_window)?;
 
-        if version == 800 {
-            let mut caption = BStr::new(&[][..]);
-            read.read_bstr(&mut caption, arena)?;
+        if version >= 600 {
+            read.read_bstr(&mut info.caption, arena)?;
 
-            let _left = read.next_u32()?;
-            let _top = read.next_u32()?;
-            let _width = read.next_u32()?;
-            let _height = read.next_u32()?;
-            let _show_border = read.next_bool()?;
-            let _allow_resize = read.next_bool()?;
-            let _topmost = read.next_bool()?;
-            let _freeze = read.next_bool()?;
+            read.read_i32(&mut info.position.0)?;
+            read.read_i32(&mut info.position.1)?;
+            read.read_u32(&mut info.size.0)?;
+            read.read_u32(&mut info.size.1)?;
+            read.read_bool(&mut info.show_border)?;
+            read.read_bool(&mut info.allow_resize)?;
+            read.read_bool(&mut info.topmost)?;
+            read.read_bool(&mut info.freeze)?;
         }
 
-        if version == 800 && !exe { let _time = read.next_f64()?; }
+        if version >= 800 && !exe { let _time = read.next_f64()?; }
 
-        let mut info = BStr::new(&[][..]);
-        read.read_bstr(&mut info, arena)?;
+        read.read_bstr(&mut info.text, arena)?;
 
         Ok(())
     }
@@ -1125,7 +1402,7 @@ fn read_settings<'a>(
     let settings = &mut game.settings;
 
     read.read_bool(&mut settings.fullscreen)?;
-    if version == 800 { read.read_bool(&mut settings.interpolation)?; }
+    if version >= 600 { read.read_bool(&mut settings.interpolation)?; }
     read.read_bool(&mut settings.hide_border)?;
     read.read_bool(&mut settings.show_cursor)?;
     read.read_i32(&mut settings.scaling)?;
@@ -1133,7 +1410,7 @@ fn read_settings<'a>(
         let _fullscreen_scale = read.next_u32()?;
         let _hardware_scale_only = read.next_bool()?;
     }
-    if version == 800 {
+    if version >= 600 {
         read.read_bool(&mut settings.allow_resize)?;
         read.read_bool(&mut settings.topmost)?;
         read.read_u32(&mut settings.background_color)?;
@@ -1150,15 +1427,13 @@ fn read_settings<'a>(
         let _fullscreen_caption = read.next_bool()?;
     }
     read.read_bool(&mut settings.hide_buttons)?;
-    if version == 800 {
-        read.read_bool(&mut settings.vsync)?;
-        read.read_bool(&mut settings.disable_screensaver)?;
-    }
+    if version >= 600 { read.read_bool(&mut settings.vsync)?; }
+    if version >= 800 { read.read_bool(&mut settings.disable_screensaver)?; }
     read.read_bool(&mut settings.default_f4)?;
     read.read_bool(&mut settings.default_f1)?;
     read.read_bool(&mut settings.default_esc)?;
     read.read_bool(&mut settings.default_f5)?;
-    if version == 800 {
+    if version >= 702 {
         read.read_bool(&mut settings.default_f9)?;
         read.read_bool(&mut settings.close_as_esc)?;
     }
@@ -1175,16 +1450,16 @@ fn read_settings<'a>(
     read.read_u32(&mut settings.load_bar)?;
     if (exe && settings.load_bar == 1) || settings.load_bar == 2 {
         let mut back = false;
-        if version == 530 { back = read.next_i32()? != -1; }
-        if version == 800 { back = read.next_i32()? != -1; }
+        if version < 800 { back = read.next_i32()? != -1; }
+        if version >= 800 { back = read.next_i32()? != -1; }
         if back {
             let mut back = vec![];
             read.read_blob_zlib(&mut back)?;
         }
 
         let mut front = false;
-        if version == 530 { front = read.next_i32()? != -1; }
-        if version == 800 { front = read.next_i32()? != -1; }
+        if version < 800 { front = read.next_i32()? != -1; }
+        if version >= 800 { front = read.next_i32()? != -1; }
         if front {
             let mut front = vec![];
             read.read_blob_zlib(&mut front)?;
@@ -1194,8 +1469,8 @@ fn read_settings<'a>(
     read.read_bool(&mut settings.load_image)?;
     if settings.load_image {
         let mut exists = false;
-        if version == 530 { exists = read.next_i32()? != -1; }
-        if version == 800 { exists = if !exe { read.next_bool()? } else { true }; }
+        if version < 800 { exists = read.next_i32()? != -1; }
+        if version >= 800 { exists = if !exe { read.next_bool()? } else { true }; }
         if exists {
             let mut image = vec![];
             read.read_blob_zlib(&mut image)?;
@@ -1206,7 +1481,7 @@ fn read_settings<'a>(
     read.read_u32(&mut settings.load_alpha)?;
     read.read_bool(&mut settings.load_scale)?;
 
-    if version == 530 || (version == 800 && !exe) {
+    if version < 800 || !exe {
         let mut icon = &[][..];
         read.read_blob(&mut icon, arena)?;
     }
@@ -1214,15 +1489,17 @@ fn read_settings<'a>(
     read.read_bool(&mut settings.error_display)?;
     read.read_bool(&mut settings.error_log)?;
     read.read_bool(&mut settings.error_abort)?;
-    read.read_bool(&mut settings.uninitialized_zero)?;
+    let errors = read.next_u32()?;
+    settings.uninitialized_zero = errors & 0x1 != 0;
+    if version >= 810 { settings.uninitialized_arguments = errors & 0x2 != 0; }
 
-    if version == 530 || (version == 800 && !exe) {
+    if version < 800 || !exe {
         let mut author = BStr::new(&[][..]);
         read.read_bstr(&mut author, arena)?;
-        if version == 530 {
+        if version < 702 {
             let _version = read.next_u32()?;
         }
-        if version == 800 {
+        if version >= 702 {
             let mut version = BStr::new(&[][..]);
             read.read_bstr(&mut version, arena)?;
         }
@@ -1230,11 +1507,39 @@ fn read_settings<'a>(
         let mut information = BStr::new(&[][..]);
         read.read_bstr(&mut information, arena)?;
 
-        if version == 530 {
+        if version < 800 {
             read_constants(read, game, arena)?;
         }
 
-        if version == 800 {
+        // 6.x only lists the paths of included files, with one set of options for all of them.
+        if version == 600 {
+            let len = read.next_u32()? as usize;
+            game.included_files.reserve(len);
+            for _ in 0..len {
+                let mut file = IncludedFile::default();
+                read.read_bstr(&mut file.path, arena)?;
+                let name = file.path.rsplit(|&c| c == b'\\').next().unwrap_or(&[]);
+                file.name = BStr::new(name);
+                file.original = true;
+                game.included_files.push(file);
+            }
+
+            // 0 => game folder
+            // 1 => temporary folder
+            let export = match read.next_u32()? {
+                0 => export_kind::GAME,
+                _ => export_kind::TEMP,
+            };
+            let overwrite = read.next_bool()?;
+            let remove = read.next_bool()?;
+            for file in &mut game.included_files[..] {
+                file.export = export;
+                file.overwrite = overwrite;
+                file.remove = remove;
+            }
+        }
+
+        if version >= 702 {
             let _major = read.next_u32()?;
             let _minor = read.next_u32()?;
             let _release = read.next_u32()?;
@@ -1247,6 +1552,8 @@ fn read_settings<'a>(
             read.read_bstr(&mut copyright, arena)?;
             let mut description = BStr::new(&[][..]);
             read.read_bstr(&mut description, arena)?;
+        }
+        if version >= 800 {
             let _time = read.next_f64()?;
         }
     }
@@ -1505,7 +1812,7 @@ pub fn read_bmp<'a>(read: &mut &[u8], transparent: bool, arena: &'a Arena) -> io
     }
 }
 
-fn alloc_buf<'a>(arena: &'a Arena, buf: &[u8]) -> &'a mut [u8] {
+pub(crate) fn alloc_buf<'a>(arena: &'a Arena, buf: &[u8]) -> &'a mut [u8] {
     // Safety: 0 < len < isize::MAX; allocation is checked and initialized before use.
     unsafe {
         let layout = Layout::for_value(buf);
@@ -1516,7 +1823,7 @@ fn alloc_buf<'a>(arena: &'a Arena, buf: &[u8]) -> &'a mut [u8] {
     }
 }
 
-trait GmRead {
+pub(crate) trait GmRead {
     fn read_u16(&mut self, buf: &mut u16) -> io::Result<usize>;
     fn read_u32(&mut self, buf: &mut u32) -> io::Result<usize>;
     fn read_i32(&mut self, buf: &mut i32) -> io::Result<usize>;
@@ -1674,3 +1981,310 @@ impl GmSliceExt for &[u8] {
         Ok(nread)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::io::{self, Write};
+    use flate2::{Compression, write::ZlibEncoder};
+    use quickdry::Arena;
+    use crate::{Game, export_kind};
+    use super::{GM_MAGIC, LOADER_OFFSET_810, LOADER_810, LOADER_SKIP_810};
+    use super::{crc_32, decrypt_810, read_project, read_exe};
+
+    fn words(data: &mut Vec<u8>, words: &[u32]) {
+        for &word in words { data.extend_from_slice(&word.to_le_bytes()); }
+    }
+
+    fn bstr(data: &mut Vec<u8>, bstr: &[u8]) {
+        words(data, &[bstr.len() as u32]);
+        data.extend_from_slice(bstr);
+    }
+
+    fn zlib(data: &[u8]) -> Vec<u8> {
+        let mut zlib = ZlibEncoder::new(Vec::default(), Compression::default());
+        zlib.write_all(data).unwrap();
+        zlib.finish().unwrap()
+    }
+
+    #[test]
+    fn crc_32_check() {
+        assert_eq!(!crc_32(b"123456789"), 0xcbf43926);
+
+        // 8.1 keys are hashed as UTF-16. These values come from zlib's CRC-32.
+        let key = |key: &str| -> Vec<u8> { key.bytes().flat_map(|c| [c, 0]).collect() };
+        assert_eq!(!crc_32(&key("_MJD0#RWK")[..]), 0x998ebdbf);
+        assert_eq!(!crc_32(&key("_MJD1234#RWK")[..]), 0x96a0ee69);
+        assert_eq!(!crc_32(&key("_MJD2147483647#RWK")[..]), 0xf992a20b);
+    }
+
+    #[test]
+    fn decrypt_810_check() {
+        // Encrypted separately, with the key 1234 and the seed 0xdeadbeef. The key's hash puts
+        // the start of the encrypted data 168 bytes in.
+        let mut data = Vec::default();
+        words(&mut data, &[1234, 0xdeadbeef]);
+        data.resize(168, 0xcc);
+        data.extend_from_slice(&[
+            0x78, 0x8d, 0xd9, 0xfb, 0xc9, 0x73, 0xe2, 0xa6,
+            0x51, 0x1a, 0x86, 0xbc, 0x62, 0x23, 0x28, 0xc4,
+        ]);
+
+        decrypt_810(&mut data[..]).unwrap();
+        assert!(data[8..168].iter().all(|&byte| byte == 0xcc));
+        assert_eq!(&data[168..], b"GameMaker81 data");
+
+        let error = decrypt_810(&mut data[..167]).err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn read_600() {
+        let mut data = Vec::default();
+        words(&mut data, &[GM_MAGIC, 600, 1234, 1, 2, 3, 4]);
+
+        // settings
+        words(&mut data, &[600, 0, 1, 0, 1, 0, 1, 0, 0x112233, 0, 0, 0, 0]);
+        words(&mut data, &[0, 1, 1, 1, 1, 1, 0, 0, 0, 0, 0, 255, 0, 0, 1, 1, 0, 1]);
+        bstr(&mut data, b"author");
+        words(&mut data, &[100]);
+        data.extend_from_slice(&0.0f64.to_le_bytes());
+        bstr(&mut data, b"information");
+        words(&mut data, &[1]);
+        bstr(&mut data, b"answer");
+        bstr(&mut data, b"42");
+        words(&mut data, &[1]);
+        bstr(&mut data, b"C:\\data\\level.txt");
+        words(&mut data, &[1, 1, 0]);
+
+        // sounds and sprites
+        words(&mut data, &[400, 0]);
+        words(&mut data, &[400, 1, 1]);
+        bstr(&mut data, b"spr");
+        words(&mut data, &[542, 2, 1, 0, 1, 0, 0, 1, 0, 1, 0, 1, 1, 0, 1, 0]);
+        bstr(&mut data, &zlib(b"BM"));
+
+        // backgrounds, paths, scripts and fonts
+        words(&mut data, &[400, 0, 420, 0, 400, 0]);
+        words(&mut data, &[540, 1, 1]);
+        bstr(&mut data, b"fnt");
+        words(&mut data, &[540]);
+        bstr(&mut data, b"Arial");
+        words(&mut data, &[12, 1, 0, 32, 127]);
+
+        // timelines, objects, rooms, instance and tile ids
+        words(&mut data, &[500, 0, 400, 0, 420, 0, 100000, 10000000]);
+
+        // game information, library initialization and room order
+        words(&mut data, &[600, 0xe1ffff, 0]);
+        bstr(&mut data, b"Game Information");
+        words(&mut data, &[u32::MAX, u32::MAX, 600, 400, 1, 1, 0, 1]);
+        bstr(&mut data, b"help");
+        words(&mut data, &[500, 0, 500, 0]);
+
+        let arena = Arena::default();
+        let mut game = Game::default();
+        read_project(&data[..], &mut game, &arena).unwrap();
+
+        assert_eq!((game.version, game.id, game.guid), (600, 1234, [1, 2, 3, 4]));
+        assert!(game.settings.interpolation);
+        assert!(game.settings.vsync);
+        assert_eq!(game.settings.background_color, 0x112233);
+        assert!(game.settings.uninitialized_zero);
+        assert_eq!(game.constants[0].value, "42");
+
+        let file = &game.included_files[0];
+        assert_eq!((file.name, file.path), ("level.txt".into(), "C:\\data\\level.txt".into()));
+        assert_eq!((file.export, file.overwrite, file.remove), (export_kind::TEMP, true, false));
+
+        let sprite = &game.sprites[0];
+        assert_eq!((sprite.name, sprite.version, sprite.size), ("spr".into(), 542, (2, 1)));
+        assert_eq!((sprite.transparent, sprite.lazy_load, sprite.precise), (true, false, true));
+        assert_eq!(sprite.origin, (1, 0));
+        assert_eq!(sprite.images[0].data, b"BM");
+
+        let font = &game.fonts[0];
+        assert_eq!((font.name, font.font, font.size), ("fnt".into(), "Arial".into(), 12));
+        assert_eq!((font.bold, font.start, font.end), (true, 32, 127));
+
+        assert_eq!(game.information.size, (600, 400));
+        assert_eq!(game.information.text, "help");
+    }
+
+    #[test]
+    fn read_701() {
+        let mut body = Vec::default();
+        words(&mut body, &[1234, 1, 2, 3, 4]);
+
+        // settings
+        words(&mut body, &[702, 0, 1, 0, 1, 0, 0, 0, 0x112233, 0, 0, 0, 0, 0, 1]);
+        words(&mut body, &[1, 1, 1, 1, 1, 1, 0, 0, 0, 0, 0, 255, 0, 0, 1, 0, 0, 1]);
+        bstr(&mut body, b"author");
+        bstr(&mut body, b"1.0");
+        body.extend_from_slice(&0.0f64.to_le_bytes());
+        bstr(&mut body, b"information");
+        words(&mut body, &[1]);
+        bstr(&mut body, b"answer");
+        bstr(&mut body, b"42");
+        words(&mut body, &[1, 0, 0, 0]);
+        for field in [&b"company"[..], b"product", b"copyright", b"description"] {
+            bstr(&mut body, field);
+        }
+
+        // sounds, sprites, backgrounds, paths and scripts
+        words(&mut body, &[400, 0, 400, 0, 400, 0, 420, 0, 400, 1, 1]);
+        bstr(&mut body, b"main");
+        words(&mut body, &[400]);
+        bstr(&mut body, b"return 42");
+
+        // fonts, timelines, objects, rooms, instance and tile ids, included files and extensions
+        words(&mut body, &[540, 0, 500, 0, 400, 0, 420, 0, 100000, 10000000, 620, 0, 700, 1]);
+        bstr(&mut body, b"Extension");
+
+        // game information, library initialization and room order
+        words(&mut body, &[600, 0xe1ffff, 0]);
+        bstr(&mut body, b"Game Information");
+        words(&mut body, &[u32::MAX, u32::MAX, 600, 400, 1, 1, 0, 1]);
+        bstr(&mut body, b"help");
+        words(&mut body, &[500, 0, 700, 0]);
+
+        // Encrypt the body with the swap table for the seed, past the junk words around it.
+
+        let seed = 12345;
+        let mut data = Vec::default();
+        words(&mut data, &[GM_MAGIC, 701, 1, 2, 0, seed, 0, 0]);
+
+        let (a, b) = (seed as usize % 250 + 6, seed as usize / 250);
+        let mut forward = [0u8; 256];
+        for (i, x) in forward.iter_mut().enumerate() { *x = i as u8; }
+        for i in 1..10001 {
+            let j = (i * a + b) % 254 + 1;
+            forward.swap(j, j + 1);
+        }
+
+        let start = data.len();
+        data.push(body[0]);
+        for (i, &x) in body.iter().enumerate().skip(1) {
+            data.push(forward[x.wrapping_add((start + i) as u8) as usize]);
+        }
+        assert_ne!(data[start..], body[..]);
+
+        let arena = Arena::default();
+        let mut game = Game::default();
+        read_project(&data[..], &mut game, &arena).unwrap();
+
+        assert_eq!((game.version, game.id, game.guid), (701, 1234, [1, 2, 3, 4]));
+        assert!(game.settings.close_as_esc);
+        assert_eq!(game.settings.background_color, 0x112233);
+        assert!(game.settings.uninitialized_zero);
+        assert_eq!(game.constants[0].value, "42");
+        let script = &game.scripts[0];
+        assert_eq!((script.name, script.body), ("main".into(), "return 42".into()));
+        assert_eq!(game.extensions[..], ["Extension"]);
+        assert_eq!(game.information.text, "help");
+
+        // A seed hidden past the end of the file is an error, not a panic.
+        let mut data = Vec::default();
+        words(&mut data, &[GM_MAGIC, 701, u32::MAX >> 2, 0]);
+        let error = read_project(&data[..], &mut Game::default(), &arena).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn read_810() {
+        // game data
+
+        let mut body = Vec::default();
+        words(&mut body, &[0, 1, 1234, 1, 2, 3, 4]);
+
+        // extensions, triggers and constants
+        words(&mut body, &[700, 0, 800, 0, 800, 1]);
+        bstr(&mut body, b"answer");
+        bstr(&mut body, b"42");
+
+        // sounds, sprites, backgrounds, paths and scripts
+        words(&mut body, &[800, 0, 800, 0, 800, 0, 800, 0, 800, 1]);
+        let mut script = Vec::default();
+        words(&mut script, &[1]);
+        bstr(&mut script, b"main");
+        words(&mut script, &[800]);
+        bstr(&mut script, b"return 42");
+        bstr(&mut body, &zlib(&script));
+
+        // fonts, timelines, objects, rooms, instance and tile ids, and included files
+        words(&mut body, &[800, 0, 800, 0, 800, 0, 800, 0, 100000, 10000000, 800, 0]);
+
+        // game information, library initialization and room order
+        let mut info = Vec::default();
+        words(&mut info, &[0xe1ffff, 0]);
+        bstr(&mut info, b"Game Information");
+        words(&mut info, &[u32::MAX, u32::MAX, 600, 400, 1, 1, 0, 1]);
+        bstr(&mut info, b"help");
+        words(&mut body, &[800]);
+        bstr(&mut body, &zlib(&info));
+        words(&mut body, &[500, 0, 700, 0]);
+
+        // 8.0 data, with its game data encrypted by a swap table
+
+        let mut forward = [0u8; 256];
+        for (i, x) in forward.iter_mut().enumerate() { *x = (i * 7) as u8; }
+
+        for i in 0..body.len() {
+            let j = usize::saturating_sub(i, forward[i & 0xff] as usize);
+            body.swap(i, j);
+        }
+        for i in 1..body.len() {
+            body[i] = forward[(body[i] as usize + body[i - 1] as usize + i) & 0xff];
+        }
+
+        let mut settings = Vec::default();
+        words(&mut settings, &[0, 1, 0, 1, 0, 0, 0, 0x112233, 0, 0, 0, 0, 0, 1, 1, 1]);
+        words(&mut settings, &[1, 1, 1, 1, 1, 0, 0, 0, 0, 0, 255, 0, 1, 0, 0, 3]);
+
+        let mut data = Vec::default();
+        words(&mut data, &[810, 0, 810]);
+        bstr(&mut data, &zlib(&settings));
+        words(&mut data, &[0, 0, 2, 1, 0, 0]);
+        data.extend_from_slice(&forward);
+        words(&mut data, &[0, body.len() as u32]);
+        data.extend_from_slice(&body);
+
+        // 8.1 header, found by the loader code and followed by data encrypted with its key
+
+        let start = LOADER_OFFSET_810 as usize + 0x1000;
+        let magic = 0x12345678;
+
+        let mut exe = vec![0; LOADER_OFFSET_810 as usize];
+        exe[..2].copy_from_slice(b"MZ");
+        exe.extend_from_slice(&LOADER_810);
+        words(&mut exe, &[start as u32]);
+        exe.extend_from_slice(&[0x90; LOADER_SKIP_810]);
+        exe.push(0x3d);
+        words(&mut exe, &[magic]);
+        exe.extend_from_slice(&[0x0f, 0x85]);
+        exe.resize(start, 0);
+        words(&mut exe, &[magic & 0xff00ff00 | 0x00aa00bb, magic & 0x00ff00ff | 0xcc00dd00]);
+
+        // The encryption is a stream of XOR masks, so it is its own inverse.
+        let mut encrypted = Vec::default();
+        words(&mut encrypted, &[12345, 67890]);
+        encrypted.extend_from_slice(&data);
+        decrypt_810(&mut encrypted[..]).unwrap();
+        assert_ne!(encrypted[encrypted.len() - data.len()..], data[..]);
+        exe.extend_from_slice(&encrypted);
+
+        let arena = Arena::default();
+        let mut game = Game::default();
+        let mut extensions = Vec::default();
+        read_exe(&mut io::Cursor::new(&exe[..]), &mut game, &mut extensions, &arena).unwrap();
+
+        assert_eq!((game.version, game.id, game.guid), (810, 1234, [1, 2, 3, 4]));
+        assert!(game.pro);
+        assert_eq!(game.settings.background_color, 0x112233);
+        assert!(game.settings.uninitialized_zero);
+        assert!(game.settings.uninitialized_arguments);
+        assert_eq!(game.constants[0].value, "42");
+        let script = &game.scripts[0];
+        assert_eq!((script.name, script.body), ("main".into(), "return 42".into()));
+        assert_eq!(game.information.text, "help");
+    }
+}
@@ -0,0 +1,345 @@
+//! Check a game for references that would break it at run time, and for likely mistakes.
+//!
+//! Resources refer to each other by index, and nothing stops a project from referring to one that
+//! was deleted or never existed. Where the runner indexes its resources directly these are errors.
+//! Action targets and arguments, view targets and collision events only fail if they are reached,
+//! so they are warnings, along with duplicate names and unused resources.
+
+use std::collections::{HashMap, HashSet};
+use std::{fmt, str};
+use bstr::BStr;
+
+use crate::{Game, Action, argument_type, event_type};
+
+/// A kind of resource, in the order of `Game`'s lists.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub enum Resource {
+    Sound,
+    Sprite,
+    Background,
+    Path,
+    Script,
+    Font,
+    Timeline,
+    Object,
+    Room,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Problem<'a> {
+    /// A reference, from the place named by `from`, to a resource that does not exist. `indexed`
+    /// references are looked up directly by the runner, and would break the game.
+    Dangling { from: String, resource: Resource, index: i32, indexed: bool },
+    /// Resources that share a name, and so cannot all be referred to from code.
+    Duplicate { name: &'a BStr, resources: Vec<(Resource, usize)> },
+    /// A resource that nothing refers to, either by index or by name.
+    Unused { resource: Resource, name: &'a BStr },
+}
+
+impl Problem<'_> {
+    /// Whether this problem would break the game, rather than being a likely mistake.
+    pub fn is_error(&self) -> bool { matches!(*self, Problem::Dangling { indexed: true, .. }) }
+}
+
+/// Find every problem in `game`, in order of the resources they are found in.
+pub fn validate<'a>(game: &Game<'a>) -> Vec<Problem<'a>> {
+    let mut validator = Validator { game, problems: Vec::default(), used: HashSet::default() };
+
+    for object in &game.objects[..] {
+        if object.name.is_empty() { continue; }
+        let from = || format!("object `{}`", object.name);
+        validator.reference(from, Resource::Sprite, object.sprite, true);
+        validator.reference(from, Resource::Object, object.parent, true);
+        validator.reference(from, Resource::Sprite, object.mask, true);
+        for event in &object.events[..] {
+            let from = || {
+                format!("object `{}` event {}({})", object.name, event.event_type, event.event_kind)
+            };
+            if event.event_type == event_type::COLLISION {
+                validator.reference(from, Resource::Object, event.event_kind, false);
+            }
+            validator.actions(from, &event.actions[..]);
+        }
+    }
+
+    for timeline in &game.timelines[..] {
+        if timeline.name.is_empty() { continue; }
+        for moment in &timeline.moments[..] {
+            let from = || format!("timeline `{}` moment {}", timeline.name, moment.moment);
+            validator.actions(from, &moment.actions[..]);
+        }
+    }
+
+    for room in &game.rooms[..] {
+        if room.name.is_empty() { continue; }
+        for background in &room.backgrounds[..] {
+            let from = || format!("room `{}` background", room.name);
+            validator.reference(from, Resource::Background, background.background, true);
+        }
+        for view in &room.views[..] {
+            let from = || format!("room `{}` view", room.name);
+            validator.reference(from, Resource::Object, view.target, false);
+        }
+        for instance in &room.instances[..] {
+            let from = || format!("room `{}` instance {}", room.name, instance.id);
+            validator.reference(from, Resource::Object, instance.object_index, true);
+        }
+        for tile in &room.tiles[..] {
+            let from = || format!("room `{}` tile {}", room.name, tile.id);
+            validator.reference(from, Resource::Background, tile.background, true);
+        }
+    }
+
+    for &room in &game.room_order[..] {
+        validator.reference(|| String::from("room order"), Resource::Room, room as i32, true);
+    }
+
+    validator.duplicates();
+    validator.unused();
+    validator.problems
+}
+
+struct Validator<'g, 'a> {
+    game: &'g Game<'a>,
+    problems: Vec<Problem<'a>>,
+    used: HashSet<(Resource, usize)>,
+}
+
+impl<'a> Validator<'_, 'a> {
+    fn names(&self, resource: Resource) -> Vec<&'a BStr> { names(self.game, resource) }
+
+    /// Check a reference by index, where negative indices mean no resource.
+    fn reference(
+        &mut self, from: impl FnOnce() -> String, resource: Resource, index: i32, indexed: bool
+    ) {
+        if index < 0 { return; }
+        let names = self.names(resource);
+        match names.get(index as usize) {
+            Some(name) if !name.is_empty() => { self.used.insert((resource, index as usize)); }
+            _ => self.problems.push(Problem::Dangling { from: from(), resource, index, indexed }),
+        }
+    }
+
+    /// Check the targets and resource arguments of a list of actions, decoded as `ActionParser`
+    /// decodes them.
+    fn actions(&mut self, from: impl Fn() -> String, actions: &[Action<'_>]) {
+        for action in actions {
+            if action.has_target {
+                self.reference(&from, Resource::Object, action.target, false);
+            }
+
+            let len = action.parameters_used as usize;
+            let arguments = Iterator::zip(action.parameters.iter(), action.arguments.iter());
+            for (&parameter, &argument) in arguments.take(len) {
+                let resource = match parameter {
+                    argument_type::SPRITE => Resource::Sprite,
+                    argument_type::SOUND => Resource::Sound,
+                    argument_type::BACKGROUND => Resource::Background,
+                    argument_type::PATH => Resource::Path,
+                    argument_type::SCRIPT => Resource::Script,
+                    argument_type::OBJECT => Resource::Object,
+                    argument_type::ROOM => Resource::Room,
+                    argument_type::FONT => Resource::Font,
+                    argument_type::TIMELINE => Resource::Timeline,
+                    _ => continue,
+                };
+                // Corrupt arguments are reported by `ActionParser` itself.
+                let Some(index) = str::from_utf8(argument).ok().and_then(|s| s.parse().ok()) else {
+                    continue;
+                };
+                self.reference(&from, resource, index, false);
+            }
+        }
+    }
+
+    /// Report names shared by more than one resource, across all kinds.
+    fn duplicates(&mut self) {
+        let mut resources: HashMap<_, Vec<_>> = HashMap::default();
+        let mut order = Vec::default();
+        for resource in RESOURCES {
+            for (index, name) in self.names(resource).into_iter().enumerate() {
+                if name.is_empty() { continue; }
+                let entry = resources.entry(name).or_default();
+                if entry.is_empty() { order.push(name); }
+                entry.push((resource, index));
+            }
+        }
+
+        for name in order {
+            let resources = resources.remove(name).unwrap();
+            if resources.len() > 1 {
+                self.problems.push(Problem::Duplicate { name, resources });
+            }
+        }
+    }
+
+    /// Report resources that are not referred to by index or by name in any code.
+    fn unused(&mut self) {
+        let mut words: HashSet<&[u8]> = HashSet::default();
+        for code in code(self.game) {
+            words.extend(code.split(|&c| !(c.is_ascii_alphanumeric() || c == b'_')));
+        }
+
+        for resource in RESOURCES {
+            for (index, name) in self.names(resource).into_iter().enumerate() {
+                if name.is_empty() || self.used.contains(&(resource, index)) { continue; }
+                if words.contains(&**name) { continue; }
+                self.problems.push(Problem::Unused { resource, name });
+            }
+        }
+    }
+}
+
+/// Every kind of resource, in the order they are reported.
+const RESOURCES: [Resource; 9] = [
+    Resource::Sound, Resource::Sprite, Resource::Background, Resource::Path, Resource::Script,
+    Resource::Font, Resource::Timeline, Resource::Object, Resource::Room,
+];
+
+/// Every piece of code in the game, which may refer to resources by name.
+fn code<'a>(game: &Game<'a>) -> Vec<&'a BStr> {
+    let mut code = Vec::default();
+    let mut actions = |actions: &[Action<'a>]| {
+        for action in actions {
+            code.push(action.code);
+            code.extend(action.arguments.iter().copied());
+        }
+    };
+    for object in &game.objects[..] {
+        for event in &object.events[..] { actions(&event.actions[..]); }
+    }
+    for timeline in &game.timelines[..] {
+        for moment in &timeline.moments[..] { actions(&moment.actions[..]); }
+    }
+
+    code.extend(game.scripts.iter().map(|script| script.body));
+    code.extend(game.triggers.iter().map(|trigger| trigger.condition));
+    code.extend(game.constants.iter().map(|constant| constant.value));
+    for room in &game.rooms[..] {
+        code.push(room.code);
+        code.extend(room.instances.iter().map(|instance| instance.code));
+    }
+    code
+}
+
+/// The names of every resource of a kind, including empty names for deleted resources.
+pub(crate) fn names<'a>(game: &Game<'a>, resource: Resource) -> Vec<&'a BStr> {
+    match resource {
+        Resource::Sound => game.sounds.iter().map(|sound| sound.name).collect(),
+        Resource::Sprite => game.sprites.iter().map(|sprite| sprite.name).collect(),
+        Resource::Background => {
+            game.backgrounds.iter().map(|background| background.name).collect()
+        }
+        Resource::Path => game.paths.iter().map(|path| path.name).collect(),
+        Resource::Script => game.scripts.iter().map(|script| script.name).collect(),
+        Resource::Font => game.fonts.iter().map(|font| font.name).collect(),
+        Resource::Timeline => game.timelines.iter().map(|timeline| timeline.name).collect(),
+        Resource::Object => game.objects.iter().map(|object| object.name).collect(),
+        Resource::Room => game.rooms.iter().map(|room| room.name).collect(),
+    }
+}
+
+impl fmt::Display for Resource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match *self {
+            Resource::Sound => "sound",
+            Resource::Sprite => "sprite",
+            Resource::Background => "background",
+            Resource::Path => "path",
+            Resource::Script => "script",
+            Resource::Font => "font",
+            Resource::Timeline => "timeline",
+            Resource::Object => "object",
+            Resource::Room => "room",
+        };
+        f.write_str(name)
+    }
+}
+
+impl fmt::Display for Problem<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Problem::Dangling { ref from, resource, index, .. } => {
+                write!(f, "{from} refers to {resource} {index}, which does not exist")
+            }
+            Problem::Duplicate { name, ref resources } => {
+                write!(f, "`{name}` is the name of more than one resource:")?;
+                for &(resource, index) in resources {
+                    write!(f, " {resource} {index}")?;
+                }
+                Ok(())
+            }
+            Problem::Unused { resource, name } => write!(f, "{resource} `{name}` is never used"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bstr::BStr;
+    use crate::{Game, Sprite, Script, Object, Event, Action, Room, Instance};
+    use crate::{event_type, argument_type};
+    use super::{Problem, Resource, validate};
+
+    #[test]
+    fn problems() {
+        let mut game = Game::default();
+        game.sprites.push(Sprite { name: BStr::new(b"spr_used"), ..Sprite::default() });
+        game.sprites.push(Sprite::default());
+        game.sprites.push(Sprite { name: BStr::new(b"spr_unused"), ..Sprite::default() });
+        game.sprites.push(Sprite { name: BStr::new(b"spr_named"), ..Sprite::default() });
+        game.scripts.push(Script {
+            name: BStr::new(b"obj_player"),
+            body: BStr::new(b"sprite_index = spr_named;"),
+        });
+        game.objects.push(Object {
+            name: BStr::new(b"obj_player"),
+            sprite: 0,
+            parent: 7,
+            mask: 1,
+            events: vec![Event {
+                event_type: event_type::COLLISION,
+                event_kind: 0,
+                actions: vec![Action {
+                    parameters_used: 2,
+                    parameters: vec![argument_type::OBJECT, argument_type::ROOM],
+                    arguments: vec![BStr::new(b"0"), BStr::new(b"3")],
+                    ..Action::default()
+                }],
+            }],
+            ..Object::default()
+        });
+        game.rooms.push(Room {
+            name: BStr::new(b"rm_first"),
+            instances: vec![
+                Instance { object_index: 0, id: 100001, ..Instance::default() },
+                Instance { object_index: 2, id: 100002, ..Instance::default() },
+            ],
+            ..Room::default()
+        });
+        game.room_order = vec![0, 1];
+
+        let problems = validate(&game);
+        let dangling = |from: &str, resource, index, indexed| {
+            Problem::Dangling { from: String::from(from), resource, index, indexed }
+        };
+        assert_eq!(problems, [
+            dangling("object `obj_player`", Resource::Object, 7, true),
+            dangling("object `obj_player`", Resource::Sprite, 1, true),
+            dangling("object `obj_player` event 4(0)", Resource::Room, 3, false),
+            dangling("room `rm_first` instance 100002", Resource::Object, 2, true),
+            dangling("room order", Resource::Room, 1, true),
+            Problem::Duplicate {
+                name: BStr::new(b"obj_player"),
+                resources: vec![(Resource::Script, 0), (Resource::Object, 0)],
+            },
+            Problem::Unused { resource: Resource::Sprite, name: BStr::new(b"spr_unused") },
+            Problem::Unused { resource: Resource::Script, name: BStr::new(b"obj_player") },
+        ]);
+        assert!(problems[0].is_error() && !problems[2].is_error() && !problems[5].is_error());
+        assert_eq!(
+            problems[0].to_string(),
+            "object `obj_player` refers to object 7, which does not exist"
+        );
+    }
+}
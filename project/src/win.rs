@@ -0,0 +1,789 @@
+//! Read GameMaker: Studio games from their `data.win` bundles.
+//!
+//! A bundle is an IFF-style `FORM` made of named chunks, which refer to each other by absolute
+//! file offset. Strings live in `STRG`, and each resource refers to its name by the offset of the
+//! string's contents. Images live in the texture pages of `TXTR`, cut up by the `TPAG` entries
+//! that sprites and backgrounds point to.
+//!
+//! Code is only present as compiled bytecode, with no source. The resources that use it are still
+//! imported, with empty code, and the missing pieces are reported as warnings.
+
+use std::io;
+use bstr::BStr;
+use quickdry::Arena;
+
+use crate::{
+    Game,
+    Sprite, Image, Bounds,
+    Background,
+    Object, Action, Event,
+    Room, RoomBackground, View, Instance, Tile,
+    shape, event_type, action_kind, action_type, argument_type,
+    Warning,
+};
+use crate::dir::{alloc, decode_png};
+use crate::read::GmRead;
+
+/// The chunks this reader understands. Any others that are present are reported and skipped.
+const CHUNKS: [&[u8; 4]; 12] = [
+    b"GEN8", b"OPTN", b"SPRT", b"BGND", b"OBJT", b"ROOM", b"CODE", b"STRG", b"TXTR", b"TPAG",
+    b"FUNC", b"VARI",
+];
+
+/// The collision mask kind of a sprite with precise collision checking.
+const MASK_PRECISE: u32 = 1;
+
+/// The `parent` of an object with none.
+const NO_PARENT: i32 = -100;
+
+/// Import a bundle, returning warnings for everything that was skipped or left empty.
+pub fn read_win<'a>(
+    file: &[u8], game: &mut Game<'a>, arena: &'a Arena
+) -> io::Result<Vec<Warning>> {
+    let mut warnings = Vec::default();
+    let form = Form::new(file)?;
+    for (name, chunk) in &form.chunks[..] {
+        if !chunk.is_empty() && !CHUNKS.contains(&name) {
+            let name = String::from_utf8_lossy(&name[..]);
+            warnings.push(Warning(format!("{name} chunk is not supported and was skipped")));
+        }
+    }
+
+    let gen8 = form.chunk(b"GEN8").ok_or_else(|| invalid("missing GEN8 chunk"))?;
+    let bytecode = read_general(&form, gen8, game)?;
+
+    if let Some(optn) = form.chunk(b"OPTN") {
+        read_options(optn, game)?;
+    }
+
+    let pages = form.chunk(b"TXTR").map_or(Ok(Vec::default()), |txtr| {
+        read_textures(&form, txtr, bytecode)
+    })?;
+
+    let code = match form.chunk(b"CODE") {
+        Some(code) => form.list(code)?.into_iter()
+            .map(|entry| form.string(form.at(entry)?.next_u32()?))
+            .collect::<io::Result<Vec<_>>>()?,
+        None => Vec::default(),
+    };
+    let mut missing = Missing::default();
+
+    if let Some(sprt) = form.chunk(b"SPRT") {
+        for entry in form.list(sprt)? {
+            let sprite = read_sprite(&form, entry, &pages[..], arena, &mut warnings)?;
+            game.sprites.push(sprite);
+        }
+    }
+
+    if let Some(bgnd) = form.chunk(b"BGND") {
+        for entry in form.list(bgnd)? {
+            let background = read_background(&form, entry, &pages[..], arena)?;
+            game.backgrounds.push(background);
+        }
+    }
+
+    if let Some(objt) = form.chunk(b"OBJT") {
+        for entry in form.list(objt)? {
+            let object = read_object(&form, entry, &mut missing, arena, &mut warnings)?;
+            game.objects.push(object);
+        }
+    }
+
+    if let Some(room) = form.chunk(b"ROOM") {
+        for entry in form.list(room)? {
+            let room = read_room(&form, entry, game, &mut missing, arena)?;
+            game.rooms.push(room);
+        }
+    }
+
+    let scripts = code.iter().filter(|name| name.starts_with(b"gml_Script_")).count();
+    if !code.is_empty() {
+        let len = code.len();
+        let message = format!(
+            "{len} code entries are only present as bytecode; {scripts} scripts were skipped, \
+            and {} actions and {} creation codes are empty",
+            missing.actions, missing.creation_code
+        );
+        warnings.push(Warning(message));
+    }
+
+    Ok(warnings)
+}
+
+/// Counts of the places that referred to code, which is left out.
+#[derive(Default)]
+struct Missing {
+    actions: usize,
+    creation_code: usize,
+}
+
+/// The chunks of a bundle, along with the file they came from to resolve offsets.
+struct Form<'f> {
+    file: &'f [u8],
+    chunks: Vec<([u8; 4], &'f [u8])>,
+}
+
+impl<'f> Form<'f> {
+    fn new(file: &'f [u8]) -> io::Result<Form<'f>> {
+        let mut read = file;
+        if read.get(..4) != Some(b"FORM") {
+            return Err(invalid("expected FORM"));
+        }
+        read = &read[4..];
+        let len = read.next_u32()? as usize;
+        let mut read = read.get(..len).ok_or_else(|| invalid("truncated FORM"))?;
+
+        let mut chunks = Vec::default();
+        while !read.is_empty() {
+            let mut name = [0; 4];
+            io::Read::read_exact(&mut read, &mut name)?;
+            let len = read.next_u32()? as usize;
+            let chunk = read.get(..len).ok_or_else(|| invalid("truncated chunk"))?;
+            chunks.push((name, chunk));
+            read = &read[len..];
+        }
+
+        Ok(Form { file, chunks })
+    }
+
+    fn chunk(&self, name: &[u8; 4]) -> Option<&'f [u8]> {
+        self.chunks.iter().find(|(chunk, _)| chunk == name).map(|&(_, chunk)| chunk)
+    }
+
+    /// The contents of the file starting at `offset`.
+    fn at(&self, offset: u32) -> io::Result<&'f [u8]> {
+        self.file.get(offset as usize..).ok_or_else(|| invalid("offset out of bounds"))
+    }
+
+    /// A string, referred to by the offset of its contents, which follow its length.
+    fn string(&self, offset: u32) -> io::Result<&'f [u8]> {
+        if offset == 0 { return Ok(&[]); }
+        let start = (offset as usize).checked_sub(4).ok_or_else(|| invalid("bad string"))?;
+        let mut read = self.file.get(start..).ok_or_else(|| invalid("bad string"))?;
+        let len = read.next_u32()? as usize;
+        read.get(..len).ok_or_else(|| invalid("bad string"))
+    }
+
+    /// A list of offsets, preceded by its length.
+    fn list(&self, mut read: &[u8]) -> io::Result<Vec<u32>> {
+        let len = read.next_u32()?;
+        (0..len).map(|_| read.next_u32()).collect()
+    }
+
+    /// A list of offsets, found at `offset`.
+    fn list_at(&self, offset: u32) -> io::Result<Vec<u32>> {
+        self.list(self.at(offset)?)
+    }
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn next_string<'a>(read: &mut &[u8], form: &Form<'_>, arena: &'a Arena) -> io::Result<&'a BStr> {
+    let string = form.string(read.next_u32()?)?;
+    Ok(BStr::new(alloc(arena, string)))
+}
+
+fn next_f32(read: &mut &[u8]) -> io::Result<f32> {
+    Ok(f32::from_bits(read.next_u32()?))
+}
+
+/// Read the game's identity and room order, returning its bytecode version.
+fn read_general(form: &Form<'_>, mut read: &[u8], game: &mut Game<'_>) -> io::Result<u32> {
+    let mut header = [0; 4];
+    io::Read::read_exact(&mut read, &mut header)?;
+    let bytecode = header[1] as u32;
+
+    let _file_name = read.next_u32()?;
+    let _config = read.next_u32()?;
+    game.last_instance = read.next_i32()?;
+    game.last_tile = read.next_i32()?;
+    game.id = read.next_u32()?;
+    for guid in &mut game.guid {
+        *guid = read.next_u32()?;
+    }
+    let _name = read.next_u32()?;
+    let _version = [read.next_u32()?, read.next_u32()?, read.next_u32()?, read.next_u32()?];
+    let _window_size = (read.next_u32()?, read.next_u32()?);
+    let _info = read.next_u32()?;
+    // Skip the license hash and checksum, and the build timestamp.
+    read = read.get(16 + 4 + 8..).ok_or_else(|| invalid("truncated GEN8"))?;
+    let _display_name = read.next_u32()?;
+    // Skip the target platforms, function classifications, and Steam app id.
+    read = read.get(8 + 8 + 4..).ok_or_else(|| invalid("truncated GEN8"))?;
+    if bytecode >= 14 {
+        let _debugger_port = read.next_u32()?;
+    }
+
+    let room_order = form.list(read)?;
+    game.room_order.extend(room_order);
+
+    Ok(bytecode)
+}
+
+/// Read the settings that have an equivalent in GM 8.
+///
+/// Newer versions of Studio pack the boolean settings into a set of flags, marked by a header with
+/// its high bit set. Older versions store each one as a separate field.
+fn read_options(mut read: &[u8], game: &mut Game<'_>) -> io::Result<()> {
+    let settings = &mut game.settings;
+    let mut marker = read;
+    if marker.next_u32()? & 0x8000_0000 != 0 {
+        let _header = (read.next_u32()?, read.next_u32()?);
+        let flags = read.next_u32()? as u64 | (read.next_u32()? as u64) << 32;
+        settings.fullscreen = flags & 0x1 != 0;
+        settings.interpolation = flags & 0x2 != 0;
+        settings.hide_border = flags & 0x8 != 0;
+        settings.show_cursor = flags & 0x10 != 0;
+        settings.allow_resize = flags & 0x20 != 0;
+        settings.topmost = flags & 0x40 != 0;
+        settings.set_resolution = flags & 0x80 != 0;
+        settings.hide_buttons = flags & 0x100 != 0;
+        settings.freeze = flags & 0x8000 != 0;
+        settings.error_display = flags & 0x80000 != 0;
+        settings.error_log = flags & 0x100000 != 0;
+        settings.error_abort = flags & 0x200000 != 0;
+        settings.uninitialized_zero = flags & 0x400000 != 0;
+        settings.scaling = read.next_i32()?;
+        return Ok(());
+    }
+
+    settings.fullscreen = read.next_bool()?;
+    settings.interpolation = read.next_bool()?;
+    let _new_audio = read.next_bool()?;
+    settings.hide_border = read.next_bool()?;
+    settings.show_cursor = read.next_bool()?;
+    settings.scaling = read.next_i32()?;
+    settings.allow_resize = read.next_bool()?;
+    settings.topmost = read.next_bool()?;
+    let _window_color = read.next_u32()?;
+    settings.set_resolution = read.next_bool()?;
+    settings.color_depth = read.next_u32()?;
+    settings.resolution = read.next_u32()?;
+    settings.frequency = read.next_u32()?;
+    settings.hide_buttons = read.next_bool()?;
+    settings.vsync = read.next_bool()?;
+    let _keys = [read.next_bool()?, read.next_bool()?, read.next_bool()?, read.next_bool()?];
+    let _screenshot_key = read.next_bool()?;
+    settings.close_as_esc = read.next_bool()?;
+    settings.priority = read.next_u32()?;
+    settings.freeze = read.next_bool()?;
+
+    Ok(())
+}
+
+/// Decode each texture page into BGRA.
+fn read_textures(form: &Form<'_>, txtr: &[u8], bytecode: u32) -> io::Result<Vec<Page>> {
+    let mut pages = Vec::default();
+    for entry in form.list(txtr)? {
+        let mut read = form.at(entry)?;
+        let _scaled = read.next_u32()?;
+        if bytecode >= 17 {
+            let _mipmaps = read.next_u32()?;
+        }
+        let data = form.at(read.next_u32()?)?;
+        let (size, data) = decode_png(data)?;
+        pages.push(Page { size, data });
+    }
+    Ok(pages)
+}
+
+struct Page {
+    size: (u32, u32),
+    data: Vec<u8>,
+}
+
+/// Cut an image of `size` out of the texture pages, from the `TPAG` entry at `offset`.
+fn read_image(form: &Form<'_>, offset: u32, pages: &[Page], size: (u32, u32)) ->
+    io::Result<Vec<u8>>
+{
+    let mut read = form.at(offset)?;
+    let mut next = || read.next_u16().map(u32::from);
+    let (source_x, source_y, source_w, source_h) = (next()?, next()?, next()?, next()?);
+    let (target_x, target_y, _target_w, _target_h) = (next()?, next()?, next()?, next()?);
+    let _bounds = (next()?, next()?);
+    let page = next()? as usize;
+    let page = pages.get(page).ok_or_else(|| invalid("bad texture page"))?;
+
+    // Images are cut out of a single page, so anything larger is corrupt.
+    let (width, height) = size;
+    if width > page.size.0 || height > page.size.1 {
+        return Err(invalid("image larger than its texture page"));
+    }
+    let mut data = vec![0; width as usize * height as usize * 4];
+    let w = u32::min(source_w, u32::saturating_sub(width, target_x));
+    let w = u32::min(w, u32::saturating_sub(page.size.0, source_x)) as usize;
+    let h = u32::min(source_h, u32::saturating_sub(height, target_y));
+    let h = u32::min(h, u32::saturating_sub(page.size.1, source_y));
+    for y in 0..h {
+        let from = ((source_y + y) * page.size.0 + source_x) as usize * 4;
+        let to = ((target_y + y) * width + target_x) as usize * 4;
+        data[to..to + w * 4].copy_from_slice(&page.data[from..from + w * 4]);
+    }
+    Ok(data)
+}
+
+fn read_sprite<'a>(
+    form: &Form<'_>, offset: u32, pages: &[Page], arena: &'a Arena,
+    warnings: &mut Vec<Warning>
+) -> io::Result<Sprite<'a>> {
+    let mut read = form.at(offset)?;
+    let mut sprite = Sprite { version: 800, ..Sprite::default() };
+    sprite.name = next_string(&mut read, form, arena)?;
+    sprite.size = (read.next_u32()?, read.next_u32()?);
+    sprite.bounds = Bounds {
+        left: read.next_i32()?,
+        right: read.next_i32()?,
+        bottom: read.next_i32()?,
+        top: read.next_i32()?,
+    };
+    sprite.transparent = read.next_bool()?;
+    let _smooth = read.next_bool()?;
+    let _preload = read.next_bool()?;
+    sprite.bounds_kind = read.next_u32()?;
+    sprite.precise = read.next_u32()? == MASK_PRECISE;
+    sprite.shape = if sprite.precise { shape::PRECISE } else { shape::RECTANGLE };
+    sprite.origin = (read.next_u32()?, read.next_u32()?);
+
+    // GMS 2 marks its extended sprite formats with -1 in place of the frame count.
+    let frames = read.next_i32()?;
+    if frames < 0 {
+        let name = sprite.name;
+        warnings.push(Warning(format!("{name}: this sprite format is not supported")));
+        return Ok(sprite);
+    }
+    for _ in 0..frames {
+        let data = read_image(form, read.next_u32()?, pages, sprite.size)?;
+        sprite.images.push(Image { size: sprite.size, data: alloc(arena, &data[..]) });
+    }
+
+    Ok(sprite)
+}
+
+fn read_background<'a>(
+    form: &Form<'_>, offset: u32, pages: &[Page], arena: &'a Arena
+) -> io::Result<Background<'a>> {
+    let mut read = form.at(offset)?;
+    let mut background = Background { version: 710, ..Background::default() };
+    background.name = next_string(&mut read, form, arena)?;
+    background.transparent = read.next_bool()?;
+    let _smooth = read.next_bool()?;
+    let _preload = read.next_bool()?;
+
+    // Backgrounds take their size from their texture page entry.
+    let image = read.next_u32()?;
+    let mut size = form.at(image)?.get(16..20).ok_or_else(|| invalid("bad texture entry"))?;
+    background.size = (u32::from(size.next_u16()?), u32::from(size.next_u16()?));
+    let data = read_image(form, image, pages, background.size)?;
+    background.data = alloc(arena, &data[..]);
+
+    Ok(background)
+}
+
+fn read_object<'a>(
+    form: &Form<'_>, offset: u32, missing: &mut Missing, arena: &'a Arena,
+    warnings: &mut Vec<Warning>
+) -> io::Result<Object<'a>> {
+    let mut read = form.at(offset)?;
+    let mut object = Object {
+        name: next_string(&mut read, form, arena)?,
+        sprite: read.next_i32()?,
+        visible: read.next_bool()?,
+        solid: read.next_bool()?,
+        depth: read.next_i32()?,
+        persistent: read.next_bool()?,
+        parent: match read.next_i32()? { NO_PARENT => -1, parent => parent },
+        mask: read.next_i32()?,
+        ..Object::default()
+    };
+
+    let physics = read.next_bool()?;
+    if physics {
+        let name = object.name;
+        warnings.push(Warning(format!("{name}: physics is not supported")));
+    }
+    // Skip the physics properties, and the vertices of the collision shape.
+    read = read.get(4 * 7..).ok_or_else(|| invalid("truncated object"))?;
+    let vertices = read.next_u32()? as usize;
+    read = read.get(4 * 3 + 4 * 2 * vertices..).ok_or_else(|| invalid("truncated object"))?;
+
+    for (event_type, events) in form.list(read)?.into_iter().enumerate() {
+        let event_type = event_type as u32;
+        for event in form.list_at(events)? {
+            let mut read = form.at(event)?;
+            let event_kind = read.next_i32()?;
+            let mut actions = Vec::default();
+            for action in form.list(read)? {
+                actions.push(read_action(form, action, missing, arena)?);
+            }
+
+            if event_type > event_type::KEY_RELEASE {
+                let name = object.name;
+                let message = format!("{name}: event type {event_type} is not supported");
+                warnings.push(Warning(message));
+                continue;
+            }
+            object.events.push(Event { event_type, event_kind, actions });
+        }
+    }
+
+    Ok(object)
+}
+
+fn read_action<'a>(
+    form: &Form<'_>, offset: u32, missing: &mut Missing, arena: &'a Arena
+) -> io::Result<Action<'a>> {
+    let mut read = form.at(offset)?;
+    let mut action = Action {
+        library: read.next_u32()?,
+        action: read.next_u32()?,
+        action_kind: read.next_u32()?,
+        has_relative: read.next_bool()?,
+        is_question: read.next_bool()?,
+        has_target: read.next_bool()?,
+        action_type: read.next_u32()?,
+        ..Action::default()
+    };
+    action.name = next_string(&mut read, form, arena)?;
+    let code = read.next_i32()?;
+    action.parameters_used = read.next_u32()?;
+    action.target = read.next_i32()?;
+    action.relative = read.next_bool()?;
+    action.negate = read.next_bool()?;
+
+    // Studio compiles every action into code, so only its code actions can be kept, and those
+    // without a body.
+    if action.action_kind == action_kind::CODE || action.action_type == action_type::CODE {
+        action.parameters_used = 1;
+        action.parameters = vec![argument_type::STRING];
+        action.arguments = vec![BStr::new(b"")];
+    }
+    if code >= 0 {
+        missing.actions += 1;
+    }
+
+    Ok(action)
+}
+
+fn read_room<'a>(
+    form: &Form<'_>, offset: u32, game: &mut Game<'a>, missing: &mut Missing, arena: &'a Arena
+) -> io::Result<Room<'a>> {
+    let mut read = form.at(offset)?;
+    let mut room = Room {
+        name: next_string(&mut read, form, arena)?,
+        caption: next_string(&mut read, form, arena)?,
+        width: read.next_u32()?,
+        height: read.next_u32()?,
+        speed: read.next_u32()?,
+        persistent: read.next_bool()?,
+        clear_color: read.next_u32()?,
+        clear: read.next_bool()?,
+        ..Room::default()
+    };
+    if read.next_i32()? >= 0 {
+        missing.creation_code += 1;
+    }
+    room.enable_views = read.next_u32()? & 0x1 != 0;
+
+    let backgrounds = read.next_u32()?;
+    let views = read.next_u32()?;
+    let instances = read.next_u32()?;
+    let tiles = read.next_u32()?;
+
+    for background in form.list_at(backgrounds)? {
+        let mut read = form.at(background)?;
+        room.backgrounds.push(RoomBackground {
+            visible: read.next_bool()?,
+            foreground: read.next_bool()?,
+            background: read.next_i32()?,
+            x: read.next_i32()?,
+            y: read.next_i32()?,
+            htiled: read.next_bool()?,
+            vtiled: read.next_bool()?,
+            hspeed: read.next_i32()?,
+            vspeed: read.next_i32()?,
+            stretch: read.next_bool()?,
+        });
+    }
+
+    for view in form.list_at(views)? {
+        let mut read = form.at(view)?;
+        room.views.push(View {
+            visible: read.next_bool()?,
+            view_x: read.next_i32()?,
+            view_y: read.next_i32()?,
+            view_w: read.next_u32()?,
+            view_h: read.next_u32()?,
+            port_x: read.next_i32()?,
+            port_y: read.next_i32()?,
+            port_w: read.next_u32()?,
+            port_h: read.next_u32()?,
+            h_border: read.next_i32()?,
+            v_border: read.next_i32()?,
+            h_speed: read.next_i32()?,
+            v_speed: read.next_i32()?,
+            target: read.next_i32()?,
+        });
+    }
+
+    for instance in form.list_at(instances)? {
+        let mut read = form.at(instance)?;
+        let x = read.next_i32()?;
+        let y = read.next_i32()?;
+        let object_index = read.next_i32()?;
+        let id = read.next_i32()?;
+        if read.next_i32()? >= 0 {
+            missing.creation_code += 1;
+        }
+        let _scale = (next_f32(&mut read)?, next_f32(&mut read)?);
+        game.last_instance = i32::max(game.last_instance, id);
+        room.instances.push(Instance { x, y, object_index, id, code: BStr::new(b"") });
+    }
+
+    for tile in form.list_at(tiles)? {
+        let mut read = form.at(tile)?;
+        room.tiles.push(Tile {
+            x: read.next_i32()?,
+            y: read.next_i32()?,
+            background: read.next_i32()?,
+            tile_x: read.next_i32()?,
+            tile_y: read.next_i32()?,
+            width: read.next_u32()?,
+            height: read.next_u32()?,
+            depth: read.next_i32()?,
+            id: read.next_i32()?,
+        });
+    }
+
+    Ok(room)
+}
+
+#[cfg(test)]
+mod tests {
+    use quickdry::Arena;
+    use crate::{Game, Warning, event_type, action_kind};
+    use crate::dir::write_png;
+    use super::{Form, Page, read_image, read_win};
+
+    /// Assembles a bundle, patching in offsets once the data they point to has been written.
+    #[derive(Default)]
+    struct Bundle {
+        data: Vec<u8>,
+    }
+
+    impl Bundle {
+        fn offset(&self) -> u32 { self.data.len() as u32 }
+
+        fn u32s(&mut self, values: &[u32]) {
+            for value in values {
+                self.data.extend_from_slice(&value.to_le_bytes());
+            }
+        }
+
+        fn u16s(&mut self, values: &[u16]) {
+            for value in values {
+                self.data.extend_from_slice(&value.to_le_bytes());
+            }
+        }
+
+        fn patch(&mut self, at: u32, value: u32) {
+            let at = at as usize;
+            self.data[at..at + 4].copy_from_slice(&value.to_le_bytes());
+        }
+
+        /// Point the slot at `at` to the current offset.
+        fn point(&mut self, at: u32) {
+            self.patch(at, self.offset());
+        }
+
+        /// Start a list of `len` offsets, returning the offset of its first slot.
+        fn list(&mut self, len: u32) -> u32 {
+            self.u32s(&[len]);
+            let slots = self.offset();
+            self.u32s(&vec![0; len as usize][..]);
+            slots
+        }
+
+        fn chunk(&mut self, name: &[u8; 4], body: impl FnOnce(&mut Bundle)) {
+            self.data.extend_from_slice(name);
+            let len = self.offset();
+            self.u32s(&[0]);
+            body(self);
+            self.patch(len, self.offset() - len - 4);
+        }
+    }
+
+    #[test]
+    fn bounds() {
+        let form = Form { file: &[1, 0, 0, 0, b'a', 0, 0, 0, 0, 0, 0, 0], chunks: Vec::default() };
+        assert_eq!(form.string(4).unwrap(), b"a");
+        assert!(form.string(2).is_err());
+
+        let form = Form { file: &[0; 22], chunks: Vec::default() };
+        let pages = [Page { size: (1, 1), data: vec![0; 4] }];
+        assert!(read_image(&form, 0, &pages, (1, 1)).is_ok());
+        assert!(read_image(&form, 0, &pages, (u16::MAX.into(), u16::MAX.into())).is_err());
+    }
+
+    #[test]
+    fn read() {
+        // One texture page, holding a sprite frame on the left and a background on the right.
+        let page = [
+            0x10, 0x20, 0x30, 0xff, 0x40, 0x50, 0x60, 0x80,
+            0x01, 0x02, 0x03, 0xff, 0x04, 0x05, 0x06, 0xff,
+        ];
+        let png = write_png((4, 1), &page[..]).unwrap();
+
+        let mut w = Bundle::default();
+        w.data.extend_from_slice(b"FORM");
+        w.u32s(&[0]);
+
+        let mut strings = Vec::default();
+        w.chunk(b"STRG", |w| {
+            let names = [
+                "spr_dot", "bg_fill", "obj_dot", "room0", "", "gml_Object_obj_dot_Create_0",
+            ];
+            let slots = w.list(names.len() as u32);
+            for (i, name) in names.iter().enumerate() {
+                w.point(slots + 4 * i as u32);
+                w.u32s(&[name.len() as u32]);
+                strings.push(w.offset());
+                w.data.extend_from_slice(name.as_bytes());
+                w.data.push(0);
+            }
+        });
+        let [spr_dot, bg_fill, obj_dot, room0, empty, create] = strings[..] else { panic!() };
+
+        w.chunk(b"GEN8", |w| {
+            w.data.extend_from_slice(&[0, 16, 0, 0]);
+            w.u32s(&[empty, empty, 100000, 10000000, 42, 1, 2, 3, 4, empty, 1, 0, 0, 0, 64, 48, 0]);
+            w.u32s(&[0; 7]);
+            w.u32s(&[empty, 0, 0, 0, 0, 0, 0]);
+            w.u32s(&[1, 0]);
+        });
+
+        w.chunk(b"OPTN", |w| {
+            w.u32s(&[1]);
+            w.u32s(&[0; 24]);
+        });
+
+        w.chunk(b"SOND", |w| w.u32s(&[0]));
+
+        w.chunk(b"TXTR", |w| {
+            let slots = w.list(1);
+            w.point(slots);
+            let data = w.offset() + 4;
+            w.u32s(&[1, data + 4]);
+            w.data.extend_from_slice(&png[..]);
+        });
+
+        let tpag = w.offset() + 8 + 4 + 4 * 2;
+        w.chunk(b"TPAG", |w| {
+            let slots = w.list(2);
+            w.point(slots);
+            w.u16s(&[0, 0, 2, 1, 0, 0, 2, 1, 2, 1, 0]);
+            w.point(slots + 4);
+            w.u16s(&[2, 0, 2, 1, 0, 0, 2, 1, 2, 1, 0]);
+        });
+
+        w.chunk(b"SPRT", |w| {
+            let slots = w.list(1);
+            w.point(slots);
+            w.u32s(&[spr_dot, 2, 1, 0, 1, 0, 0, 0, 0, 0, 0, 1, 1, 0]);
+            w.u32s(&[1, tpag, 0]);
+        });
+
+        w.chunk(b"BGND", |w| {
+            let slots = w.list(1);
+            w.point(slots);
+            w.u32s(&[bg_fill, 0, 0, 0, tpag + 22]);
+        });
+
+        w.chunk(b"OBJT", |w| {
+            let slots = w.list(1);
+            w.point(slots);
+            w.u32s(&[obj_dot, 0, 1, 0, -5i32 as u32, 0, -100i32 as u32, -1i32 as u32, 0]);
+            w.u32s(&[0; 7]);
+            w.u32s(&[0, 0, 0, 0]);
+            let types = w.list(12);
+            for event_type in 0..12 {
+                w.point(types + 4 * event_type);
+                if event_type != event_type::CREATE {
+                    w.list(0);
+                    continue;
+                }
+                let events = w.list(1);
+                w.point(events);
+                w.u32s(&[0]);
+                let actions = w.list(1);
+                w.point(actions);
+                w.u32s(&[1, 603, action_kind::CODE, 0, 0, 1, 2, empty, 0, 1, -1i32 as u32]);
+                w.u32s(&[0, 0, 0]);
+            }
+        });
+
+        w.chunk(b"ROOM", |w| {
+            let slots = w.list(1);
+            w.point(slots);
+            w.u32s(&[room0, empty, 64, 48, 30, 0, 0xc0c0c0, 1, -1i32 as u32, 0]);
+            let lists = w.offset();
+            w.u32s(&[0; 4]);
+            w.u32s(&[0, 0, 0, 64, 48, 0, 0x41200000, 0]);
+            for (i, len) in [0, 0, 1, 0].into_iter().enumerate() {
+                w.point(lists + 4 * i as u32);
+                let slots = w.list(len);
+                if len > 0 {
+                    w.point(slots);
+                    w.u32s(&[16, 8, 0, 100001, 0, 1.0f32.to_bits(), 1.0f32.to_bits(), !0, 0]);
+                }
+            }
+        });
+
+        w.chunk(b"CODE", |w| {
+            let slots = w.list(1);
+            w.point(slots);
+            w.u32s(&[create, 0]);
+        });
+
+        let len = w.offset() - 8;
+        w.patch(4, len);
+
+        let arena = Arena::default();
+        let mut game = Game::default();
+        let warnings = read_win(&w.data[..], &mut game, &arena).unwrap();
+
+        assert_eq!((game.id, game.guid), (42, [1, 2, 3, 4]));
+        assert!(game.settings.fullscreen);
+        assert_eq!(game.room_order, [0]);
+
+        let sprite = &game.sprites[0];
+        assert_eq!((sprite.name, sprite.size, sprite.origin), ("spr_dot".into(), (2, 1), (1, 0)));
+        assert!(sprite.precise);
+        assert_eq!(sprite.images[0].data, &page[..8]);
+
+        let background = &game.backgrounds[0];
+        assert_eq!((background.name, background.size), ("bg_fill".into(), (2, 1)));
+        assert_eq!(background.data, &page[8..]);
+
+        let object = &game.objects[0];
+        assert_eq!((object.name, object.sprite, object.parent), ("obj_dot".into(), 0, -1));
+        assert_eq!((object.visible, object.depth), (true, -5));
+        let event = &object.events[0];
+        assert_eq!((event.event_type, event.event_kind), (event_type::CREATE, 0));
+        assert_eq!(event.actions[0].arguments, ["".into()] as [&bstr::BStr; 1]);
+
+        let room = &game.rooms[0];
+        assert_eq!((room.name, room.width, room.height), ("room0".into(), 64, 48));
+        assert_eq!(room.instances[0].id, 100001);
+        assert_eq!((room.instances[0].x, room.instances[0].y), (16, 8));
+        assert_eq!(game.last_instance, 100001);
+
+        let warnings: Vec<_> = warnings.iter().map(Warning::to_string).collect();
+        assert_eq!(warnings, [
+            "SOND chunk is not supported and was skipped",
+            "1 code entries are only present as bytecode; 0 scripts were skipped, and 1 actions \
+            and 1 creation codes are empty",
+        ]);
+    }
+}
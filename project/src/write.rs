@@ -0,0 +1,1036 @@
+use std::io::{self, Write};
+use bstr::BStr;
+use flate2::Compression;
+use flate2::write::ZlibEncoder;
+
+use crate::{
+    Game, Settings, Trigger, Constant, IncludedFile, GameInformation,
+    Sound,
+    Sprite,
+    Background,
+    Path,
+    Script,
+    Font,
+    Timeline,
+    Object, Action,
+    Room,
+};
+
+const GM_MAGIC: u32 = 1234321;
+
+/// The modification time written for every resource. GM stores these but never reads them back.
+const TIME: f64 = 0.0;
+
+/// Write `game` as a GM 8.1 project if it was read from 8.1, or as a GM 8.0 project otherwise.
+///
+/// 8.0 has nowhere to keep font character sets or antialiasing, or the setting for uninitialized
+/// arguments, so they are only written for 8.1 games.
+pub fn write_project<W: Write>(write: &mut W, game: &Game<'_>) -> io::Result<()> {
+    let buf = &mut Vec::default();
+    let version = if game.version >= 810 { 810 } else { 800 };
+
+    write.write_u32(GM_MAGIC)?;
+    write.write_u32(version)?;
+
+    write_body(write, buf, version, game)?;
+
+    Ok(())
+}
+
+fn write_body<W: Write>(
+    write: &mut W, buf: &mut Vec<u8>, version: u32, game: &Game<'_>
+) -> io::Result<()> {
+    write.write_u32(game.id)?;
+    write.write_u32(game.guid[0])?;
+    write.write_u32(game.guid[1])?;
+    write.write_u32(game.guid[2])?;
+    write.write_u32(game.guid[3])?;
+
+    // settings
+
+    write.write_u32(version)?;
+    buf.clear();
+    write_settings(buf, version, &game.settings)?;
+    write.write_blob_zlib(buf)?;
+
+    // triggers
+
+    write.write_u32(800)?;
+    write.write_u32(game.triggers.len() as u32)?;
+    for trigger in &game.triggers[..] {
+        buf.clear();
+        write_trigger(buf, trigger)?;
+        write.write_blob_zlib(buf)?;
+    }
+    write.write_f64(TIME)?;
+
+    fn write_trigger(write: &mut Vec<u8>, trigger: &Trigger<'_>) -> io::Result<()> {
+        write.write_bool(!trigger.name.is_empty())?;
+        if trigger.name.is_empty() { return Ok(()); }
+
+        write.write_u32(800)?;
+
+        write.write_bstr(trigger.name)?;
+        write.write_bstr(trigger.condition)?;
+        write.write_u32(trigger.moment)?;
+        write.write_bstr(trigger.constant)?;
+
+        Ok(())
+    }
+
+    // constants
+
+    write.write_u32(800)?;
+    write_constants(write, &game.constants[..])?;
+    write.write_f64(TIME)?;
+
+    // sounds
+
+    write.write_u32(800)?;
+    write.write_u32(game.sounds.len() as u32)?;
+    for sound in &game.sounds[..] {
+        buf.clear();
+        write_sound(buf, sound)?;
+        write.write_blob_zlib(buf)?;
+    }
+
+    fn write_sound(write: &mut Vec<u8>, sound: &Sound<'_>) -> io::Result<()> {
+        write.write_bool(!sound.name.is_empty())?;
+        if sound.name.is_empty() { return Ok(()); }
+
+        write.write_bstr(sound.name)?;
+        write.write_f64(TIME)?;
+
+        write.write_u32(800)?;
+
+        write.write_u32(sound.kind)?;
+        write.write_bstr(sound.file_type)?;
+        write.write_bstr(sound.file_name)?;
+        write.write_bool(!sound.data.is_empty())?;
+        if !sound.data.is_empty() {
+            write.write_blob(sound.data)?;
+        }
+        write.write_u32(sound.effects)?;
+        write.write_f64(sound.volume)?;
+        write.write_f64(sound.pan)?;
+        write.write_bool(sound.preload)?;
+
+        Ok(())
+    }
+
+    // sprites
+
+    write.write_u32(800)?;
+    write.write_u32(game.sprites.len() as u32)?;
+    for sprite in &game.sprites[..] {
+        buf.clear();
+        write_sprite(buf, sprite)?;
+        write.write_blob_zlib(buf)?;
+    }
+
+    fn write_sprite(write: &mut Vec<u8>, sprite: &Sprite<'_>) -> io::Result<()> {
+        write.write_bool(!sprite.name.is_empty())?;
+        if sprite.name.is_empty() { return Ok(()); }
+
+        write.write_bstr(sprite.name)?;
+        write.write_f64(TIME)?;
+
+        // Sprites read from older versions keep their BMP images, which GM 8.0 still accepts.
+        let version = if sprite.version < 800 { 400 } else { 800 };
+        write.write_u32(version)?;
+
+        if version == 400 {
+            write.write_u32(sprite.size.0)?;
+            write.write_u32(sprite.size.1)?;
+
+            write.write_i32(sprite.bounds.left)?;
+            write.write_i32(sprite.bounds.right)?;
+            write.write_i32(sprite.bounds.bottom)?;
+            write.write_i32(sprite.bounds.top)?;
+
+            write.write_bool(sprite.transparent)?;
+
+            write.write_u32(sprite.bounds_kind)?;
+            write.write_bool(sprite.precise)?;
+            write.write_bool(sprite.use_vram)?;
+            write.write_bool(sprite.lazy_load)?;
+        }
+
+        write.write_u32(sprite.origin.0)?;
+        write.write_u32(sprite.origin.1)?;
+
+        write.write_u32(sprite.images.len() as u32)?;
+        for image in &sprite.images[..] {
+            if version == 400 {
+                if image.data.is_empty() {
+                    write.write_i32(-1)?;
+                    continue;
+                }
+
+                write.write_i32(10)?;
+                write.write_blob_zlib(image.data)?;
+            }
+            if version == 800 {
+                write.write_u32(800)?;
+
+                write.write_u32(image.size.0)?;
+                write.write_u32(image.size.1)?;
+                if image.size.0 != 0 && image.size.1 != 0 {
+                    write.write_blob(image.data)?;
+                }
+            }
+        }
+
+        if version == 800 {
+            write.write_u32(sprite.shape)?;
+            write.write_u32(sprite.alpha_tolerance)?;
+            write.write_bool(sprite.separate_collision)?;
+            write.write_u32(sprite.bounds_kind)?;
+            write.write_i32(sprite.bounds.left)?;
+            write.write_i32(sprite.bounds.right)?;
+            write.write_i32(sprite.bounds.bottom)?;
+            write.write_i32(sprite.bounds.top)?;
+        }
+
+        Ok(())
+    }
+
+    // backgrounds
+
+    write.write_u32(800)?;
+    write.write_u32(game.backgrounds.len() as u32)?;
+    for background in &game.backgrounds[..] {
+        buf.clear();
+        write_background(buf, background)?;
+        write.write_blob_zlib(buf)?;
+    }
+
+    fn write_background(write: &mut Vec<u8>, background: &Background<'_>) -> io::Result<()> {
+        write.write_bool(!background.name.is_empty())?;
+        if background.name.is_empty() { return Ok(()); }
+
+        write.write_bstr(background.name)?;
+        write.write_f64(TIME)?;
+
+        let version = if background.version < 710 { 400 } else { 710 };
+        write.write_u32(version)?;
+
+        if version == 400 {
+            write.write_u32(background.size.0)?;
+            write.write_u32(background.size.1)?;
+
+            write.write_bool(background.transparent)?;
+            write.write_bool(background.use_vram)?;
+            write.write_bool(background.lazy_load)?;
+
+            write.write_bool(!background.data.is_empty())?;
+            if !background.data.is_empty() {
+                write.write_i32(10)?;
+                write.write_blob_zlib(background.data)?;
+            }
+        }
+        if version == 710 {
+            write.write_bool(false)?; // tileset
+            write.write_u32(16)?; // tile_width
+            write.write_u32(16)?; // tile_height
+            write.write_u32(0)?; // tile_off_x
+            write.write_u32(0)?; // tile_off_y
+            write.write_u32(0)?; // tile_sep_x
+            write.write_u32(0)?; // tile_sep_y
+
+            write.write_u32(800)?;
+
+            write.write_u32(background.size.0)?;
+            write.write_u32(background.size.1)?;
+            if background.size.0 > 0 && background.size.1 > 0 {
+                write.write_blob(background.data)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    // paths
+
+    write.write_u32(800)?;
+    write.write_u32(game.paths.len() as u32)?;
+    for path in &game.paths[..] {
+        buf.clear();
+        write_path(buf, path)?;
+        write.write_blob_zlib(buf)?;
+    }
+
+    fn write_path(write: &mut Vec<u8>, path: &Path<'_>) -> io::Result<()> {
+        write.write_bool(!path.name.is_empty())?;
+        if path.name.is_empty() { return Ok(()); }
+
+        write.write_bstr(path.name)?;
+        write.write_f64(TIME)?;
+
+        write.write_u32(530)?;
+
+        write.write_bool(path.smooth)?;
+        write.write_bool(path.closed)?;
+        write.write_u32(path.precision)?;
+        write.write_i32(-1)?; // room
+        write.write_u32(16)?; // snap_x
+        write.write_u32(16)?; // snap_y
+
+        write.write_u32(path.points.len() as u32)?;
+        for point in &path.points[..] {
+            write.write_f64(point.position.0)?;
+            write.write_f64(point.position.1)?;
+            write.write_f64(point.speed)?;
+        }
+
+        Ok(())
+    }
+
+    // scripts
+
+    write.write_u32(800)?;
+    write.write_u32(game.scripts.len() as u32)?;
+    for script in &game.scripts[..] {
+        buf.clear();
+        write_script(buf, script)?;
+        write.write_blob_zlib(buf)?;
+    }
+
+    fn write_script(write: &mut Vec<u8>, script: &Script<'_>) -> io::Result<()> {
+        write.write_bool(!script.name.is_empty())?;
+        if script.name.is_empty() { return Ok(()); }
+
+        write.write_bstr(script.name)?;
+        write.write_f64(TIME)?;
+
+        write.write_u32(800)?;
+
+        write.write_bstr(script.body)?;
+
+        Ok(())
+    }
+
+    // fonts
+
+    write.write_u32(800)?;
+    write.write_u32(game.fonts.len() as u32)?;
+    for font in &game.fonts[..] {
+        buf.clear();
+        write_font(buf, version, font)?;
+        write.write_blob_zlib(buf)?;
+    }
+
+    fn write_font(write: &mut Vec<u8>, version: u32, font: &Font<'_>) -> io::Result<()> {
+        write.write_bool(!font.name.is_empty())?;
+        if font.name.is_empty() { return Ok(()); }
+
+        write.write_bstr(font.name)?;
+        write.write_f64(TIME)?;
+
+        write.write_u32(800)?;
+
+        write.write_bstr(font.font)?;
+        write.write_u32(font.size)?;
+        write.write_bool(font.bold)?;
+        write.write_bool(font.italic)?;
+        let mut start = font.start;
+        if version >= 810 {
+            start = start & 0xffff | (font.charset & 0xff) << 16 | font.antialias << 24;
+        }
+        write.write_u32(start)?;
+        write.write_u32(font.end)?;
+
+        Ok(())
+    }
+
+    // timelines
+
+    write.write_u32(800)?;
+    write.write_u32(game.timelines.len() as u32)?;
+    for timeline in &game.timelines[..] {
+        buf.clear();
+        write_timeline(buf, timeline)?;
+        write.write_blob_zlib(buf)?;
+    }
+
+    fn write_timeline(write: &mut Vec<u8>, timeline: &Timeline<'_>) -> io::Result<()> {
+        write.write_bool(!timeline.name.is_empty())?;
+        if timeline.name.is_empty() { return Ok(()); }
+
+        write.write_bstr(timeline.name)?;
+        write.write_f64(TIME)?;
+
+        write.write_u32(500)?;
+
+        write.write_u32(timeline.moments.len() as u32)?;
+        for moment in &timeline.moments[..] {
+            write.write_u32(moment.moment)?;
+
+            write.write_u32(400)?;
+
+            write.write_u32(moment.actions.len() as u32)?;
+            for action in &moment.actions[..] {
+                write_action(write, action)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    // objects
+
+    write.write_u32(800)?;
+    write.write_u32(game.objects.len() as u32)?;
+    for object in &game.objects[..] {
+        buf.clear();
+        write_object(buf, object)?;
+        write.write_blob_zlib(buf)?;
+    }
+
+    fn write_object(write: &mut Vec<u8>, object: &Object<'_>) -> io::Result<()> {
+        write.write_bool(!object.name.is_empty())?;
+        if object.name.is_empty() { return Ok(()); }
+
+        write.write_bstr(object.name)?;
+        write.write_f64(TIME)?;
+
+        write.write_u32(430)?;
+
+        write.write_i32(object.sprite)?;
+        write.write_bool(object.solid)?;
+        write.write_bool(object.visible)?;
+        write.write_i32(object.depth)?;
+        write.write_bool(object.persistent)?;
+        write.write_i32(object.parent)?;
+        write.write_i32(object.mask)?;
+
+        // GM 8.0 always writes every event type, up to and including triggers.
+        let len = object.events.iter()
+            .map(|event| event.event_type)
+            .fold(crate::event_type::TRIGGER, u32::max);
+        write.write_u32(len)?;
+        for event_type in 0..len + 1 {
+            for event in object.events.iter().filter(|event| event.event_type == event_type) {
+                write.write_i32(event.event_kind)?;
+
+                write.write_u32(400)?;
+
+                write.write_u32(event.actions.len() as u32)?;
+                for action in &event.actions[..] {
+                    write_action(write, action)?;
+                }
+            }
+            write.write_i32(-1)?;
+        }
+
+        Ok(())
+    }
+
+    // rooms
+
+    write.write_u32(800)?;
+    write.write_u32(game.rooms.len() as u32)?;
+    for room in &game.rooms[..] {
+        buf.clear();
+        write_room(buf, room)?;
+        write.write_blob_zlib(buf)?;
+    }
+
+    fn write_room(write: &mut Vec<u8>, room: &Room<'_>) -> io::Result<()> {
+        write.write_bool(!room.name.is_empty())?;
+        if room.name.is_empty() { return Ok(()); }
+
+        write.write_bstr(room.name)?;
+        write.write_f64(TIME)?;
+
+        write.write_u32(541)?;
+
+        write.write_bstr(room.caption)?;
+
+        write.write_u32(room.width)?;
+        write.write_u32(room.height)?;
+        write.write_u32(16)?; // snap_x
+        write.write_u32(16)?; // snap_y
+        write.write_bool(false)?; // isometric
+        write.write_u32(room.speed)?;
+        write.write_bool(room.persistent)?;
+        write.write_u32(room.clear_color)?;
+        write.write_bool(room.clear)?;
+
+        write.write_bstr(room.code)?;
+
+        write.write_u32(room.backgrounds.len() as u32)?;
+        for background in &room.backgrounds[..] {
+            write.write_bool(background.visible)?;
+            write.write_bool(background.foreground)?;
+            write.write_i32(background.background)?;
+            write.write_i32(background.x)?;
+            write.write_i32(background.y)?;
+            write.write_bool(background.htiled)?;
+            write.write_bool(background.vtiled)?;
+            write.write_i32(background.hspeed)?;
+            write.write_i32(background.vspeed)?;
+            write.write_bool(background.stretch)?;
+        }
+
+        write.write_bool(room.enable_views)?;
+        write.write_u32(room.views.len() as u32)?;
+        for view in &room.views[..] {
+            write.write_bool(view.visible)?;
+            write.write_i32(view.view_x)?;
+            write.write_i32(view.view_y)?;
+            write.write_u32(view.view_w)?;
+            write.write_u32(view.view_h)?;
+            write.write_i32(view.port_x)?;
+            write.write_i32(view.port_y)?;
+            write.write_u32(view.port_w)?;
+            write.write_u32(view.port_h)?;
+            write.write_i32(view.h_border)?;
+            write.write_i32(view.v_border)?;
+            write.write_i32(view.h_speed)?;
+            write.write_i32(view.v_speed)?;
+            write.write_i32(view.target)?;
+        }
+
+        write.write_u32(room.instances.len() as u32)?;
+        for instance in &room.instances[..] {
+            write.write_i32(instance.x)?;
+            write.write_i32(instance.y)?;
+            write.write_i32(instance.object_index)?;
+            write.write_i32(instance.id)?;
+            write.write_bstr(instance.code)?;
+            write.write_bool(false)?; // locked
+        }
+
+        write.write_u32(room.tiles.len() as u32)?;
+        for tile in &room.tiles[..] {
+            write.write_i32(tile.x)?;
+            write.write_i32(tile.y)?;
+            write.write_i32(tile.background)?;
+            write.write_i32(tile.tile_x)?;
+            write.write_i32(tile.tile_y)?;
+            write.write_u32(tile.width)?;
+            write.write_u32(tile.height)?;
+            write.write_i32(tile.depth)?;
+            write.write_i32(tile.id)?;
+            write.write_bool(false)?; // locked
+        }
+
+        write.write_bool(true)?; // configured
+        write.write_u32(room.width)?; // editor_width
+        write.write_u32(room.height)?; // editor_height
+        write.write_bool(true)?; // editor_grid
+        write.write_bool(true)?; // editor_objects
+        write.write_bool(true)?; // editor_tiles
+        write.write_bool(true)?; // editor_backgrounds
+        write.write_bool(true)?; // editor_foregrounds
+        write.write_bool(false)?; // editor_views
+        write.write_bool(true)?; // editor_delete_objects
+        write.write_bool(true)?; // editor_delete_tiles
+        write.write_u32(0)?; // editor_tab
+        write.write_u32(0)?; // editor_x
+        write.write_u32(0)?; // editor_y
+
+        Ok(())
+    }
+
+    write.write_i32(game.last_instance)?;
+    write.write_i32(game.last_tile)?;
+
+    // includes
+
+    write.write_u32(800)?;
+    write.write_u32(game.included_files.len() as u32)?;
+    for file in &game.included_files[..] {
+        buf.clear();
+        write_include(buf, file)?;
+        write.write_blob_zlib(buf)?;
+    }
+
+    fn write_include(write: &mut Vec<u8>, file: &IncludedFile<'_>) -> io::Result<()> {
+        write.write_f64(TIME)?;
+
+        write.write_u32(800)?;
+
+        write.write_bstr(file.name)?;
+        write.write_bstr(file.path)?;
+
+        write.write_bool(file.original)?;
+        write.write_u32(file.size)?;
+        write.write_bool(file.stored)?;
+        if file.original && file.stored {
+            write.write_blob(file.data)?;
+        }
+
+        write.write_u32(file.export)?;
+        write.write_bstr(file.folder)?;
+        write.write_bool(file.overwrite)?;
+        write.write_bool(file.free)?;
+        write.write_bool(file.remove)?;
+
+        Ok(())
+    }
+
+    // extensions
+
+    write.write_u32(700)?;
+    write.write_u32(game.extensions.len() as u32)?;
+    for &extension in &game.extensions[..] {
+        write.write_bstr(extension)?;
+    }
+
+    // game info
+
+    write.write_u32(800)?;
+    buf.clear();
+    write_info(buf, &game.information)?;
+    write.write_blob_zlib(buf)?;
+
+    fn write_info(write: &mut Vec<u8>, info: &GameInformation<'_>) -> io::Result<()> {
+        write.write_u32(info.background_color)?;
+        write.write_bool(info.separate_window)?;
+
+        write.write_bstr(info.caption)?;
+
+        write.write_i32(info.position.0)?;
+        write.write_i32(info.position.1)?;
+        write.write_u32(info.size.0)?;
+        write.write_u32(info.size.1)?;
+        write.write_bool(info.show_border)?;
+        write.write_bool(info.allow_resize)?;
+        write.write_bool(info.topmost)?;
+        write.write_bool(info.freeze)?;
+
+        write.write_f64(TIME)?;
+
+        write.write_bstr(info.text)?;
+
+        Ok(())
+    }
+
+    // library initialization
+
+    write.write_u32(500)?;
+    write.write_u32(0)?;
+
+    // room order
+
+    write.write_u32(700)?;
+    write.write_u32(game.room_order.len() as u32)?;
+    for &room in &game.room_order[..] {
+        write.write_u32(room)?;
+    }
+
+    // resource tree
+
+    write_tree(write, game)?;
+
+    Ok(())
+}
+
+fn write_settings(write: &mut Vec<u8>, version: u32, settings: &Settings) -> io::Result<()> {
+    write.write_bool(settings.fullscreen)?;
+    write.write_bool(settings.interpolation)?;
+    write.write_bool(settings.hide_border)?;
+    write.write_bool(settings.show_cursor)?;
+    write.write_i32(settings.scaling)?;
+    write.write_bool(settings.allow_resize)?;
+    write.write_bool(settings.topmost)?;
+    write.write_u32(settings.background_color)?;
+    write.write_bool(settings.set_resolution)?;
+    write.write_u32(settings.color_depth)?;
+    write.write_u32(settings.resolution)?;
+    write.write_u32(settings.frequency)?;
+    write.write_bool(settings.hide_buttons)?;
+    write.write_bool(settings.vsync)?;
+    write.write_bool(settings.disable_screensaver)?;
+    write.write_bool(settings.default_f4)?;
+    write.write_bool(settings.default_f1)?;
+    write.write_bool(settings.default_esc)?;
+    write.write_bool(settings.default_f5)?;
+    write.write_bool(settings.default_f9)?;
+    write.write_bool(settings.close_as_esc)?;
+    write.write_u32(settings.priority)?;
+    write.write_bool(settings.freeze)?;
+
+    // The images for a custom loading bar are not kept, so fall back to the default one.
+    let load_bar = if settings.load_bar == 2 { 1 } else { settings.load_bar };
+    write.write_u32(load_bar)?;
+
+    write.write_bool(settings.load_image)?;
+    if settings.load_image {
+        write.write_bool(false)?; // exists
+    }
+
+    write.write_bool(settings.load_transparent)?;
+    write.write_u32(settings.load_alpha)?;
+    write.write_bool(settings.load_scale)?;
+
+    write.write_blob(&[])?; // icon
+
+    write.write_bool(settings.error_display)?;
+    write.write_bool(settings.error_log)?;
+    write.write_bool(settings.error_abort)?;
+    let mut errors = settings.uninitialized_zero as u32;
+    if version >= 810 { errors |= (settings.uninitialized_arguments as u32) << 1; }
+    write.write_u32(errors)?;
+
+    write.write_bstr(BStr::new(b""))?; // author
+    write.write_bstr(BStr::new(b"100"))?; // version
+    write.write_f64(TIME)?;
+    write.write_bstr(BStr::new(b""))?; // information
+
+    write.write_u32(1)?; // major
+    write.write_u32(0)?; // minor
+    write.write_u32(0)?; // release
+    write.write_u32(0)?; // build
+    write.write_bstr(BStr::new(b""))?; // company
+    write.write_bstr(BStr::new(b""))?; // product
+    write.write_bstr(BStr::new(b""))?; // copyright
+    write.write_bstr(BStr::new(b""))?; // description
+    write.write_f64(TIME)?;
+
+    Ok(())
+}
+
+fn write_constants<W: Write>(write: &mut W, constants: &[Constant<'_>]) -> io::Result<()> {
+    write.write_u32(constants.len() as u32)?;
+    for constant in constants {
+        write.write_bstr(constant.name)?;
+        write.write_bstr(constant.value)?;
+    }
+
+    Ok(())
+}
+
+fn write_action<W: Write>(write: &mut W, action: &Action<'_>) -> io::Result<()> {
+    write.write_u32(440)?;
+
+    write.write_u32(action.library)?;
+    write.write_u32(action.action)?;
+    write.write_u32(action.action_kind)?;
+    write.write_bool(action.has_relative)?;
+    write.write_bool(action.is_question)?;
+    write.write_bool(action.has_target)?;
+    write.write_u32(action.action_type)?;
+    write.write_bstr(action.name)?;
+    write.write_bstr(action.code)?;
+    write.write_u32(action.parameters_used)?;
+
+    write.write_u32(action.parameters.len() as u32)?;
+    for &parameter in &action.parameters[..] {
+        write.write_u32(parameter)?;
+    }
+
+    write.write_i32(action.target)?;
+    write.write_bool(action.relative)?;
+
+    write.write_u32(action.arguments.len() as u32)?;
+    for &argument in &action.arguments[..] {
+        write.write_bstr(argument)?;
+    }
+
+    write.write_bool(action.negate)?;
+
+    Ok(())
+}
+
+/// Write a flat resource tree, with every resource directly inside its primary group.
+fn write_tree<W: Write>(write: &mut W, game: &Game<'_>) -> io::Result<()> {
+    const PRIMARY: u32 = 1;
+    const RESOURCE: u32 = 3;
+
+    fn names<'a, T>(resources: &'a [T], name: impl Fn(&T) -> &'a BStr) -> Vec<(u32, &'a BStr)> {
+        resources.iter().enumerate()
+            .map(|(index, resource)| (index as u32, name(resource)))
+            .filter(|&(_, name)| !name.is_empty())
+            .collect()
+    }
+
+    let groups = [
+        (2, "Sprites", names(&game.sprites[..], |sprite| sprite.name)),
+        (3, "Sounds", names(&game.sounds[..], |sound| sound.name)),
+        (6, "Backgrounds", names(&game.backgrounds[..], |background| background.name)),
+        (8, "Paths", names(&game.paths[..], |path| path.name)),
+        (7, "Scripts", names(&game.scripts[..], |script| script.name)),
+        (9, "Fonts", names(&game.fonts[..], |font| font.name)),
+        (12, "Time Lines", names(&game.timelines[..], |timeline| timeline.name)),
+        (1, "Objects", names(&game.objects[..], |object| object.name)),
+        (4, "Rooms", names(&game.rooms[..], |room| room.name)),
+        (10, "Game Information", Vec::default()),
+        (11, "Global Game Settings", Vec::default()),
+        (13, "Extension Packages", Vec::default()),
+    ];
+    for (kind, group, resources) in groups {
+        write.write_u32(PRIMARY)?;
+        write.write_u32(kind)?;
+        write.write_u32(0)?;
+        write.write_bstr(BStr::new(group))?;
+        write.write_u32(resources.len() as u32)?;
+        for (index, name) in resources {
+            write.write_u32(RESOURCE)?;
+            write.write_u32(kind)?;
+            write.write_u32(index)?;
+            write.write_bstr(name)?;
+            write.write_u32(0)?;
+        }
+    }
+
+    Ok(())
+}
+
+trait GmWrite {
+    fn write_u32(&mut self, value: u32) -> io::Result<()>;
+    fn write_i32(&mut self, value: i32) -> io::Result<()>;
+    fn write_bool(&mut self, value: bool) -> io::Result<()>;
+    fn write_f64(&mut self, value: f64) -> io::Result<()>;
+
+    fn write_blob(&mut self, value: &[u8]) -> io::Result<()>;
+    fn write_bstr(&mut self, value: &BStr) -> io::Result<()> { self.write_blob(value) }
+    fn write_blob_zlib(&mut self, value: &[u8]) -> io::Result<()>;
+}
+
+impl<W: Write> GmWrite for W {
+    fn write_u32(&mut self, value: u32) -> io::Result<()> {
+        self.write_all(&value.to_le_bytes())
+    }
+
+    fn write_i32(&mut self, value: i32) -> io::Result<()> {
+        self.write_all(&value.to_le_bytes())
+    }
+
+    fn write_bool(&mut self, value: bool) -> io::Result<()> {
+        self.write_u32(value as u32)
+    }
+
+    fn write_f64(&mut self, value: f64) -> io::Result<()> {
+        self.write_all(&value.to_le_bytes())
+    }
+
+    fn write_blob(&mut self, value: &[u8]) -> io::Result<()> {
+        self.write_u32(value.len() as u32)?;
+        self.write_all(value)
+    }
+
+    fn write_blob_zlib(&mut self, value: &[u8]) -> io::Result<()> {
+        let mut encoder = ZlibEncoder::new(Vec::default(), Compression::default());
+        encoder.write_all(value)?;
+        self.write_blob(&encoder.finish()?[..])
+    }
+}
+
+#[cfg(all(test, feature = "read"))]
+mod tests {
+    use bstr::BStr;
+    use quickdry::Arena;
+    use crate::*;
+
+    fn game<'a>(image: &'a [u8]) -> Game<'a> {
+        let mut game = Game { version: 800, id: 1234, guid: [1, 2, 3, 4], ..Game::default() };
+        game.settings.fullscreen = true;
+        game.settings.uninitialized_zero = true;
+        game.triggers.push(Trigger {
+            name: BStr::new(b"boss_dead"),
+            condition: BStr::new(b"return instance_number(boss_obj) == 0"),
+            moment: trigger_moment::END_STEP,
+            constant: BStr::new(b"trg_boss_dead"),
+        });
+        game.constants.push(Constant { name: BStr::new(b"lives"), value: BStr::new(b"3") });
+        game.sounds.push(Sound {
+            name: BStr::new(b"jump_snd"),
+            file_type: BStr::new(b".wav"),
+            file_name: BStr::new(b"jump.wav"),
+            data: b"RIFF",
+            volume: 0.5,
+            preload: true,
+            ..Sound::default()
+        });
+        game.sprites.push(Sprite {
+            name: BStr::new(b"player_spr"),
+            version: 800,
+            origin: (1, 1),
+            images: vec![Image { size: (2, 2), data: image }],
+            bounds: Bounds { left: 0, right: 1, bottom: 1, top: 0 },
+            ..Sprite::default()
+        });
+        game.sprites.push(Sprite::default());
+        game.backgrounds.push(Background {
+            name: BStr::new(b"sky_bk"),
+            version: 710,
+            size: (2, 2),
+            data: image,
+            ..Background::default()
+        });
+        game.paths.push(Path {
+            name: BStr::new(b"loop_pth"),
+            closed: true,
+            precision: 4,
+            points: vec![Point { position: (0.0, 8.0), speed: 100.0 }],
+            ..Path::default()
+        });
+        game.scripts.push(Script { name: BStr::new(b"scr_hello"), body: BStr::new(b"return 1") });
+        game.fonts.push(Font {
+            name: BStr::new(b"title_fnt"),
+            font: BStr::new(b"Arial"),
+            size: 24,
+            bold: true,
+            start: 32,
+            end: 127,
+            ..Font::default()
+        });
+        game.timelines.push(Timeline {
+            name: BStr::new(b"intro_tl"),
+            moments: vec![Moment {
+                moment: 30,
+                actions: vec![Action {
+                    library: 1,
+                    action: 603,
+                    action_kind: action_kind::CODE,
+                    parameters: vec![argument_type::STRING],
+                    arguments: vec![BStr::new(b"room_goto_next()")],
+                    ..Action::default()
+                }],
+            }],
+        });
+        game.objects.push(Object {
+            name: BStr::new(b"player_obj"),
+            sprite: 0,
+            visible: true,
+            parent: -1,
+            mask: -1,
+            events: vec![
+                Event { event_type: event_type::CREATE, event_kind: 0, actions: vec![] },
+                Event {
+                    event_type: event_type::STEP,
+                    event_kind: event_kind::STEP,
+                    actions: vec![Action {
+                        library: 1,
+                        action: 603,
+                        action_kind: action_kind::CODE,
+                        parameters_used: 1,
+                        parameters: vec![argument_type::STRING],
+                        target: -1,
+                        arguments: vec![BStr::new(b"x += 1")],
+                        ..Action::default()
+                    }],
+                },
+            ],
+            ..Object::default()
+        });
+        game.rooms.push(Room {
+            name: BStr::new(b"start_rm"),
+            caption: BStr::new(b"Start"),
+            width: 640,
+            height: 480,
+            speed: 30,
+            clear: true,
+            code: BStr::new(b""),
+            backgrounds: vec![RoomBackground { visible: true, ..RoomBackground::default() }],
+            views: vec![View { view_w: 320, view_h: 240, target: -1, ..View::default() }],
+            instances: vec![Instance {
+                x: 32, y: 64, object_index: 0, id: 100001, code: BStr::new(b"hspeed = 2"),
+            }],
+            tiles: vec![Tile { x: 8, width: 2, height: 2, id: 10000001, ..Tile::default() }],
+            ..Room::default()
+        });
+        game.last_instance = 100001;
+        game.last_tile = 10000001;
+        game.included_files.push(IncludedFile {
+            name: BStr::new(b"levels.txt"),
+            path: BStr::new(b"C:\\levels.txt"),
+            original: true,
+            size: 5,
+            stored: true,
+            data: b"1 2 3",
+            export: export_kind::TEMP,
+            ..IncludedFile::default()
+        });
+        game.extensions.push(BStr::new(b"Widgets"));
+        game.information.text = BStr::new(b"{\\rtf1 Press space to jump.}");
+        game.room_order.push(0);
+        game
+    }
+
+    #[test]
+    fn round_trip() {
+        let image = [0xff; 16];
+        let original = game(&image[..]);
+
+        let mut data = Vec::default();
+        write_project(&mut data, &original).unwrap();
+
+        let arena = Arena::default();
+        let mut game = Game::default();
+        read_project(&data[..], &mut game, &arena).unwrap();
+
+        assert_eq!((game.version, game.id, game.guid), (800, 1234, [1, 2, 3, 4]));
+        assert!(game.settings.fullscreen && game.settings.uninitialized_zero);
+        let trigger = &game.triggers[0];
+        assert_eq!((trigger.constant, trigger.moment), ("trg_boss_dead".into(), 2));
+        assert_eq!(game.constants[0].value, "3");
+        assert_eq!((game.sounds[0].name, game.sounds[0].data), ("jump_snd".into(), &b"RIFF"[..]));
+        assert_eq!(game.sounds[0].volume, 0.5);
+        assert_eq!(game.sprites.len(), 2);
+        assert_eq!(game.sprites[0].images[0].data, &image[..]);
+        assert_eq!(game.sprites[0].bounds.right, 1);
+        assert!(game.sprites[1].name.is_empty());
+        assert_eq!((game.backgrounds[0].size, game.backgrounds[0].data), ((2, 2), &image[..]));
+        assert_eq!(game.paths[0].points[0].speed, 100.0);
+        assert_eq!(game.scripts[0].body, "return 1");
+        let font = &game.fonts[0];
+        assert_eq!((font.font, font.size, font.bold, font.end), ("Arial".into(), 24, true, 127));
+        let moment = &game.timelines[0].moments[0];
+        assert_eq!(moment.moment, 30);
+        assert_eq!(moment.actions[0].arguments[0], "room_goto_next()");
+        let events = &game.objects[0].events;
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[1].event_type, event_type::STEP);
+        assert_eq!(events[1].actions[0].arguments[0], "x += 1");
+        let room = &game.rooms[0];
+        assert_eq!((room.caption, room.width, room.speed), ("Start".into(), 640, 30));
+        assert_eq!(room.views[0].view_w, 320);
+        assert_eq!(room.instances[0].code, "hspeed = 2");
+        assert_eq!(room.tiles[0].id, 10000001);
+        assert_eq!((game.last_instance, game.last_tile), (100001, 10000001));
+        let file = &game.included_files[0];
+        assert_eq!((file.name, file.data, file.export), ("levels.txt".into(), &b"1 2 3"[..], 1));
+        assert_eq!(game.extensions[0], "Widgets");
+        assert_eq!(game.information.text, "{\\rtf1 Press space to jump.}");
+        assert_eq!(game.information.caption, "Game Information");
+        assert_eq!(game.room_order, [0]);
+
+        // Everything the reader keeps survives a second trip unchanged.
+        let mut again = Vec::default();
+        write_project(&mut again, &game).unwrap();
+        assert_eq!(data, again);
+    }
+
+    #[test]
+    fn round_trip_810() {
+        let image = [0xff; 16];
+        let mut original = game(&image[..]);
+        original.version = 810;
+        original.settings.uninitialized_arguments = true;
+        original.fonts[0].charset = 1;
+        original.fonts[0].antialias = 3;
+
+        // The 8.1 format only differs from 8.0 in its version numbers and a few packed fields.
+        let mut data = Vec::default();
+        write_project(&mut data, &original).unwrap();
+        assert_eq!(data[4..8], 810u32.to_le_bytes());
+        assert_eq!(data[28..32], 810u32.to_le_bytes());
+
+        let arena = Arena::default();
+        let mut game = Game::default();
+        read_project(&data[..], &mut game, &arena).unwrap();
+        assert_eq!(game.version, 810);
+        assert!(game.settings.uninitialized_zero && game.settings.uninitialized_arguments);
+        let font = &game.fonts[0];
+        assert_eq!((font.start, font.end, font.charset, font.antialias), (32, 127, 1, 3));
+        assert_eq!(game.rooms[0].name, "start_rm");
+
+        let mut again = Vec::default();
+        write_project(&mut again, &game).unwrap();
+        assert_eq!(data, again);
+    }
+}
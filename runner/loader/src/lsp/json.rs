@@ -0,0 +1,240 @@
+use std::{fmt, str};
+use gml::diagnostic::JsonString;
+
+/// A JSON value, just enough to speak the language server protocol.
+#[derive(Clone, PartialEq, Debug)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    pub fn parse(text: &str) -> Option<Json> {
+        let mut parser = Parser { text: text.as_bytes(), pos: 0 };
+        let value = parser.value()?;
+        parser.whitespace();
+        if parser.pos < parser.text.len() { return None; }
+        Some(value)
+    }
+
+    pub fn object<const N: usize>(fields: [(&str, Json); N]) -> Json {
+        Json::Object(fields.into_iter().map(|(key, value)| (String::from(key), value)).collect())
+    }
+
+    /// Look up a field of an object, or `Null` if there is none.
+    pub fn get(&self, key: &str) -> &Json {
+        match *self {
+            Json::Object(ref fields) => fields.iter()
+                .find(|&(field, _)| field == key)
+                .map_or(&Json::Null, |(_, value)| value),
+            _ => &Json::Null,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match *self { Json::String(ref value) => Some(value), _ => None }
+    }
+
+    pub fn as_usize(&self) -> Option<usize> {
+        match *self {
+            Json::Number(value) if value >= 0.0 && value.fract() == 0.0 => Some(value as usize),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> &[Json] {
+        match *self { Json::Array(ref values) => values, _ => &[] }
+    }
+}
+
+impl From<&str> for Json {
+    fn from(value: &str) -> Json { Json::String(String::from(value)) }
+}
+
+impl From<String> for Json {
+    fn from(value: String) -> Json { Json::String(value) }
+}
+
+impl From<usize> for Json {
+    fn from(value: usize) -> Json { Json::Number(value as f64) }
+}
+
+impl From<bool> for Json {
+    fn from(value: bool) -> Json { Json::Bool(value) }
+}
+
+impl fmt::Display for Json {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Json::Null => write!(f, "null"),
+            Json::Bool(value) => write!(f, "{value}"),
+            Json::Number(value) if value.is_finite() => write!(f, "{value}"),
+            Json::Number(_) => write!(f, "null"),
+            Json::String(ref value) => write!(f, "{}", JsonString(value)),
+            Json::Array(ref values) => {
+                write!(f, "[")?;
+                for (i, value) in values.iter().enumerate() {
+                    if i > 0 { write!(f, ",")?; }
+                    write!(f, "{value}")?;
+                }
+                write!(f, "]")
+            }
+            Json::Object(ref fields) => {
+                write!(f, "{{")?;
+                for (i, (key, value)) in fields.iter().enumerate() {
+                    if i > 0 { write!(f, ",")?; }
+                    write!(f, "{}", JsonString(key))?;
+                    write!(f, ":{value}")?;
+                }
+                write!(f, "}}")
+            }
+        }
+    }
+}
+
+struct Parser<'a> {
+    text: &'a [u8],
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn value(&mut self) -> Option<Json> {
+        self.whitespace();
+        match *self.text.get(self.pos)? {
+            b'n' => self.keyword("null", Json::Null),
+            b't' => self.keyword("true", Json::Bool(true)),
+            b'f' => self.keyword("false", Json::Bool(false)),
+            b'"' => self.string().map(Json::String),
+            b'[' => {
+                self.pos += 1;
+                let mut values = Vec::default();
+                if self.next_if(b']') { return Some(Json::Array(values)); }
+                loop {
+                    values.push(self.value()?);
+                    if self.next_if(b']') { return Some(Json::Array(values)); }
+                    if !self.next_if(b',') { return None; }
+                }
+            }
+            b'{' => {
+                self.pos += 1;
+                let mut fields = Vec::default();
+                if self.next_if(b'}') { return Some(Json::Object(fields)); }
+                loop {
+                    self.whitespace();
+                    let key = self.string()?;
+                    if !self.next_if(b':') { return None; }
+                    fields.push((key, self.value()?));
+                    if self.next_if(b'}') { return Some(Json::Object(fields)); }
+                    if !self.next_if(b',') { return None; }
+                }
+            }
+            _ => self.number(),
+        }
+    }
+
+    fn whitespace(&mut self) {
+        while let Some(b' ' | b'\t' | b'\n' | b'\r') = self.text.get(self.pos) {
+            self.pos += 1;
+        }
+    }
+
+    /// Skip whitespace and consume `c` if it comes next.
+    fn next_if(&mut self, c: u8) -> bool {
+        self.whitespace();
+        if self.text.get(self.pos) != Some(&c) { return false; }
+        self.pos += 1;
+        true
+    }
+
+    fn keyword(&mut self, keyword: &str, value: Json) -> Option<Json> {
+        if !self.text[self.pos..].starts_with(keyword.as_bytes()) { return None; }
+        self.pos += keyword.len();
+        Some(value)
+    }
+
+    fn number(&mut self) -> Option<Json> {
+        let start = self.pos;
+        while let Some(b'0'..=b'9' | b'-' | b'+' | b'.' | b'e' | b'E') = self.text.get(self.pos) {
+            self.pos += 1;
+        }
+        let number = str::from_utf8(&self.text[start..self.pos]).ok()?;
+        number.parse().ok().map(Json::Number)
+    }
+
+    fn string(&mut self) -> Option<String> {
+        if self.text.get(self.pos) != Some(&b'"') { return None; }
+        self.pos += 1;
+
+        let mut value = Vec::default();
+        loop {
+            match *self.text.get(self.pos)? {
+                b'"' => { self.pos += 1; break; }
+                b'\\' => {
+                    self.pos += 1;
+                    let c = match *self.text.get(self.pos)? {
+                        b'"' => '"',
+                        b'\\' => '\\',
+                        b'/' => '/',
+                        b'b' => '\x08',
+                        b'f' => '\x0c',
+                        b'n' => '\n',
+                        b'r' => '\r',
+                        b't' => '\t',
+                        b'u' => {
+                            let high = self.hex()?;
+                            let c = if (0xd800..0xdc00).contains(&high) {
+                                if !self.text[self.pos + 1..].starts_with(b"\\u") { return None; }
+                                self.pos += 2;
+                                let low = self.hex()?;
+                                0x10000 + ((high - 0xd800) << 10) + low.checked_sub(0xdc00)?
+                            } else {
+                                high
+                            };
+                            char::from_u32(c)?
+                        }
+                        _ => return None,
+                    };
+                    let mut buf = [0; 4];
+                    value.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+                    self.pos += 1;
+                }
+                c => { value.push(c); self.pos += 1; }
+            }
+        }
+        String::from_utf8(value).ok()
+    }
+
+    /// Read the four hex digits of a `\u` escape, leaving `pos` on the last one.
+    fn hex(&mut self) -> Option<u32> {
+        let digits = self.text.get(self.pos + 1..self.pos + 5)?;
+        let value = u32::from_str_radix(str::from_utf8(digits).ok()?, 16).ok()?;
+        self.pos += 4;
+        Some(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Json;
+
+    #[test]
+    fn round_trip() {
+        let text = r#"{
+            "id": 1,
+            "params": {"text": "a\"b\\c\n\u00e9\ud83d\ude00", "list": [true, null, -2.5]}
+        }"#;
+        let value = Json::parse(text).unwrap();
+        assert_eq!(value.get("id").as_usize(), Some(1));
+        assert_eq!(value.get("params").get("text").as_str(), Some("a\"b\\c\n\u{e9}\u{1f600}"));
+        assert_eq!(value.get("params").get("list").as_array().len(), 3);
+        assert_eq!(value.get("missing"), &Json::Null);
+        assert_eq!(Json::parse(&value.to_string()), Some(value));
+
+        assert_eq!(Json::parse("[1,]"), None);
+        assert_eq!(Json::parse("{} x"), None);
+    }
+}
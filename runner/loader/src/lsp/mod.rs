@@ -0,0 +1,446 @@
+//! A language server for GML.
+//!
+//! Editors run this as `loader lsp` and speak the language server protocol over stdin and stdout.
+//! It checks scripts as they are opened and saved, and understands project directories written by
+//! `project::write_dir`: scripts, objects and constants can be looked up by name, alongside the
+//! functions and variables built into the runner.
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::Write as _;
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::{fs, str};
+use gml::symbol::Symbol;
+use gml::ErrorPrinter;
+use gml::diagnostic::{Diagnostic, Location, Severity};
+use gml::front::{Lexer, Parser, Codegen, Lines};
+use gml::front::token::Token;
+use gml::back::ssa;
+
+use self::json::Json;
+
+mod json;
+
+pub fn run() -> Result<(), Box<dyn Error>> {
+    let mut read = io::stdin().lock();
+    let mut write = io::stdout().lock();
+    let mut server = Server::default();
+    while let Some(message) = read_message(&mut read)? {
+        if !server.handle(&message, &mut write)? { break; }
+    }
+    Ok(())
+}
+
+#[derive(Default)]
+struct Server {
+    root: Option<PathBuf>,
+    prototypes: HashMap<Symbol, ssa::Prototype>,
+    /// The builtin variables, offered as completions.
+    members: Vec<Symbol>,
+    /// The file and line where each script, object and constant is defined.
+    definitions: HashMap<Symbol, (PathBuf, usize)>,
+    /// Whether the project reads uninitialized variables as 0, rather than reporting them.
+    uninitialized_zero: bool,
+    /// The text of each open document, by URI.
+    documents: HashMap<String, String>,
+}
+
+impl Server {
+    /// Respond to a single message, returning false once the client asks the server to exit.
+    fn handle<W: Write>(&mut self, message: &Json, write: &mut W) -> io::Result<bool> {
+        let method = message.get("method").as_str().unwrap_or_default();
+        let params = message.get("params");
+        let uri = params.get("textDocument").get("uri").as_str().unwrap_or_default();
+
+        // Notifications have no id, and get no response.
+        let id = message.get("id");
+        if *id == Json::Null {
+            match method {
+                "exit" => return Ok(false),
+                "textDocument/didOpen" => {
+                    let text = params.get("textDocument").get("text").as_str().unwrap_or_default();
+                    self.documents.insert(String::from(uri), String::from(text));
+                    self.publish(uri, write)?;
+                }
+                "textDocument/didChange" => {
+                    // The server asks for full documents rather than incremental changes.
+                    let change = params.get("contentChanges").as_array().last();
+                    let text = change.and_then(|change| change.get("text").as_str());
+                    if let (Some(document), Some(text)) = (self.documents.get_mut(uri), text) {
+                        *document = String::from(text);
+                    }
+                }
+                "textDocument/didSave" => {
+                    // Saving a file in the project may add or rename resources.
+                    let path = uri_path(uri);
+                    let root = self.root.as_deref();
+                    if path.zip(root).is_some_and(|(path, root)| path.starts_with(root)) {
+                        self.load();
+                    }
+                    self.publish(uri, write)?;
+                }
+                "textDocument/didClose" => {
+                    self.documents.remove(uri);
+                    self.publish(uri, write)?;
+                }
+                _ => {}
+            }
+            return Ok(true);
+        }
+
+        let result = match method {
+            "initialize" => {
+                self.root = params.get("rootUri").as_str().and_then(uri_path);
+                self.load();
+
+                let sync = Json::object([
+                    ("openClose", true.into()),
+                    ("change", 1.into()),
+                    ("save", Json::object([])),
+                ]);
+                let capabilities = Json::object([
+                    ("textDocumentSync", sync),
+                    ("definitionProvider", true.into()),
+                    ("hoverProvider", true.into()),
+                    ("completionProvider", Json::object([])),
+                ]);
+                let info = Json::object([("name", "dejavu".into())]);
+                Json::object([("capabilities", capabilities), ("serverInfo", info)])
+            }
+            "shutdown" => Json::Null,
+            "textDocument/definition" => self.definition(uri, params.get("position")),
+            "textDocument/hover" => self.hover(uri, params.get("position")),
+            "textDocument/completion" => self.completion(),
+            _ => {
+                let message = format!("unsupported method {method}");
+                let code = Json::Number(-32601.0);
+                let error = Json::object([("code", code), ("message", message.into())]);
+                let response = Json::object([
+                    ("jsonrpc", "2.0".into()),
+                    ("id", id.clone()),
+                    ("error", error),
+                ]);
+                write_message(write, &response)?;
+                return Ok(true);
+            }
+        };
+        let response = Json::object([
+            ("jsonrpc", "2.0".into()),
+            ("id", id.clone()),
+            ("result", result),
+        ]);
+        write_message(write, &response)?;
+        Ok(true)
+    }
+
+    /// Read the project and the runner's builtins, to resolve names in code.
+    fn load(&mut self) {
+        let mut items = HashMap::default();
+        runner::World::register(&mut items);
+
+        let arena = quickdry::Arena::default();
+        let mut game = project::Game::default();
+        self.definitions.clear();
+        if let Some(root) = self.root.as_deref() && root.join("game.txt").is_file() {
+            match project::read_dir(root, &mut game, &arena) {
+                Ok(()) => { self.definitions = definitions(root, &game); }
+                Err(error) => {
+                    eprintln!("error: could not read project: {error}");
+                    game = project::Game::default();
+                }
+            }
+        }
+
+        self.prototypes = gml::prototypes(&game, &[], &items);
+        self.uninitialized_zero = game.settings.uninitialized_zero;
+        self.members = items.iter()
+            .filter(|&(_, item)| matches!(item, gml::Item::Member(..)))
+            .map(|(&name, _)| name)
+            .collect();
+        self.members.sort_by(|a, b| <[u8]>::cmp(a, b));
+    }
+
+    /// Send the errors in a document, or clear them once it is closed.
+    fn publish<W: Write>(&self, uri: &str, write: &mut W) -> io::Result<()> {
+        let diagnostics = match self.documents.get(uri) {
+            Some(text) if uri.ends_with(".gml") => self.diagnostics(text),
+            _ => Vec::default(),
+        };
+        let params = Json::object([("uri", uri.into()), ("diagnostics", Json::Array(diagnostics))]);
+        let notification = Json::object([
+            ("jsonrpc", "2.0".into()),
+            ("method", "textDocument/publishDiagnostics".into()),
+            ("params", params),
+        ]);
+        write_message(write, &notification)
+    }
+
+    fn diagnostics(&self, text: &str) -> Vec<Json> {
+        // Only generate code for programs that parse, as codegen expects a well-formed tree.
+        let mut diagnostics = Vec::default();
+        let lines = Lines::from_code(text.as_bytes());
+        let mut errors = ErrorPrinter::new(gml::FunctionDisplay::String, &lines, &mut diagnostics);
+        let program = Parser::new(Lexer::new(text.as_bytes(), 0), &mut errors).parse_program();
+        if errors.count == 0 {
+            let program = Codegen::new(&self.prototypes, &mut errors).compile_program(&program);
+            if errors.count == 0 && !self.uninitialized_zero {
+                gml::front::check_uninitialized_locals(&program, &mut errors);
+            }
+        }
+
+        diagnostics.into_iter().filter_map(|diagnostic| {
+            let Diagnostic { severity, code, message, location, .. } = diagnostic;
+            let Location { span, .. } = location?;
+
+            // Some errors point at a single position, so widen those to the token there.
+            let low = usize::min(span.low, text.len());
+            let high = if span.high > low { usize::min(span.high, text.len()) } else {
+                let (_, span) = Lexer::new(&text.as_bytes()[low..], low).read_token();
+                if span.low == low { span.high } else { low }
+            };
+
+            let (start, end) = (position_at(text, low), position_at(text, high));
+            let range = Json::object([("start", start), ("end", end)]);
+            let severity = match severity { Severity::Error => 1, Severity::Warning => 2 };
+            Some(Json::object([
+                ("range", range),
+                ("severity", severity.into()),
+                ("code", code.into()),
+                ("source", "gml".into()),
+                ("message", message.into()),
+            ]))
+        }).collect()
+    }
+
+    fn definition(&self, uri: &str, position: &Json) -> Json {
+        let Some((symbol, _)) = self.symbol_at(uri, position) else { return Json::Null };
+        let Some(&(ref path, line)) = self.definitions.get(&symbol) else { return Json::Null };
+        let start = Json::object([("line", line.into()), ("character", 0.into())]);
+        let range = Json::object([("start", start.clone()), ("end", start)]);
+        Json::object([("uri", path_uri(path).into()), ("range", range)])
+    }
+
+    fn hover(&self, uri: &str, position: &Json) -> Json {
+        let Some((symbol, range)) = self.symbol_at(uri, position) else { return Json::Null };
+        let Some(&prototype) = self.prototypes.get(&symbol) else { return Json::Null };
+        let description = match prototype {
+            ssa::Prototype::Native { arity, variadic, dll } => {
+                let kind = if dll { "extension function" } else { "function" };
+                let least = if variadic { "at least " } else { "" };
+                let s = if arity == 1 { "" } else { "s" };
+                format!("{kind} taking {least}{arity} argument{s}")
+            }
+            ssa::Prototype::Member => String::from("builtin variable"),
+            ssa::Prototype::Resource { script: true, .. } => String::from("script"),
+            ssa::Prototype::Resource { id, script: false } => format!("resource {id}"),
+            ssa::Prototype::Constant { .. } => String::from("constant"),
+        };
+        let value = format!("```gml\n{symbol}\n```\n{description}");
+        let contents = Json::object([("kind", "markdown".into()), ("value", value.into())]);
+        Json::object([("contents", contents), ("range", range)])
+    }
+
+    fn completion(&self) -> Json {
+        Json::Array(self.members.iter().map(|member| {
+            // Completion item kind 6 is a variable.
+            Json::object([("label", member.to_string().into()), ("kind", 6.into())])
+        }).collect())
+    }
+
+    /// Find the identifier under the cursor, along with its range.
+    fn symbol_at(&self, uri: &str, position: &Json) -> Option<(Symbol, Json)> {
+        let text = self.documents.get(uri)?;
+        let offset = offset_at(text, position)?;
+        let mut lexer = Lexer::new(text.as_bytes(), 0);
+        loop {
+            match lexer.read_token() {
+                (Token::Eof, _) => return None,
+                (_, span) if span.low > offset => return None,
+                (Token::Ident(symbol), span) if offset <= span.high => {
+                    let start = position_at(text, span.low);
+                    let end = position_at(text, span.high);
+                    return Some((symbol, Json::object([("start", start), ("end", end)])));
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Find the files defining each script, object and constant in a project directory.
+fn definitions(root: &Path, game: &project::Game<'_>) -> HashMap<Symbol, (PathBuf, usize)> {
+    let mut definitions = HashMap::default();
+
+    let scripts = game.scripts.iter().map(|script| script.name);
+    let scripts = scripts.zip(project::resource_files(game, project::Resource::Script));
+    let objects = game.objects.iter().map(|object| object.name);
+    let objects = objects.zip(project::resource_files(game, project::Resource::Object));
+    for (name, file) in Iterator::chain(scripts, objects) {
+        if name.is_empty() { continue; }
+        definitions.insert(Symbol::intern(name), (root.join(file), 0));
+    }
+
+    // Constants are listed in order in game.txt, after the triggers.
+    let path = root.join("game.txt");
+    let text = fs::read_to_string(&path).unwrap_or_default();
+    let lines = text.lines().enumerate();
+    let mut lines = lines.skip_while(|(_, line)| !line.starts_with("constants "));
+    for constant in &game.constants[..] {
+        let key = format!("constant \"{}\"", constant.name);
+        if let Some((line, _)) = lines.find(|(_, line)| line.trim() == key) {
+            definitions.insert(Symbol::intern(constant.name), (path.clone(), line));
+        }
+    }
+
+    definitions
+}
+
+/// Convert a byte offset into a position, which counts UTF-16 code units from the line start.
+fn position_at(text: &str, offset: usize) -> Json {
+    let mut offset = usize::min(offset, text.len());
+    while !text.is_char_boundary(offset) { offset -= 1; }
+    let start = text[..offset].rfind('\n').map_or(0, |newline| newline + 1);
+    let line = text[..start].matches('\n').count();
+    let character = text[start..offset].encode_utf16().count();
+    Json::object([("line", line.into()), ("character", character.into())])
+}
+
+/// Convert a position into a byte offset.
+fn offset_at(text: &str, position: &Json) -> Option<usize> {
+    let line = position.get("line").as_usize()?;
+    let character = position.get("character").as_usize()?;
+    let start = match line {
+        0 => 0,
+        line => text.match_indices('\n').nth(line - 1)?.0 + 1,
+    };
+    let mut units = 0;
+    for (i, c) in text[start..].char_indices() {
+        if units >= character || c == '\n' { return Some(start + i); }
+        units += c.len_utf16();
+    }
+    Some(text.len())
+}
+
+fn uri_path(uri: &str) -> Option<PathBuf> {
+    let path = uri.strip_prefix("file://")?.as_bytes();
+    let mut bytes = Vec::with_capacity(path.len());
+    let mut i = 0;
+    while i < path.len() {
+        let escape = path.get(i + 1..i + 3).filter(|_| path[i] == b'%');
+        let escape = escape.and_then(|hex| u8::from_str_radix(str::from_utf8(hex).ok()?, 16).ok());
+        match escape {
+            Some(c) => { bytes.push(c); i += 3; }
+            None => { bytes.push(path[i]); i += 1; }
+        }
+    }
+    let path = String::from_utf8(bytes).ok()?;
+
+    // Windows paths look like file:///c:/path.
+    let path = match path.strip_prefix('/') {
+        Some(rest) if cfg!(windows) && rest.as_bytes().get(1) == Some(&b':') => rest,
+        _ => &path[..],
+    };
+    Some(PathBuf::from(path))
+}
+
+fn path_uri(path: &Path) -> String {
+    let path = path.to_string_lossy();
+    let mut uri = String::from("file://");
+    if !path.starts_with('/') { uri.push('/'); }
+    for c in path.bytes() {
+        match c {
+            b'\\' => uri.push('/'),
+            b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'/' | b':' | b'-' | b'_' | b'.' | b'~' => {
+                uri.push(c as char);
+            }
+            c => { let _ = write!(uri, "%{c:02X}"); }
+        }
+    }
+    uri
+}
+
+fn read_message<R: BufRead>(read: &mut R) -> io::Result<Option<Json>> {
+    let mut length = None;
+    let mut header = String::default();
+    loop {
+        header.clear();
+        if read.read_line(&mut header)? == 0 { return Ok(None); }
+        let header = header.trim_end();
+        if header.is_empty() { break; }
+        if let Some((key, value)) = header.split_once(':') &&
+            key.eq_ignore_ascii_case("content-length")
+        {
+            length = value.trim().parse().ok();
+        }
+    }
+    let length = length.ok_or_else(|| invalid_data("expected Content-Length header"))?;
+
+    let mut content = vec![0; length];
+    read.read_exact(&mut content)?;
+    let content = str::from_utf8(&content).ok().and_then(Json::parse);
+    let content = content.ok_or_else(|| invalid_data("invalid JSON message"))?;
+    Ok(Some(content))
+}
+
+fn write_message<W: Write>(write: &mut W, message: &Json) -> io::Result<()> {
+    let content = message.to_string();
+    write!(write, "Content-Length: {}\r\n\r\n{content}", content.len())?;
+    write.flush()
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Server, Json, read_message};
+
+    #[test]
+    fn script() {
+        let mut server = Server::default();
+        let mut write = Vec::default();
+        let messages = [
+            r#"{"jsonrpc":"2.0","id":1,"method":"initialize","params":{"rootUri":null}}"#,
+            r#"{"jsonrpc":"2.0","method":"initialized","params":{}}"#,
+            r#"{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":{
+                "uri":"file:///a.gml","languageId":"gml","version":1,
+                "text":"var a;\na = random_range(1, 2);\nb = ;"
+            }}}"#,
+            r#"{"jsonrpc":"2.0","id":2,"method":"textDocument/hover","params":{
+                "textDocument":{"uri":"file:///a.gml"},"position":{"line":1,"character":6}
+            }}"#,
+            r#"{"jsonrpc":"2.0","id":3,"method":"textDocument/completion","params":{
+                "textDocument":{"uri":"file:///a.gml"},"position":{"line":0,"character":0}
+            }}"#,
+        ];
+        for message in messages {
+            assert!(server.handle(&Json::parse(message).unwrap(), &mut write).unwrap());
+        }
+
+        let mut read = &write[..];
+        let mut responses = Vec::default();
+        while let Some(message) = read_message(&mut read).unwrap() {
+            responses.push(message);
+        }
+        let [initialize, diagnostics, hover, completion] = &responses[..] else { panic!() };
+
+        let capabilities = initialize.get("result").get("capabilities");
+        assert_eq!(capabilities.get("hoverProvider"), &Json::Bool(true));
+
+        let diagnostics = diagnostics.get("params").get("diagnostics").as_array();
+        let [diagnostic] = diagnostics else { panic!("{diagnostics:?}") };
+        let start = diagnostic.get("range").get("start");
+        assert_eq!(start.get("line").as_usize(), Some(2));
+        assert_eq!(start.get("character").as_usize(), Some(4));
+        let end = diagnostic.get("range").get("end");
+        assert_eq!(end.get("character").as_usize(), Some(5));
+
+        let hover = hover.get("result").get("contents").get("value").as_str().unwrap();
+        assert!(hover.ends_with("function taking 2 arguments"), "{hover}");
+
+        let completion = completion.get("result").as_array();
+        assert!(completion.iter().any(|item| item.get("label").as_str() == Some("hspeed")));
+    }
+}
@@ -2,21 +2,81 @@
 
 use std::{env, fs, io, mem};
 use std::error::Error;
-use std::ffi::OsStr;
+use std::ffi::{OsStr, OsString};
 use std::fs::File;
-use std::path::Path;
-use bstr::BStr;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use bstr::{BStr, ByteSlice};
+use gml::diagnostic::{Diagnostic, Emit, Severity, Text, Json};
+
+mod lsp;
 
 fn main() -> Result<(), Box<dyn Error>> {
     let mut path = None;
     let mut installed = Vec::default();
+    let mut frames = None;
+    let mut throttle = true;
+    let mut record = None;
+    let mut replay = None;
+    let mut report = false;
+    let mut explore = None;
+    let mut stages = None;
+    let mut cache = None;
+    let mut json = false;
 
     let mut args = env::args_os();
     args.next();
+    let mut args = args.peekable();
+    if args.next_if(|arg| arg == OsStr::new("extract")).is_some() {
+        return extract(args);
+    }
+    if args.next_if(|arg| arg == OsStr::new("lsp")).is_some() {
+        return lsp::run();
+    }
+    if args.next_if(|arg| arg == OsStr::new("fmt")).is_some() {
+        return format(args);
+    }
     while let Some(arg) = args.next() {
         if arg == OsStr::new("--extension") {
             let extension = args.next().ok_or("expected extension (.ged or .gex)")?;
             installed.push(extension);
+        } else if arg == OsStr::new("--frames") {
+            let count = args.next().ok_or("expected frame count")?;
+            let count = count.to_str().and_then(|count| count.parse::<u64>().ok());
+            frames = Some(count.ok_or("expected frame count")?);
+        } else if arg == OsStr::new("--unthrottled") {
+            throttle = false;
+        } else if arg == OsStr::new("--record") {
+            record = Some(args.next().ok_or("expected replay file")?);
+        } else if arg == OsStr::new("--replay") {
+            replay = Some(args.next().ok_or("expected replay file")?);
+        } else if arg == OsStr::new("--report") {
+            report = true;
+        } else if arg == OsStr::new("--explore") {
+            let function = args.next().ok_or("expected script or event")?;
+            explore = Some(function.into_string().map_err(|_| "expected script or event")?);
+        } else if arg == OsStr::new("--emit") {
+            let emit = args.next().ok_or("expected stages (ast, ssa, code or cfg)")?;
+            let emit = emit.to_str().ok_or("expected stages (ast, ssa, code or cfg)")?;
+            let stages = stages.get_or_insert(gml::explore::Stages::none());
+            for stage in emit.split(',') {
+                match stage {
+                    "ast" => stages.ast = true,
+                    "ssa" => stages.ssa = true,
+                    "code" => stages.code = true,
+                    "cfg" => stages.cfg = true,
+                    _ => Err("expected stages (ast, ssa, code or cfg)")?,
+                }
+            }
+        } else if arg == OsStr::new("--cache") {
+            cache = Some(PathBuf::from(args.next().ok_or("expected cache file")?));
+        } else if arg == OsStr::new("--error-format") {
+            let format = args.next().ok_or("expected error format (text or json)")?;
+            json = match format.to_str() {
+                Some("text") => false,
+                Some("json") => true,
+                _ => Err("expected error format (text or json)")?,
+            };
         } else if path.is_none() {
             path = Some(arg);
         } else {
@@ -25,26 +85,56 @@ fn main() -> Result<(), Box<dyn Error>> {
     }
     let (path, kind) = path.as_deref()
         .map(Path::new)
-        .and_then(|path| { let kind = path.extension()?; Some((path, kind)) })
-        .ok_or("expected project (.gmd or .gmk), executable (.exe), or script (.gml)")?;
+        .and_then(|path| {
+            // Projects stored as directories have no extension.
+            let kind = if path.is_dir() { OsStr::new("") } else { path.extension()? };
+            Some((path, kind))
+        })
+        .ok_or(concat!(
+            "expected project (directory, .gmd, .gm6, .gmk, .gm81 or .project.gmx), ",
+            "executable (.exe or data.win), or script (.gml)",
+        ))?;
 
     let arena = quickdry::Arena::default();
     let mut game = project::Game::default();
     let mut extensions = Vec::with_capacity(installed.len());
 
     let gml;
-    if kind == OsStr::new("gmk") {
+    let mut warnings = Vec::default();
+    if path.is_dir() {
+        project::read_dir(path, &mut game, &arena)?;
+    } else if ["gmd", "gm6", "gmk", "gm81"].iter().any(|&project| kind == OsStr::new(project)) {
         let read = fs::read(path)?;
         project::read_project(&read[..], &mut game, &arena)?;
+    } else if kind == OsStr::new("gmx") {
+        warnings = project::read_gmx(path, &mut game, &arena)?;
+    } else if kind == OsStr::new("win") {
+        let read = fs::read(path)?;
+        warnings = project::read_win(&read[..], &mut game, &arena)?;
     } else if kind == OsStr::new("exe") {
         let mut read = io::BufReader::new(File::open(path)?);
         project::read_exe(&mut read, &mut game, &mut extensions, &arena)?;
     } else if kind == OsStr::new("gml") {
         gml = fs::read(path)?;
         let mut room = project::Room::default();
+        room.name = BStr::new(b"room0");
         room.code = BStr::new(&gml[..]);
+        room.speed = 30;
         game.rooms.push(room);
         game.room_order.push(0);
+    } else {
+        Err("unrecognized project type")?;
+    }
+
+    // Importing from Studio is best effort, so report what was left out like any other warning.
+    for warning in warnings {
+        let message = warning.to_string();
+        let diagnostic = Diagnostic::new(Severity::Warning, "incomplete-import", message);
+        if json {
+            Json(io::stderr()).emit(diagnostic);
+        } else {
+            Text(io::stderr()).emit(diagnostic);
+        }
     }
 
     for path in installed {
@@ -64,7 +154,44 @@ fn main() -> Result<(), Box<dyn Error>> {
         }
     }
 
-    let (mut assets, debug) = match runner::build(&game, &extensions[..], &arena, io::stderr) {
+    // List what the runner is missing to run this game, rather than running it.
+    if report {
+        runner::report(&game, &extensions[..], io::stdout().lock())?;
+        return Ok(());
+    }
+
+    // Print how a single function is compiled, rather than running the game.
+    if let Some(function) = explore {
+        let stages = stages.unwrap_or_default();
+        let write = &mut io::stdout().lock();
+        let errors = if json {
+            runner::explore(&game, &extensions[..], &function, stages, Json(io::stderr()), write)?
+        } else {
+            runner::explore(&game, &extensions[..], &function, stages, Text(io::stderr()), write)?
+        };
+        if errors > 0 {
+            Err(format!("could not compile {} due to previous errors", function))?;
+        }
+        return Ok(());
+    }
+
+    // Errors go to stderr either way, but as JSON they are one object per line for other tools.
+    // A missing or outdated cache just means everything is compiled again.
+    let mut compiled = cache.as_ref().map(|path| {
+        let read = File::open(path).and_then(|file| {
+            gml::cache::Cache::read(&mut io::BufReader::new(file))
+        });
+        read.unwrap_or_default()
+    });
+    let built = if json {
+        runner::build(&game, &extensions[..], &arena, compiled.as_mut(), || Json(io::stderr()))
+    } else {
+        runner::build(&game, &extensions[..], &arena, compiled.as_mut(), || Text(io::stderr()))
+    };
+    if let (Some(path), Some(compiled)) = (cache, compiled) {
+        write_cache(&path, &compiled)?;
+    }
+    let (mut assets, debug) = match built {
         Ok(assets) => assets,
         Err(errors) => {
             let error = if errors > 1 {
@@ -79,7 +206,154 @@ fn main() -> Result<(), Box<dyn Error>> {
     mem::drop(arena);
 
     let world = runner::World::from_assets(&assets, debug);
-    runner::run(runner::Context { world, assets });
+    let replay = match replay {
+        Some(path) => Some(runner::Replay::read(&mut io::BufReader::new(File::open(path)?))?),
+        None => None,
+    };
+    // The windowed platform runs until the window is closed, at the display's refresh rate.
+    #[cfg(windows)]
+    let options = {
+        let _ = (frames, throttle);
+        runner::Options { record: record.is_some(), replay }
+    };
+    #[cfg(not(windows))]
+    let options = runner::Options { frames, throttle, record: record.is_some(), replay };
+    let recording = runner::run_with(runner::Context { world, assets }, options);
+    if let (Some(path), Some(recording)) = (record, recording) {
+        recording.write(&mut io::BufWriter::new(File::create(path)?))?;
+    }
+
+    Ok(())
+}
+
+/// Write the cache beside its final path and then move it into place, so an interrupted write
+/// never leaves a truncated cache behind.
+fn write_cache(path: &Path, cache: &gml::cache::Cache) -> io::Result<()> {
+    let mut temp = path.as_os_str().to_owned();
+    temp.push(".tmp");
+    let temp = PathBuf::from(temp);
+
+    let written = File::create(&temp).and_then(|file| {
+        let mut write = io::BufWriter::new(file);
+        cache.write(&mut write)?;
+        write.into_inner()?.sync_all()
+    });
+    if let Err(error) = written {
+        let _ = fs::remove_file(&temp);
+        return Err(error);
+    }
+    fs::rename(&temp, path)
+}
+
+/// Recover an editable project from a game executable, along with its extension files.
+///
+/// Included files are stored in the project itself. An output path with no extension is written
+/// as a project directory.
+fn extract(mut args: impl Iterator<Item = OsString>) -> Result<(), Box<dyn Error>> {
+    let path = args.next().ok_or("expected executable (.exe)")?;
+    let path = Path::new(path.as_os_str());
+    let output = args.next().map(PathBuf::from).unwrap_or_else(|| path.with_extension("gmk"));
+    if args.next().is_some() {
+        Err("expected a single executable and output project")?;
+    }
+
+    let arena = quickdry::Arena::default();
+    let mut game = project::Game::default();
+    let mut extensions = Vec::default();
+    let mut read = io::BufReader::new(File::open(path)?);
+    project::read_exe(&mut read, &mut game, &mut extensions, &arena)?;
+
+    if output.extension().is_none() {
+        project::write_dir(&output, &game)?;
+    } else {
+        let mut write = io::BufWriter::new(File::create(&output)?);
+        project::write_project(&mut write, &game)?;
+        write.flush()?;
+    }
+
+    // GM projects only refer to extensions by name, so write out the files each one installs.
+    // They go in a directory next to the project, with one subdirectory per extension.
+    let folder = output.with_extension("extensions");
+    for extension in &extensions[..] {
+        let dir = folder.join(file_name(extension.name)?);
+        fs::create_dir_all(&dir)?;
+        for file in &extension.files[..] {
+            fs::write(dir.join(file_name(file.file_name)?), file.contents)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Reformat scripts in place, or with `--check`, list the ones that would change.
+///
+/// Project directories have every script in them formatted.
+fn format(args: impl Iterator<Item = OsString>) -> Result<(), Box<dyn Error>> {
+    let mut options = gml::front::format::Options::default();
+    let mut check = false;
+    let mut paths = Vec::default();
+    for arg in args {
+        if arg == OsStr::new("--check") {
+            check = true;
+        } else if arg == OsStr::new("--keep-syntax") {
+            options.normalize = false;
+        } else if arg == OsStr::new("--keep-semicolons") {
+            options.semicolons = false;
+        } else {
+            paths.push(PathBuf::from(arg));
+        }
+    }
+    if paths.is_empty() {
+        Err("expected script (.gml) or project directory")?;
+    }
+
+    let mut files = Vec::default();
+    for path in paths {
+        if !path.is_dir() {
+            files.push(path);
+            continue;
+        }
+        let scripts = path.join("scripts");
+        if !scripts.is_dir() { continue; }
+        let mut scripts: Vec<_> = fs::read_dir(scripts)?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<Result<_, _>>()?;
+        scripts.retain(|path| path.extension() == Some(OsStr::new("gml")));
+        scripts.sort();
+        files.extend(scripts);
+    }
+
+    let mut errors = 0;
+    let mut changed = 0;
+    for file in files {
+        let code = fs::read(&file)?;
+        let script = file.file_stem().unwrap_or_default().to_string_lossy();
+        let script = gml::symbol::Symbol::intern(script.as_bytes());
+        let name = gml::FunctionDisplay::Script { script };
+        match gml::format_program(name, &code, &options, Text(io::stderr())) {
+            Ok(formatted) if formatted == code => {}
+            Ok(_) if check => {
+                println!("{}", file.display());
+                changed += 1;
+            }
+            Ok(formatted) => { fs::write(&file, formatted)?; }
+            Err(count) => { errors += count; }
+        }
+    }
+    if errors > 0 {
+        Err(format!("could not format due to {errors} errors"))?;
+    }
+    if changed > 0 {
+        let s = if changed == 1 { "" } else { "s" };
+        Err(format!("{changed} script{s} need formatting"))?;
+    }
 
     Ok(())
 }
+
+/// Convert a name from the game into a single path component.
+fn file_name(name: &BStr) -> Result<PathBuf, Box<dyn Error>> {
+    let name = name.to_str_lossy();
+    let name = Path::new(&*name).file_name().ok_or("invalid file name in executable")?;
+    Ok(PathBuf::from(name))
+}
@@ -1,26 +1,206 @@
-pub struct Draw;
+use crate::batch::Vertex;
+
+/// A software renderer, drawing into an RGBA framebuffer in memory.
+#[derive(Default)]
+pub struct Draw {
+    pub size: (u32, u32),
+    pub data: Vec<u8>,
+}
 
 pub fn load(cx: &mut crate::Context) {
     let crate::Context { world, .. } = cx;
     let crate::World { draw, .. } = world;
     let crate::draw::State { graphics, .. } = draw;
 
-    *graphics = Some(Draw);
+    *graphics = Some(Draw::default());
 }
 
 pub fn frame(cx: &mut crate::Context) {
-    let crate::Context { world, .. } = cx;
-    let crate::World { draw, .. } = world;
+    let crate::Context { world, assets } = cx;
+    let crate::World { room, draw, .. } = world;
+    let (room_width, room_height) = assets.rooms[room.room as usize].size;
     let crate::draw::State { graphics, .. } = draw;
-    let Draw = graphics.as_mut().unwrap();
+    let Draw { size, data } = graphics.as_mut().unwrap();
+
+    *size = (room_width, room_height);
+    data.clear();
+    data.resize(room_width as usize * room_height as usize * 4, 0);
+
+    let gray = 192;
+    for pixel in data.chunks_exact_mut(4) {
+        pixel.copy_from_slice(&[gray, gray, gray, 255]);
+    }
 }
 
 pub fn batch(cx: &mut crate::Context) {
-    let crate::Context { world, .. } = cx;
+    let crate::Context { world, assets, .. } = cx;
     let crate::World { draw, .. } = world;
-    let crate::draw::State { graphics, .. } = draw;
-    let Draw = graphics.as_mut().unwrap();
+    let crate::draw::State { graphics, batch, .. } = draw;
+    let target = graphics.as_mut().unwrap();
+    if batch.index.is_empty() {
+        return;
+    }
+
+    let atlas = &assets.textures[batch.texture as usize];
+    for triangle in batch.index.chunks_exact(3) {
+        let a = &batch.vertex[triangle[0] as usize];
+        let b = &batch.vertex[triangle[1] as usize];
+        let c = &batch.vertex[triangle[2] as usize];
+        draw_triangle(target, atlas, a, b, c);
+    }
 }
 
 pub fn present(_cx: &mut crate::Context) {
 }
+
+fn draw_triangle(target: &mut Draw, atlas: &atlas::Texture, a: &Vertex, b: &Vertex, c: &Vertex) {
+    let (width, height) = target.size;
+
+    // GM uses D3D8, which samples the viewport at the upper-left corners of pixels. The other
+    // backends emulate this by shifting clip space by half a pixel, which here is the same as
+    // sampling each pixel at its integer coordinates rather than at its center.
+    let [ax, ay, _] = a.position;
+    let [bx, by, _] = b.position;
+    let [cx, cy, _] = c.position;
+
+    let area = edge(ax, ay, bx, by, cx, cy);
+    if area == 0.0 {
+        return;
+    }
+
+    let x0 = f32::max(f32::ceil(f32::min(ax, f32::min(bx, cx))), 0.0) as u32;
+    let y0 = f32::max(f32::ceil(f32::min(ay, f32::min(by, cy))), 0.0) as u32;
+    let x1 = f32::min(f32::ceil(f32::max(ax, f32::max(bx, cx))), width as f32) as u32;
+    let y1 = f32::min(f32::ceil(f32::max(ay, f32::max(by, cy))), height as f32) as u32;
+
+    // Like WebGL2, do not cull back faces, but keep a consistent top-left fill rule.
+    let sign = f32::signum(area);
+    let bias = [
+        top_left(sign, bx, by, cx, cy),
+        top_left(sign, cx, cy, ax, ay),
+        top_left(sign, ax, ay, bx, by),
+    ];
+
+    for y in y0..y1 {
+        for x in x0..x1 {
+            let (px, py) = (x as f32, y as f32);
+            let w = [
+                sign * edge(bx, by, cx, cy, px, py),
+                sign * edge(cx, cy, ax, ay, px, py),
+                sign * edge(ax, ay, bx, by, px, py),
+            ];
+            if !(0..3).all(|i| w[i] > 0.0 || w[i] == 0.0 && bias[i]) {
+                continue;
+            }
+
+            let total = w[0] + w[1] + w[2];
+            let (wa, wb, wc) = (w[0] / total, w[1] / total, w[2] / total);
+            let u = wa * a.uv[0] + wb * b.uv[0] + wc * c.uv[0];
+            let v = wa * a.uv[1] + wb * b.uv[1] + wc * c.uv[1];
+
+            // The image rectangle is not interpolated; use the first vertex.
+            let src = sample(atlas, a.image, u, v);
+            let i = (y as usize * width as usize + x as usize) * 4;
+            blend(&mut target.data[i..i + 4], src);
+        }
+    }
+}
+
+/// Twice the signed area of the triangle (a, b, p).
+fn edge(ax: f32, ay: f32, bx: f32, by: f32, px: f32, py: f32) -> f32 {
+    (bx - ax) * (py - ay) - (by - ay) * (px - ax)
+}
+
+/// Whether pixels exactly on the edge from a to b belong to this triangle.
+fn top_left(sign: f32, ax: f32, ay: f32, bx: f32, by: f32) -> bool {
+    let (dx, dy) = (sign * (bx - ax), sign * (by - ay));
+    dy < 0.0 || dy == 0.0 && dx > 0.0
+}
+
+/// Sample the atlas at a point within an image, with clamping and nearest filtering.
+fn sample(atlas: &atlas::Texture, image: [f32; 4], u: f32, v: f32) -> [f32; 4] {
+    let (width, height) = atlas.size;
+    let [x, y, w, h] = image;
+
+    // Emulate clamped texture sampling within the atlas texture.
+    let s = f32::clamp(u * w, 0.5, f32::max(w - 0.5, 0.5));
+    let t = f32::clamp(v * h, 0.5, f32::max(h - 0.5, 0.5));
+    let s = f32::clamp(f32::floor(x + s), 0.0, width as f32 - 1.0) as usize;
+    let t = f32::clamp(f32::floor(y + t), 0.0, height as f32 - 1.0) as usize;
+
+    let i = (t * width as usize + s) * 4;
+    let [b, g, r, a] = [atlas.data[i], atlas.data[i + 1], atlas.data[i + 2], atlas.data[i + 3]];
+    [r, g, b, a].map(|c| c as f32 / 255.0)
+}
+
+/// Blend a source color over a destination pixel, as with `SRC_ALPHA, INV_SRC_ALPHA`.
+fn blend(dst: &mut [u8], src: [f32; 4]) {
+    let alpha = src[3];
+    for (i, dst) in dst.iter_mut().enumerate() {
+        let value = src[i] * alpha + *dst as f32 / 255.0 * (1.0 - alpha);
+        *dst = f32::round(f32::clamp(value, 0.0, 1.0) * 255.0) as u8;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::batch::{Batch, Rect};
+    use super::{Draw, draw_triangle};
+
+    fn texture() -> atlas::Texture {
+        // A 2x1 atlas: one opaque red texel and one transparent texel, in BGRA.
+        atlas::Texture { size: (2, 1), data: vec![0, 0, 255, 255, 0, 0, 0, 0] }
+    }
+
+    fn target(width: u32, height: u32) -> Draw {
+        let data = vec![0; width as usize * height as usize * 4];
+        Draw { size: (width, height), data }
+    }
+
+    fn draw(target: &mut Draw, batch: &Batch) {
+        let atlas = texture();
+        for triangle in batch.index.chunks_exact(3) {
+            let [a, b, c] = [0, 1, 2].map(|i| &batch.vertex[triangle[i] as usize]);
+            draw_triangle(target, &atlas, a, b, c);
+        }
+    }
+
+    #[test]
+    fn quad_covers_pixels() {
+        let mut target = target(4, 4);
+        let mut batch = Batch::default();
+        batch.quad(
+            Rect { x: 1.0, y: 1.0, w: 2.0, h: 2.0 },
+            Rect { x: 0.0, y: 0.0, w: 1.0, h: 1.0 },
+            Rect { x: 0.0, y: 0.0, w: 1.0, h: 1.0 },
+        );
+        draw(&mut target, &batch);
+
+        for y in 0..4 {
+            for x in 0..4 {
+                let i = (y * 4 + x) * 4;
+                let expected: &[u8] = if (1..3).contains(&x) && (1..3).contains(&y) {
+                    &[255, 0, 0, 255]
+                } else {
+                    &[0, 0, 0, 0]
+                };
+                assert_eq!(&target.data[i..i + 4], expected, "pixel ({x}, {y})");
+            }
+        }
+    }
+
+    #[test]
+    fn transparent_texels_blend() {
+        let mut target = target(1, 1);
+        target.data.copy_from_slice(&[0, 255, 0, 255]);
+        let mut batch = Batch::default();
+        batch.quad(
+            Rect { x: -0.5, y: -0.5, w: 1.0, h: 1.0 },
+            Rect { x: 0.0, y: 0.0, w: 1.0, h: 1.0 },
+            Rect { x: 1.0, y: 0.0, w: 1.0, h: 1.0 },
+        );
+        draw(&mut target, &batch);
+
+        assert_eq!(&target.data[..], &[0, 255, 0, 255]);
+    }
+}
@@ -1,21 +1,28 @@
-use std::{io, iter};
+use std::{io, iter, error};
 use std::ops::Range;
 use std::collections::HashMap;
 use quickdry::Arena;
 use gml::vm;
+use gml::diagnostic::{Diagnostic, Emit, Severity};
 use gml::symbol::Symbol;
 
 pub use crate::world::*;
 pub use crate::batch::Batch;
+pub use crate::replay::{Replay, Frame};
 
 #[cfg(target_arch = "wasm32")]
 pub use crate::platform::State;
 pub use crate::platform::run;
+#[cfg(not(target_arch = "wasm32"))]
+pub use crate::platform::{Options, run_with};
+#[cfg(not(any(target_arch = "wasm32", windows)))]
+pub use crate::platform::State;
 #[cfg(target_arch = "wasm32")]
 pub use crate::platform::end;
 
 mod world;
 mod batch;
+mod replay;
 
 #[cfg_attr(target_arch = "wasm32", path = "platform/web.rs")]
 #[cfg_attr(windows, path = "platform/win32.rs")]
@@ -99,23 +106,49 @@ pub struct Instance {
 }
 
 /// Build a Game Maker project.
-pub fn build<F: Clone + FnMut() -> E, E: io::Write>(
-    game: &project::Game<'_>, extensions: &[project::Extension<'_>], arena: &Arena, mut errors: F
+///
+/// With a `cache`, functions whose source has not changed since it was last used are not
+/// recompiled.
+pub fn build<F: Clone + FnMut() -> E, E: Emit>(
+    game: &project::Game<'_>, extensions: &[project::Extension<'_>], arena: &Arena,
+    cache: Option<&mut gml::cache::Cache>, mut errors: F
 ) -> Result<(Assets, vm::Debug), u32> {
     let mut assets = Assets::default();
     let debug;
 
+    // Some resources are looked up by index below and at run time, so reject any that are
+    // missing. Other problems are likely mistakes, but do not stop the game from running.
+    let mut count = 0;
+    for problem in project::validate(game) {
+        let (severity, code) = match problem {
+            project::Problem::Dangling { indexed, .. } => {
+                let severity = if indexed { Severity::Error } else { Severity::Warning };
+                (severity, "dangling-reference")
+            }
+            project::Problem::Duplicate { .. } => (Severity::Warning, "duplicate-name"),
+            project::Problem::Unused { .. } => (Severity::Warning, "unused-resource"),
+        };
+        errors().emit(Diagnostic::new(severity, code, problem.to_string()));
+        if problem.is_error() { count += 1; }
+    }
+    if count > 0 {
+        return Err(count);
+    }
+
     let mut items = HashMap::default();
     World::register(&mut items);
-    (assets.code, debug) = gml::build(game, extensions, &items, errors.clone())?;
+    (assets.code, debug) = match cache {
+        Some(cache) => gml::build_cached(game, extensions, &items, cache, errors.clone())?,
+        None => gml::build(game, extensions, &items, errors.clone())?,
+    };
 
     let mut builder = atlas::Builder::default();
     for sprite @ &project::Sprite { origin, .. } in &game.sprites[..] {
         let start = builder.len();
         for &project::Image { size, data } in &sprite.images[..] {
             let data = match sprite.version {
-                400 => { build_bmp(data, sprite.transparent, arena, errors())? }
-                800 => { data }
+                400 | 542 => { build_bmp(data, sprite.transparent, arena, errors())? }
+                800 | 810 => { data }
                 _ => unreachable!()
             };
             builder.insert(size, data);
@@ -129,7 +162,7 @@ pub fn build<F: Clone + FnMut() -> E, E: io::Write>(
         let (width, height) = size;
         if !name.is_empty() && width * height > 0 {
             let data = match background.version {
-                400 => { build_bmp(data, background.transparent, arena, errors())? }
+                400 | 543 => { build_bmp(data, background.transparent, arena, errors())? }
                 710 => { data }
                 _ => unreachable!()
             };
@@ -181,19 +214,57 @@ pub fn build<F: Clone + FnMut() -> E, E: io::Write>(
     Ok((assets, debug))
 }
 
-fn build_bmp<'a, E: io::Write>(
+/// Write the functions and builtin variables used by `game` that the runner does not implement,
+/// with the number and location of their uses.
+pub fn report<W: io::Write>(
+    game: &project::Game<'_>, extensions: &[project::Extension<'_>], mut write: W
+) -> io::Result<()> {
+    let mut items = HashMap::default();
+    World::register(&mut items);
+
+    let missing = gml::report::missing(game, extensions, &items);
+    for gml::report::Missing { kind, name, uses } in &missing[..] {
+        let s = if uses.len() == 1 { "" } else { "s" };
+        writeln!(write, "{kind} {name}: {} use{s}", uses.len())?;
+        for location in uses {
+            writeln!(write, "  {location}")?;
+        }
+    }
+    let functions = missing.iter().filter(|missing| missing.kind == gml::report::Kind::Function);
+    let functions = functions.count();
+    let variables = missing.len() - functions;
+    writeln!(write, "{functions} missing functions, {variables} missing variables")
+}
+
+/// Write the chosen stages of compiling a single script or event in `game`, to debug the compiler.
+///
+/// Returns the number of errors, which stop the function from being compiled further.
+pub fn explore<E: Emit>(
+    game: &project::Game<'_>, extensions: &[project::Extension<'_>], function: &str,
+    stages: gml::explore::Stages, errors: E, write: &mut dyn io::Write
+) -> io::Result<u32> {
+    let mut items = HashMap::default();
+    World::register(&mut items);
+
+    gml::explore::explore(game, extensions, &items, function, stages, errors, write)
+}
+
+fn build_bmp<'a, E: Emit>(
     data: &[u8], transparent: bool, arena: &'a Arena, mut errors: E
 ) -> Result<&'a [u8], u32> {
     match project::read_bmp(&mut { data }, transparent, arena) {
         Ok(data) => { Ok(data) }
         Err(err) => {
-            let _ = writeln!(errors, "error reading sprite: {err}");
+            let message = format!("could not read image: {err}");
+            errors.emit(Diagnostic::new(Severity::Error, "image", message));
             Err(1)
         }
     }
 }
 
-pub fn load(assets: &mut Assets, extensions: &[project::Extension<'_>]) -> io::Result<()> {
+pub fn load(
+    assets: &mut Assets, extensions: &[project::Extension<'_>]
+) -> Result<(), Box<dyn error::Error>> {
     let mut items = HashMap::default();
     World::register(&mut items);
     gml::load(&mut assets.code, &items);
@@ -202,23 +273,23 @@ pub fn load(assets: &mut Assets, extensions: &[project::Extension<'_>]) -> io::R
         for file in &extension.files[..] {
             if file.kind == project::extension_kind::DLL {
                 let file_name = Symbol::intern(file.file_name);
-                std::fs::write(std::str::from_utf8(&file_name[..]).unwrap(), file.contents)?;
-                let dll = platform::Library::load(file_name).unwrap();
+                std::fs::write(std::str::from_utf8(&file_name[..])?, file.contents)?;
+                let dll = platform::Library::load(file_name).ok_or(external::Error::Load)?;
 
                 for function in &file.functions[..] {
                     let name = Symbol::intern(function.name);
                     let external_name = Symbol::intern(function.external_name);
-                    let proc = dll.symbol(external_name.as_cstr()).unwrap();
+                    let proc = dll.symbol(external_name.as_cstr()).ok_or(external::Error::Symbol)?;
 
                     let calltype = match function.calling_convention {
                         project::calling_convention::CDECL => { vm::dll::Cc::Cdecl }
                         project::calling_convention::STDCALL => { vm::dll::Cc::Stdcall }
-                        _ => panic!()
+                        _ => { return Err(external::Error::CallingConvention)?; }
                     };
                     let restype = match function.result {
                         project::parameter_type::REAL => { vm::dll::Type::Real }
                         project::parameter_type::STRING => { vm::dll::Type::String }
-                        _ => panic!(),
+                        _ => { return Err(external::Error::Type)?; }
                     };
 
                     let mut types = [vm::dll::Type::Real; 16];
@@ -227,11 +298,11 @@ pub fn load(assets: &mut Assets, extensions: &[project::Extension<'_>]) -> io::R
                         *ty = match argtype {
                             project::parameter_type::REAL => { vm::dll::Type::Real }
                             project::parameter_type::STRING => { vm::dll::Type::String }
-                            _ => panic!()
+                            _ => { return Err(external::Error::Type)?; }
                         }
                     }
 
-                    let thunk = vm::dll::thunk(calltype, restype, &types[..argtypes.len()]).unwrap();
+                    let thunk = vm::dll::thunk(calltype, restype, &types[..argtypes.len()])?;
 
                     assets.code.dll.insert(name, (proc, thunk));
                 }
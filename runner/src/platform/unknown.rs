@@ -1,41 +1,228 @@
+use std::time::{Duration, Instant};
 use std::ffi::c_char;
 use gml::symbol::Symbol;
 use gml::vm;
+use gml::diagnostic::{Diagnostic, Severity};
+use crate::input::Event;
+use crate::replay::{Replay, Frame};
 
 #[derive(Default)]
 pub struct Draw;
 
-pub fn run(mut cx: crate::Context) {
-    let mut thread = vm::Thread::default();
+/// Options for the headless frame loop.
+pub struct Options {
+    /// Stop after this many frames, even if the game has not called `game_end`.
+    pub frames: Option<u64>,
+    /// Wait between frames to match the room speed, rather than running as fast as possible.
+    pub throttle: bool,
+    /// Record input, to be returned when the game ends.
+    pub record: bool,
+    /// Feed input from a recording, stopping when it runs out.
+    pub replay: Option<Replay>,
+}
+
+impl Default for Options {
+    fn default() -> Options {
+        Options { frames: None, throttle: true, record: false, replay: None }
+    }
+}
+
+/// A game running without a window, driven one frame at a time.
+pub struct State {
+    pub cx: crate::Context,
+    thread: vm::Thread,
+    seed: i32,
+    recording: Option<Replay>,
+}
 
-    if let Err(error) = gml::vm::World::load(&mut cx, &mut thread) {
-        let crate::World { debug, .. } = &cx.world;
-        debug.show_vm_error(&*error);
+impl State {
+    /// Load the game and its first room.
+    pub fn new(mut cx: crate::Context) -> State {
+        let mut thread = vm::Thread::default();
+        let seed = cx.world.real.random_get_seed();
+
+        if let Err(error) = gml::vm::World::load(&mut cx, &mut thread) {
+            let crate::World { debug, .. } = &cx.world;
+            debug.show_vm_error(&*error);
+        }
+
+        crate::graphics::load(&mut cx);
+
+        let room = cx.assets.room_order[0] as i32;
+        if let Err(error) = crate::room::State::load_room(&mut cx, &mut thread, room) {
+            let crate::World { debug, .. } = &cx.world;
+            debug.show_vm_error(&*error);
+        }
+
+        State { cx, thread, seed, recording: None }
     }
 
-    crate::graphics::load(&mut cx);
+    /// Start recording input, along with the random seed the game started with.
+    pub fn record(&mut self) {
+        self.recording = Some(Replay { seed: self.seed, frames: Vec::default() });
+    }
 
-    let room = cx.assets.room_order[0] as i32;
-    if let Err(error) = crate::room::State::load_room(&mut cx, &mut thread, room) {
-        let crate::World { debug, .. } = &cx.world;
-        debug.show_vm_error(&*error);
+    /// Stop recording input and return the recording so far.
+    pub fn finish_recording(&mut self) -> Option<Replay> {
+        self.recording.take()
     }
 
-    if let Err(error) = crate::draw::State::draw(&mut cx, &mut thread) {
-        let crate::World { debug, .. } = &cx.world;
-        debug.show_vm_error(&*error);
+    pub fn draw(&mut self) {
+        let State { cx, thread, .. } = self;
+        if let Err(error) = crate::draw::State::draw(cx, thread) {
+            let crate::World { debug, .. } = &cx.world;
+            debug.show_vm_error(&*error);
+        }
+        crate::draw::State::animate(cx);
     }
-    crate::draw::State::animate(&mut cx);
 
-    if let Err(error) = crate::instance::State::step(&mut cx, &mut thread) {
-        let crate::World { debug, .. } = &cx.world;
-        debug.show_vm_error(&*error);
+    pub fn step(&mut self) {
+        self.step_with(&[]);
+    }
+
+    /// Deliver a frame's worth of input and step the game.
+    pub fn step_with(&mut self, events: &[Event]) {
+        let State { cx, thread, recording, .. } = self;
+        let crate::World { room, input, .. } = &mut cx.world;
+        input.begin_frame();
+        for &event in events {
+            input.apply(event);
+        }
+        if let Some(Replay { frames, .. }) = recording {
+            frames.push(Frame { room: room.room, events: input.events.clone() });
+        }
+
+        if let Err(error) = crate::instance::State::step(cx, thread) {
+            let crate::World { debug, .. } = &cx.world;
+            debug.show_vm_error(&*error);
+        }
+        crate::motion::State::simulate(cx);
+    }
+
+    /// Step the game with a recorded frame, reporting whether it is still in sync.
+    pub fn replay_step(&mut self, frame: &Frame) -> bool {
+        let in_sync = self.cx.world.room.room == frame.room;
+        self.step_with(&frame.events[..]);
+        in_sync
+    }
+
+    /// Whether the game has called `game_end`.
+    pub fn ended(&self) -> bool { self.cx.world.control.ended }
+
+    /// The size and RGBA pixels of the most recently drawn frame.
+    pub fn framebuffer(&self) -> ((u32, u32), &[u8]) {
+        let crate::World { draw, .. } = &self.cx.world;
+        let crate::graphics::Draw { size, data } = draw.graphics.as_ref().unwrap();
+        (*size, &data[..])
+    }
+}
+
+pub fn run(cx: crate::Context) {
+    run_with(cx, Options::default());
+}
+
+pub fn run_with(mut cx: crate::Context, options: Options) -> Option<Replay> {
+    if let Some(Replay { seed, .. }) = options.replay {
+        cx.world.real.random_set_seed(seed);
+    }
+
+    let mut state = State::new(cx);
+    if options.record {
+        state.record();
+    }
+
+    let mut target = Instant::now();
+    let mut frame = 0;
+    loop {
+        state.draw();
+
+        frame += 1;
+        if state.ended() { break; }
+        if options.frames.is_some_and(|frames| frame >= frames) { break; }
+
+        if options.throttle {
+            let crate::Context { world, assets } = &state.cx;
+            let crate::World { room, .. } = world;
+            let speed = u32::max(assets.rooms[room.room as usize].speed, 1);
+            target += Duration::from_secs(1) / speed;
+
+            // Catch up rather than trying to make up for lost frames.
+            let now = Instant::now();
+            if target > now {
+                std::thread::sleep(target - now);
+            } else {
+                target = now;
+            }
+        }
+
+        match options.replay {
+            Some(Replay { ref frames, .. }) => {
+                let Some(next) = frames.get(frame as usize - 1) else { break };
+                if !state.replay_step(next) {
+                    let message = format!("replay out of sync at frame {frame}");
+                    let diagnostic = Diagnostic::new(Severity::Warning, "replay-desync", message);
+                    state.cx.world.debug.show_diagnostic(diagnostic);
+                }
+            }
+            None => state.step(),
+        }
+        if state.ended() { break; }
+    }
+
+    state.finish_recording()
+}
+
+#[cfg(unix)]
+pub struct Library(*mut libc::c_void);
+
+#[cfg(unix)]
+impl Library {
+    pub fn load(dll: Symbol) -> Option<Library> {
+        // Games refer to libraries by their Windows names. Fall back to a native port of the
+        // library with the same name.
+        let name = &dll[..];
+        let mut names = vec![name.to_vec()];
+        if let Some(len) = name.len().checked_sub(4) && name[len..].eq_ignore_ascii_case(b".dll") {
+            names.push([&name[..len], b".so"].concat());
+        }
+
+        // Like LoadLibrary, look in the working directory before the system search path.
+        let mut paths = Vec::default();
+        for name in names {
+            if !name.contains(&b'/') {
+                paths.push([b"./", &name[..]].concat());
+            }
+            paths.push(name);
+        }
+
+        paths.into_iter().find_map(|mut path| {
+            path.push(b'\0');
+            let dll = unsafe { libc::dlopen(path.as_ptr() as *const c_char, libc::RTLD_NOW) };
+            if dll.is_null() { return None; }
+            Some(Library(dll))
+        })
+    }
+
+    pub fn symbol(&self, sym: *const c_char) -> Option<vm::Proc> {
+        let Library(dll) = *self;
+        let sym = unsafe { libc::dlsym(dll, sym) };
+        if sym.is_null() { return None; }
+        Some(sym as vm::Proc)
+    }
+}
+
+#[cfg(unix)]
+impl Drop for Library {
+    fn drop(&mut self) {
+        let Library(dll) = *self;
+        unsafe { libc::dlclose(dll) };
     }
-    crate::motion::State::simulate(&mut cx);
 }
 
+#[cfg(not(unix))]
 pub struct Library;
 
+#[cfg(not(unix))]
 impl Library {
     pub fn load(_dll: Symbol) -> Option<Library> { None }
 
@@ -14,7 +14,10 @@ use winapi::ctypes::c_char;
 
 use gml::vm;
 use gml::symbol::Symbol;
+use gml::diagnostic::{Diagnostic, Severity};
 use crate::Context;
+use crate::input::Event;
+use crate::replay::{Replay, Frame};
 
 pub struct Draw {
     pub hwnd: HWND,
@@ -28,12 +31,32 @@ impl Default for Draw {
     }
 }
 
+/// Options for the windowed frame loop.
+#[derive(Default)]
+pub struct Options {
+    /// Record input, to be returned when the window is closed.
+    pub record: bool,
+    /// Feed input from a recording in place of live input, stopping when it runs out.
+    pub replay: Option<Replay>,
+}
+
+/// Input received by the window procedure, waiting to be delivered at the next step.
+struct Input {
+    events: Vec<Event>,
+    /// The number of window pixels per room pixel.
+    scale: f32,
+}
+
 unsafe extern "C" {
     #[allow(improper_ctypes)]
     static __ImageBase: ();
 }
 
-pub fn run(mut cx: Context) { unsafe {
+pub fn run(cx: Context) {
+    run_with(cx, Options::default());
+}
+
+pub fn run_with(mut cx: Context, options: Options) -> Option<Replay> { unsafe {
     let hInstance = &__ImageBase as *const _ as HINSTANCE;
     let nCmdShow = SW_SHOW;
 
@@ -80,7 +103,16 @@ pub fn run(mut cx: Context) { unsafe {
         panic!("failed to create window");
     }
 
+    // The window procedure only sees the window, so give it somewhere to put input.
+    let pending = Box::into_raw(Box::new(Input { events: Vec::default(), scale }));
+    SetWindowLongPtrW(hwnd, GWLP_USERDATA, pending as _);
+
     let mut thread = vm::Thread::default();
+    if let Some(Replay { seed, .. }) = options.replay {
+        cx.world.real.random_set_seed(seed);
+    }
+    let seed = cx.world.real.random_get_seed();
+    let mut recording = options.record.then(|| Replay { seed, frames: Vec::default() });
 
     if let Err(error) = gml::vm::World::load(&mut cx, &mut thread) {
         let crate::World { debug, .. } = &cx.world;
@@ -102,6 +134,7 @@ pub fn run(mut cx: Context) { unsafe {
         debug.show_vm_error(&*error);
     }
 
+    let mut frame = 0;
     'main: loop {
         if let Err(error) = crate::draw::State::draw(&mut cx, &mut thread) {
             let crate::World { debug, .. } = &cx.world;
@@ -115,12 +148,37 @@ pub fn run(mut cx: Context) { unsafe {
             DispatchMessageW(&msg);
         }
 
+        frame += 1;
+        let mut events = mem::take(&mut (*pending).events);
+        let crate::World { room, input, debug, .. } = &mut cx.world;
+        // Live input is still drained from the window, but ignored while playing back.
+        if let Some(Replay { ref frames, .. }) = options.replay {
+            let Some(next) = frames.get(frame - 1) else { break };
+            if room.room != next.room {
+                let message = format!("replay out of sync at frame {frame}");
+                debug.show_diagnostic(Diagnostic::new(Severity::Warning, "replay-desync", message));
+            }
+            events.clone_from(&next.events);
+        }
+
+        input.begin_frame();
+        for event in events {
+            input.apply(event);
+        }
+        if let Some(Replay { frames, .. }) = &mut recording {
+            frames.push(Frame { room: room.room, events: input.events.clone() });
+        }
+
         if let Err(error) = crate::instance::State::step(&mut cx, &mut thread) {
             let crate::World { debug, .. } = &cx.world;
             debug.show_vm_error(&*error);
         }
         crate::motion::State::simulate(&mut cx);
     }
+
+    SetWindowLongPtrW(hwnd, GWLP_USERDATA, 0);
+    drop(Box::from_raw(pending));
+    recording
 } }
 
 unsafe extern "system" fn WindowProc(
@@ -138,12 +196,44 @@ unsafe extern "system" fn WindowProc(
             );
             0
         }
-        WM_KEYDOWN => { 0 }
-        WM_KEYUP => { 0 }
+        WM_KEYDOWN => {
+            // Auto-repeat would otherwise be delivered, and recorded, as extra presses.
+            if lParam & (1 << 30) == 0 {
+                queue_input(hwnd, Event::KeyDown(wParam as u8));
+            }
+            0
+        }
+        WM_KEYUP => { queue_input(hwnd, Event::KeyUp(wParam as u8)); 0 }
+        WM_MOUSEMOVE => {
+            let x = (lParam & 0xffff) as i16 as i32;
+            let y = ((lParam >> 16) & 0xffff) as i16 as i32;
+            queue_input(hwnd, Event::MouseMove(x, y));
+            0
+        }
+        WM_LBUTTONDOWN => { queue_input(hwnd, Event::ButtonDown(1)); 0 }
+        WM_LBUTTONUP => { queue_input(hwnd, Event::ButtonUp(1)); 0 }
+        WM_RBUTTONDOWN => { queue_input(hwnd, Event::ButtonDown(2)); 0 }
+        WM_RBUTTONUP => { queue_input(hwnd, Event::ButtonUp(2)); 0 }
+        WM_MBUTTONDOWN => { queue_input(hwnd, Event::ButtonDown(3)); 0 }
+        WM_MBUTTONUP => { queue_input(hwnd, Event::ButtonUp(3)); 0 }
         _ => DefWindowProcW(hwnd, uMsg, wParam, lParam),
     }
 } }
 
+/// Hold an input event for the next step, converting mouse positions to room pixels.
+unsafe fn queue_input(hwnd: HWND, event: Event) { unsafe {
+    let input = GetWindowLongPtrW(hwnd, GWLP_USERDATA) as *mut Input;
+    let Some(input) = input.as_mut() else { return };
+    let event = match event {
+        Event::MouseMove(x, y) => {
+            let (x, y) = (x as f32 / input.scale, y as f32 / input.scale);
+            Event::MouseMove(x as i32, y as i32)
+        }
+        event => event,
+    };
+    input.events.push(event);
+} }
+
 pub struct Library(HMODULE);
 
 impl Library {
@@ -0,0 +1,152 @@
+use std::io::{self, Read, Write};
+use crate::input::Event;
+
+/// A recording of everything needed to play a game back deterministically.
+#[derive(Default, PartialEq, Debug)]
+pub struct Replay {
+    /// The random seed at the start of the game.
+    pub seed: i32,
+    pub frames: Vec<Frame>,
+}
+
+/// The input delivered before one step.
+#[derive(Default, PartialEq, Debug)]
+pub struct Frame {
+    /// The room the step ran in, used to detect when playback has diverged.
+    pub room: i32,
+    pub events: Vec<Event>,
+}
+
+const MAGIC: &[u8; 4] = b"DJVR";
+const VERSION: u32 = 1;
+
+const KEY_DOWN: u8 = 0;
+const KEY_UP: u8 = 1;
+const MOUSE_MOVE: u8 = 2;
+const BUTTON_DOWN: u8 = 3;
+const BUTTON_UP: u8 = 4;
+
+impl Replay {
+    pub fn read<R: Read>(read: &mut R) -> io::Result<Replay> {
+        let mut magic = [0; 4];
+        read.read_exact(&mut magic)?;
+        if &magic != MAGIC || read_u32(read)? != VERSION {
+            return Err(io::Error::from(io::ErrorKind::InvalidData));
+        }
+
+        let seed = read_u32(read)? as i32;
+        let len = read_u32(read)? as usize;
+        let mut frames = Vec::default();
+        for _ in 0..len {
+            let room = read_u32(read)? as i32;
+            let len = read_u32(read)? as usize;
+            let mut events = Vec::default();
+            for _ in 0..len {
+                let mut kind = [0; 1];
+                read.read_exact(&mut kind)?;
+                let event = match kind[0] {
+                    KEY_DOWN => Event::KeyDown(read_u8(read)?),
+                    KEY_UP => Event::KeyUp(read_u8(read)?),
+                    MOUSE_MOVE => Event::MouseMove(read_u32(read)? as i32, read_u32(read)? as i32),
+                    BUTTON_DOWN => Event::ButtonDown(read_u8(read)?),
+                    BUTTON_UP => Event::ButtonUp(read_u8(read)?),
+                    _ => return Err(io::Error::from(io::ErrorKind::InvalidData)),
+                };
+                events.push(event);
+            }
+            frames.push(Frame { room, events });
+        }
+
+        Ok(Replay { seed, frames })
+    }
+
+    pub fn write<W: Write>(&self, write: &mut W) -> io::Result<()> {
+        write.write_all(MAGIC)?;
+        write.write_all(&VERSION.to_le_bytes())?;
+
+        write.write_all(&self.seed.to_le_bytes())?;
+        write.write_all(&(self.frames.len() as u32).to_le_bytes())?;
+        for &Frame { room, ref events } in &self.frames[..] {
+            write.write_all(&room.to_le_bytes())?;
+            write.write_all(&(events.len() as u32).to_le_bytes())?;
+            for &event in &events[..] {
+                match event {
+                    Event::KeyDown(key) => write.write_all(&[KEY_DOWN, key])?,
+                    Event::KeyUp(key) => write.write_all(&[KEY_UP, key])?,
+                    Event::MouseMove(x, y) => {
+                        write.write_all(&[MOUSE_MOVE])?;
+                        write.write_all(&x.to_le_bytes())?;
+                        write.write_all(&y.to_le_bytes())?;
+                    }
+                    Event::ButtonDown(button) => write.write_all(&[BUTTON_DOWN, button])?,
+                    Event::ButtonUp(button) => write.write_all(&[BUTTON_UP, button])?,
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn read_u8<R: Read>(read: &mut R) -> io::Result<u8> {
+    let mut buf = [0; 1];
+    read.read_exact(&mut buf)?;
+    Ok(buf[0])
+}
+
+fn read_u32<R: Read>(read: &mut R) -> io::Result<u32> {
+    let mut buf = [0; 4];
+    read.read_exact(&mut buf)?;
+    Ok(u32::from_le_bytes(buf))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+    use crate::input::Event;
+    use super::{Replay, Frame};
+
+    #[test]
+    fn round_trip() {
+        let replay = Replay {
+            seed: -12345,
+            frames: vec![
+                Frame { room: 0, events: vec![] },
+                Frame { room: 0, events: vec![Event::KeyDown(37), Event::MouseMove(-4, 300)] },
+                Frame { room: 2, events: vec![Event::ButtonDown(1), Event::KeyUp(37)] },
+                Frame { room: 2, events: vec![Event::ButtonUp(1)] },
+            ],
+        };
+
+        let mut data = Vec::default();
+        replay.write(&mut data).unwrap();
+        assert_eq!(Replay::read(&mut &data[..]).unwrap(), replay);
+    }
+
+    #[test]
+    fn corrupt() {
+        let replay = Replay {
+            seed: 0,
+            frames: vec![Frame { room: 0, events: vec![Event::KeyDown(37)] }],
+        };
+        let mut data = Vec::default();
+        replay.write(&mut data).unwrap();
+
+        // Lengths are read from the file, so a huge one must run out of data rather than memory.
+        for at in [12, 20] {
+            let mut data = data.clone();
+            data[at..at + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+            let error = Replay::read(&mut &data[..]).err().unwrap();
+            assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+        }
+
+        let mut data = data.clone();
+        data[24] = 0xff;
+        let error = Replay::read(&mut &data[..]).err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+
+        data[0] += 1;
+        let error = Replay::read(&mut &data[..]).err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+}
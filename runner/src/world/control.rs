@@ -1,11 +1,13 @@
-use std::{fmt, error, ops::Range};
+use std::{fmt, error, ops::Range, collections::HashMap};
 use gml::symbol::Symbol;
-use gml::{self, vm};
+use gml::{self, vm, diagnostic::{ErrorsOnly, Text}};
 use crate::{Context, real};
 
 #[derive(Default)]
 pub struct State {
     next_id: i32,
+    /// Set by `game_end`, and checked by the platform between frames.
+    pub ended: bool,
 }
 
 #[derive(Debug)]
@@ -27,6 +29,16 @@ impl error::Error for Error {}
 
 #[gml::bind]
 impl State {
+    #[gml::api]
+    pub fn game_end(&mut self) {
+        self.ended = true;
+    }
+
+    #[gml::api]
+    pub fn action_end_game(&mut self) {
+        self.game_end();
+    }
+
     #[gml::api]
     pub fn execute_string(
         cx: &mut Context, thread: &mut vm::Thread,
@@ -38,12 +50,15 @@ impl State {
         let id = control.next_id;
         control.next_id += 1;
 
-        let errors = &mut *debug.write;
+        // Code built at run time only reports errors, not warnings about its style.
+        let errors = ErrorsOnly(Text(&mut *debug.write));
         let prototypes = &assets.code.prototypes;
 
         let function = gml::Function::String { id };
         let name = gml::FunctionDisplay::String;
-        let (code, locations, errors) = gml::compile_program(prototypes, name, &str[..], errors);
+        let constants = HashMap::default();
+        let (code, locations, _, errors) =
+            gml::compile_program(prototypes, &constants, false, name, &str[..], errors);
         if errors > 0 { return Ok(vm::Value::from(0.0)); }
 
         assets.code.code.insert(function, code);
@@ -1,10 +1,13 @@
 use std::io::{self, Write};
 use gml::symbol::Symbol;
 use gml::{vm, front::Span, ErrorPrinter};
+use gml::diagnostic::{Diagnostic, Emit, Location, Severity, Text};
 
 pub struct State {
     pub debug: vm::Debug,
     pub error: fn(&Self, &vm::Error),
+    /// Report a problem with the runner itself, rather than with the game's code.
+    pub diagnostic: fn(&Self, Diagnostic),
     pub write: Box<dyn Write>,
 }
 
@@ -13,6 +16,7 @@ impl Default for State {
         State {
             debug: vm::Debug::default(),
             error: |state, error| state.show_vm_error_write(error, io::stderr()),
+            diagnostic: |_, diagnostic| Text(io::stderr()).emit(diagnostic),
             write: Box::new(io::stdout()),
         }
     }
@@ -21,12 +25,16 @@ impl Default for State {
 #[gml::bind]
 impl State {
     pub fn show_vm_error(&self, error: &vm::Error) { (self.error)(self, error); }
+    pub fn show_diagnostic(&self, diagnostic: Diagnostic) { (self.diagnostic)(self, diagnostic); }
     pub fn show_vm_error_write<W: Write>(&self, error: &vm::Error, write: W) {
         if let [ref frame, ref stack @ ..] = error.frames[..] {
-            let mut errors = ErrorPrinter::from_debug(&self.debug, frame.function, write);
+            let mut errors = ErrorPrinter::from_debug(&self.debug, frame.function, Text(write));
             let span = Span::from_debug(&self.debug, frame);
-            ErrorPrinter::error(&mut errors, span, format_args!("{}", error.kind));
-            ErrorPrinter::stack_from_debug(&mut errors, &self.debug, stack);
+            let message = format_args!("{}", error.kind);
+            let notes = stack.iter()
+                .map(|frame| format!("called from {}", Location::from_debug(&self.debug, frame)))
+                .collect();
+            ErrorPrinter::report(&mut errors, Severity::Error, "runtime", span, message, notes);
         }
     }
 
@@ -102,3 +102,30 @@ impl State {
         self.libraries.remove(&dll);
     }
 }
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use gml::symbol::Symbol;
+    use gml::vm;
+    use super::*;
+
+    #[test]
+    fn call_native_library() {
+        let mut state = State::default();
+        let dll = Symbol::intern(b"libm.so.6");
+        let name = Symbol::intern(b"cos");
+        let real = Type::Real as u32;
+        let argtypes = [vm::Value::from(real)];
+        let id = state.external_define(dll, name, Cc::Cdecl as u32, real, 1, &argtypes).unwrap();
+        let result = state.external_call(id, &[vm::Value::from(0.0)]).unwrap();
+        assert_eq!(result, vm::Value::from(1.0));
+    }
+
+    #[test]
+    fn missing_library() {
+        let mut state = State::default();
+        let dll = Symbol::intern(b"missing.dll");
+        let name = Symbol::intern(b"missing");
+        assert!(state.external_define(dll, name, 0, 0, 0, &[]).is_err());
+    }
+}
@@ -0,0 +1,143 @@
+/// Keyboard and mouse state, as seen by the game during a step.
+pub struct State {
+    keys: [bool; 256],
+    keys_pressed: [bool; 256],
+    keys_released: [bool; 256],
+
+    mouse_x: i32,
+    mouse_y: i32,
+    buttons: [bool; 4],
+    buttons_pressed: [bool; 4],
+    buttons_released: [bool; 4],
+
+    /// Input received since the start of the current frame, for recording.
+    pub events: Vec<Event>,
+}
+
+/// A single input event, delivered between steps.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Event {
+    KeyDown(u8),
+    KeyUp(u8),
+    MouseMove(i32, i32),
+    ButtonDown(u8),
+    ButtonUp(u8),
+}
+
+pub const VK_NOKEY: i32 = 0;
+pub const VK_ANYKEY: i32 = 1;
+
+pub const MB_ANY: i32 = -1;
+pub const MB_NONE: i32 = 0;
+
+impl Default for State {
+    fn default() -> State {
+        State {
+            keys: [false; 256],
+            keys_pressed: [false; 256],
+            keys_released: [false; 256],
+
+            mouse_x: 0,
+            mouse_y: 0,
+            buttons: [false; 4],
+            buttons_pressed: [false; 4],
+            buttons_released: [false; 4],
+
+            events: Vec::default(),
+        }
+    }
+}
+
+impl State {
+    /// Forget the previous frame's presses and releases.
+    pub fn begin_frame(&mut self) {
+        self.keys_pressed = [false; 256];
+        self.keys_released = [false; 256];
+        self.buttons_pressed = [false; 4];
+        self.buttons_released = [false; 4];
+        self.events.clear();
+    }
+
+    pub fn apply(&mut self, event: Event) {
+        match event {
+            Event::KeyDown(key) => {
+                let key = key as usize;
+                if !self.keys[key] { self.keys_pressed[key] = true; }
+                self.keys[key] = true;
+            }
+            Event::KeyUp(key) => {
+                let key = key as usize;
+                if self.keys[key] { self.keys_released[key] = true; }
+                self.keys[key] = false;
+            }
+            Event::MouseMove(x, y) => {
+                self.mouse_x = x;
+                self.mouse_y = y;
+            }
+            Event::ButtonDown(button) => {
+                let button = button as usize % 4;
+                if !self.buttons[button] { self.buttons_pressed[button] = true; }
+                self.buttons[button] = true;
+            }
+            Event::ButtonUp(button) => {
+                let button = button as usize % 4;
+                if self.buttons[button] { self.buttons_released[button] = true; }
+                self.buttons[button] = false;
+            }
+        }
+        self.events.push(event);
+    }
+
+    fn check_key(keys: &[bool; 256], key: i32) -> bool {
+        match key {
+            VK_NOKEY => !keys.iter().any(|&key| key),
+            VK_ANYKEY => keys.iter().any(|&key| key),
+            key => keys.get(key as usize).copied().unwrap_or(false),
+        }
+    }
+
+    fn check_button(buttons: &[bool; 4], button: i32) -> bool {
+        match button {
+            MB_ANY => buttons.iter().any(|&button| button),
+            MB_NONE => !buttons.iter().any(|&button| button),
+            button => buttons.get(button as usize).copied().unwrap_or(false),
+        }
+    }
+}
+
+#[gml::bind]
+impl State {
+    #[gml::api]
+    pub fn keyboard_check(&mut self, key: i32) -> bool { Self::check_key(&self.keys, key) }
+
+    #[gml::api]
+    pub fn keyboard_check_pressed(&mut self, key: i32) -> bool {
+        Self::check_key(&self.keys_pressed, key)
+    }
+
+    #[gml::api]
+    pub fn keyboard_check_released(&mut self, key: i32) -> bool {
+        Self::check_key(&self.keys_released, key)
+    }
+
+    #[gml::api]
+    pub fn mouse_check_button(&mut self, button: i32) -> bool {
+        Self::check_button(&self.buttons, button)
+    }
+
+    #[gml::api]
+    pub fn mouse_check_button_pressed(&mut self, button: i32) -> bool {
+        Self::check_button(&self.buttons_pressed, button)
+    }
+
+    #[gml::api]
+    pub fn mouse_check_button_released(&mut self, button: i32) -> bool {
+        Self::check_button(&self.buttons_released, button)
+    }
+
+    #[gml::get(mouse_x)]
+    pub fn get_mouse_x(&self) -> i32 { self.mouse_x }
+
+    #[gml::get(mouse_y)]
+    pub fn get_mouse_y(&self) -> i32 { self.mouse_y }
+}
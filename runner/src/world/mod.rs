@@ -14,6 +14,7 @@ pub mod ini;
 pub mod data;
 pub mod external;
 pub mod control;
+pub mod input;
 
 #[derive(Default)]
 pub struct World {
@@ -30,6 +31,7 @@ pub struct World {
     pub data: data::State,
     pub external: external::State,
     pub control: control::State,
+    pub input: input::State,
 }
 
 impl<'r> vm::Project<'r, (&'r mut vm::World,)> for Context {
@@ -128,6 +130,20 @@ impl<'r> vm::Project<'r, (&'r mut external::State,)> for Context {
     }
 }
 
+impl<'r> vm::Project<'r, (&'r mut control::State,)> for Context {
+    fn fields(&'r mut self) -> (&'r mut control::State,) {
+        let Context { world, .. } = self;
+        (&mut world.control,)
+    }
+}
+
+impl<'r> vm::Project<'r, (&'r mut input::State,)> for Context {
+    fn fields(&'r mut self) -> (&'r mut input::State,) {
+        let Context { world, .. } = self;
+        (&mut world.input,)
+    }
+}
+
 impl World {
     pub fn from_assets(assets: &crate::Assets, debug: vm::Debug) -> Self {
         let mut world = Self::default();
@@ -149,5 +165,6 @@ impl World {
         data::State::register(items);
         external::State::register(items);
         control::State::register(items);
+        input::State::register(items);
     }
 }
@@ -0,0 +1,201 @@
+#![cfg(not(any(target_arch = "wasm32", windows)))]
+
+//! Golden-image tests for the renderer.
+//!
+//! Each test runs a game in the headless runner and compares selected frames against reference
+//! images in `tests/render`. Set `DEJAVU_BLESS=1` to write new reference images instead. When a
+//! frame does not match, the actual frame and a diff image are written to the target directory.
+
+use std::{env, fs, io};
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use bstr::BStr;
+use gml::diagnostic::Text;
+
+/// The largest per-channel difference that still counts as a match.
+const TOLERANCE: u8 = 2;
+
+#[test]
+fn sprites() {
+    let sprite = circle(16, [0x20, 0x40, 0xE0, 0xFF]);
+    let background = checker(8, [0x60, 0x60, 0x60, 0xFF], [0x90, 0x90, 0x90, 0xFF]);
+
+    let mut game = project::Game::default();
+    game.sprites.push(project::Sprite {
+        name: BStr::new(b"circle_spr"),
+        version: 800,
+        origin: (8, 8),
+        images: vec![project::Image { size: (16, 16), data: &sprite[..] }],
+        ..project::Sprite::default()
+    });
+    game.backgrounds.push(project::Background {
+        name: BStr::new(b"checker_bk"),
+        version: 710,
+        size: (8, 8),
+        data: &background[..],
+        ..project::Background::default()
+    });
+    game.objects.push(project::Object {
+        name: BStr::new(b"circle_obj"),
+        sprite: 0,
+        visible: true,
+        ..project::Object::default()
+    });
+    game.rooms.push(project::Room {
+        name: BStr::new(b"render_rm"),
+        width: 64,
+        height: 48,
+        speed: 30,
+        code: BStr::new(b""),
+        backgrounds: vec![project::RoomBackground {
+            visible: true,
+            background: 0,
+            htiled: true,
+            vtiled: true,
+            ..project::RoomBackground::default()
+        }],
+        instances: vec![
+            project::Instance {
+                x: 16, y: 16, object_index: 0, id: 100001,
+                code: BStr::new(b""),
+            },
+            project::Instance {
+                x: 8, y: 32, object_index: 0, id: 100002,
+                code: BStr::new(b"hspeed = 3"),
+            },
+        ],
+        ..project::Room::default()
+    });
+    game.room_order.push(0);
+    game.last_instance = 100002;
+
+    check("sprites", &game, &[1, 5]);
+}
+
+/// Run `game` and compare each of `frames` against its reference image.
+fn check(name: &str, game: &project::Game<'_>, frames: &[u64]) {
+    let arena = quickdry::Arena::default();
+    let (mut assets, debug) = runner::build(game, &[], &arena, None, || Text(io::stderr()))
+        .unwrap_or_else(|errors| panic!("{errors} errors building {name}"));
+    runner::load(&mut assets, &[]).unwrap();
+    let world = runner::World::from_assets(&assets, debug);
+    let mut state = runner::State::new(runner::Context { world, assets });
+
+    let bless = env::var_os("DEJAVU_BLESS").is_some();
+    let mut failures = Vec::default();
+    let last = frames.iter().copied().max().unwrap_or(0);
+    for frame in 1..=last {
+        state.draw();
+        if frames.contains(&frame) {
+            let (size, data) = state.framebuffer();
+            let file = format!("{name}-{frame}");
+            let reference = reference_dir().join(&file).with_extension("png");
+            if bless {
+                write_png(&reference, size, data);
+            } else if let Err(message) = compare(&file, &reference, size, data) {
+                failures.push(message);
+            }
+        }
+        state.step();
+    }
+
+    if !failures.is_empty() {
+        panic!("{}", failures.join("\n"));
+    }
+}
+
+/// Compare a frame against a reference image, writing the frame and a diff on mismatch.
+fn compare(file: &str, reference: &Path, size: (u32, u32), data: &[u8]) -> Result<(), String> {
+    let Some((expected_size, expected)) = read_png(reference) else {
+        return Err(format!("{file}: missing reference {}", reference.display()));
+    };
+
+    let output = output_dir();
+    let actual_path = output.join(format!("{file}-actual.png"));
+    let diff_path = output.join(format!("{file}-diff.png"));
+
+    if expected_size != size {
+        write_png(&actual_path, size, data);
+        return Err(format!(
+            "{file}: expected {expected_size:?} but got {size:?}; see {}",
+            actual_path.display()
+        ));
+    }
+
+    let mut mismatches = 0;
+    let mut diff = Vec::with_capacity(data.len());
+    for (expected, actual) in Iterator::zip(expected.chunks_exact(4), data.chunks_exact(4)) {
+        let matches = Iterator::zip(expected.iter(), actual.iter())
+            .all(|(&e, &a)| u8::abs_diff(e, a) <= TOLERANCE);
+        if matches {
+            // Show matching pixels as a faded version of the reference.
+            let luma = (expected[0] as u32 + expected[1] as u32 + expected[2] as u32) / 3;
+            let faded = (luma / 4 + 128) as u8;
+            diff.extend_from_slice(&[faded, faded, faded, 255]);
+        } else {
+            mismatches += 1;
+            diff.extend_from_slice(&[255, 0, 0, 255]);
+        }
+    }
+    if mismatches == 0 {
+        return Ok(());
+    }
+
+    write_png(&actual_path, size, data);
+    write_png(&diff_path, size, &diff[..]);
+    Err(format!("{file}: {mismatches} pixels differ; see {}", diff_path.display()))
+}
+
+fn reference_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("render")
+}
+
+fn output_dir() -> PathBuf {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("render");
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn read_png(path: &Path) -> Option<((u32, u32), Vec<u8>)> {
+    let decoder = png::Decoder::new(File::open(path).ok()?);
+    let mut reader = decoder.read_info().unwrap();
+    let mut data = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut data[..]).unwrap();
+    assert_eq!((info.color_type, info.bit_depth), (png::ColorType::Rgba, png::BitDepth::Eight));
+    data.truncate(info.buffer_size());
+    Some(((info.width, info.height), data))
+}
+
+fn write_png(path: &Path, (width, height): (u32, u32), data: &[u8]) {
+    let mut encoder = png::Encoder::new(io::BufWriter::new(File::create(path).unwrap()), width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header().unwrap();
+    writer.write_image_data(data).unwrap();
+}
+
+/// A filled circle in BGRA, on a transparent background.
+fn circle(size: u32, color: [u8; 4]) -> Vec<u8> {
+    let r = size as f32 / 2.0;
+    let mut data = Vec::with_capacity(size as usize * size as usize * 4);
+    for y in 0..size {
+        for x in 0..size {
+            let (dx, dy) = (x as f32 + 0.5 - r, y as f32 + 0.5 - r);
+            let inside = dx * dx + dy * dy <= r * r;
+            data.extend_from_slice(if inside { &color } else { &[0, 0, 0, 0] });
+        }
+    }
+    data
+}
+
+/// A two-color checkerboard in BGRA, with square cells.
+fn checker(size: u32, a: [u8; 4], b: [u8; 4]) -> Vec<u8> {
+    let cell = size / 2;
+    let mut data = Vec::with_capacity(size as usize * size as usize * 4);
+    for y in 0..size {
+        for x in 0..size {
+            data.extend_from_slice(if (x / cell + y / cell).is_multiple_of(2) { &a } else { &b });
+        }
+    }
+    data
+}
@@ -0,0 +1,129 @@
+#![cfg(not(any(target_arch = "wasm32", windows)))]
+
+use std::io;
+use bstr::BStr;
+use gml::diagnostic::Text;
+use runner::input::Event;
+
+/// A game with a single instance that runs `step` every frame.
+fn game<'a>(step: &'a [u8]) -> project::Game<'a> {
+    let mut game = project::Game::default();
+    game.objects.push(project::Object {
+        name: BStr::new(b"input_obj"),
+        sprite: -1,
+        events: vec![
+            project::Event {
+                event_type: project::event_type::STEP,
+                event_kind: project::event_kind::STEP,
+                actions: vec![
+                    project::Action {
+                        library: 1,
+                        action: 603,
+                        action_kind: project::action_kind::CODE,
+                        has_target: true,
+                        parameters_used: 1,
+                        parameters: vec![project::argument_type::STRING],
+                        target: gml::vm::SELF,
+                        arguments: vec![BStr::new(step)],
+                        ..project::Action::default()
+                    },
+                ],
+            },
+        ],
+        ..project::Object::default()
+    });
+    game.rooms.push(project::Room {
+        name: BStr::new(b"input_rm"),
+        width: 32,
+        height: 32,
+        speed: 30,
+        code: BStr::new(b""),
+        instances: vec![
+            project::Instance { x: 0, y: 0, object_index: 0, id: 100001, code: BStr::new(b"") },
+        ],
+        ..project::Room::default()
+    });
+    game.room_order.push(0);
+    game.last_instance = 100001;
+    game
+}
+
+fn context(game: &project::Game<'_>, seed: i32) -> runner::Context {
+    let arena = quickdry::Arena::default();
+    let (mut assets, debug) = runner::build(game, &[], &arena, None, || Text(io::stderr()))
+        .unwrap();
+    runner::load(&mut assets, &[]).unwrap();
+    let mut world = runner::World::from_assets(&assets, debug);
+    world.real.random_set_seed(seed);
+    runner::Context { world, assets }
+}
+
+fn start(game: &project::Game<'_>, seed: i32) -> runner::State {
+    runner::State::new(context(game, seed))
+}
+
+#[test]
+fn record_and_replay() {
+    let game = game(b"if keyboard_check_pressed(65) { game_end() }");
+
+    let mut state = start(&game, 42);
+    state.record();
+    state.step_with(&[Event::MouseMove(3, 4)]);
+    state.step_with(&[Event::KeyDown(66)]);
+    assert!(!state.ended());
+    state.step_with(&[Event::KeyUp(66), Event::KeyDown(65)]);
+    assert!(state.ended());
+
+    let replay = state.finish_recording().unwrap();
+    assert_eq!(replay.seed, 42);
+    assert_eq!(replay.frames.len(), 3);
+    assert_eq!(replay.frames[2].events, [Event::KeyUp(66), Event::KeyDown(65)]);
+
+    let mut state = start(&game, replay.seed);
+    for (i, frame) in replay.frames.iter().enumerate() {
+        assert!(!state.ended(), "ended early at frame {i}");
+        assert!(state.replay_step(frame));
+    }
+    assert!(state.ended());
+}
+
+#[test]
+fn run_record_and_replay() {
+    let game = game(b"if keyboard_check_pressed(65) { game_end() }");
+
+    let options = runner::Options {
+        frames: Some(4),
+        throttle: false,
+        record: true,
+        ..runner::Options::default()
+    };
+    let replay = runner::run_with(context(&game, 7), options).unwrap();
+    assert_eq!(replay.seed, 7);
+    assert_eq!(replay.frames.len(), 3);
+
+    // Playing a recording back while recording again reproduces it.
+    let mut data = Vec::default();
+    replay.write(&mut data).unwrap();
+    let options = runner::Options {
+        throttle: false,
+        record: true,
+        replay: Some(runner::Replay::read(&mut &data[..]).unwrap()),
+        ..runner::Options::default()
+    };
+    assert_eq!(runner::run_with(context(&game, 0), options), Some(replay));
+}
+
+#[test]
+fn held_keys_are_pressed_once() {
+    let pressed = game(b"if keyboard_check_pressed(65) { game_end() }");
+    let mut state = start(&pressed, 0);
+    state.step_with(&[Event::KeyDown(65)]);
+    assert!(state.ended());
+
+    let held = game(b"if keyboard_check(65) && !keyboard_check_pressed(65) { game_end() }");
+    let mut state = start(&held, 0);
+    state.step_with(&[Event::KeyDown(65)]);
+    assert!(!state.ended());
+    state.step();
+    assert!(state.ended());
+}